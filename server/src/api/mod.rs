@@ -4,6 +4,7 @@
 //! Unterstützt Multi-Client-Synchronisation via Broadcast-Channel
 
 mod routes;
+mod nmos;
 mod websocket;
 
-pub use routes::start_api_server;
+pub use routes::{start_api_server, ApiContext};
@@ -0,0 +1,231 @@
+//! NMOS APIs
+//!
+//! IS-04 Node API (`/x-nmos/node/v1.3`) und IS-05 Connection API
+//! (`/x-nmos/connection/v1.1`) für Broadcast-Controller. Antworten und
+//! Fehler folgen den AMWA-Schemas (`{code, error, debug}`), nicht dem
+//! `ApiResponse` der übrigen REST API. Pfade gelten mit und ohne
+//! abschließenden Schrägstrich.
+
+use std::sync::Arc;
+use axum::{
+    routing::{get, MethodRouter},
+    Router,
+    Json,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde_json::{Value, json};
+
+use crate::network_audio::nmos::{CONNECTION_API_VERSION, NODE_API_VERSION, NmosError, NmosNode, ResourceType};
+
+use super::routes::AppState;
+
+/// Routen der Node und Connection API
+pub fn router() -> Router<AppState> {
+    let node = format!("/x-nmos/node/{}", NODE_API_VERSION);
+    let connection = format!("/x-nmos/connection/{}", CONNECTION_API_VERSION);
+    let routes: Vec<(String, MethodRouter<AppState>)> = vec![
+        ("/x-nmos".to_string(), get(|| listing(&["node/", "connection/"]))),
+        ("/x-nmos/node".to_string(), get(|| async { Json(json!([format!("{}/", NODE_API_VERSION)])) })),
+        (node.clone(), get(|| listing(&["self/", "sources/", "flows/", "devices/", "senders/", "receivers/"]))),
+        (format!("{}/self", node), get(get_node_self)),
+        (format!("{}/:resources", node), get(get_resources)),
+        (format!("{}/:resources/:id", node), get(get_resource)),
+        ("/x-nmos/connection".to_string(), get(|| async { Json(json!([format!("{}/", CONNECTION_API_VERSION)])) })),
+        (connection.clone(), get(|| listing(&["bulk/", "single/"]))),
+        (format!("{}/bulk", connection), get(|| listing(&["senders/", "receivers/"]))),
+        (format!("{}/bulk/:kind", connection), get(bulk_listing).post(post_bulk)),
+        (format!("{}/single", connection), get(|| listing(&["senders/", "receivers/"]))),
+        (format!("{}/single/:kind", connection), get(get_connection_ids)),
+        (format!("{}/single/:kind/:id", connection), get(get_connection_endpoints)),
+        (format!("{}/single/:kind/:id/constraints", connection), get(get_constraints)),
+        (format!("{}/single/:kind/:id/staged", connection), get(get_staged).patch(patch_staged)),
+        (format!("{}/single/:kind/:id/active", connection), get(get_active)),
+        (format!("{}/single/:kind/:id/transporttype", connection), get(get_transport_type)),
+        (format!("{}/single/:kind/:id/transportfile", connection), get(get_transport_file)),
+    ];
+
+    routes.into_iter().fold(Router::new(), |router, (path, method_router)| {
+        router.route(&format!("{}/", path), method_router.clone()).route(&path, method_router)
+    })
+}
+
+/// Sender oder Receiver der Connection API
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Sender,
+    Receiver,
+}
+
+impl Kind {
+    fn parse(kind: &str) -> Result<Self, NmosError> {
+        match kind {
+            "senders" => Ok(Kind::Sender),
+            "receivers" => Ok(Kind::Receiver),
+            _ => Err(NmosError::not_found(format!("'{}' gibt es nicht", kind))),
+        }
+    }
+}
+
+async fn listing(entries: &'static [&'static str]) -> Json<Value> {
+    Json(json!(entries))
+}
+
+fn nmos_node(state: &AppState) -> Result<Arc<NmosNode>, NmosError> {
+    state.nmos.clone().ok_or_else(|| NmosError::not_found("NMOS Node nicht aktiv"))
+}
+
+fn reply(result: Result<Value, NmosError>) -> Response {
+    match result {
+        Ok(value) => Json(value).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(e: NmosError) -> Response {
+    let status = StatusCode::from_u16(e.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, Json(e.to_json())).into_response()
+}
+
+// === IS-04 Node API ===
+
+/// Der Node selbst
+async fn get_node_self(State(state): State<AppState>) -> Response {
+    reply(nmos_node(&state).and_then(|node| {
+        node.resource(ResourceType::Node, node.node_id())
+            .ok_or_else(|| NmosError::internal("Node fehlt"))
+    }))
+}
+
+/// Alle Ressourcen eines Typs (devices, sources, flows, senders, receivers)
+async fn get_resources(State(state): State<AppState>, Path(resources): Path<String>) -> Response {
+    reply(nmos_node(&state).and_then(|node| {
+        let kind = ResourceType::from_plural(&resources)
+            .filter(|kind| *kind != ResourceType::Node)
+            .ok_or_else(|| NmosError::not_found(format!("'{}' gibt es nicht", resources)))?;
+        Ok(json!(node.resources_of(kind)))
+    }))
+}
+
+/// Eine Ressource
+async fn get_resource(State(state): State<AppState>, Path((resources, id)): Path<(String, String)>) -> Response {
+    reply(nmos_node(&state).and_then(|node| {
+        ResourceType::from_plural(&resources)
+            .filter(|kind| *kind != ResourceType::Node)
+            .and_then(|kind| node.resource(kind, &id))
+            .ok_or_else(|| NmosError::not_found(format!("'{}/{}' nicht gefunden", resources, id)))
+    }))
+}
+
+// === IS-05 Connection API ===
+
+/// Bulk-Endpunkte nehmen nur POST an
+async fn bulk_listing(Path(kind): Path<String>) -> Response {
+    match Kind::parse(&kind) {
+        Ok(_) => error_response(NmosError { code: 405, error: "Nur POST".to_string() }),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Mehrere Sender oder Receiver auf einmal ändern
+async fn post_bulk(State(state): State<AppState>, Path(kind): Path<String>, Json(body): Json<Value>) -> Response {
+    let node = match nmos_node(&state).and_then(|node| Ok((node, Kind::parse(&kind)?))) {
+        Ok(node) => node,
+        Err(e) => return error_response(e),
+    };
+    // Aktivierungen öffnen Sockets und starten Empfänger
+    let result = tokio::task::spawn_blocking(move || match node {
+        (node, Kind::Receiver) => node.bulk_receivers(&body),
+        (node, Kind::Sender) => node.bulk_senders(&body),
+    }).await;
+    reply(result.unwrap_or_else(|e| Err(NmosError::internal(e.to_string()))))
+}
+
+/// IDs aller Sender oder Receiver
+async fn get_connection_ids(State(state): State<AppState>, Path(kind): Path<String>) -> Response {
+    reply(nmos_node(&state).and_then(|node| {
+        let ids = match Kind::parse(&kind)? {
+            Kind::Sender => node.sender_ids(),
+            Kind::Receiver => node.receiver_ids(),
+        };
+        Ok(json!(ids.into_iter().map(|id| format!("{}/", id)).collect::<Vec<_>>()))
+    }))
+}
+
+/// Endpunkte eines Senders oder Receivers
+async fn get_connection_endpoints(State(state): State<AppState>, Path((kind, id)): Path<(String, String)>) -> Response {
+    reply(nmos_node(&state).and_then(|node| match Kind::parse(&kind)? {
+        Kind::Sender => {
+            node.sender_staged(&id)?;
+            Ok(json!(["constraints/", "staged/", "active/", "transportfile/", "transporttype/"]))
+        }
+        Kind::Receiver => {
+            node.receiver_staged(&id)?;
+            Ok(json!(["constraints/", "staged/", "active/", "transporttype/"]))
+        }
+    }))
+}
+
+async fn get_constraints(State(state): State<AppState>, Path((kind, id)): Path<(String, String)>) -> Response {
+    reply(nmos_node(&state).and_then(|node| match Kind::parse(&kind)? {
+        Kind::Sender => node.sender_constraints(&id),
+        Kind::Receiver => node.receiver_constraints(&id),
+    }))
+}
+
+async fn get_staged(State(state): State<AppState>, Path((kind, id)): Path<(String, String)>) -> Response {
+    reply(nmos_node(&state).and_then(|node| match Kind::parse(&kind)? {
+        Kind::Sender => node.sender_staged(&id),
+        Kind::Receiver => node.receiver_staged(&id),
+    }))
+}
+
+/// Parameter vormerken und ggf. aktivieren (200 sofort, 202 geplant)
+async fn patch_staged(
+    State(state): State<AppState>,
+    Path((kind, id)): Path<(String, String)>,
+    Json(patch): Json<Value>,
+) -> Response {
+    let (node, kind) = match nmos_node(&state).and_then(|node| Ok((node, Kind::parse(&kind)?))) {
+        Ok(node) => node,
+        Err(e) => return error_response(e),
+    };
+    let result = tokio::task::spawn_blocking(move || match kind {
+        Kind::Receiver => node.patch_receiver(&id, &patch),
+        Kind::Sender => node.patch_sender(&id, &patch),
+    }).await.unwrap_or_else(|e| Err(NmosError::internal(e.to_string())));
+    match result {
+        Ok((code, staged)) => (StatusCode::from_u16(code).unwrap_or(StatusCode::OK), Json(staged)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn get_active(State(state): State<AppState>, Path((kind, id)): Path<(String, String)>) -> Response {
+    reply(nmos_node(&state).and_then(|node| match Kind::parse(&kind)? {
+        Kind::Sender => node.sender_active(&id),
+        Kind::Receiver => node.receiver_active(&id),
+    }))
+}
+
+async fn get_transport_type(State(state): State<AppState>, Path((kind, id)): Path<(String, String)>) -> Response {
+    reply(nmos_node(&state).and_then(|node| {
+        match Kind::parse(&kind)? {
+            Kind::Sender => node.sender_staged(&id)?,
+            Kind::Receiver => node.receiver_staged(&id)?,
+        };
+        Ok(json!(crate::network_audio::nmos::connection::TRANSPORT_RTP))
+    }))
+}
+
+/// SDP eines Senders (Receiver haben keine Transport-Datei)
+async fn get_transport_file(State(state): State<AppState>, Path((kind, id)): Path<(String, String)>) -> Response {
+    let sdp = nmos_node(&state).and_then(|node| match Kind::parse(&kind)? {
+        Kind::Sender => node.sender_transport_file(&id),
+        Kind::Receiver => Err(NmosError::not_found("Receiver haben keine Transport-Datei")),
+    });
+    match sdp {
+        Ok(sdp) => ([(header::CONTENT_TYPE, "application/sdp")], sdp).into_response(),
+        Err(e) => error_response(e),
+    }
+}
@@ -5,10 +5,11 @@
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use axum::{
-    routing::{get, patch, post, delete},
+    routing::{get, patch, post, put, delete},
     Router,
     Json,
     extract::{Path, State, WebSocketUpgrade},
+    http::StatusCode,
     response::IntoResponse,
 };
 use tower_http::cors::{CorsLayer, Any};
@@ -17,12 +18,13 @@ use tokio::sync::{RwLock, broadcast};
 use audiomultiverse_protocol::ServerMessage;
 
 use crate::config::ApiConfig;
-use crate::mixer::{Mixer, SceneManager, SceneMetadata, MasterSection, MasterState};
-use crate::network_audio::{NetworkDevice, SapDiscovery, PtpClock};
-use crate::audio::AudioCommandSender;
+use crate::mixer::{ChannelBusSends, Mixer, SceneManager, SceneMetadata, MasterSection, MasterState, MatrixOutputState, MatrixSource};
+use crate::network_audio::nmos::NmosNode;
+use crate::network_audio::{Aes67Subscriptions, Aes67TxStreams, BackendInfo, ChannelPatch, NetworkBackends, NetworkDevice, RtcpStats, SapDiscovery, SavedSubscription, PtpClock, PtpStats, SubscriptionInfo, TxStreamConfig, TxStreamInfo};
+use crate::audio::{AudioCommandSender, DriftMonitor, EqBandParams, OutputProcessingParams};
 use audiomultiverse_protocol::{ApiResponse, ChannelState, MixerState, ServerInfo};
 
-use super::websocket::handle_websocket;
+use super::websocket::{forward_health_events, forward_sap_events, handle_websocket};
 
 /// App State für alle Handlers
 #[derive(Clone)]
@@ -35,8 +37,18 @@ pub struct AppState {
     pub sap_discovery: Option<Arc<SapDiscovery>>,
     /// PTP Clock for AES67 synchronization (thread-safe)
     pub ptp_clock: Option<Arc<PtpClock>>,
+    /// Empfangene AES67 Streams mit Kanal-Patch (thread-safe)
+    pub aes67_subscriptions: Option<Arc<Aes67Subscriptions>>,
+    /// Gesendete AES67 Streams (thread-safe)
+    pub aes67_tx_streams: Option<Arc<Aes67TxStreams>>,
+    /// Laufende Netzwerk-Audio Backends (AES67, virtuell, ...)
+    pub network_backends: NetworkBackends,
+    /// NMOS Node (IS-04/IS-05), falls aktiviert
+    pub nmos: Option<Arc<NmosNode>>,
     /// Command sender for AudioEngine control (thread-safe)
     pub audio_cmd: Option<AudioCommandSender>,
+    /// Clock-Drift Messung (Soundkarte gegenüber PTP)
+    pub drift_monitor: Arc<DriftMonitor>,
     /// Broadcast-Channel für Multi-Client-Synchronisation
     /// Alle Änderungen werden an alle verbundenen Clients gesendet
     pub broadcast_tx: broadcast::Sender<ServerMessage>,
@@ -44,19 +56,42 @@ pub struct AppState {
     pub client_count: Arc<AtomicUsize>,
 }
 
+/// Dienste, auf die die API zugreift
+pub struct ApiContext {
+    pub mixer: Arc<Mixer>,
+    pub scene_manager: Arc<RwLock<SceneManager>>,
+    pub master: Arc<MasterSection>,
+    /// Laufende Netzwerk-Audio Backends, liefern auch SAP, PTP und AES67 Streams
+    pub network_backends: NetworkBackends,
+    /// NMOS Node (IS-04/IS-05), falls aktiviert
+    pub nmos: Option<Arc<NmosNode>>,
+    /// Command sender for AudioEngine control (thread-safe)
+    pub audio_cmd: Option<AudioCommandSender>,
+    /// Clock-Drift Messung (Soundkarte gegenüber PTP)
+    pub drift_monitor: Arc<DriftMonitor>,
+}
+
 /// API Server starten
-pub async fn start_api_server(
-    config: ApiConfig,
-    mixer: Arc<Mixer>,
-    scene_manager: Arc<RwLock<SceneManager>>,
-    master: Arc<MasterSection>,
-    sap_discovery: Option<Arc<SapDiscovery>>,
-    ptp_clock: Option<Arc<PtpClock>>,
-    audio_cmd: Option<AudioCommandSender>,
-) -> anyhow::Result<()> {
+pub async fn start_api_server(config: ApiConfig, context: ApiContext) -> anyhow::Result<()> {
+    let ApiContext { mixer, scene_manager, master, network_backends, nmos, audio_cmd, drift_monitor } = context;
+    let sap_discovery = network_backends.sap_discovery();
+    let ptp_clock = network_backends.ptp_clock();
+    let aes67_subscriptions = network_backends.aes67_subscriptions();
+    let aes67_tx_streams = network_backends.aes67_tx_streams();
+    
     // Broadcast-Channel für Multi-Client-Sync (Kapazität für bis zu 256 gepufferte Nachrichten)
     let (broadcast_tx, _) = broadcast::channel::<ServerMessage>(256);
     
+    // SAP-Änderungen an alle Clients weiterleiten
+    if let Some(ref sap) = sap_discovery {
+        forward_sap_events(sap, broadcast_tx.clone());
+    }
+    
+    // Stream-Alarme an alle Clients weiterleiten
+    if let Some(ref subscriptions) = aes67_subscriptions {
+        forward_health_events(subscriptions, broadcast_tx.clone());
+    }
+    
     let state = AppState {
         mixer,
         config: config.clone(),
@@ -64,7 +99,12 @@ pub async fn start_api_server(
         master,
         sap_discovery,
         ptp_clock,
+        aes67_subscriptions,
+        aes67_tx_streams,
+        network_backends,
+        nmos,
         audio_cmd,
+        drift_monitor,
         broadcast_tx,
         client_count: Arc::new(AtomicUsize::new(0)),
     };
@@ -93,11 +133,22 @@ pub async fn start_api_server(
         .route("/api/channels/:id/fader", post(set_fader))
         .route("/api/channels/:id/mute", post(set_mute))
         .route("/api/channels/:id/solo", post(set_solo))
+        .route("/api/channels/:id/sends", get(get_channel_sends))
+        .route("/api/channels/:id/sends/aux/:bus", post(set_channel_aux_send))
+        .route("/api/channels/:id/sends/group/:group", post(set_channel_group))
         
         // Routing
         .route("/api/routing", get(get_routing))
         .route("/api/routing", post(set_routing))
         
+        // Matrix-Ausgänge
+        .route("/api/outputs", get(get_outputs))
+        .route("/api/outputs/:id", get(get_output))
+        .route("/api/outputs/:id", patch(update_output))
+        .route("/api/outputs/:id/sends", post(set_output_send))
+        .route("/api/outputs/:id/eq/:band", post(set_output_eq_band))
+        .route("/api/outputs/:id/processing", put(set_output_processing))
+        
         // Szenen
         .route("/api/scenes", get(get_scenes))
         .route("/api/scenes", post(save_scene))
@@ -120,6 +171,21 @@ pub async fn start_api_server(
         .route("/api/aes67/streams/:id/subscribe", post(subscribe_aes67_stream))
         .route("/api/aes67/streams/:id/unsubscribe", post(unsubscribe_aes67_stream))
         .route("/api/aes67/refresh", post(refresh_aes67_discovery))
+        .route("/api/aes67/subscriptions", get(get_aes67_subscriptions))
+        .route("/api/aes67/subscriptions/:id/patch", put(set_aes67_patch))
+        .route("/api/aes67/subscriptions/:id/delay", put(set_aes67_delay))
+        .route("/api/aes67/subscriptions/align", post(align_aes67_subscriptions))
+        .route("/api/aes67/tx-streams", get(get_aes67_tx_streams).post(add_aes67_tx_stream))
+        .route("/api/aes67/tx-streams/:id", delete(remove_aes67_tx_stream))
+        
+        // Netzwerk-Audio Backends (alle Backends einheitlich)
+        .route("/api/network/backends", get(get_network_backends))
+        .route("/api/network/backends/:backend/devices", get(get_network_devices))
+        .route("/api/network/backends/:backend/devices/:id/connect", post(connect_network_device))
+        .route("/api/network/backends/:backend/disconnect", post(disconnect_network_backend))
+        
+        // NMOS Node und Connection API (IS-04/IS-05)
+        .merge(super::nmos::router())
         
         // Health Check
         .route("/health", get(health_check))
@@ -161,7 +227,7 @@ async fn get_server_info(State(state): State<AppState>) -> Json<ApiResponse<Serv
         version: env!("CARGO_PKG_VERSION").to_string(),
         input_count: state.mixer.input_count as u32,
         output_count: state.mixer.output_count as u32,
-        sample_rate: 48000,
+        sample_rate: state.mixer.sample_rate,
         client_count: state.client_count.load(Ordering::Relaxed) as u32,
         audio_backend: "aes67".to_string(),
     };
@@ -282,6 +348,51 @@ async fn set_solo(
     }
 }
 
+/// Bus-Sends eines Kanals (Aux-Pegel, Gruppen-Zuweisung)
+async fn get_channel_sends(
+    State(state): State<AppState>,
+    Path(id): Path<u32>,
+) -> Json<ApiResponse<ChannelBusSends>> {
+    match state.mixer.get_channel_sends(id) {
+        Some(sends) => Json(ApiResponse::ok(sends)),
+        None => Json(ApiResponse::err(format!("Kanal {} nicht gefunden", id))),
+    }
+}
+
+/// Aux-Send setzen
+#[derive(serde::Deserialize)]
+pub struct AuxSendRequest {
+    pub level: f32,
+}
+
+async fn set_channel_aux_send(
+    State(state): State<AppState>,
+    Path((id, bus)): Path<(u32, usize)>,
+    Json(req): Json<AuxSendRequest>,
+) -> Json<ApiResponse<ChannelBusSends>> {
+    match state.mixer.set_channel_aux_send(id, bus, req.level) {
+        Some(sends) => Json(ApiResponse::ok(sends)),
+        None => Json(ApiResponse::err(format!("Aux {} auf Kanal {} nicht gefunden", bus, id))),
+    }
+}
+
+/// Gruppen-Zuweisung setzen
+#[derive(serde::Deserialize)]
+pub struct GroupAssignRequest {
+    pub assigned: bool,
+}
+
+async fn set_channel_group(
+    State(state): State<AppState>,
+    Path((id, group)): Path<(u32, usize)>,
+    Json(req): Json<GroupAssignRequest>,
+) -> Json<ApiResponse<ChannelBusSends>> {
+    match state.mixer.set_channel_group(id, group, req.assigned) {
+        Some(sends) => Json(ApiResponse::ok(sends)),
+        None => Json(ApiResponse::err(format!("Gruppe {} auf Kanal {} nicht gefunden", group, id))),
+    }
+}
+
 /// Routing-Matrix abrufen
 async fn get_routing(State(state): State<AppState>) -> Json<ApiResponse<Vec<Vec<f32>>>> {
     let routing = state.mixer.get_routing();
@@ -304,6 +415,103 @@ async fn set_routing(
     Json(ApiResponse::ok(success))
 }
 
+// === Matrix-Ausgänge Handlers ===
+
+/// Alle Matrix-Ausgänge abrufen
+async fn get_outputs(State(state): State<AppState>) -> Json<ApiResponse<Vec<MatrixOutputState>>> {
+    Json(ApiResponse::ok(state.mixer.get_matrix_outputs()))
+}
+
+/// Einzelnen Matrix-Ausgang abrufen
+async fn get_output(
+    State(state): State<AppState>,
+    Path(id): Path<u32>,
+) -> Json<ApiResponse<MatrixOutputState>> {
+    match state.mixer.get_matrix_output(id) {
+        Some(output) => Json(ApiResponse::ok(output)),
+        None => Json(ApiResponse::err(format!("Ausgang {} nicht gefunden", id))),
+    }
+}
+
+/// Matrix-Ausgang aktualisieren (PATCH)
+#[derive(serde::Deserialize)]
+pub struct OutputUpdate {
+    pub name: Option<String>,
+    pub level_db: Option<f32>,
+    pub mute: Option<bool>,
+    pub follow_main: Option<bool>,
+}
+
+async fn update_output(
+    State(state): State<AppState>,
+    Path(id): Path<u32>,
+    Json(update): Json<OutputUpdate>,
+) -> (StatusCode, Json<ApiResponse<MatrixOutputState>>) {
+    // Unbekannte Ausgänge ablehnen, bevor etwas geändert wird
+    if state.mixer.get_matrix_output(id).is_none() {
+        return (StatusCode::NOT_FOUND, Json(ApiResponse::err(format!("Ausgang {} nicht gefunden", id))));
+    }
+    if let Some(name) = update.name {
+        state.mixer.set_output_name(id, name);
+    }
+    if let Some(level_db) = update.level_db {
+        state.mixer.set_output_level(id, level_db);
+    }
+    if let Some(mute) = update.mute {
+        state.mixer.set_output_mute(id, mute);
+    }
+    if let Some(follow_main) = update.follow_main {
+        state.mixer.set_output_follow_main(id, follow_main);
+    }
+    
+    match state.mixer.get_matrix_output(id) {
+        Some(output) => (StatusCode::OK, Json(ApiResponse::ok(output))),
+        None => (StatusCode::NOT_FOUND, Json(ApiResponse::err(format!("Ausgang {} nicht gefunden", id)))),
+    }
+}
+
+/// Bus-Send auf Matrix-Ausgang setzen
+#[derive(serde::Deserialize)]
+pub struct OutputSendRequest {
+    pub source: MatrixSource,
+    pub level: f32,
+}
+
+async fn set_output_send(
+    State(state): State<AppState>,
+    Path(id): Path<u32>,
+    Json(req): Json<OutputSendRequest>,
+) -> Json<ApiResponse<MatrixOutputState>> {
+    match state.mixer.set_output_send(id, req.source, req.level) {
+        Some(output) => Json(ApiResponse::ok(output)),
+        None => Json(ApiResponse::err(format!("Ungültiger Send {:?} auf Ausgang {}", req.source, id))),
+    }
+}
+
+/// EQ-Band eines Matrix-Ausgangs setzen
+async fn set_output_eq_band(
+    State(state): State<AppState>,
+    Path((id, band)): Path<(u32, usize)>,
+    Json(params): Json<EqBandParams>,
+) -> Json<ApiResponse<MatrixOutputState>> {
+    match state.mixer.set_output_eq_band(id, band, params) {
+        Some(output) => Json(ApiResponse::ok(output)),
+        None => Json(ApiResponse::err(format!("EQ-Band {} auf Ausgang {} nicht gefunden", band, id))),
+    }
+}
+
+/// Lautsprecher-Processing eines Ausgangs setzen
+async fn set_output_processing(
+    State(state): State<AppState>,
+    Path(id): Path<u32>,
+    Json(params): Json<OutputProcessingParams>,
+) -> Json<ApiResponse<MatrixOutputState>> {
+    match state.mixer.set_output_processing(id, params) {
+        Some(output) => Json(ApiResponse::ok(output)),
+        None => Json(ApiResponse::err(format!("Ausgang {} nicht gefunden", id))),
+    }
+}
+
 /// Szenen abrufen
 async fn get_scenes(State(state): State<AppState>) -> Json<ApiResponse<Vec<SceneMetadata>>> {
     let manager = state.scene_manager.read().await;
@@ -506,8 +714,30 @@ pub struct Aes67Status {
     pub enabled: bool,
     pub ptp_synchronized: bool,
     pub ptp_offset_ns: i64,
+    /// PTP-Details (Grandmaster, Path Delay, Servo), `None` ohne PTP
+    pub ptp: Option<PtpStats>,
     pub our_stream: Option<Aes67StreamInfo>,
     pub subscribed_streams: Vec<String>,
+    /// Gespeicherte Subscriptions, deren Stream noch nicht angekündigt wurde
+    pub pending_subscriptions: Vec<SavedSubscription>,
+    pub clock_drift_ppm: f64,
+    pub src_correction_ppm: f64,
+    pub buffer_fill: u32,
+    pub buffer_target: u32,
+    /// Verlust, Jitter und Round-Trip pro Stream (RTCP)
+    pub stream_quality: Vec<Aes67StreamQuality>,
+    /// Größte gemessene Ende-zu-Ende-Latenz der empfangenen Streams (µs)
+    pub max_latency_us: Option<i64>,
+}
+
+/// RTCP-Empfangsqualität eines gesendeten oder empfangenen Streams
+#[derive(serde::Serialize, Clone)]
+pub struct Aes67StreamQuality {
+    pub id: String,
+    pub name: String,
+    pub direction: String,
+    #[serde(flatten)]
+    pub rtcp: RtcpStats,
 }
 
 /// AES67 Stream Info für API
@@ -534,18 +764,63 @@ async fn get_aes67_status(
     };
     
     let enabled = state.sap_discovery.is_some();
+    let drift = state.drift_monitor.status();
     
     let status = Aes67Status {
         enabled,
         ptp_synchronized: ptp_sync,
         ptp_offset_ns: offset,
-        our_stream: None, // Our output stream info - would need separate tracking
-        subscribed_streams: vec![], // TODO: Track subscribed streams via channel
+        ptp: state.ptp_clock.as_ref().map(|ptp| ptp.stats()),
+        our_stream: state.aes67_tx_streams.as_ref()
+            .and_then(|s| s.infos().into_iter().next())
+            .map(|s| Aes67StreamInfo {
+                id: s.id,
+                name: s.config.name,
+                channels: s.config.channels,
+                sample_rate: s.sample_rate,
+                multicast_addr: s.config.destination.to_string(),
+                port: s.config.port,
+                direction: "Send".to_string(),
+                origin: String::new(),
+            }),
+        subscribed_streams: state.aes67_subscriptions.as_ref()
+            .map(|s| s.stream_ids())
+            .unwrap_or_default(),
+        pending_subscriptions: state.aes67_subscriptions.as_ref()
+            .map(|s| s.pending())
+            .unwrap_or_default(),
+        clock_drift_ppm: drift.drift_ppm,
+        src_correction_ppm: drift.correction_ppm,
+        buffer_fill: drift.buffer_fill,
+        buffer_target: drift.buffer_target,
+        stream_quality: stream_quality(&state),
+        max_latency_us: state.aes67_subscriptions.as_ref().and_then(|s| s.max_latency_us()),
     };
     
     Json(ApiResponse::ok(status))
 }
 
+/// RTCP-Statistik aller gesendeten und empfangenen Streams
+fn stream_quality(state: &AppState) -> Vec<Aes67StreamQuality> {
+    let sent = state.aes67_tx_streams.iter()
+        .flat_map(|streams| streams.infos())
+        .filter_map(|s| Some(Aes67StreamQuality {
+            rtcp: s.rtcp?,
+            id: s.id,
+            name: s.config.name,
+            direction: "Send".to_string(),
+        }));
+    let received = state.aes67_subscriptions.iter()
+        .flat_map(|subscriptions| subscriptions.infos())
+        .filter_map(|s| Some(Aes67StreamQuality {
+            rtcp: s.rtcp?,
+            id: s.stream_id,
+            name: s.stream_name,
+            direction: "Receive".to_string(),
+        }));
+    sent.chain(received).collect()
+}
+
 /// Get discovered AES67 streams
 async fn get_aes67_streams(
     State(state): State<AppState>,
@@ -576,6 +851,9 @@ async fn get_aes67_streams(
 pub struct SubscribeRequest {
     /// Which local input channels to map this stream to (starting channel)
     pub start_channel: Option<u32>,
+    /// Mixer input per stream channel (null = not patched), overrides start_channel
+    #[serde(default)]
+    pub patch: Option<ChannelPatch>,
 }
 
 async fn subscribe_aes67_stream(
@@ -590,7 +868,7 @@ async fn subscribe_aes67_stream(
     };
     
     // Subscribe via command channel
-    match audio_cmd.subscribe_stream(stream_id.clone(), req.start_channel).await {
+    match audio_cmd.subscribe_stream(stream_id.clone(), req.start_channel, req.patch).await {
         Ok(result) => {
             let msg = format!(
                 "✅ Subscribed to '{}' ({} channels) -> Kanal {}",
@@ -650,3 +928,234 @@ async fn refresh_aes67_discovery(
     
     Json(ApiResponse::ok(streams))
 }
+
+/// Get active AES67 subscriptions with their channel patch
+async fn get_aes67_subscriptions(
+    State(state): State<AppState>,
+) -> Json<ApiResponse<Vec<SubscriptionInfo>>> {
+    let subscriptions = state.aes67_subscriptions.as_ref()
+        .map(|s| s.infos())
+        .unwrap_or_default();
+    
+    Json(ApiResponse::ok(subscriptions))
+}
+
+/// Request für Kanal-Patch eines Streams
+#[derive(serde::Deserialize)]
+pub struct PatchRequest {
+    /// Mixer-Eingang je Stream-Kanal (null = nicht verbunden)
+    pub patch: ChannelPatch,
+}
+
+/// Set the channel patch of a subscribed AES67 stream
+async fn set_aes67_patch(
+    State(state): State<AppState>,
+    Path(stream_id): Path<String>,
+    Json(req): Json<PatchRequest>,
+) -> Json<ApiResponse<SubscriptionInfo>> {
+    let Some(ref subscriptions) = state.aes67_subscriptions else {
+        return Json(ApiResponse::err("AES67 nicht aktiv".to_string()));
+    };
+    
+    match subscriptions.set_patch(&stream_id, req.patch) {
+        Ok(info) => Json(ApiResponse::ok(info)),
+        Err(e) => Json(ApiResponse::err(e.to_string())),
+    }
+}
+
+/// Request für die Ausrichtungs-Verzögerung eines Streams
+#[derive(serde::Deserialize)]
+pub struct DelayRequest {
+    /// Zusätzliche Verzögerung zum Link Offset in µs
+    pub delay_us: u32,
+}
+
+/// Delay a subscribed AES67 stream to align it with other streams
+async fn set_aes67_delay(
+    State(state): State<AppState>,
+    Path(stream_id): Path<String>,
+    Json(req): Json<DelayRequest>,
+) -> Json<ApiResponse<SubscriptionInfo>> {
+    let Some(ref subscriptions) = state.aes67_subscriptions else {
+        return Json(ApiResponse::err("AES67 nicht aktiv".to_string()));
+    };
+    
+    match subscriptions.set_delay(&stream_id, req.delay_us) {
+        Ok(info) => Json(ApiResponse::ok(info)),
+        Err(e) => Json(ApiResponse::err(e.to_string())),
+    }
+}
+
+/// Align all subscribed AES67 streams to the same latency
+async fn align_aes67_subscriptions(
+    State(state): State<AppState>,
+) -> Json<ApiResponse<Vec<SubscriptionInfo>>> {
+    let Some(ref subscriptions) = state.aes67_subscriptions else {
+        return Json(ApiResponse::err("AES67 nicht aktiv".to_string()));
+    };
+    
+    Json(ApiResponse::ok(subscriptions.align()))
+}
+
+/// Get outgoing AES67 streams
+async fn get_aes67_tx_streams(
+    State(state): State<AppState>,
+) -> Json<ApiResponse<Vec<TxStreamInfo>>> {
+    let streams = state.aes67_tx_streams.as_ref()
+        .map(|s| s.infos())
+        .unwrap_or_default();
+    
+    Json(ApiResponse::ok(streams))
+}
+
+/// Start sending a new AES67 stream (announced via SAP)
+async fn add_aes67_tx_stream(
+    State(state): State<AppState>,
+    Json(req): Json<TxStreamConfig>,
+) -> Json<ApiResponse<TxStreamInfo>> {
+    let Some(streams) = state.aes67_tx_streams.clone() else {
+        return Json(ApiResponse::err("AES67 nicht aktiv".to_string()));
+    };
+    
+    // Socket-Setup und SAP-Ankündigung blockieren kurz
+    match tokio::task::spawn_blocking(move || streams.add(req)).await {
+        Ok(Ok(info)) => Json(ApiResponse::ok(info)),
+        Ok(Err(e)) => Json(ApiResponse::err(e.to_string())),
+        Err(e) => Json(ApiResponse::err(e.to_string())),
+    }
+}
+
+/// Stop an outgoing AES67 stream and withdraw its announcement
+async fn remove_aes67_tx_stream(
+    State(state): State<AppState>,
+    Path(stream_id): Path<String>,
+) -> Json<ApiResponse<String>> {
+    let Some(streams) = state.aes67_tx_streams.clone() else {
+        return Json(ApiResponse::err("AES67 nicht aktiv".to_string()));
+    };
+    
+    let id = stream_id.clone();
+    match tokio::task::spawn_blocking(move || streams.remove(&id)).await {
+        Ok(Ok(())) => Json(ApiResponse::ok(format!("Stream '{}' entfernt", stream_id))),
+        Ok(Err(e)) => Json(ApiResponse::err(e.to_string())),
+        Err(e) => Json(ApiResponse::err(e.to_string())),
+    }
+}
+
+/// Get all running network audio backends with their devices
+async fn get_network_backends(
+    State(state): State<AppState>,
+) -> Json<ApiResponse<Vec<BackendInfo>>> {
+    let backends = state.network_backends.clone();
+    // Discovery kann kurz auf Backend-Locks warten
+    match tokio::task::spawn_blocking(move || backends.infos()).await {
+        Ok(infos) => Json(ApiResponse::ok(infos)),
+        Err(e) => Json(ApiResponse::err(e.to_string())),
+    }
+}
+
+/// Get the devices of one network audio backend
+async fn get_network_devices(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Json<ApiResponse<Vec<NetworkDevice>>> {
+    let Some((_, backend)) = state.network_backends.get(&name) else {
+        return Json(ApiResponse::err(format!("Backend '{}' nicht aktiv", name)));
+    };
+    
+    match tokio::task::spawn_blocking(move || backend.read().discover()).await {
+        Ok(Ok(devices)) => Json(ApiResponse::ok(devices)),
+        Ok(Err(e)) => Json(ApiResponse::err(e.to_string())),
+        Err(e) => Json(ApiResponse::err(e.to_string())),
+    }
+}
+
+/// Connect a device of a network audio backend
+async fn connect_network_device(
+    State(state): State<AppState>,
+    Path((name, device_id)): Path<(String, String)>,
+) -> Json<ApiResponse<BackendInfo>> {
+    let Some((id, backend)) = state.network_backends.get(&name) else {
+        return Json(ApiResponse::err(format!("Backend '{}' nicht aktiv", name)));
+    };
+    
+    // Verbinden öffnet Sockets und startet Empfänger
+    let connected = tokio::task::spawn_blocking(move || {
+        let mut backend = backend.write();
+        let device = backend.discover()?
+            .into_iter()
+            .find(|d| d.id == device_id)
+            .ok_or_else(|| anyhow::anyhow!("Gerät '{}' nicht gefunden", device_id))?;
+        backend.connect(&device)?;
+        info!("🔌 Network device connected: {} ({})", device.name, backend.name());
+        Ok::<_, anyhow::Error>(BackendInfo::of(id, backend.as_ref()))
+    }).await;
+    match connected {
+        Ok(Ok(info)) => Json(ApiResponse::ok(info)),
+        Ok(Err(e)) => Json(ApiResponse::err(e.to_string())),
+        Err(e) => Json(ApiResponse::err(e.to_string())),
+    }
+}
+
+/// Disconnect all devices of a network audio backend
+async fn disconnect_network_backend(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Json<ApiResponse<BackendInfo>> {
+    let Some((id, backend)) = state.network_backends.get(&name) else {
+        return Json(ApiResponse::err(format!("Backend '{}' nicht aktiv", name)));
+    };
+    
+    let disconnected = tokio::task::spawn_blocking(move || {
+        let mut backend = backend.write();
+        backend.disconnect()?;
+        Ok::<_, anyhow::Error>(BackendInfo::of(id, backend.as_ref()))
+    }).await;
+    match disconnected {
+        Ok(Ok(info)) => Json(ApiResponse::ok(info)),
+        Ok(Err(e)) => Json(ApiResponse::err(e.to_string())),
+        Err(e) => Json(ApiResponse::err(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+
+    fn test_state(mixer: Arc<Mixer>) -> AppState {
+        let scenes = tempfile::TempDir::new().unwrap();
+        let (broadcast_tx, _) = broadcast::channel(16);
+        AppState {
+            mixer,
+            config: ServerConfig::default().api,
+            scene_manager: Arc::new(RwLock::new(SceneManager::new(scenes.path().to_str().unwrap()))),
+            master: Arc::new(MasterSection::new()),
+            sap_discovery: None,
+            ptp_clock: None,
+            aes67_subscriptions: None,
+            aes67_tx_streams: None,
+            network_backends: NetworkBackends::default(),
+            nmos: None,
+            audio_cmd: None,
+            drift_monitor: Arc::new(DriftMonitor::new()),
+            broadcast_tx,
+            client_count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_unknown_output() {
+        let mixer = Arc::new(Mixer::new(4, 2));
+        let update = || OutputUpdate { name: Some("Delay".to_string()), level_db: Some(-6.0), mute: Some(true), follow_main: None };
+
+        let (status, Json(response)) = update_output(State(test_state(mixer.clone())), Path(2), Json(update())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(!response.success);
+
+        let (status, Json(response)) = update_output(State(test_state(mixer.clone())), Path(1), Json(update())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(response.data.is_some_and(|output| output.mute && output.name == "Delay"));
+        assert!(!mixer.get_matrix_output(0).unwrap().mute);
+    }
+}
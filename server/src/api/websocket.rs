@@ -15,6 +15,7 @@ use audiomultiverse_protocol::{
     Aes67Status, Aes67StreamInfo,
 };
 use super::routes::AppState;
+use crate::network_audio::{Aes67Stream, Aes67Subscriptions, HealthEvent, SapDiscovery, SapEvent};
 
 /// WebSocket Verbindung handhaben mit Multi-Client-Support
 pub async fn handle_websocket(socket: WebSocket, state: AppState) {
@@ -36,7 +37,7 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
             version: env!("CARGO_PKG_VERSION").to_string(),
             input_count: state.mixer.input_count as u32,
             output_count: state.mixer.output_count as u32,
-            sample_rate: 48000,
+            sample_rate: state.mixer.sample_rate,
             client_count: client_num as u32,
             audio_backend: "aes67".to_string(),
         },
@@ -210,7 +211,7 @@ async fn handle_client_message(
                 version: env!("CARGO_PKG_VERSION").to_string(),
                 input_count: state.mixer.input_count as u32,
                 output_count: state.mixer.output_count as u32,
-                sample_rate: 48000,
+                sample_rate: state.mixer.sample_rate,
                 client_count: state.client_count.load(Ordering::Relaxed) as u32,
                 audio_backend: "aes67".to_string(),
             })), false)
@@ -281,12 +282,31 @@ async fn handle_client_message(
                 (false, 0)
             };
             
+            let drift = state.drift_monitor.status();
+            
             (Some(ServerMessage::Aes67Status(Aes67Status {
                 enabled: state.sap_discovery.is_some(),
                 ptp_synchronized: ptp_sync,
                 ptp_offset_ns: offset,
-                our_stream: None,
-                subscribed_streams: vec![],
+                our_stream: state.aes67_tx_streams.as_ref()
+                    .and_then(|s| s.infos().into_iter().next())
+                    .map(|s| Aes67StreamInfo {
+                        id: s.id,
+                        name: s.config.name,
+                        channels: s.config.channels,
+                        sample_rate: s.sample_rate,
+                        multicast_addr: s.config.destination.to_string(),
+                        port: s.config.port,
+                        direction: "Send".to_string(),
+                        origin: String::new(),
+                    }),
+                subscribed_streams: state.aes67_subscriptions.as_ref()
+                    .map(|s| s.stream_ids())
+                    .unwrap_or_default(),
+                clock_drift_ppm: drift.drift_ppm,
+                src_correction_ppm: drift.correction_ppm,
+                buffer_fill: drift.buffer_fill,
+                buffer_target: drift.buffer_target,
             })), false)
         }
         
@@ -294,16 +314,7 @@ async fn handle_client_message(
             let streams = if let Some(ref sap) = state.sap_discovery {
                 sap.streams()
                     .into_iter()
-                    .map(|s| Aes67StreamInfo {
-                        id: s.session_id.clone(),
-                        name: s.name.clone(),
-                        channels: s.channels,
-                        sample_rate: s.sample_rate,
-                        multicast_addr: s.multicast_addr.to_string(),
-                        port: s.port,
-                        direction: format!("{:?}", s.direction),
-                        origin: s.origin.clone(),
-                    })
+                    .map(|s| aes67_stream_info(&s))
                     .collect()
             } else {
                 vec![]
@@ -312,7 +323,7 @@ async fn handle_client_message(
             (Some(ServerMessage::Aes67Streams(streams)), false)
         }
         
-        ClientMessage::SubscribeAes67Stream { stream_id, start_channel } => {
+        ClientMessage::SubscribeAes67Stream { stream_id, start_channel, patch } => {
             let audio_cmd = match &state.audio_cmd {
                 Some(cmd) => cmd.clone(),
                 None => return (Some(ServerMessage::Error {
@@ -322,7 +333,8 @@ async fn handle_client_message(
             };
             
             // Subscribe async - AES67 Subscriptions MÜSSEN gebroadcastet werden
-            match audio_cmd.subscribe_stream(stream_id.clone(), start_channel).await {
+            let patch = patch.map(|p| p.into_iter().map(|input| input.map(|i| i as usize)).collect());
+            match audio_cmd.subscribe_stream(stream_id.clone(), start_channel, patch).await {
                 Ok(result) => {
                     info!("🔊 Client {} subscribed to '{}' ({} ch) -> Kanal {}", 
                           &client_id[..8], result.stream_name, result.channels, result.start_channel);
@@ -365,16 +377,7 @@ async fn handle_client_message(
             let streams = if let Some(ref sap) = state.sap_discovery {
                 sap.streams()
                     .into_iter()
-                    .map(|s| Aes67StreamInfo {
-                        id: s.session_id.clone(),
-                        name: s.name.clone(),
-                        channels: s.channels,
-                        sample_rate: s.sample_rate,
-                        multicast_addr: s.multicast_addr.to_string(),
-                        port: s.port,
-                        direction: format!("{:?}", s.direction),
-                        origin: s.origin.clone(),
-                    })
+                    .map(|s| aes67_stream_info(&s))
                     .collect()
             } else {
                 vec![]
@@ -393,3 +396,67 @@ async fn handle_client_message(
         }
     }
 }
+
+/// AES67 Stream für Clients
+fn aes67_stream_info(stream: &Aes67Stream) -> Aes67StreamInfo {
+    Aes67StreamInfo {
+        id: stream.session_id.clone(),
+        name: stream.name.clone(),
+        channels: stream.channels,
+        sample_rate: stream.sample_rate,
+        multicast_addr: stream.multicast_addr.to_string(),
+        port: stream.port,
+        direction: format!("{:?}", stream.direction),
+        origin: stream.origin.clone(),
+    }
+}
+
+/// SAP-Änderungen (neue, geänderte, entfernte Streams) an alle Clients senden
+pub fn forward_sap_events(sap: &SapDiscovery, broadcast_tx: broadcast::Sender<ServerMessage>) {
+    let mut events = sap.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let msg = match events.recv().await {
+                Ok(SapEvent::Discovered(stream)) | Ok(SapEvent::Updated(stream)) => {
+                    ServerMessage::Aes67StreamAnnounced(aes67_stream_info(&stream))
+                }
+                Ok(SapEvent::Removed { session_id, .. }) => {
+                    ServerMessage::Aes67StreamRemoved { stream_id: session_id }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("{} SAP-Änderungen verpasst", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            // Ohne verbundene Clients gibt es keine Empfänger
+            let _ = broadcast_tx.send(msg);
+        }
+    });
+}
+
+/// Stream-Alarme (Ausfall, Erholung, automatisches Neu-Abonnieren) an alle Clients senden
+pub fn forward_health_events(subscriptions: &Aes67Subscriptions, broadcast_tx: broadcast::Sender<ServerMessage>) {
+    let mut events = subscriptions.subscribe_events();
+    tokio::spawn(async move {
+        loop {
+            let msg = match events.recv().await {
+                Ok(HealthEvent::Stalled { stream_id, name }) => {
+                    ServerMessage::Aes67StreamStalled { stream_id, stream_name: name }
+                }
+                Ok(HealthEvent::Recovered { stream_id, name }) => {
+                    ServerMessage::Aes67StreamRecovered { stream_id, stream_name: name }
+                }
+                Ok(HealthEvent::Resubscribed { previous_id, stream_id, name }) => {
+                    ServerMessage::Aes67StreamResubscribed { previous_id, stream_id, stream_name: name }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("{} Stream-Alarme verpasst", n);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let _ = broadcast_tx.send(msg);
+        }
+    });
+}
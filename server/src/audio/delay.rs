@@ -0,0 +1,107 @@
+//! Delay-Modul
+//!
+//! Einfache Sample-Delay-Line (z.B. für Ausgangs-/Laufzeitverzögerung)
+
+/// Mono Delay-Line mit fester Maximal-Länge
+///
+/// Der Puffer wird beim Erstellen allokiert, `process` allokiert nicht
+/// und kann im Audio-Thread verwendet werden.
+#[derive(Debug, Clone)]
+pub struct DelayLine {
+    /// Ringpuffer
+    buffer: Vec<f32>,
+
+    /// Schreibposition
+    write_pos: usize,
+
+    /// Aktuelle Verzögerung in Samples
+    delay_samples: usize,
+}
+
+impl DelayLine {
+    /// Neue Delay-Line mit maximaler Verzögerung in Samples
+    pub fn new(max_delay_samples: usize) -> Self {
+        Self {
+            buffer: vec![0.0; max_delay_samples + 1],
+            write_pos: 0,
+            delay_samples: 0,
+        }
+    }
+
+    /// Neue Delay-Line mit maximaler Verzögerung in Millisekunden
+    pub fn with_max_ms(max_delay_ms: f32, sample_rate: f32) -> Self {
+        Self::new(ms_to_samples(max_delay_ms, sample_rate))
+    }
+
+    /// Maximale Verzögerung in Samples
+    pub fn max_delay(&self) -> usize {
+        self.buffer.len() - 1
+    }
+
+    /// Verzögerung in Samples setzen (wird auf Maximum begrenzt)
+    pub fn set_delay(&mut self, samples: usize) {
+        self.delay_samples = samples.min(self.max_delay());
+    }
+
+    /// Verzögerung in Millisekunden setzen
+    pub fn set_delay_ms(&mut self, delay_ms: f32, sample_rate: f32) {
+        self.set_delay(ms_to_samples(delay_ms, sample_rate));
+    }
+
+    /// Aktuelle Verzögerung in Samples
+    pub fn delay(&self) -> usize {
+        self.delay_samples
+    }
+
+    /// Ein Sample verarbeiten
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[self.write_pos] = sample;
+
+        let read_pos = (self.write_pos + len - self.delay_samples) % len;
+        let output = self.buffer[read_pos];
+
+        self.write_pos = (self.write_pos + 1) % len;
+        output
+    }
+
+    /// Puffer leeren
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|s| *s = 0.0);
+        self.write_pos = 0;
+    }
+}
+
+/// Millisekunden in Samples umrechnen
+pub fn ms_to_samples(ms: f32, sample_rate: f32) -> usize {
+    (ms.max(0.0) * sample_rate / 1000.0).round() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_delay_passthrough() {
+        let mut delay = DelayLine::new(16);
+        assert_eq!(delay.process(0.5), 0.5);
+        assert_eq!(delay.process(-0.25), -0.25);
+    }
+
+    #[test]
+    fn test_delay_samples() {
+        let mut delay = DelayLine::new(16);
+        delay.set_delay(3);
+
+        let input = [1.0, 0.0, 0.0, 0.0, 0.0];
+        let output: Vec<f32> = input.iter().map(|&s| delay.process(s)).collect();
+        assert_eq!(output, vec![0.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_delay_clamped_to_max() {
+        let mut delay = DelayLine::with_max_ms(10.0, 48000.0);
+        delay.set_delay_ms(50.0, 48000.0);
+        assert_eq!(delay.delay(), 480);
+    }
+}
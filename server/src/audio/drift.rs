@@ -0,0 +1,430 @@
+//! Clock-Drift-Kompensation
+//!
+//! Die lokale Soundkarte und die AES67/PTP-Domäne laufen mit unabhängigen
+//! Clocks. Der `DriftEstimator` misst die Abweichung der Geräte-Clock gegenüber
+//! der PTP Media Clock (lineare Regression über ein gleitendes Fenster), der
+//! `DriftController` führt daraus und aus dem Pufferfüllstand das Verhältnis
+//! des Resamplers nach (PI-Regler), damit Puffer weder über- noch leerlaufen.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+
+/// Länge des Messfensters in Sekunden
+const WINDOW_SECONDS: f64 = 10.0;
+
+/// Mindestabstand zwischen zwei Messpunkten in Sekunden
+const MIN_POINT_INTERVAL: f64 = 0.1;
+
+/// Mindestdauer des Fensters bevor ein Wert geliefert wird (Sekunden)
+const MIN_MEASUREMENT_SECONDS: f64 = 1.0;
+
+/// Glättung des Ergebnisses (0..1, größer = träger)
+const SMOOTHING: f64 = 0.9;
+
+/// Proportional-Anteil (ppm pro Frame Abweichung)
+const KP_PPM_PER_FRAME: f64 = 4.0;
+
+/// Integral-Anteil (ppm pro Frame und Aufruf)
+const KI_PPM_PER_FRAME: f64 = 0.0002;
+
+/// Glättung des Füllstands (Pakete kommen in Schüben an)
+const FILL_SMOOTHING: f64 = 0.01;
+
+/// Maximale Korrektur in ppm
+const MAX_CORRECTION_PPM: f64 = 500.0;
+
+/// Drift-Status (für API/UI)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DriftStatus {
+    /// Messung aktiv
+    pub active: bool,
+
+    /// Gemessene Drift der Geräte-Clock gegenüber PTP in ppm
+    /// (positiv = Soundkarte läuft schneller)
+    pub drift_ppm: f64,
+
+    /// Aktuelle Korrektur des Resamplers in ppm
+    pub correction_ppm: f64,
+
+    /// Pufferfüllstand in Frames
+    pub buffer_fill: u32,
+
+    /// Ziel-Füllstand in Frames
+    pub buffer_target: u32,
+}
+
+/// Thread-sicherer Drift-Monitor
+///
+/// Wird vom Audio-Callback (Frame-Zähler), vom Engine-Thread (Messung) und
+/// von den Empfängern (Füllstand, Korrektur) gemeinsam genutzt.
+#[derive(Debug)]
+pub struct DriftMonitor {
+    /// Vom Gerät verarbeitete Frames seit Start
+    device_frames: AtomicU64,
+
+    /// Frame-Zähler zu Beginn des letzten Callbacks
+    callback_frames: AtomicU64,
+
+    /// Systemzeit (ns seit Epoch) des letzten Callbacks
+    callback_time_ns: AtomicU64,
+
+    /// Sequenz-Zähler für konsistentes Lesen von Zählerstand und Zeit
+    /// (ungerade = Schreibvorgang läuft)
+    callback_seq: AtomicU64,
+
+    /// Messung aktiv
+    active: AtomicBool,
+
+    /// Drift in ppm (f64 bits)
+    drift_ppm_bits: AtomicU64,
+
+    /// Korrektur in ppm (f64 bits)
+    correction_ppm_bits: AtomicU64,
+
+    /// Pufferfüllstand in Frames
+    buffer_fill: AtomicU32,
+
+    /// Ziel-Füllstand in Frames
+    buffer_target: AtomicU32,
+}
+
+impl DriftMonitor {
+    /// Neuen Monitor erstellen
+    pub fn new() -> Self {
+        Self {
+            device_frames: AtomicU64::new(0),
+            callback_frames: AtomicU64::new(0),
+            callback_time_ns: AtomicU64::new(0),
+            callback_seq: AtomicU64::new(0),
+            active: AtomicBool::new(false),
+            drift_ppm_bits: AtomicU64::new(0.0f64.to_bits()),
+            correction_ppm_bits: AtomicU64::new(0.0f64.to_bits()),
+            buffer_fill: AtomicU32::new(0),
+            buffer_target: AtomicU32::new(0),
+        }
+    }
+
+    /// Vom Audio-Callback aufrufen: Frames des Blocks zählen
+    ///
+    /// Gespeichert wird der Zählerstand zu Beginn des Blocks zusammen mit der
+    /// Systemzeit, damit die Messung unabhängig vom Zeitpunkt der Auswertung ist.
+    pub fn on_device_block(&self, frames: usize) {
+        let now = system_time_ns();
+        let start = self.device_frames.fetch_add(frames as u64, Ordering::Relaxed);
+
+        // Seqlock (nur ein Schreiber: der Audio-Callback)
+        let seq = self.callback_seq.load(Ordering::Relaxed);
+        self.callback_seq.store(seq + 1, Ordering::Relaxed);
+        std::sync::atomic::fence(Ordering::Release);
+        self.callback_frames.store(start, Ordering::Relaxed);
+        self.callback_time_ns.store(now, Ordering::Relaxed);
+        self.callback_seq.store(seq + 2, Ordering::Release);
+    }
+
+    /// Letzter Callback: (Frames bis Blockbeginn, Systemzeit in ns)
+    pub fn last_callback(&self) -> Option<(u64, u64)> {
+        loop {
+            let seq = self.callback_seq.load(Ordering::Acquire);
+            if seq % 2 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+
+            let frames = self.callback_frames.load(Ordering::Relaxed);
+            let time = self.callback_time_ns.load(Ordering::Relaxed);
+            std::sync::atomic::fence(Ordering::Acquire);
+
+            if self.callback_seq.load(Ordering::Relaxed) == seq {
+                return (time != 0).then_some((frames, time));
+            }
+        }
+    }
+
+    /// Gemessene Drift in ppm
+    pub fn drift_ppm(&self) -> f64 {
+        f64::from_bits(self.drift_ppm_bits.load(Ordering::Relaxed))
+    }
+
+    /// Gemessene Drift setzen
+    pub fn set_drift_ppm(&self, ppm: f64) {
+        self.drift_ppm_bits.store(ppm.to_bits(), Ordering::Relaxed);
+        self.active.store(true, Ordering::Relaxed);
+    }
+
+    /// Korrektur und Füllstand eines Empfängers melden
+    pub fn report_buffer(&self, fill: usize, target: usize, correction_ppm: f64) {
+        self.buffer_fill.store(fill as u32, Ordering::Relaxed);
+        self.buffer_target.store(target as u32, Ordering::Relaxed);
+        self.correction_ppm_bits.store(correction_ppm.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Messung zurücksetzen (z.B. nach Neustart der Engine)
+    pub fn reset(&self) {
+        self.active.store(false, Ordering::Relaxed);
+        self.drift_ppm_bits.store(0.0f64.to_bits(), Ordering::Relaxed);
+        self.correction_ppm_bits.store(0.0f64.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Status abrufen
+    pub fn status(&self) -> DriftStatus {
+        DriftStatus {
+            active: self.active.load(Ordering::Relaxed),
+            drift_ppm: self.drift_ppm(),
+            correction_ppm: f64::from_bits(self.correction_ppm_bits.load(Ordering::Relaxed)),
+            buffer_fill: self.buffer_fill.load(Ordering::Relaxed),
+            buffer_target: self.buffer_target.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for DriftMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Systemzeit in Nanosekunden seit Epoch
+pub fn system_time_ns() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// Drift-Messung Geräte-Clock gegen Media Clock
+#[derive(Debug, Clone)]
+pub struct DriftEstimator {
+    /// Sample Rate beider Clocks
+    sample_rate: f64,
+
+    /// Messpunkte (Media-Frames, Geräte-Frames), beide relativ zum ersten Punkt
+    points: VecDeque<(f64, f64)>,
+
+    /// Erster Punkt (Geräte-Frames, Media-Timestamp)
+    origin: Option<(u64, u32)>,
+
+    /// Entpackte Media-Zeit des letzten Punkts
+    last_media: Option<(u32, f64)>,
+
+    /// Geglättete Drift in ppm
+    smoothed_ppm: Option<f64>,
+}
+
+impl DriftEstimator {
+    /// Neuen Estimator erstellen
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as f64,
+            points: VecDeque::new(),
+            origin: None,
+            last_media: None,
+            smoothed_ppm: None,
+        }
+    }
+
+    /// Messpunkt hinzufügen
+    ///
+    /// `device_frames` ist der Frame-Zähler der Soundkarte, `media_timestamp`
+    /// der RTP Media-Timestamp (`PtpClock::media_timestamp`) zum selben Zeitpunkt.
+    /// Gibt die aktuelle Drift in ppm zurück, sobald genug Daten vorliegen.
+    pub fn update(&mut self, device_frames: u64, media_timestamp: u32) -> Option<f64> {
+        let (origin_frames, _) = *self.origin.get_or_insert((device_frames, media_timestamp));
+
+        // 32-Bit Media-Timestamp entpacken
+        let media = match self.last_media {
+            Some((last_ts, last)) => last + media_timestamp.wrapping_sub(last_ts) as i32 as f64,
+            None => 0.0,
+        };
+
+        if let Some(&(last_media, _)) = self.points.back() {
+            if media - last_media < MIN_POINT_INTERVAL * self.sample_rate {
+                return self.smoothed_ppm;
+            }
+        }
+
+        self.last_media = Some((media_timestamp, media));
+        let device = device_frames.saturating_sub(origin_frames) as f64;
+        self.points.push_back((media, device));
+
+        while let Some(&(first, _)) = self.points.front() {
+            if media - first > WINDOW_SECONDS * self.sample_rate {
+                self.points.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let span = media - self.points.front().map(|p| p.0).unwrap_or(media);
+        if span < MIN_MEASUREMENT_SECONDS * self.sample_rate || self.points.len() < 3 {
+            return None;
+        }
+
+        let ppm = (regression_slope(&self.points)? - 1.0) * 1e6;
+        let smoothed = match self.smoothed_ppm {
+            Some(prev) => prev * SMOOTHING + ppm * (1.0 - SMOOTHING),
+            None => ppm,
+        };
+        self.smoothed_ppm = Some(smoothed);
+        Some(smoothed)
+    }
+
+    /// Messung zurücksetzen
+    pub fn reset(&mut self) {
+        self.points.clear();
+        self.origin = None;
+        self.last_media = None;
+        self.smoothed_ppm = None;
+    }
+}
+
+/// Steigung der Ausgleichsgeraden y = a + b*x
+fn regression_slope(points: &VecDeque<(f64, f64)>) -> Option<f64> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for &(x, y) in points {
+        sxy += (x - mean_x) * (y - mean_y);
+        sxx += (x - mean_x) * (x - mean_x);
+    }
+
+    if sxx <= 0.0 {
+        None
+    } else {
+        Some(sxy / sxx)
+    }
+}
+
+/// PI-Regler für das Resampling-Verhältnis eines Empfangspuffers
+#[derive(Debug, Clone)]
+pub struct DriftController {
+    /// Ziel-Füllstand in Frames
+    target_fill: f64,
+
+    /// Geglätteter Füllstand in Frames
+    smoothed_fill: Option<f64>,
+
+    /// Integrator in ppm
+    integral: f64,
+}
+
+impl DriftController {
+    /// Neuen Regler mit Ziel-Füllstand erstellen
+    pub fn new(target_fill: usize) -> Self {
+        Self {
+            target_fill: target_fill as f64,
+            smoothed_fill: None,
+            integral: 0.0,
+        }
+    }
+
+    /// Ziel-Füllstand in Frames
+    pub fn target_fill(&self) -> usize {
+        self.target_fill as usize
+    }
+
+    /// Korrektur für den Resampler in ppm berechnen
+    ///
+    /// `drift_ppm` ist die gemessene Drift der Geräte-Clock (Vorsteuerung),
+    /// `fill` der aktuelle Füllstand. Ist der Puffer zu voll, wird schneller
+    /// gelesen (positive Korrektur), ist er zu leer, langsamer.
+    pub fn update(&mut self, drift_ppm: f64, fill: usize) -> f64 {
+        let fill = match self.smoothed_fill {
+            Some(prev) => prev + (fill as f64 - prev) * FILL_SMOOTHING,
+            None => fill as f64,
+        };
+        self.smoothed_fill = Some(fill);
+        let error = fill - self.target_fill;
+
+        self.integral = (self.integral + error * KI_PPM_PER_FRAME)
+            .clamp(-MAX_CORRECTION_PPM, MAX_CORRECTION_PPM);
+
+        // Läuft die Soundkarte schneller, liefert die Quelle pro Ausgangs-Sample
+        // weniger Frames: Verhältnis entsprechend verkleinern
+        let feed_forward = -drift_ppm;
+
+        (feed_forward + error * KP_PPM_PER_FRAME + self.integral)
+            .clamp(-MAX_CORRECTION_PPM, MAX_CORRECTION_PPM)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::Resampler;
+
+    #[test]
+    fn test_estimator_measures_drift() {
+        let mut estimator = DriftEstimator::new(48000);
+
+        // Soundkarte läuft 50 ppm schneller als die Media Clock,
+        // Start kurz vor dem 32-Bit Überlauf des Timestamps
+        let start_ts = u32::MAX - 48000;
+        let mut result = None;
+        for step in 0..200u64 {
+            let media = step * 4800; // alle 100ms
+            let device = (media as f64 * 1.000_05) as u64 + 1_000;
+            result = estimator.update(device, start_ts.wrapping_add(media as u32));
+        }
+
+        let ppm = result.unwrap();
+        assert!((ppm - 50.0).abs() < 1.0, "ppm = {}", ppm);
+    }
+
+    #[test]
+    fn test_estimator_needs_min_duration() {
+        let mut estimator = DriftEstimator::new(48000);
+        assert!(estimator.update(0, 0).is_none());
+        assert!(estimator.update(4800, 4800).is_none());
+    }
+
+    #[test]
+    fn test_controller_direction() {
+        assert!(DriftController::new(480).update(0.0, 960) > 0.0);
+        assert!(DriftController::new(480).update(0.0, 0) < 0.0);
+        assert!(DriftController::new(480).update(100.0, 480) < 0.0);
+    }
+
+    #[test]
+    fn test_controller_keeps_buffer_stable() {
+        // Quelle liefert 48000 Hz, Gerät liest mit 48000 * (1 + 100ppm),
+        // die gemessene Drift dient als Vorsteuerung
+        let source_rate = 48000.0;
+        let device_rate = 48000.0 * 1.0001;
+        let block = 48;
+        let target = 480;
+
+        let mut resampler = Resampler::new(1, 48000, 48000);
+        let mut controller = DriftController::new(target);
+        let mut buffer: VecDeque<f32> = (0..target).map(|_| 0.0).collect();
+        let mut produced_source = 0.0;
+        let mut out = vec![0.0; block];
+        let mut fill = target;
+        let mut correction = 0.0;
+
+        // 20 Sekunden simulieren
+        let blocks = (20.0 * device_rate / block as f64) as usize;
+        for i in 0..blocks {
+            // Quelle bis zum aktuellen Zeitpunkt auffüllen
+            let t = (i * block) as f64 / device_rate;
+            while produced_source < t * source_rate {
+                buffer.push_back(0.0);
+                produced_source += 1.0;
+            }
+
+            fill = buffer.len() + resampler.buffered_frames() as usize;
+            correction = controller.update(100.0, fill);
+            resampler.set_ratio_adjust_ppm(correction);
+
+            let needed = resampler.input_frames_needed(block);
+            let input: Vec<f32> = buffer.drain(..needed.min(buffer.len())).collect();
+            resampler.push(&input);
+            resampler.pull(&mut out);
+        }
+
+        assert!((fill as i64 - target as i64).abs() < 48, "fill = {}", fill);
+        assert!((correction + 100.0).abs() < 20.0);
+    }
+}
@@ -10,8 +10,13 @@ use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, Host, Stream, StreamConfig, SampleFormat};
 use tokio::sync::mpsc;
 
+use super::resampler::Resampler;
+use super::host;
+use crate::config::{AudioConfig, PortConnection};
+use super::drift::{DriftMonitor, DriftEstimator, system_time_ns};
 use crate::mixer::{Mixer, MasterSection};
-use crate::network_audio::{Aes67Backend, Aes67Config, AudioNetworkBackend, NetworkDevice, SapDiscovery, PtpClock};
+use crate::network_audio::{Aes67Backend, Aes67Subscriptions, AudioNetworkBackend, BackendContext, BackendRegistry, ChannelPatch, NetworkBackends, NetworkDevice, PtpClock, Aes67TxStreams, MediaTicker};
+use crate::network_audio::registry::SharedBackend;
 
 /// Befehle für die Audio Engine (von WebSocket/API)
 #[derive(Debug)]
@@ -20,6 +25,8 @@ pub enum AudioCommand {
     SubscribeStream {
         stream_id: String,
         start_channel: Option<u32>,
+        /// Patch je Stream-Kanal (überschreibt start_channel)
+        patch: Option<ChannelPatch>,
         /// Antwort-Kanal
         response: tokio::sync::oneshot::Sender<Result<Aes67SubscribeResult, String>>,
     },
@@ -37,6 +44,7 @@ pub struct Aes67SubscribeResult {
     pub stream_name: String,
     pub channels: u8,
     pub start_channel: u32,
+    pub patch: ChannelPatch,
 }
 
 /// Audio Device Info
@@ -67,12 +75,30 @@ pub struct AudioEngine {
     output_stream: Option<Stream>,
     mixer: Option<Arc<Mixer>>,
     master: Option<Arc<MasterSection>>,
-    /// AES67 Backend for network audio
-    aes67_backend: Option<Aes67Backend>,
+    /// Mixer-Takt ohne lokales Audiogerät (vor dem Backend beendet)
+    standalone_clock: Option<MediaTicker>,
+    /// Laufende Netzwerk-Audio Backends (AES67, virtuell, ...)
+    network_backends: NetworkBackends,
     /// Current audio source
     audio_source: AudioSource,
     /// Command receiver for async control
     command_rx: Option<mpsc::Receiver<AudioCommand>>,
+    /// Host ist JACK (Port-Verbindungen möglich)
+    host_is_jack: bool,
+    /// Eingabegerät (Name, None = Standard)
+    input_device: Option<String>,
+    /// Ausgabegerät (Name, None = Standard)
+    output_device: Option<String>,
+    /// Kanäle pro Gerät
+    device_channels: u16,
+    /// Port-Verbindungsregeln (JACK)
+    connections: Vec<PortConnection>,
+    /// AES67 Anbindung für den Audio-Callback (Empfang und Sender)
+    network: Arc<parking_lot::Mutex<Option<NetworkBridge>>>,
+    /// Clock-Drift Messung (Frame-Zähler aus dem Output-Callback)
+    drift_monitor: Arc<DriftMonitor>,
+    /// Drift-Schätzung Soundkarte gegenüber PTP Media Clock
+    drift_estimator: DriftEstimator,
 }
 
 /// Handle für Befehle an die AudioEngine (thread-safe, cloneable)
@@ -88,12 +114,13 @@ impl AudioCommandSender {
     }
     
     /// Subscribe to an AES67 stream
-    pub async fn subscribe_stream(&self, stream_id: String, start_channel: Option<u32>) -> Result<Aes67SubscribeResult, String> {
+    pub async fn subscribe_stream(&self, stream_id: String, start_channel: Option<u32>, patch: Option<ChannelPatch>) -> Result<Aes67SubscribeResult, String> {
         let (response_tx, response_rx) = tokio::sync::oneshot::channel();
         
         self.tx.send(AudioCommand::SubscribeStream {
             stream_id,
             start_channel,
+            patch,
             response: response_tx,
         }).await.map_err(|e| format!("Command send error: {}", e))?;
         
@@ -122,14 +149,23 @@ impl AudioEngine {
             host,
             sample_rate,
             buffer_size,
+            host_is_jack: false,
+            input_device: None,
+            output_device: None,
+            device_channels: 2,
+            connections: Vec::new(),
             running: Arc::new(AtomicBool::new(false)),
             input_stream: None,
             output_stream: None,
             mixer: None,
             master: None,
-            aes67_backend: None,
+            standalone_clock: None,
+            network_backends: NetworkBackends::default(),
             audio_source: AudioSource::Local,
             command_rx: None,
+            network: Arc::new(parking_lot::Mutex::new(None)),
+            drift_monitor: Arc::new(DriftMonitor::new()),
+            drift_estimator: DriftEstimator::new(sample_rate),
         }
     }
     
@@ -159,8 +195,8 @@ impl AudioEngine {
         // Jetzt können wir self frei mutieren
         for cmd in commands {
             match cmd {
-                AudioCommand::SubscribeStream { stream_id, start_channel, response } => {
-                    let result = self.handle_subscribe_stream(&stream_id, start_channel);
+                AudioCommand::SubscribeStream { stream_id, start_channel, patch, response } => {
+                    let result = self.handle_subscribe_stream(&stream_id, start_channel, patch);
                     let _ = response.send(result);
                 }
                 AudioCommand::UnsubscribeStream { stream_id, response } => {
@@ -172,10 +208,18 @@ impl AudioEngine {
     }
     
     /// Subscribe to stream (internal)
-    fn handle_subscribe_stream(&mut self, stream_id: &str, start_channel: Option<u32>) -> Result<Aes67SubscribeResult, String> {
-        let backend = self.aes67_backend.as_ref()
-            .ok_or("AES67 not initialized")?;
-        
+    ///
+    /// Weitere Streams werden zusätzlich empfangen. Ohne Patch werden die
+    /// Kanäle ab `start_channel` (sonst ab dem ersten freien Eingang) gelegt.
+    fn handle_subscribe_stream(&mut self, stream_id: &str, start_channel: Option<u32>, patch: Option<ChannelPatch>) -> Result<Aes67SubscribeResult, String> {
+        let result = self.network_backends
+            .with(|backend: &mut Aes67Backend| Self::subscribe_stream(backend, stream_id, start_channel, patch))
+            .ok_or("AES67 not initialized")??;
+        self.audio_source = AudioSource::Aes67;
+        Ok(result)
+    }
+    
+    fn subscribe_stream(backend: &mut Aes67Backend, stream_id: &str, start_channel: Option<u32>, patch: Option<ChannelPatch>) -> Result<Aes67SubscribeResult, String> {
         // Stream in Discovery finden
         let stream = backend.sap_discovery()
             .streams()
@@ -183,39 +227,47 @@ impl AudioEngine {
             .find(|s| s.session_id == stream_id)
             .ok_or_else(|| format!("Stream '{}' not found", stream_id))?;
         
-        let device = NetworkDevice {
-            id: stream.session_id.clone(),
-            name: stream.name.clone(),
-            device_type: crate::network_audio::NetworkDeviceType::Transmitter,
-            channels: stream.channels as u32,
-            sample_rate: stream.sample_rate,
-            ip_address: Some(stream.origin.clone()),
-            multicast_group: Some(stream.multicast_addr.to_string()),
+        let subscriptions = backend.subscriptions();
+        let patch = match patch {
+            Some(patch) => patch,
+            None => {
+                let start = start_channel
+                    .map(|c| c as usize)
+                    .unwrap_or_else(|| subscriptions.next_free_input());
+                subscriptions.consecutive_patch(start, stream.channels)
+                    .map_err(|e| e.to_string())?
+            }
         };
         
-        let start_ch = start_channel.unwrap_or(0);
-        
-        // Connect to stream
-        self.connect_aes67(&device)
+        backend.subscribe(&stream, patch.clone())
             .map_err(|e| format!("Connection failed: {}", e))?;
         
-        info!("🔊 Subscribed to '{}' ({} channels) -> channel {}", 
-              stream.name, stream.channels, start_ch);
+        let start_ch = patch.iter().flatten().min().copied().unwrap_or(0) as u32;
+        info!("🔊 Subscribed to '{}' ({} channels) -> channel {} ({} streams active)", 
+              stream.name, stream.channels, start_ch, subscriptions.len());
         
         Ok(Aes67SubscribeResult {
             stream_id: stream.session_id,
             stream_name: stream.name,
             channels: stream.channels,
             start_channel: start_ch,
+            patch,
         })
     }
     
     /// Unsubscribe from stream (internal)
-    fn handle_unsubscribe_stream(&mut self, _stream_id: &str) -> Result<(), String> {
-        self.disconnect_aes67()
+    fn handle_unsubscribe_stream(&mut self, stream_id: &str) -> Result<(), String> {
+        let connected = self.network_backends
+            .with(|backend: &mut Aes67Backend| {
+                backend.unsubscribe(stream_id).map(|_| backend.is_connected())
+            })
+            .ok_or("AES67 not initialized")?
             .map_err(|e| format!("Disconnect failed: {}", e))?;
+        if !connected {
+            self.audio_source = AudioSource::Local;
+        }
         
-        info!("🔇 Unsubscribed from stream");
+        info!("🔇 Unsubscribed from stream '{}'", stream_id);
         Ok(())
     }
 
@@ -229,15 +281,124 @@ impl AudioEngine {
         self.master = Some(master);
     }
 
-    /// Initialize AES67 backend
-    pub fn init_aes67(&mut self, config: Option<Aes67Config>) -> Result<()> {
-        info!("🌐 Initializing AES67 backend...");
+    /// Backend, Geräte und Port-Verbindungen aus der Konfiguration übernehmen
+    ///
+    /// Muss vor `start()` aufgerufen werden.
+    pub fn configure(&mut self, config: &AudioConfig) -> Result<()> {
+        let selection = host::create_host(config.backend)?;
+        
+        info!("🔊 Audio-Backend: {:?} ({})", config.backend, selection.host.id().name());
+        
+        self.host = selection.host;
+        self.host_is_jack = selection.is_jack;
+        self.input_device = config.input_device.clone().or(selection.default_device.clone());
+        self.output_device = config.output_device.clone().or(selection.default_device);
+        self.device_channels = config.device_channels.max(1);
+        self.connections = config.connections.clone();
+        Ok(())
+    }
+
+    /// Drift-Monitor setzen (wird mit API und Empfängern geteilt)
+    pub fn set_drift_monitor(&mut self, monitor: Arc<DriftMonitor>) {
+        self.drift_monitor = monitor;
+    }
+
+    /// Drift-Monitor abrufen
+    pub fn drift_monitor(&self) -> Arc<DriftMonitor> {
+        self.drift_monitor.clone()
+    }
+
+    /// Clock-Drift zwischen Soundkarte und PTP messen
+    ///
+    /// Wird regelmäßig aus dem Engine-Thread aufgerufen (nicht im Audio-Callback).
+    /// Der Frame-Zähler des letzten Callbacks wird mit dem PTP Media-Timestamp
+    /// zum selben Zeitpunkt verglichen.
+    pub fn update_drift(&mut self) {
+        let Some(ptp) = self.ptp_clock().filter(|p| p.is_synchronized()) else {
+            return;
+        };
+        if !self.is_running() {
+            return;
+        }
+        let Some((frames, callback_ns)) = self.drift_monitor.last_callback() else {
+            return;
+        };
+
+        // Media-Timestamp auf den Zeitpunkt des Callbacks zurückrechnen
+        let now_ns = system_time_ns();
+        let media_now = ptp.media_timestamp(self.sample_rate);
+        let elapsed = now_ns.saturating_sub(callback_ns) as u128 * self.sample_rate as u128 / 1_000_000_000;
+        let media_ts = media_now.wrapping_sub(elapsed as u32);
+
+        if let Some(ppm) = self.drift_estimator.update(frames, media_ts) {
+            self.drift_monitor.set_drift_ppm(ppm);
+        }
+    }
+
+    /// AES67-Subscriptions überwachen
+    ///
+    /// Wird regelmäßig aus dem Engine-Thread aufgerufen. Prüft den Zustand der
+    /// empfangenen Streams und abonniert wieder auftauchende Streams neu
+    /// (auch gespeicherte Subscriptions nach einem Neustart).
+    pub fn supervise_aes67(&mut self) {
+        let connected = self.network_backends.with(|backend: &mut Aes67Backend| {
+            backend.supervise();
+            backend.is_connected()
+        });
+        if connected == Some(true) {
+            self.audio_source = AudioSource::Aes67;
+        }
+    }
+
+    /// Netzwerk-Audio Backends starten
+    ///
+    /// `names` wählt die Backends aus der Registry (z.B. "aes67" und
+    /// "virtual"), alle laufen gleichzeitig am Mixer. Ein unbekannter Name
+    /// ist ein Fehler, ein Backend das nicht startet wird ausgelassen.
+    pub fn init_network(&mut self, registry: &BackendRegistry, names: &[String], mut context: BackendContext) -> Result<()> {
+        info!("🌐 Initializing network audio backends: {}", names.join(", "));
+        
+        let standalone = !self.is_running();
+        if standalone {
+            // Der Mixer-Takt liefert 1 ms Blöcke, entsprechend wenig vorpuffern
+            context.aes67.buffer_size = (self.sample_rate / 1000) as usize;
+        }
+        context.drift_monitor = self.drift_monitor.clone();
         
-        let config = config.unwrap_or_default();
-        let mut backend = Aes67Backend::with_config(config);
-        backend.init()?;
+        let backends = registry.start(names, &context)?;
+        if backends.is_empty() {
+            return Err(anyhow!("No network audio backend running"));
+        }
+        
+        // Empfangene und gesendete Audiodaten aller Backends an den Mixer anbinden
+        *self.network.lock() = Some(NetworkBridge::new(&backends));
+        
+        // Ohne lokales Audiogerät taktet die PTP Media Clock den Mixer
+        // (ohne AES67 die Systemzeit)
+        if standalone {
+            if let Some(mixer) = self.mixer.clone() {
+                info!("   Kein lokales Audiogerät: Mixer läuft im PTP-Takt (1 ms)");
+                let clock = backends.ptp_clock()
+                    .unwrap_or_else(|| Arc::new(PtpClock::new("")));
+                let master = self.master.clone();
+                let network = self.network.clone();
+                let mut buffers = MixBuffers::default();
+                let frames_per_tick = (self.sample_rate / 1000).max(1) as u16;
+                self.standalone_clock = Some(MediaTicker::start(
+                    clock,
+                    self.sample_rate,
+                    frames_per_tick,
+                    Box::new(move |frames| {
+                        let main_gain = master.as_ref().map_or(1.0, |m| m.get_effective_gain());
+                        let mut network = network.lock();
+                        buffers.clear_inputs(frames * mixer.input_count);
+                        run_mixer(&mixer, main_gain, frames, &mut buffers, network.as_mut());
+                    }),
+                )?);
+            }
+        }
         
-        self.aes67_backend = Some(backend);
+        self.network_backends = backends;
         
         Ok(())
     }
@@ -253,61 +414,42 @@ impl AudioEngine {
         self.audio_source
     }
 
-    /// Get AES67 backend reference
-    pub fn aes67_backend(&self) -> Option<&Aes67Backend> {
-        self.aes67_backend.as_ref()
-    }
-
-    /// Get AES67 backend mutable reference
-    pub fn aes67_backend_mut(&mut self) -> Option<&mut Aes67Backend> {
-        self.aes67_backend.as_mut()
-    }
-
-    /// Get SAP discovery reference (thread-safe, can be shared with API)
-    pub fn sap_discovery(&self) -> Option<Arc<SapDiscovery>> {
-        self.aes67_backend.as_ref().map(|b| b.sap_discovery())
+    /// Laufende Netzwerk-Audio Backends (thread-safe, für die API)
+    pub fn network_backends(&self) -> NetworkBackends {
+        self.network_backends.clone()
     }
 
     /// Get PTP clock reference (thread-safe, can be shared with API)
     pub fn ptp_clock(&self) -> Option<Arc<PtpClock>> {
-        self.aes67_backend.as_ref().map(|b| b.ptp_clock())
+        self.network_backends.ptp_clock()
     }
 
     /// Discover AES67 devices
     pub fn discover_aes67_devices(&self) -> Result<Vec<NetworkDevice>> {
-        if let Some(backend) = &self.aes67_backend {
-            backend.discover()
-        } else {
-            Ok(vec![])
-        }
+        self.network_backends.with(|b: &mut Aes67Backend| b.discover())
+            .unwrap_or_else(|| Ok(vec![]))
     }
 
     /// Connect to AES67 device
     pub fn connect_aes67(&mut self, device: &NetworkDevice) -> Result<()> {
-        if let Some(backend) = &mut self.aes67_backend {
-            backend.connect(device)?;
-            self.audio_source = AudioSource::Aes67;
-            Ok(())
-        } else {
-            Err(anyhow!("AES67 backend not initialized"))
-        }
+        self.network_backends.with(|b: &mut Aes67Backend| b.connect(device))
+            .ok_or_else(|| anyhow!("AES67 backend not initialized"))??;
+        self.audio_source = AudioSource::Aes67;
+        Ok(())
     }
 
     /// Disconnect from AES67 device
     pub fn disconnect_aes67(&mut self) -> Result<()> {
-        if let Some(backend) = &mut self.aes67_backend {
-            backend.disconnect()?;
+        if let Some(result) = self.network_backends.with(|b: &mut Aes67Backend| b.disconnect()) {
+            result?;
             self.audio_source = AudioSource::Local;
-            Ok(())
-        } else {
-            Ok(())
         }
+        Ok(())
     }
 
     /// Check if AES67 PTP is synchronized
     pub fn is_aes67_synchronized(&self) -> bool {
-        self.aes67_backend.as_ref()
-            .map(|b| b.is_ptp_synchronized())
+        self.network_backends.with(|b: &mut Aes67Backend| b.is_ptp_synchronized())
             .unwrap_or(false)
     }
 
@@ -317,34 +459,79 @@ impl AudioEngine {
         info!("   Sample Rate: {} Hz", self.sample_rate);
         info!("   Buffer Size: {} samples", self.buffer_size);
         
-        let input_device = self.host.default_input_device()
-            .ok_or_else(|| anyhow!("Kein Eingabegerät gefunden"))?;
-        let output_device = self.host.default_output_device()
-            .ok_or_else(|| anyhow!("Kein Ausgabegerät gefunden"))?;
+        let input_device = host::find_input_device(&self.host, self.input_device.as_deref())?;
+        let output_device = host::find_output_device(&self.host, self.output_device.as_deref())?;
+        let channels = self.device_channels as usize;
         
         info!("   Input:  {}", input_device.name().unwrap_or_default());
         info!("   Output: {}", output_device.name().unwrap_or_default());
+        info!("   Channels: {}", channels);
+        
+        // Ausgabegerät bestimmt die Engine-Clock und muss die Sample Rate direkt unterstützen
+        if !supports_output_rate(&output_device, self.sample_rate, self.device_channels) {
+            let device_rate = output_device.default_output_config()
+                .map(|c| c.sample_rate().0.to_string())
+                .unwrap_or_else(|_| "?".to_string());
+            return Err(anyhow!(
+                "Ausgabegerät unterstützt {} Hz mit {} Kanälen nicht (Geräte-Rate: {} Hz)",
+                self.sample_rate, channels, device_rate
+            ));
+        }
         
         let config = StreamConfig {
-            channels: 2,
+            channels: self.device_channels,
             sample_rate: cpal::SampleRate(self.sample_rate),
             buffer_size: cpal::BufferSize::Fixed(self.buffer_size as u32),
         };
         
+        // Eingabegerät mit abweichender Rate: nativ öffnen und per SRC anpassen
+        let input_rate = if supports_input_rate(&input_device, self.sample_rate, self.device_channels) {
+            self.sample_rate
+        } else {
+            input_device.default_input_config()
+                .map(|c| c.sample_rate().0)
+                .unwrap_or(self.sample_rate)
+        };
+        let input_config = StreamConfig {
+            sample_rate: cpal::SampleRate(input_rate),
+            ..config.clone()
+        };
+        let mut input_resampler = if input_rate != self.sample_rate {
+            info!("   Input SRC: {} Hz -> {} Hz", input_rate, self.sample_rate);
+            Some(Resampler::new(channels, input_rate, self.sample_rate))
+        } else {
+            None
+        };
+        let mut resampled = Vec::with_capacity(self.buffer_size * 8);
+        
         // Ringbuffer für Audio-Daten zwischen Input und Output
-        let (producer, consumer) = create_ring_buffer(self.buffer_size * 4);
+        let (producer, consumer) = create_ring_buffer(self.buffer_size * channels * 2);
         
         let mixer = self.mixer.clone();
         let master = self.master.clone();
+        let network = self.network.clone();
         let sample_rate = self.sample_rate as f32;
         let running = self.running.clone();
+        let drift_monitor = self.drift_monitor.clone();
+        self.drift_monitor.reset();
+        self.drift_estimator.reset();
         
         // Input Stream
         let input_stream = input_device.build_input_stream(
-            &config,
+            &input_config,
             move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                // Bei abweichender Geräte-Rate erst auf die Engine-Rate konvertieren
+                let samples = match input_resampler.as_mut() {
+                    Some(resampler) => {
+                        resampled.clear();
+                        resampler.process(data, &mut resampled);
+                        &resampled[..]
+                    }
+                    None => data,
+                };
+                
                 // Input-Samples in Ringbuffer schreiben
-                for sample in data {
+                for sample in samples {
                     let _ = producer.try_push(*sample);
                 }
             },
@@ -355,23 +542,33 @@ impl AudioEngine {
         // Oszillator-Phase für Master (muss im Closure bleiben)
         let mut osc_phase = 0.0f32;
         
+        // Mixer-Puffer (werden im Callback wiederverwendet)
+        let mut mix_buffers = MixBuffers::default();
+        
         // Output Stream
         let output_stream = output_device.build_output_stream(
             &config,
             move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                // Geräte-Clock für die Drift-Messung mitzählen
+                drift_monitor.on_device_block(data.len() / channels);
+                
                 // Samples aus Ringbuffer lesen und verarbeiten
                 for sample in data.iter_mut() {
                     *sample = consumer.try_pop().unwrap_or(0.0);
                 }
                 
-                // Channel-Mixer-Processing anwenden
+                // Mixer-Processing anwenden (Kanäle, Routing, Matrix-Ausgänge, AES67)
                 if let Some(ref mixer) = mixer {
-                    process_channels(data, mixer, 2);
+                    let main_gain = master.as_ref().map_or(1.0, |m| m.get_effective_gain());
+                    // Nicht blockieren: ist die Anbindung gerade gesperrt, fällt AES67 für einen Block aus
+                    let mut network = network.try_lock();
+                    let bridge = network.as_mut().and_then(|n| n.as_mut());
+                    process_mixer(data, mixer, main_gain, &mut mix_buffers, channels, bridge);
                 }
                 
                 // Master-Processing anwenden (Limiter, Mono, Oszillator etc.)
                 if let Some(ref master) = master {
-                    process_master(data, master, &mut osc_phase, sample_rate, 2);
+                    process_master(data, master, &mut osc_phase, sample_rate, channels);
                 }
             },
             |err| error!("Output Stream Fehler: {}", err),
@@ -381,6 +578,14 @@ impl AudioEngine {
         input_stream.play()?;
         output_stream.play()?;
         
+        // JACK: Ports nach Regeln verbinden (ohne Regeln bleiben die System-Ports verbunden)
+        if self.host_is_jack && !self.connections.is_empty() {
+            match host::apply_port_connections(&self.connections) {
+                Ok(count) => info!("   {} JACK-Verbindung(en) hergestellt", count),
+                Err(e) => warn!("JACK-Verbindungen fehlgeschlagen: {}", e),
+            }
+        }
+        
         self.input_stream = Some(input_stream);
         self.output_stream = Some(output_stream);
         self.running.store(true, Ordering::SeqCst);
@@ -458,6 +663,28 @@ impl AudioEngine {
     }
 }
 
+/// Prüft ob ein Eingabegerät die Sample Rate mit der Kanalanzahl direkt unterstützt
+fn supports_input_rate(device: &Device, sample_rate: u32, channels: u16) -> bool {
+    device.supported_input_configs()
+        .map(|mut configs| configs.any(|c| {
+            c.channels() >= channels
+                && c.min_sample_rate().0 <= sample_rate
+                && c.max_sample_rate().0 >= sample_rate
+        }))
+        .unwrap_or(true)
+}
+
+/// Prüft ob ein Ausgabegerät die Sample Rate mit der Kanalanzahl unterstützt
+fn supports_output_rate(device: &Device, sample_rate: u32, channels: u16) -> bool {
+    device.supported_output_configs()
+        .map(|mut configs| configs.any(|c| {
+            c.channels() >= channels
+                && c.min_sample_rate().0 <= sample_rate
+                && c.max_sample_rate().0 >= sample_rate
+        }))
+        .unwrap_or(true)
+}
+
 /// Einfacher Lock-Free Ringbuffer
 struct RingBuffer<T> {
     buffer: Vec<std::sync::atomic::AtomicU32>,
@@ -521,30 +748,138 @@ fn create_ring_buffer(capacity: usize) -> (Producer, Consumer) {
     )
 }
 
-/// Audio-Processing im Output-Callback (Channel-Mixer)
-fn process_channels(output: &mut [f32], mixer: &Mixer, channels: usize) {
-    let channel_states = mixer.get_all_channels();
+/// Wiederverwendbare Puffer für das Mixer-Processing im Audio-Callback
+#[derive(Default)]
+struct MixBuffers {
+    inputs: Vec<f32>,
+    outputs: Vec<f32>,
+}
+
+impl MixBuffers {
+    /// Eingangspuffer auf `len` Samples bringen und mit Stille füllen
+    fn clear_inputs(&mut self, len: usize) {
+        self.inputs.resize(len, 0.0);
+        self.inputs.iter_mut().for_each(|s| *s = 0.0);
+    }
+}
+
+/// Größe des Zwischenpuffers der Netzwerk-Anbindung (Samples)
+///
+/// Wird beim Anlegen reserviert und nie vergrößert, größere Blöcke werden
+/// stückweise gelesen.
+const NETWORK_SCRATCH_SAMPLES: usize = 16 * 1024;
+
+/// Netzwerk-Anbindung des Mixers
+///
+/// Empfangene AES67 Streams werden gemäß Patch auf die Mixer-Eingänge
+/// addiert, jeder gesendete Stream bekommt seine Mixer-Ausgänge. AES67 läuft
+/// direkt über seine Tabellen, alle anderen Backends über
+/// `read_samples`/`write_samples`.
+struct NetworkBridge {
+    /// Empfangene und gesendete Streams des AES67 Backends
+    aes67: Option<(Arc<Aes67Subscriptions>, Arc<Aes67TxStreams>)>,
+    /// Weitere Backends
+    backends: Vec<SharedBackend>,
+    scratch: Vec<f32>,
+    tx_buffer: Vec<f32>,
+}
+
+impl NetworkBridge {
+    fn new(backends: &NetworkBackends) -> Self {
+        let aes67 = backends.with(|b: &mut Aes67Backend| (b.subscriptions(), b.tx_streams()));
+        let backends = backends.iter()
+            .filter(|(_, backend)| !backend.read().as_any().is::<Aes67Backend>())
+            .map(|(_, backend)| backend.clone())
+            .collect();
+        Self {
+            aes67,
+            backends,
+            scratch: vec![0.0; NETWORK_SCRATCH_SAMPLES],
+            tx_buffer: vec![0.0; NETWORK_SCRATCH_SAMPLES],
+        }
+    }
+
+    /// Empfangene Samples auf die Mixer-Eingänge addieren
+    fn read_inputs(&mut self, inputs: &mut [f32], input_count: usize) {
+        if let Some((subscriptions, _)) = &self.aes67 {
+            subscriptions.read_into(inputs, input_count, &mut self.scratch);
+        }
+        for backend in &self.backends {
+            // Nicht blockieren: ist ein Backend gerade gesperrt, fällt es für einen Block aus
+            let Some(backend) = backend.try_read() else {
+                continue;
+            };
+            let chunk = self.scratch.len() / input_count.max(1) * input_count;
+            if chunk == 0 {
+                continue;
+            }
+            for inputs in inputs.chunks_mut(chunk) {
+                let scratch = &mut self.scratch[..inputs.len()];
+                if backend.read_samples(scratch, input_count) > 0 {
+                    inputs.iter_mut().zip(scratch.iter()).for_each(|(input, sample)| *input += sample);
+                }
+            }
+        }
+    }
+
+    /// Mixer-Ausgänge an alle Sender übergeben
+    fn write_outputs(&mut self, outputs: &[f32], output_count: usize, frames: usize) {
+        if let Some((_, tx_streams)) = &self.aes67 {
+            tx_streams.write_outputs(outputs, output_count, frames, &mut self.tx_buffer);
+        }
+        for backend in &self.backends {
+            if let Some(backend) = backend.try_read() {
+                backend.write_samples(&outputs[..frames * output_count], output_count);
+            }
+        }
+    }
+}
+
+/// Mixer für einen Block rechnen (Eingänge müssen bereits in `buffers.inputs` stehen)
+fn run_mixer(mixer: &Mixer, main_gain: f32, frames: usize, buffers: &mut MixBuffers, mut network: Option<&mut NetworkBridge>) {
+    let input_count = mixer.input_count;
+    let output_count = mixer.output_count;
+    
+    buffers.outputs.resize(frames * output_count, 0.0);
+    
+    if let Some(bridge) = network.as_mut() {
+        bridge.read_inputs(&mut buffers.inputs, input_count);
+    }
+    
+    mixer.process_block(&buffers.inputs, &mut buffers.outputs, frames, main_gain);
+    
+    if let Some(bridge) = network {
+        bridge.write_outputs(&buffers.outputs, output_count, frames);
+    }
+}
+
+/// Audio-Processing im Output-Callback (Mixer mit Routing und Matrix-Ausgängen)
+///
+/// Die Gerätekanäle werden auf die ersten Mixer-Eingänge gelegt, empfangene
+/// AES67 Streams kommen gemäß Patch hinzu. Die ersten Mixer-Ausgänge gehen
+/// zurück an das Gerät.
+fn process_mixer(data: &mut [f32], mixer: &Mixer, main_gain: f32, buffers: &mut MixBuffers, channels: usize, network: Option<&mut NetworkBridge>) {
+    let frames = data.len() / channels;
+    let input_count = mixer.input_count;
+    let output_count = mixer.output_count;
+    
+    buffers.clear_inputs(frames * input_count);
     
-    // Für jeden Sample-Frame
-    let frames = output.len() / channels;
     for frame in 0..frames {
-        for ch in 0..channels.min(channel_states.len()) {
-            let idx = frame * channels + ch;
-            if idx < output.len() {
-                let state = &channel_states[ch];
-                
-                // Fader und Mute anwenden
-                let gain = if state.mute {
-                    0.0
-                } else {
-                    fader_to_gain(state.fader)
-                };
-                
-                output[idx] *= gain;
-                
-                // Meter aktualisieren (Peak)
-                mixer.update_meter(ch as u32, output[idx].abs());
-            }
+        for ch in 0..channels.min(input_count) {
+            buffers.inputs[frame * input_count + ch] = data[frame * channels + ch];
+        }
+    }
+    
+    run_mixer(mixer, main_gain, frames, buffers, network);
+    
+    for frame in 0..frames {
+        for ch in 0..channels {
+            data[frame * channels + ch] = if ch < output_count {
+                buffers.outputs[frame * output_count + ch]
+            } else {
+                0.0
+            };
         }
     }
 }
@@ -580,21 +915,6 @@ fn process_master(output: &mut [f32], master: &MasterSection, osc_phase: &mut f3
     }
 }
 
-/// Fader-Wert (0.0-1.25) zu linearem Gain konvertieren
-fn fader_to_gain(fader: f32) -> f32 {
-    if fader < 0.001 {
-        0.0 // -∞ dB
-    } else if fader <= 0.75 {
-        // 0 bis 0.75 -> -∞ bis 0 dB (exponentiell)
-        let normalized = fader / 0.75;
-        normalized * normalized
-    } else {
-        // 0.75 bis 1.25 -> 0 bis +10 dB
-        let db = ((fader - 0.75) / 0.25) * 10.0;
-        10.0_f32.powf(db / 20.0)
-    }
-}
-
 /// Audio-Callback Funktion (wird vom Audio-Thread aufgerufen)
 /// 
 /// Diese Funktion wird in Echtzeit aufgerufen und darf NICHT:
@@ -636,4 +956,29 @@ mod tests {
         assert_eq!(engine.buffer_size, 256);
         assert!(!engine.is_running());
     }
+
+    #[test]
+    fn test_network_bridge_backends() {
+        use crate::network_audio::Aes67Config;
+        use crate::network_audio::loopback::LOOPBACK_DEVICE_ID;
+
+        let context = BackendContext {
+            sample_rate: 48000,
+            aes67: Aes67Config::default(),
+            virtual_channels: 2,
+            drift_monitor: Arc::new(DriftMonitor::new()),
+        };
+        let backends = BackendRegistry::with_defaults().start(&["virtual".to_string()], &context).unwrap();
+        let (_, backend) = backends.get("virtual").unwrap();
+        let device = backend.read().discover().unwrap().remove(0);
+        assert_eq!(device.id, LOOPBACK_DEVICE_ID);
+        backend.write().connect(&device).unwrap();
+
+        // 2 frames, 3 outputs looped onto the last 2 of 4 inputs, added to what is there
+        let mut bridge = NetworkBridge::new(&backends);
+        bridge.write_outputs(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6], 3, 2);
+        let mut inputs = [0.5; 8];
+        bridge.read_inputs(&mut inputs, 4);
+        assert_eq!(inputs, [0.5, 0.5, 0.6, 0.7, 0.5, 0.5, 0.9, 1.0]);
+    }
 }
@@ -270,6 +270,15 @@ impl EqBand {
         (out_l, out_r)
     }
     
+    /// Mono-Sample filtern (nutzt den Zustand des linken Kanals)
+    pub fn process_mono(&mut self, sample: f32) -> f32 {
+        if !self.params.enabled {
+            return sample;
+        }
+        
+        Self::process_sample(sample, &self.coeffs, &mut self.state_l)
+    }
+    
     /// Filter-Zustand zurücksetzen
     pub fn reset(&mut self) {
         self.state_l = BiquadState::default();
@@ -348,6 +357,20 @@ impl ParametricEq {
         }
     }
     
+    /// EQ mit beliebigen Bändern erstellen
+    pub fn with_bands(params: Vec<EqBandParams>, sample_rate: f32) -> Self {
+        let bands = params
+            .into_iter()
+            .map(|p| EqBand::new(p, sample_rate))
+            .collect();
+        
+        Self {
+            bands,
+            enabled: true,
+            sample_rate,
+        }
+    }
+    
     /// Stereo-Sample durch alle Bänder verarbeiten
     pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
         if !self.enabled {
@@ -366,6 +389,15 @@ impl ParametricEq {
         (l, r)
     }
     
+    /// Mono-Sample durch alle Bänder verarbeiten
+    pub fn process_mono(&mut self, sample: f32) -> f32 {
+        if !self.enabled {
+            return sample;
+        }
+        
+        self.bands.iter_mut().fold(sample, |s, band| band.process_mono(s))
+    }
+    
     /// Audio-Buffer verarbeiten (interleaved Stereo)
     pub fn process_buffer(&mut self, buffer: &mut [f32]) {
         if !self.enabled {
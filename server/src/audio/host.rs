@@ -0,0 +1,316 @@
+//! Audio-Host Auswahl
+//!
+//! Wählt das lokale Audio-Backend (ALSA, JACK, PipeWire), sucht Geräte per
+//! Name und verbindet JACK-Ports nach konfigurierbaren Regeln.
+
+use anyhow::{Result, anyhow};
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{Device, Host};
+use tracing::info;
+
+use crate::config::{AudioBackend, PortConnection};
+
+/// JACK-Client der Eingangsports (von cpal vergeben)
+#[cfg(all(feature = "jack", target_os = "linux"))]
+pub const JACK_INPUT_CLIENT: &str = "cpal_client_in";
+
+/// JACK-Client der Ausgangsports (von cpal vergeben)
+#[cfg(all(feature = "jack", target_os = "linux"))]
+pub const JACK_OUTPUT_CLIENT: &str = "cpal_client_out";
+
+/// ALSA-Gerät des PipeWire-Plugins (pipewire-alsa)
+const PIPEWIRE_ALSA_DEVICE: &str = "pipewire";
+
+/// Ergebnis der Host-Auswahl
+pub struct HostSelection {
+    /// cpal Host
+    pub host: Host,
+
+    /// Host ist JACK (auch PipeWire über pipewire-jack)
+    pub is_jack: bool,
+
+    /// Vorgabe für das Gerät, falls keines konfiguriert ist
+    pub default_device: Option<String>,
+}
+
+/// Host für das konfigurierte Backend erstellen
+pub fn create_host(backend: AudioBackend) -> Result<HostSelection> {
+    match backend {
+        AudioBackend::Default => Ok(HostSelection {
+            host: cpal::default_host(),
+            is_jack: false,
+            default_device: None,
+        }),
+        AudioBackend::Alsa => Ok(HostSelection {
+            host: alsa_host()?,
+            is_jack: false,
+            default_device: None,
+        }),
+        AudioBackend::Jack => Ok(HostSelection {
+            host: jack_host()?,
+            is_jack: true,
+            default_device: None,
+        }),
+        AudioBackend::PipeWire => match jack_host() {
+            // PipeWire stellt eine JACK-kompatible Schnittstelle bereit
+            Ok(host) => Ok(HostSelection {
+                host,
+                is_jack: true,
+                default_device: None,
+            }),
+            Err(e) => {
+                info!("PipeWire über JACK nicht verfügbar ({}), verwende ALSA-Plugin", e);
+                Ok(HostSelection {
+                    host: alsa_host()?,
+                    is_jack: false,
+                    default_device: Some(PIPEWIRE_ALSA_DEVICE.to_string()),
+                })
+            }
+        },
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn alsa_host() -> Result<Host> {
+    cpal::host_from_id(cpal::HostId::Alsa)
+        .map_err(|e| anyhow!("ALSA nicht verfügbar: {}", e))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn alsa_host() -> Result<Host> {
+    Err(anyhow!("ALSA ist nur unter Linux verfügbar"))
+}
+
+#[cfg(all(feature = "jack", target_os = "linux"))]
+fn jack_host() -> Result<Host> {
+    let host: Host = cpal::platform::JackHost::new()
+        .map_err(|e| anyhow!("JACK nicht verfügbar: {}", e))?
+        .into();
+
+    // cpal legt die Clients beim Erstellen des Hosts an, ohne Server gibt es keine Geräte
+    if host.default_output_device().is_none() {
+        return Err(anyhow!("Kein JACK-Server erreichbar"));
+    }
+    Ok(host)
+}
+
+#[cfg(not(all(feature = "jack", target_os = "linux")))]
+fn jack_host() -> Result<Host> {
+    Err(anyhow!("JACK-Unterstützung nicht kompiliert (Feature \"jack\")"))
+}
+
+/// Eingabegerät per Name suchen (None = Standardgerät)
+pub fn find_input_device(host: &Host, name: Option<&str>) -> Result<Device> {
+    match name {
+        None => host.default_input_device()
+            .ok_or_else(|| anyhow!("Kein Eingabegerät gefunden")),
+        Some(name) => {
+            let devices: Vec<Device> = host.input_devices()?.collect();
+            find_by_name(devices, name)
+                .ok_or_else(|| anyhow!("Eingabegerät '{}' nicht gefunden", name))
+        }
+    }
+}
+
+/// Ausgabegerät per Name suchen (None = Standardgerät)
+pub fn find_output_device(host: &Host, name: Option<&str>) -> Result<Device> {
+    match name {
+        None => host.default_output_device()
+            .ok_or_else(|| anyhow!("Kein Ausgabegerät gefunden")),
+        Some(name) => {
+            let devices: Vec<Device> = host.output_devices()?.collect();
+            find_by_name(devices, name)
+                .ok_or_else(|| anyhow!("Ausgabegerät '{}' nicht gefunden", name))
+        }
+    }
+}
+
+fn find_by_name(devices: Vec<Device>, wanted: &str) -> Option<Device> {
+    let names: Vec<String> = devices
+        .iter()
+        .map(|d| d.name().unwrap_or_default())
+        .collect();
+    let index = match_device_name(&names, wanted)?;
+    devices.into_iter().nth(index)
+}
+
+/// Gerät nach Name auswählen: exakter Treffer vor Namensteil (ohne Groß-/Kleinschreibung)
+pub fn match_device_name(names: &[String], wanted: &str) -> Option<usize> {
+    if let Some(index) = names.iter().position(|n| n == wanted) {
+        return Some(index);
+    }
+    let wanted = wanted.to_lowercase();
+    names.iter().position(|n| n.to_lowercase().contains(&wanted))
+}
+
+/// Platzhalter `{input}`/`{output}` in einer Port-Regel ersetzen
+#[cfg(all(feature = "jack", target_os = "linux"))]
+pub fn expand_port_pattern(pattern: &str) -> String {
+    pattern
+        .replace("{input}", JACK_INPUT_CLIENT)
+        .replace("{output}", JACK_OUTPUT_CLIENT)
+}
+
+/// Ports paarweise zuordnen (natürliche Sortierung, z.B. capture_2 vor capture_10)
+#[cfg(all(feature = "jack", target_os = "linux"))]
+pub fn pair_ports(sources: &[String], destinations: &[String]) -> Vec<(String, String)> {
+    let mut sources = sources.to_vec();
+    let mut destinations = destinations.to_vec();
+    sources.sort_by_key(|p| natural_key(p));
+    destinations.sort_by_key(|p| natural_key(p));
+
+    sources.into_iter().zip(destinations).collect()
+}
+
+/// Sortierschlüssel: Name ohne Ziffern am Ende + Zahl am Ende
+#[cfg(all(feature = "jack", target_os = "linux"))]
+fn natural_key(name: &str) -> (String, u64) {
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (prefix, number) = name.split_at(name.len() - digits);
+    (prefix.to_string(), number.parse().unwrap_or(0))
+}
+
+/// Port-Regeln anwenden (JACK)
+///
+/// Die automatischen Verbindungen von cpal werden zuvor gelöst, damit nur die
+/// konfigurierten Verbindungen bestehen. Gibt die Anzahl Verbindungen zurück.
+#[cfg(all(feature = "jack", target_os = "linux"))]
+pub fn apply_port_connections(rules: &[PortConnection]) -> Result<usize> {
+    use jack::PortFlags;
+    use tracing::warn;
+
+    let (client, _) = jack::Client::new("audiomultiverse_connect", jack::ClientOptions::NO_START_SERVER)
+        .map_err(|e| anyhow!("JACK-Client konnte nicht erstellt werden: {}", e))?;
+
+    // Eigene Ports von bestehenden Verbindungen lösen
+    let own_pattern = format!("^({}|{}):", JACK_INPUT_CLIENT, JACK_OUTPUT_CLIENT);
+    let all_ports = client.ports(None, None, PortFlags::empty());
+    for name in client.ports(Some(&own_pattern), None, PortFlags::empty()) {
+        let Some(port) = client.port_by_name(&name) else {
+            continue;
+        };
+        if port.connected_count().unwrap_or(0) == 0 {
+            continue;
+        }
+        let is_output = port.flags().contains(PortFlags::IS_OUTPUT);
+        let connections = all_ports
+            .iter()
+            .filter(|other| port.is_connected_to(other).unwrap_or(false));
+        for other in connections {
+            let result = if is_output {
+                client.disconnect_ports_by_name(&name, other)
+            } else {
+                client.disconnect_ports_by_name(other, &name)
+            };
+            if let Err(e) = result {
+                warn!("JACK: {} <-> {} konnte nicht getrennt werden: {}", name, other, e);
+            }
+        }
+    }
+
+    let mut connected = 0;
+    for rule in rules {
+        let sources = client.ports(Some(&expand_port_pattern(&rule.source)), None, PortFlags::IS_OUTPUT);
+        let destinations = client.ports(Some(&expand_port_pattern(&rule.destination)), None, PortFlags::IS_INPUT);
+
+        if sources.is_empty() || destinations.is_empty() {
+            warn!("JACK: Regel {} -> {} passt auf keine Ports", rule.source, rule.destination);
+            continue;
+        }
+
+        for (source, destination) in pair_ports(&sources, &destinations) {
+            match client.connect_ports_by_name(&source, &destination) {
+                Ok(()) => {
+                    info!("   JACK: {} -> {}", source, destination);
+                    connected += 1;
+                }
+                Err(e) => warn!("JACK: {} -> {} fehlgeschlagen: {}", source, destination, e),
+            }
+        }
+    }
+
+    Ok(connected)
+}
+
+#[cfg(not(all(feature = "jack", target_os = "linux")))]
+pub fn apply_port_connections(rules: &[PortConnection]) -> Result<usize> {
+    if rules.is_empty() {
+        Ok(0)
+    } else {
+        Err(anyhow!("Port-Verbindungen benötigen JACK-Unterstützung (Feature \"jack\")"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_match_device_name() {
+        let devices = names(&["default", "hw:CARD=USB,DEV=0", "Scarlett 18i20 USB"]);
+
+        assert_eq!(match_device_name(&devices, "default"), Some(0));
+        assert_eq!(match_device_name(&devices, "scarlett"), Some(2));
+        assert_eq!(match_device_name(&devices, "hw:CARD=USB"), Some(1));
+        assert_eq!(match_device_name(&devices, "RME"), None);
+    }
+
+    #[cfg(all(feature = "jack", target_os = "linux"))]
+    #[test]
+    fn test_pair_ports_natural_order() {
+        let sources = names(&["system:capture_10", "system:capture_2", "system:capture_1"]);
+        let destinations = names(&["cpal_client_in:in_2", "cpal_client_in:in_1"]);
+
+        let pairs = pair_ports(&sources, &destinations);
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0], ("system:capture_1".to_string(), "cpal_client_in:in_1".to_string()));
+        assert_eq!(pairs[1], ("system:capture_2".to_string(), "cpal_client_in:in_2".to_string()));
+    }
+
+    #[cfg(all(feature = "jack", target_os = "linux"))]
+    #[test]
+    fn test_expand_port_pattern() {
+        assert_eq!(expand_port_pattern("{output}:out_.*"), "cpal_client_out:out_.*");
+        assert_eq!(expand_port_pattern("{input}:in_1"), "cpal_client_in:in_1");
+        assert_eq!(expand_port_pattern("system:capture_.*"), "system:capture_.*");
+    }
+
+    #[test]
+    fn test_default_backend_host() {
+        let selection = create_host(AudioBackend::Default).unwrap();
+        assert!(!selection.is_jack);
+    }
+
+    /// Benötigt einen laufenden JACK-Server, z.B. `jackd -d dummy -r 48000 -p 256`
+    #[cfg(all(feature = "jack", target_os = "linux"))]
+    #[test]
+    #[ignore]
+    fn test_jack_dummy_connections() {
+        use cpal::traits::StreamTrait;
+        use cpal::StreamConfig;
+
+        let selection = create_host(AudioBackend::Jack).expect("JACK-Server läuft nicht");
+        let device = find_output_device(&selection.host, None).unwrap();
+        let sample_rate = device.default_output_config().unwrap().sample_rate();
+
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate,
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let stream = device
+            .build_output_stream(&config, |data: &mut [f32], _| data.fill(0.0), |_| {}, None)
+            .unwrap();
+        stream.play().unwrap();
+
+        let rules = vec![PortConnection {
+            source: "{output}:out_.*".to_string(),
+            destination: "system:playback_.*".to_string(),
+        }];
+        assert_eq!(apply_port_connections(&rules).unwrap(), 2);
+    }
+}
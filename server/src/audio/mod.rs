@@ -4,6 +4,14 @@
 
 mod engine;
 pub mod eq;
+pub mod delay;
+pub mod output_processor;
+pub mod resampler;
+pub mod drift;
+pub mod host;
 
 pub use engine::{AudioEngine, AudioDeviceInfo, AudioCommandSender, AudioCommand, Aes67SubscribeResult};
 pub use eq::{ParametricEq, EqBand, EqBandParams, FilterType, HighPassFilter};
+pub use resampler::Resampler;
+pub use drift::{DriftMonitor, DriftController};
+pub use output_processor::{OutputProcessor, OutputProcessingParams};
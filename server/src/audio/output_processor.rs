@@ -0,0 +1,470 @@
+//! Ausgangs-Processing (Lautsprecher-Management)
+//!
+//! Processing-Block pro Ausgang, damit AudioMultiverse als System-Controller
+//! Lautsprecher direkt (z.B. über AES67) ansteuern kann:
+//! Crossover (Linkwitz-Riley HP/LP) → EQ → Gain/Polarität → Limiter → Delay
+//!
+//! Jeder Matrix-Ausgang (`mixer::matrix`) endet in einem solchen Block.
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use serde::{Deserialize, Serialize};
+
+use super::delay::DelayLine;
+use super::eq::{EqBand, EqBandParams, FilterType, ParametricEq};
+
+/// Maximale Ausgangs-Verzögerung in Millisekunden
+pub const MAX_OUTPUT_DELAY_MS: f32 = 1000.0;
+
+/// Anzahl Bänder im Ausgangs-EQ
+pub const OUTPUT_EQ_BANDS: usize = 8;
+
+/// Linkwitz-Riley Flankensteilheit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CrossoverSlope {
+    /// LR2 - 12 dB/Oktave
+    Lr12,
+    /// LR4 - 24 dB/Oktave
+    #[default]
+    Lr24,
+    /// LR8 - 48 dB/Oktave
+    Lr48,
+}
+
+impl CrossoverSlope {
+    /// Q-Werte der kaskadierten Biquads
+    ///
+    /// Ein LR-Filter der Ordnung 2n besteht aus zwei identischen
+    /// Butterworth-Filtern der Ordnung n.
+    fn biquad_qs(&self) -> &'static [f32] {
+        match self {
+            // Zwei Butterworth 1. Ordnung = ein Biquad mit Q 0.5
+            CrossoverSlope::Lr12 => &[0.5],
+            // Zwei Butterworth 2. Ordnung
+            CrossoverSlope::Lr24 => &[FRAC_1_SQRT_2, FRAC_1_SQRT_2],
+            // Zwei Butterworth 4. Ordnung
+            CrossoverSlope::Lr48 => &[0.541_196_1, 1.306_563, 0.541_196_1, 1.306_563],
+        }
+    }
+}
+
+/// Crossover-Filter Parameter (Hoch- oder Tiefpass)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CrossoverParams {
+    /// Filter aktiv
+    pub enabled: bool,
+
+    /// Trennfrequenz in Hz
+    pub frequency: f32,
+
+    /// Flankensteilheit
+    pub slope: CrossoverSlope,
+}
+
+/// Limiter Parameter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LimiterParams {
+    /// Limiter aktiv
+    pub enabled: bool,
+
+    /// Threshold in dBFS
+    pub threshold_db: f32,
+
+    /// Attack in Millisekunden
+    pub attack_ms: f32,
+
+    /// Release in Millisekunden
+    pub release_ms: f32,
+}
+
+impl Default for LimiterParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -1.0,
+            attack_ms: 1.0,
+            release_ms: 100.0,
+        }
+    }
+}
+
+/// Komplette Processing-Parameter eines Ausgangs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputProcessingParams {
+    /// Gain in dB (-40 bis +15)
+    pub gain_db: f32,
+
+    /// Polarität invertiert
+    pub polarity_invert: bool,
+
+    /// Verzögerung in Millisekunden
+    pub delay_ms: f32,
+
+    /// Hochpass (Crossover)
+    pub highpass: CrossoverParams,
+
+    /// Tiefpass (Crossover)
+    pub lowpass: CrossoverParams,
+
+    /// EQ aktiviert
+    pub eq_enabled: bool,
+
+    /// EQ-Bänder
+    pub eq: Vec<EqBandParams>,
+
+    /// Limiter
+    pub limiter: LimiterParams,
+}
+
+impl Default for OutputProcessingParams {
+    fn default() -> Self {
+        // Parametrische Bänder auf Oktav-Abständen, alle neutral
+        let eq = (0..OUTPUT_EQ_BANDS)
+            .map(|i| EqBandParams {
+                frequency: 63.0 * 2.0_f32.powi(i as i32),
+                gain: 0.0,
+                q: 1.4,
+                filter_type: FilterType::Peak,
+                enabled: true,
+            })
+            .collect();
+
+        Self {
+            gain_db: 0.0,
+            polarity_invert: false,
+            delay_ms: 0.0,
+            highpass: CrossoverParams {
+                enabled: false,
+                frequency: 80.0,
+                slope: CrossoverSlope::Lr24,
+            },
+            lowpass: CrossoverParams {
+                enabled: false,
+                frequency: 20000.0,
+                slope: CrossoverSlope::Lr24,
+            },
+            eq_enabled: false,
+            eq,
+            limiter: LimiterParams::default(),
+        }
+    }
+}
+
+/// Linkwitz-Riley Filter (Kaskade aus Biquads)
+#[derive(Debug, Clone)]
+struct CrossoverFilter {
+    params: CrossoverParams,
+    sections: Vec<EqBand>,
+}
+
+impl CrossoverFilter {
+    fn new(params: CrossoverParams, filter_type: FilterType, sample_rate: f32) -> Self {
+        let frequency = params.frequency.clamp(10.0, sample_rate * 0.45);
+        let sections = params
+            .slope
+            .biquad_qs()
+            .iter()
+            .map(|&q| {
+                EqBand::new(
+                    EqBandParams {
+                        frequency,
+                        gain: 0.0,
+                        q,
+                        filter_type,
+                        enabled: true,
+                    },
+                    sample_rate,
+                )
+            })
+            .collect();
+
+        Self { params, sections }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        if !self.params.enabled {
+            return sample;
+        }
+        self.sections.iter_mut().fold(sample, |s, band| band.process_mono(s))
+    }
+}
+
+/// Peak-Limiter mit Attack/Release Hüllkurve
+#[derive(Debug, Clone)]
+struct Limiter {
+    params: LimiterParams,
+    threshold: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    gain: f32,
+}
+
+impl Limiter {
+    fn new(params: LimiterParams, sample_rate: f32) -> Self {
+        Self {
+            params,
+            threshold: 10.0_f32.powf(params.threshold_db / 20.0),
+            attack_coeff: time_constant(params.attack_ms, sample_rate),
+            release_coeff: time_constant(params.release_ms, sample_rate),
+            gain: 1.0,
+        }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        if !self.params.enabled {
+            self.gain = 1.0;
+            return sample;
+        }
+
+        let level = sample.abs();
+        let target = if level > self.threshold {
+            self.threshold / level
+        } else {
+            1.0
+        };
+
+        let coeff = if target < self.gain {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.gain = target + coeff * (self.gain - target);
+
+        // Harte Begrenzung als Schutz gegen Überschwinger während der Attack-Phase
+        (sample * self.gain).clamp(-self.threshold, self.threshold)
+    }
+
+    fn gain_reduction_db(&self) -> f32 {
+        if self.gain >= 1.0 {
+            0.0
+        } else {
+            20.0 * self.gain.max(1e-5).log10()
+        }
+    }
+}
+
+/// Glättungskoeffizient für eine Zeitkonstante
+fn time_constant(ms: f32, sample_rate: f32) -> f32 {
+    let samples = ms.max(0.01) * sample_rate / 1000.0;
+    (-1.0 / samples).exp()
+}
+
+/// Processing-Block für einen Ausgang
+#[derive(Debug, Clone)]
+pub struct OutputProcessor {
+    params: OutputProcessingParams,
+    sample_rate: f32,
+    gain: f32,
+    highpass: CrossoverFilter,
+    lowpass: CrossoverFilter,
+    eq: ParametricEq,
+    limiter: Limiter,
+    delay: DelayLine,
+}
+
+impl OutputProcessor {
+    /// Neuen Processor mit neutralen Einstellungen erstellen
+    pub fn new(sample_rate: f32) -> Self {
+        let params = OutputProcessingParams::default();
+        let mut processor = Self {
+            gain: 1.0,
+            highpass: CrossoverFilter::new(params.highpass, FilterType::HighPass, sample_rate),
+            lowpass: CrossoverFilter::new(params.lowpass, FilterType::LowPass, sample_rate),
+            eq: ParametricEq::with_bands(params.eq.clone(), sample_rate),
+            limiter: Limiter::new(params.limiter, sample_rate),
+            delay: DelayLine::with_max_ms(MAX_OUTPUT_DELAY_MS, sample_rate),
+            params: params.clone(),
+            sample_rate,
+        };
+        processor.set_params(params);
+        processor
+    }
+
+    /// Parameter abrufen
+    pub fn params(&self) -> &OutputProcessingParams {
+        &self.params
+    }
+
+    /// Parameter setzen (Filter werden nur bei Änderung neu aufgebaut)
+    pub fn set_params(&mut self, mut params: OutputProcessingParams) {
+        let sample_rate = self.sample_rate;
+
+        params.gain_db = params.gain_db.clamp(-40.0, 15.0);
+        params.delay_ms = params.delay_ms.clamp(0.0, MAX_OUTPUT_DELAY_MS);
+        params.limiter.threshold_db = params.limiter.threshold_db.clamp(-40.0, 0.0);
+        params.eq.truncate(OUTPUT_EQ_BANDS);
+
+        if params.highpass != self.highpass.params || self.highpass.sections.is_empty() {
+            self.highpass = CrossoverFilter::new(params.highpass, FilterType::HighPass, sample_rate);
+        }
+        if params.lowpass != self.lowpass.params || self.lowpass.sections.is_empty() {
+            self.lowpass = CrossoverFilter::new(params.lowpass, FilterType::LowPass, sample_rate);
+        }
+        if params.limiter != self.limiter.params {
+            self.limiter = Limiter::new(params.limiter, sample_rate);
+        }
+
+        if params.eq.len() != self.eq.band_count() {
+            self.eq = ParametricEq::with_bands(params.eq.clone(), sample_rate);
+        } else {
+            for (i, band) in params.eq.iter().enumerate() {
+                self.eq.set_band_params(i, band.clone());
+            }
+        }
+        if !params.eq_enabled {
+            self.eq.reset();
+        }
+
+        self.gain = 10.0_f32.powf(params.gain_db / 20.0);
+        if params.polarity_invert {
+            self.gain = -self.gain;
+        }
+
+        self.delay.set_delay_ms(params.delay_ms, sample_rate);
+        self.params = params;
+    }
+
+    /// EQ-Band setzen
+    pub fn set_eq_band(&mut self, band: usize, params: EqBandParams) -> bool {
+        if band >= self.params.eq.len() {
+            return false;
+        }
+        self.params.eq[band] = params.clone();
+        self.eq.set_band_params(band, params);
+        true
+    }
+
+    /// Aktuelle Gain Reduction des Limiters in dB
+    pub fn limiter_gr_db(&self) -> f32 {
+        self.limiter.gain_reduction_db()
+    }
+
+    /// Ein Sample verarbeiten
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let mut value = self.highpass.process(sample);
+        value = self.lowpass.process(value);
+
+        if self.params.eq_enabled {
+            value = self.eq.process_mono(value);
+        }
+
+        value *= self.gain;
+        value = self.limiter.process(value);
+
+        self.delay.process(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RMS-Pegel eines Sinus nach dem Einschwingen
+    fn sine_rms(processor: &mut OutputProcessor, freq: f32, sample_rate: f32) -> f32 {
+        let total = (sample_rate * 0.5) as usize;
+        let settle = total / 2;
+        let mut sum = 0.0;
+        for n in 0..total {
+            let x = (2.0 * std::f32::consts::PI * freq * n as f32 / sample_rate).sin();
+            let y = processor.process(x);
+            if n >= settle {
+                sum += y * y;
+            }
+        }
+        (sum / (total - settle) as f32).sqrt()
+    }
+
+    #[test]
+    fn test_neutral_passthrough() {
+        let mut processor = OutputProcessor::new(48000.0);
+        assert!((processor.process(0.5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gain_and_polarity() {
+        let mut processor = OutputProcessor::new(48000.0);
+        let mut params = processor.params().clone();
+        params.gain_db = -6.0;
+        params.polarity_invert = true;
+        processor.set_params(params);
+
+        let out = processor.process(1.0);
+        assert!((out + 0.5012).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_delay() {
+        let mut processor = OutputProcessor::new(48000.0);
+        let mut params = processor.params().clone();
+        params.delay_ms = 1.0; // 48 Samples
+        processor.set_params(params);
+
+        let outputs: Vec<f32> = (0..50).map(|n| processor.process(if n == 0 { 1.0 } else { 0.0 })).collect();
+        assert_eq!(outputs[0], 0.0);
+        assert!((outputs[48] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_linkwitz_riley_crossover_sums_flat() {
+        // LR4 Hoch- und Tiefpass ergeben an der Trennfrequenz je -6dB
+        // und summieren sich zu einem flachen Amplitudengang
+        let sample_rate = 48000.0;
+        let crossover = CrossoverParams {
+            enabled: true,
+            frequency: 1000.0,
+            slope: CrossoverSlope::Lr24,
+        };
+
+        let mut high = OutputProcessor::new(sample_rate);
+        let mut params = high.params().clone();
+        params.highpass = crossover;
+        high.set_params(params);
+
+        let mut low = OutputProcessor::new(sample_rate);
+        let mut params = low.params().clone();
+        params.lowpass = crossover;
+        low.set_params(params);
+
+        let high_rms = sine_rms(&mut high, 1000.0, sample_rate);
+        let low_rms = sine_rms(&mut low, 1000.0, sample_rate);
+        let expected = std::f32::consts::FRAC_1_SQRT_2 * 0.5;
+        assert!((high_rms - expected).abs() < 0.01);
+        assert!((low_rms - expected).abs() < 0.01);
+
+        // Summe bei verschiedenen Frequenzen
+        for freq in [200.0, 1000.0, 5000.0] {
+            high.delay.reset();
+            let total = 24000;
+            let mut sum = 0.0;
+            for n in 0..total {
+                let x = (2.0 * std::f32::consts::PI * freq * n as f32 / sample_rate).sin();
+                let y = high.process(x) + low.process(x);
+                if n >= total / 2 {
+                    sum += y * y;
+                }
+            }
+            let rms = (sum / (total / 2) as f32).sqrt();
+            assert!((rms - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.02, "freq {}: {}", freq, rms);
+        }
+    }
+
+    #[test]
+    fn test_limiter() {
+        let mut processor = OutputProcessor::new(48000.0);
+        let mut params = processor.params().clone();
+        params.limiter = LimiterParams {
+            enabled: true,
+            threshold_db: -6.0,
+            attack_ms: 0.1,
+            release_ms: 50.0,
+        };
+        processor.set_params(params);
+
+        let threshold = 10.0_f32.powf(-6.0 / 20.0);
+        for _ in 0..1000 {
+            let out = processor.process(1.0);
+            assert!(out <= threshold + 1e-6);
+        }
+        assert!(processor.limiter_gr_db() < -5.0);
+    }
+}
@@ -0,0 +1,334 @@
+//! Asynchrone Sample-Rate-Konvertierung (ASRC)
+//!
+//! Bandbegrenzte Interpolation mit gefensterter Sinc-Funktion (Blackman-Harris).
+//! Die Filterkoeffizienten liegen als Polyphasen-Tabelle vor, zwischen den
+//! Phasen wird linear interpoliert. Das Verhältnis kann zur Laufzeit fein
+//! nachgeführt werden (z.B. für Clock-Drift zwischen Quelle und Engine).
+
+use std::f64::consts::PI;
+
+/// Halbe Filterlänge in Samples (Taps pro Seite)
+const HALF_TAPS: usize = 32;
+
+/// Gesamte Filterlänge
+const TAPS: usize = HALF_TAPS * 2;
+
+/// Anzahl Phasen der Polyphasen-Tabelle
+const PHASES: usize = 256;
+
+/// Maximale Feinkorrektur des Verhältnisses in ppm
+pub const MAX_RATIO_ADJUST_PPM: f64 = 1000.0;
+
+/// Mehrkanal Sample-Rate-Konverter (interleaved)
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    /// Anzahl Kanäle
+    channels: usize,
+
+    /// Nominales Verhältnis (Eingangs-Samples pro Ausgangs-Sample)
+    nominal_ratio: f64,
+
+    /// Aktuelles Verhältnis inkl. Feinkorrektur
+    ratio: f64,
+
+    /// Polyphasen-Filtertabelle ((PHASES + 1) * TAPS)
+    table: Vec<f32>,
+
+    /// Eingangs-Historie (interleaved)
+    history: Vec<f32>,
+
+    /// Lese-Position in Frames (relativ zum Anfang der Historie)
+    position: f64,
+}
+
+impl Resampler {
+    /// Neuen Konverter erstellen
+    pub fn new(channels: usize, input_rate: u32, output_rate: u32) -> Self {
+        let channels = channels.max(1);
+        let nominal_ratio = input_rate as f64 / output_rate as f64;
+
+        // Beim Heruntertakten muss die Grenzfrequenz mitgehen (Anti-Aliasing)
+        let cutoff = 0.95 * (1.0 / nominal_ratio).min(1.0);
+
+        let mut resampler = Self {
+            channels,
+            nominal_ratio,
+            ratio: nominal_ratio,
+            table: build_table(cutoff),
+            history: Vec::new(),
+            position: 0.0,
+        };
+        resampler.reset();
+        resampler
+    }
+
+    /// Feinkorrektur in ppm setzen
+    ///
+    /// Positive Werte verbrauchen mehr Eingangs-Samples pro Ausgangs-Sample
+    /// (die Quelle läuft schneller als die Engine).
+    pub fn set_ratio_adjust_ppm(&mut self, ppm: f64) {
+        let ppm = ppm.clamp(-MAX_RATIO_ADJUST_PPM, MAX_RATIO_ADJUST_PPM);
+        self.ratio = self.nominal_ratio * (1.0 + ppm * 1e-6);
+    }
+
+    /// Zustand zurücksetzen
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.history.resize(HALF_TAPS * self.channels, 0.0);
+        self.position = HALF_TAPS as f64;
+    }
+
+    /// Eingangs-Frames (interleaved) anhängen
+    pub fn push(&mut self, input: &[f32]) {
+        let frames = input.len() / self.channels;
+        self.history.extend_from_slice(&input[..frames * self.channels]);
+    }
+
+    /// Gepufferte Eingangs-Frames, die noch nicht verbraucht wurden
+    pub fn buffered_frames(&self) -> f64 {
+        self.history_frames() as f64 - self.position
+    }
+
+    /// Anzahl Ausgangs-Frames, die ohne weitere Eingabe erzeugt werden können
+    pub fn available_frames(&self) -> usize {
+        let usable = self.history_frames() as f64 - HALF_TAPS as f64 - self.position;
+        if usable <= 0.0 {
+            0
+        } else {
+            (usable / self.ratio).ceil() as usize
+        }
+    }
+
+    /// Benötigte Eingangs-Frames für `output_frames` zusätzliche Ausgangs-Frames
+    pub fn input_frames_needed(&self, output_frames: usize) -> usize {
+        if output_frames == 0 {
+            return 0;
+        }
+        let last = self.position + (output_frames - 1) as f64 * self.ratio;
+        (last.floor() as usize + HALF_TAPS + 1).saturating_sub(self.history_frames())
+    }
+
+    /// Ausgangs-Frames erzeugen (interleaved), gibt die Anzahl Frames zurück
+    pub fn pull(&mut self, output: &mut [f32]) -> usize {
+        let channels = self.channels;
+        let max_frames = output.len() / channels;
+        let history_frames = self.history_frames();
+        let mut produced = 0;
+
+        while produced < max_frames {
+            let index = self.position.floor() as usize;
+            if index + HALF_TAPS >= history_frames {
+                break;
+            }
+
+            let frac = self.position - index as f64;
+            let phase_pos = frac * PHASES as f64;
+            let phase = (phase_pos as usize).min(PHASES - 1);
+            let blend = (phase_pos - phase as f64) as f32;
+
+            let coeffs_a = &self.table[phase * TAPS..(phase + 1) * TAPS];
+            let coeffs_b = &self.table[(phase + 1) * TAPS..(phase + 2) * TAPS];
+
+            let start = index + 1 - HALF_TAPS;
+            let frame_out = &mut output[produced * channels..(produced + 1) * channels];
+            frame_out.iter_mut().for_each(|s| *s = 0.0);
+
+            for tap in 0..TAPS {
+                let coeff = coeffs_a[tap] + (coeffs_b[tap] - coeffs_a[tap]) * blend;
+                let base = (start + tap) * channels;
+                for (ch, out) in frame_out.iter_mut().enumerate() {
+                    *out += self.history[base + ch] * coeff;
+                }
+            }
+
+            self.position += self.ratio;
+            produced += 1;
+        }
+
+        // Verbrauchte Historie verwerfen (Filter-Vorlauf bleibt erhalten)
+        let consumed = (self.position.floor() as usize + 1)
+            .saturating_sub(HALF_TAPS)
+            .min(history_frames);
+        if consumed > 0 {
+            self.history.drain(..consumed * channels);
+            self.position -= consumed as f64;
+        }
+
+        produced
+    }
+
+    /// Eingabe verarbeiten und alle verfügbaren Ausgangs-Frames anhängen
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) -> usize {
+        self.push(input);
+
+        let frames = self.available_frames();
+        let start = output.len();
+        output.resize(start + frames * self.channels, 0.0);
+        let produced = self.pull(&mut output[start..]);
+        output.truncate(start + produced * self.channels);
+        produced
+    }
+
+    fn history_frames(&self) -> usize {
+        self.history.len() / self.channels
+    }
+}
+
+/// Polyphasen-Tabelle berechnen
+///
+/// Zeile `p` enthält die Koeffizienten für den Bruchteil `p / PHASES`,
+/// Tap `k` gehört zum Eingangs-Sample `index + 1 - HALF_TAPS + k`.
+fn build_table(cutoff: f64) -> Vec<f32> {
+    let mut table = vec![0.0f32; (PHASES + 1) * TAPS];
+
+    for phase in 0..=PHASES {
+        let frac = phase as f64 / PHASES as f64;
+        let row = &mut table[phase * TAPS..(phase + 1) * TAPS];
+        let mut sum = 0.0;
+
+        for (tap, coeff) in row.iter_mut().enumerate() {
+            let t = tap as f64 + 1.0 - HALF_TAPS as f64 - frac;
+            let value = cutoff * sinc(cutoff * t) * window(t);
+            *coeff = value as f32;
+            sum += value;
+        }
+
+        // Auf Einheitsverstärkung normieren (DC)
+        if sum.abs() > 1e-9 {
+            row.iter_mut().for_each(|c| *c = (*c as f64 / sum) as f32);
+        }
+    }
+
+    table
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman-Harris Fenster, zentriert um 0 mit Halbbreite HALF_TAPS
+fn window(t: f64) -> f64 {
+    let half = HALF_TAPS as f64;
+    if t.abs() >= half {
+        return 0.0;
+    }
+    let x = (t + half) / (2.0 * half);
+    0.35875 - 0.48829 * (2.0 * PI * x).cos() + 0.14128 * (4.0 * PI * x).cos()
+        - 0.01168 * (6.0 * PI * x).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|n| (2.0 * std::f32::consts::PI * freq * n as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    /// Maximaler Fehler gegenüber dem idealen Sinus (nach dem Einschwingen)
+    fn sine_error(output: &[f32], freq: f32, rate: u32) -> f32 {
+        output
+            .iter()
+            .enumerate()
+            .skip(200)
+            .map(|(n, &y)| {
+                let t = n as f64 / rate as f64;
+                let expected = (2.0 * std::f64::consts::PI * freq as f64 * t).sin() as f32;
+                (y - expected).abs()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_unity_ratio_passthrough() {
+        let mut resampler = Resampler::new(1, 48000, 48000);
+        let input = sine(1000.0, 48000, 2000);
+        let mut output = Vec::new();
+        resampler.process(&input, &mut output);
+
+        // Der Filter-Vorlauf hält HALF_TAPS Frames zurück, das Signal selbst ist nicht verzögert
+        assert_eq!(output.len(), input.len() - HALF_TAPS);
+        for n in 100..output.len() {
+            assert!((output[n] - input[n]).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_upsample_44100_to_48000() {
+        let mut resampler = Resampler::new(1, 44100, 48000);
+        let input = sine(1000.0, 44100, 4410);
+        let mut output = Vec::new();
+        let frames = resampler.process(&input, &mut output);
+
+        // Etwa 4800 Frames abzüglich Filter-Vorlauf
+        assert!(frames > 4700 && frames <= 4800, "frames = {}", frames);
+
+        assert!(sine_error(&output, 1000.0, 48000) < 0.01);
+    }
+
+    #[test]
+    fn test_downsample_96000_to_48000_stereo() {
+        let mut resampler = Resampler::new(2, 96000, 48000);
+        let mono = sine(440.0, 96000, 9600);
+        let input: Vec<f32> = mono.iter().flat_map(|&s| [s, -s]).collect();
+
+        let mut output = Vec::new();
+        // In kleinen Blöcken verarbeiten wie im Audio-Callback
+        for block in input.chunks(2 * 96) {
+            resampler.process(block, &mut output);
+        }
+
+        let left: Vec<f32> = output.iter().step_by(2).copied().collect();
+        let right: Vec<f32> = output.iter().skip(1).step_by(2).copied().collect();
+        assert!(left.len() > 4700);
+
+        assert!(sine_error(&left, 440.0, 48000) < 0.01);
+        for (l, r) in left.iter().zip(right.iter()) {
+            assert!((l + r).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_downsample_rejects_alias() {
+        // 30 kHz bei 96 kHz würde bei 48 kHz auf 18 kHz spiegeln
+        let mut resampler = Resampler::new(1, 96000, 48000);
+        let input = sine(30000.0, 96000, 9600);
+        let mut output = Vec::new();
+        resampler.process(&input, &mut output);
+
+        let peak = output.iter().skip(100).fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak < 0.01, "alias peak = {}", peak);
+    }
+
+    #[test]
+    fn test_ratio_adjust() {
+        let mut resampler = Resampler::new(1, 48000, 48000);
+        let nominal = resampler.input_frames_needed(100_001);
+
+        // +100 ppm: 10 Eingangs-Samples mehr pro 100000 Ausgangs-Samples
+        resampler.set_ratio_adjust_ppm(100.0);
+        assert!((resampler.input_frames_needed(100_001) - nominal).abs_diff(10) <= 1);
+
+        // Begrenzt auf MAX_RATIO_ADJUST_PPM
+        resampler.set_ratio_adjust_ppm(1e6);
+        let extra = (100_000.0 * MAX_RATIO_ADJUST_PPM * 1e-6) as usize;
+        assert!((resampler.input_frames_needed(100_001) - nominal).abs_diff(extra) <= 1);
+    }
+
+    #[test]
+    fn test_input_frames_needed() {
+        let mut resampler = Resampler::new(2, 44100, 48000);
+        let needed = resampler.input_frames_needed(256);
+        resampler.push(&vec![0.0; needed * 2]);
+        assert!(resampler.available_frames() >= 256);
+
+        let mut out = vec![0.0; 512];
+        assert_eq!(resampler.pull(&mut out), 256);
+    }
+}
@@ -3,10 +3,13 @@
 //! Lädt Server-Konfiguration aus TOML-Datei
 
 use anyhow::{Result, Context};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::net::Ipv4Addr;
 use std::path::Path;
 use std::fs;
 
+use crate::network_audio::{BackendRegistry, PtpTimestamping, TxStreamConfig};
+
 /// Haupt-Konfiguration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -14,6 +17,8 @@ pub struct ServerConfig {
     pub midi: MidiConfig,
     pub api: ApiConfig,
     pub network_audio: NetworkAudioConfig,
+    #[serde(default)]
+    pub nmos: NmosConfig,
 }
 
 /// Audio-Engine Konfiguration
@@ -36,12 +41,64 @@ pub struct AudioConfig {
     pub output_channels: usize,
     
     /// Sample Rate (44100, 48000, 96000)
+    ///
+    /// Gilt für die gesamte Signalkette (Mixer, lokales Audio, AES67).
+    /// Quellen mit abweichender Rate werden per SRC angepasst.
     #[serde(default = "default_sample_rate")]
     pub sample_rate: u32,
     
     /// Buffer-Größe (64, 128, 256, 512, 1024)
     #[serde(default = "default_buffer_size")]
     pub buffer_size: usize,
+    
+    /// Audio-Backend ("default", "alsa", "jack", "pipewire")
+    #[serde(default)]
+    pub backend: AudioBackend,
+    
+    /// Eingabegerät (Name oder Namensteil, leer = Standardgerät)
+    #[serde(default)]
+    pub input_device: Option<String>,
+    
+    /// Ausgabegerät (Name oder Namensteil, leer = Standardgerät)
+    #[serde(default)]
+    pub output_device: Option<String>,
+    
+    /// Anzahl Kanäle, mit denen die Geräte geöffnet werden
+    #[serde(default = "default_device_channels")]
+    pub device_channels: u16,
+    
+    /// Automatische Port-Verbindungen (JACK/PipeWire)
+    #[serde(default)]
+    pub connections: Vec<PortConnection>,
+}
+
+/// Audio-Backend für lokale Geräte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioBackend {
+    /// Standard-Host der Plattform (ALSA unter Linux, WASAPI unter Windows)
+    #[default]
+    Default,
+    /// ALSA direkt (Gerät per Name, z.B. "hw:CARD=USB")
+    Alsa,
+    /// JACK Audio Connection Kit
+    Jack,
+    /// PipeWire (über die JACK-Schnittstelle, sonst über das ALSA-Plugin)
+    PipeWire,
+}
+
+/// Regel für automatische Port-Verbindungen
+///
+/// `source` und `destination` sind reguläre Ausdrücke für JACK-Portnamen.
+/// `{input}` und `{output}` stehen für die eigenen Eingangs- bzw. Ausgangsports.
+/// Passende Ports werden paarweise in natürlicher Reihenfolge verbunden.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortConnection {
+    /// Quell-Ports (Ausgänge)
+    pub source: String,
+    
+    /// Ziel-Ports (Eingänge)
+    pub destination: String,
 }
 
 /// MIDI Konfiguration
@@ -82,27 +139,140 @@ pub struct ApiConfig {
 /// Netzwerk-Audio Konfiguration (AES67/DANTE)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkAudioConfig {
-    /// Backend: "aes67" oder "dante"
-    #[serde(default = "default_backend")]
-    pub backend: String,
+    /// Backends, die gleichzeitig laufen: ein Name oder eine Liste
+    /// ("aes67", "virtual", mit Feature `dante` auch "dante")
+    #[serde(default = "default_backend", deserialize_with = "one_or_many")]
+    pub backend: Vec<String>,
+    
+    /// Kanäle des virtuellen Loopback-Backends (Mixer-Ausgänge zurück auf
+    /// die letzten Mixer-Eingänge)
+    #[serde(default = "default_virtual_channels")]
+    pub virtual_channels: usize,
     
-    /// Netzwerk-Interface
+    /// Netzwerk-Interface für PTP, SAP, mDNS und RTP (Name oder IPv4-Adresse,
+    /// None = alle Interfaces); fehlt es, startet AES67 nicht
     pub interface: Option<String>,
     
-    /// Multicast-Gruppen für AES67
+    /// Zweites Netzwerk-Interface für SMPTE ST 2022-7 Redundanz
     #[serde(default)]
-    pub multicast_groups: Vec<String>,
+    pub secondary_interface: Option<String>,
+    
+    /// Multicast-Gruppen der Standard-Streams (je ein Stream mit 8 Kanälen,
+    /// nur ohne `streams`)
+    #[serde(default)]
+    pub multicast_groups: Vec<Ipv4Addr>,
+    
+    /// Link Offset für empfangene Streams in µs (Wiedergabe hinter PTP-Zeit)
+    #[serde(default = "default_link_offset_us")]
+    pub link_offset_us: u32,
+    
+    /// PTP-Master übernehmen, wenn keine bessere Clock im Netz ist (BMCA)
+    #[serde(default = "default_true")]
+    pub ptp_master: bool,
+    
+    /// PTP priority1 als Master (kleiner gewinnt, Geräte-Default ist 128)
+    #[serde(default = "default_ptp_priority1")]
+    pub ptp_priority1: u8,
+    
+    /// Zeitstempel für PTP: "auto", "hardware", "software" (Kernel) oder "user"
+    #[serde(default)]
+    pub ptp_timestamping: PtpTimestamping,
+    
+    /// UDP-Ports für PTP Event- und General-Nachrichten (Standard 319/320,
+    /// benötigen root oder CAP_NET_BIND_SERVICE)
+    #[serde(default = "default_ptp_event_port")]
+    pub ptp_event_port: u16,
+    #[serde(default = "default_ptp_general_port")]
+    pub ptp_general_port: u16,
+    
+    /// Gesendete AES67 Streams (leer = ein Stream mit 8 Kanälen)
+    #[serde(default)]
+    pub streams: Vec<TxStreamConfig>,
+}
+
+/// NMOS Node Konfiguration (IS-04 Registrierung, IS-05 Verbindungen)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NmosConfig {
+    /// NMOS Node aktiviert (benötigt das AES67 Backend)
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Name des Nodes (None = Hostname)
+    #[serde(default)]
+    pub label: Option<String>,
+    
+    /// Registry-URL ("http://host:port", None = Suche per DNS-SD)
+    #[serde(default)]
+    pub registry: Option<String>,
+    
+    /// Anzahl der NMOS Receiver
+    #[serde(default = "default_nmos_receivers")]
+    pub receivers: usize,
+    
+    /// Kanäle je Receiver
+    #[serde(default = "default_nmos_receiver_channels")]
+    pub receiver_channels: usize,
+    
+    /// Mixer-Eingang des ersten Receivers (1-basiert), die Receiver
+    /// belegen die folgenden Eingänge lückenlos
+    #[serde(default = "default_nmos_first_input")]
+    pub first_input: usize,
+    
+    /// Heartbeat-Intervall zur Registry in Sekunden
+    #[serde(default = "default_nmos_heartbeat_s")]
+    pub heartbeat_interval_s: u64,
 }
 
+/// Unterstützte Sample Rates in Hz
+pub const SUPPORTED_SAMPLE_RATES: [u32; 3] = [44100, 48000, 96000];
+
 // Default-Werte
 fn default_channels() -> usize { 32 }
 fn default_sample_rate() -> u32 { 48000 }
 fn default_buffer_size() -> usize { 256 }
+fn default_device_channels() -> u16 { 2 }
 fn default_true() -> bool { true }
 fn default_host() -> String { "0.0.0.0".to_string() }
 fn default_port() -> u16 { 8080 }
 fn default_max_clients() -> usize { 10 }
-fn default_backend() -> String { "aes67".to_string() }
+fn default_backend() -> Vec<String> { vec!["aes67".to_string()] }
+fn default_virtual_channels() -> usize { 8 }
+
+/// Einzelner Wert oder Liste (`backend = "aes67"` wie `backend = ["aes67"]`)
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(name) => vec![name],
+        OneOrMany::Many(names) => names,
+    })
+}
+fn default_link_offset_us() -> u32 { 3000 }
+fn default_ptp_priority1() -> u8 { 250 }
+fn default_ptp_event_port() -> u16 { 319 }
+fn default_ptp_general_port() -> u16 { 320 }
+fn default_nmos_receivers() -> usize { 4 }
+fn default_nmos_receiver_channels() -> usize { 8 }
+fn default_nmos_first_input() -> usize { 1 }
+fn default_nmos_heartbeat_s() -> u64 { 5 }
+
+impl Default for NmosConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            label: None,
+            registry: None,
+            receivers: default_nmos_receivers(),
+            receiver_channels: default_nmos_receiver_channels(),
+            first_input: default_nmos_first_input(),
+            heartbeat_interval_s: default_nmos_heartbeat_s(),
+        }
+    }
+}
 
 impl Default for ServerConfig {
     fn default() -> Self {
@@ -114,6 +284,11 @@ impl Default for ServerConfig {
                 sample_rate: 48000,
                 buffer_size: 256,
                 aes67_enabled: Some(true),
+                backend: AudioBackend::Default,
+                input_device: None,
+                output_device: None,
+                device_channels: 2,
+                connections: vec![],
             },
             midi: MidiConfig {
                 enabled: true,
@@ -127,10 +302,20 @@ impl Default for ServerConfig {
                 max_clients: 10,
             },
             network_audio: NetworkAudioConfig {
-                backend: "aes67".to_string(),
+                backend: default_backend(),
+                virtual_channels: 8,
                 interface: None,
+                secondary_interface: None,
                 multicast_groups: vec![],
+                link_offset_us: 3000,
+                ptp_master: true,
+                ptp_priority1: 250,
+                ptp_timestamping: PtpTimestamping::Auto,
+                ptp_event_port: 319,
+                ptp_general_port: 320,
+                streams: vec![],
             },
+            nmos: NmosConfig::default(),
         }
     }
 }
@@ -163,11 +348,68 @@ impl ServerConfig {
         
         let config: ServerConfig = toml::from_str(&content)
             .with_context(|| format!("Fehler beim Parsen von {}", path))?;
+        config.validate()?;
         
         tracing::info!("Konfiguration geladen von: {}", path);
         Ok(config)
     }
 
+    /// Konfiguration prüfen
+    pub fn validate(&self) -> Result<()> {
+        if !SUPPORTED_SAMPLE_RATES.contains(&self.audio.sample_rate) {
+            anyhow::bail!(
+                "Nicht unterstützte Sample Rate {} Hz (erlaubt: {:?})",
+                self.audio.sample_rate,
+                SUPPORTED_SAMPLE_RATES
+            );
+        }
+        if self.audio.device_channels == 0 || self.audio.device_channels > 64 {
+            anyhow::bail!("Ungültige Kanalanzahl {} (1-64)", self.audio.device_channels);
+        }
+        if self.network_audio.link_offset_us == 0 || self.network_audio.link_offset_us > 100_000 {
+            anyhow::bail!("Ungültiger Link Offset {} µs (1-100000)", self.network_audio.link_offset_us);
+        }
+        let registry = BackendRegistry::with_defaults();
+        for name in &self.network_audio.backend {
+            if !registry.contains(name) {
+                anyhow::bail!("Unbekanntes Netzwerk-Audio Backend '{}' (verfügbar: {})", name, registry.names().join(", "));
+            }
+        }
+        if self.network_audio.virtual_channels == 0 || self.network_audio.virtual_channels > 64 {
+            anyhow::bail!("Ungültige Kanalanzahl {} für das virtuelle Backend (1-64)", self.network_audio.virtual_channels);
+        }
+        for stream in &self.network_audio.streams {
+            stream.validate(self.audio.sample_rate, self.audio.output_channels)
+                .context("Ungültiger AES67 Stream")?;
+        }
+        if self.nmos.enabled {
+            self.validate_nmos()?;
+        }
+        Ok(())
+    }
+
+    /// NMOS Receiver müssen auf vorhandene Mixer-Eingänge passen
+    fn validate_nmos(&self) -> Result<()> {
+        let nmos = &self.nmos;
+        if nmos.receiver_channels == 0 || nmos.receiver_channels > 64 {
+            anyhow::bail!("Ungültige Kanalanzahl {} je NMOS Receiver (1-64)", nmos.receiver_channels);
+        }
+        let last_input = (nmos.first_input + nmos.receivers * nmos.receiver_channels).saturating_sub(1);
+        if nmos.first_input == 0 || last_input > self.audio.input_channels {
+            anyhow::bail!(
+                "NMOS Receiver belegen die Eingänge {}-{}, der Mixer hat {} Eingänge",
+                nmos.first_input, last_input, self.audio.input_channels
+            );
+        }
+        if nmos.heartbeat_interval_s == 0 || nmos.heartbeat_interval_s > 60 {
+            anyhow::bail!("Ungültiges NMOS Heartbeat-Intervall {} s (1-60)", nmos.heartbeat_interval_s);
+        }
+        if nmos.registry.as_deref().is_some_and(|url| !url.starts_with("http://")) {
+            anyhow::bail!("NMOS Registry muss eine http:// URL sein");
+        }
+        Ok(())
+    }
+
     /// Konfiguration in Datei speichern
     pub fn save_to_file(&self, path: &str) -> Result<()> {
         let content = toml::to_string_pretty(self)
@@ -179,3 +421,38 @@ impl ServerConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_backend_selection() {
+        let network: NetworkAudioConfig = toml::from_str(r#"backend = "aes67""#).unwrap();
+        assert_eq!(network.backend, vec!["aes67"]);
+        assert_eq!(network.virtual_channels, 8);
+
+        let network: NetworkAudioConfig = toml::from_str(r#"backend = ["aes67", "virtual"]"#).unwrap();
+        assert_eq!(network.backend, vec!["aes67", "virtual"]);
+        let config = ServerConfig { network_audio: network, ..Default::default() };
+        assert!(config.validate().is_ok());
+
+        let network: NetworkAudioConfig = toml::from_str(r#"backend = ["aes67", "ravenna"]"#).unwrap();
+        let config = ServerConfig { network_audio: network, ..Default::default() };
+        assert!(config.validate().unwrap_err().to_string().contains("'ravenna'"));
+    }
+
+    #[test]
+    fn test_nmos_receivers_fit_inputs() {
+        let nmos: NmosConfig = toml::from_str("enabled = true\nreceivers = 4\nfirst_input = 1").unwrap();
+        assert_eq!((nmos.receiver_channels, nmos.heartbeat_interval_s), (8, 5));
+        let mut config = ServerConfig { nmos, ..Default::default() };
+        assert!(config.validate().is_ok());
+
+        // 4 x 8 Kanäle ab Eingang 2 enden bei 33
+        config.nmos.first_input = 2;
+        assert!(config.validate().unwrap_err().to_string().contains("2-33"));
+        config.nmos.enabled = false;
+        assert!(config.validate().is_ok());
+    }
+}
@@ -0,0 +1,13 @@
+//! AudioMultiverse Server
+//!
+//! Audio-Engine, Mixer, Netzwerk-Audio (AES67, NMOS), MIDI und API. Das
+//! Programm in `main.rs` verbindet die Module; als Bibliothek stehen sie
+//! auch den Integrationstests in `tests/` zur Verfügung.
+
+pub mod audio;
+pub mod midi;
+pub mod network_audio;
+pub mod api;
+pub mod config;
+pub mod mixer;
+pub mod discovery;
@@ -9,26 +9,23 @@
 //! - WebSocket API für Remote-Clients
 //! - REST API für Hausautomatisierung
 
-mod audio;
-mod midi;
-mod network_audio;
-mod api;
-mod config;
-mod mixer;
-mod discovery;
-
+use std::net::Ipv4Addr;
 use std::sync::Arc;
+use std::time::Duration;
 use anyhow::Result;
 use tokio::sync::RwLock;
-use tracing::{info, Level};
+use tracing::{info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
-use crate::config::ServerConfig;
-use crate::mixer::{Mixer, SceneManager, MasterSection};
-use crate::audio::{AudioEngine, AudioCommandSender};
-use crate::midi::MidiController;
-use crate::api::start_api_server;
-use crate::network_audio::{SapDiscovery, PtpClock};
+use audiomultiverse_server::config::ServerConfig;
+use audiomultiverse_server::mixer::{Mixer, SceneManager, MasterSection};
+use audiomultiverse_server::audio::{AudioEngine, AudioCommand, AudioCommandSender, DriftMonitor};
+use audiomultiverse_server::midi::MidiController;
+use audiomultiverse_server::api::{start_api_server, ApiContext};
+use audiomultiverse_server::network_audio::{Aes67Config, BackendContext, BackendRegistry, NetworkBackends};
+use audiomultiverse_server::network_audio::interface;
+use audiomultiverse_server::network_audio::nmos::{NmosNode, NodeConfig, Registration};
+use audiomultiverse_server::discovery::DiscoveryService;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -49,9 +46,10 @@ async fn main() -> Result<()> {
           config.audio.output_channels);
 
     // Mixer erstellen
-    let mixer = Arc::new(Mixer::new(
+    let mixer = Arc::new(Mixer::with_sample_rate(
         config.audio.input_channels,
         config.audio.output_channels,
+        config.audio.sample_rate,
     ));
     info!("Mixer initialisiert: {}x{} Matrix", 
           config.audio.input_channels, 
@@ -66,21 +64,43 @@ async fn main() -> Result<()> {
     let scene_manager = Arc::new(RwLock::new(SceneManager::new(&scenes_path)));
     info!("Szenen-Manager initialisiert: {}", scenes_path);
 
+    // Clock-Drift Messung (Soundkarte gegenüber PTP), geteilt mit der API
+    let drift_monitor = Arc::new(DriftMonitor::new());
+
     // Audio-Engine Konfiguration für den Thread vorbereiten
     let audio_sample_rate = config.audio.sample_rate;
     let audio_buffer_size = config.audio.buffer_size;
     let audio_enabled = config.audio.enabled;
+    let audio_input_channels = config.audio.input_channels;
+    let audio_output_channels = config.audio.output_channels;
+    let aes67_streams = config.network_audio.streams.clone();
+    let aes67_link_offset_us = config.network_audio.link_offset_us;
+    let aes67_subscriptions_file = shellexpand::tilde("~/.audiomultiverse/aes67_subscriptions.json").to_string();
+    let aes67_ptp_master = config.network_audio.ptp_master;
+    let aes67_ptp_priority1 = config.network_audio.ptp_priority1;
+    let aes67_ptp_timestamping = config.network_audio.ptp_timestamping;
+    let aes67_ptp_ports = (config.network_audio.ptp_event_port, config.network_audio.ptp_general_port);
+    let aes67_interface = config.network_audio.interface.clone();
+    let aes67_secondary_interface = config.network_audio.secondary_interface.clone();
+    let aes67_multicast_groups = config.network_audio.multicast_groups.clone();
+    let audio_config = config.audio.clone();
     let aes67_enabled = config.audio.aes67_enabled.unwrap_or(true);
+    // Netzwerk-Backends laut Konfiguration (AES67 lässt sich auch per audio.aes67_enabled abschalten)
+    let network_backend_names: Vec<String> = config.network_audio.backend.iter()
+        .filter(|name| aes67_enabled || !name.eq_ignore_ascii_case("aes67"))
+        .cloned()
+        .collect();
+    let virtual_channels = config.network_audio.virtual_channels;
     let mixer_for_audio = mixer.clone();
     let master_for_audio = master.clone();
+    let drift_for_audio = drift_monitor.clone();
     
     // Command-Channel erstellen (Sender bleibt hier, Receiver geht in den Thread)
-    let (cmd_tx, cmd_rx) = tokio::sync::mpsc::channel::<crate::audio::AudioCommand>(32);
+    let (cmd_tx, cmd_rx) = tokio::sync::mpsc::channel::<AudioCommand>(32);
     let audio_cmd = AudioCommandSender::from_sender(cmd_tx);
     
-    // Channels für SAP/PTP Referenzen aus dem Audio-Thread
-    let (sap_tx, sap_rx) = std::sync::mpsc::channel::<Option<Arc<SapDiscovery>>>();
-    let (ptp_tx, ptp_rx) = std::sync::mpsc::channel::<Option<Arc<PtpClock>>>();
+    // Channel für die Netzwerk-Backends aus dem Audio-Thread (SAP, PTP, AES67 Streams)
+    let (backends_tx, backends_rx) = std::sync::mpsc::channel::<NetworkBackends>();
     
     // Audio Engine komplett im eigenen Thread erstellen und starten
     // Damit vermeiden wir das Send-Problem mit cpal::Stream
@@ -94,7 +114,11 @@ async fn main() -> Result<()> {
         let mut audio_engine = AudioEngine::new(audio_sample_rate, audio_buffer_size);
         audio_engine.set_mixer(mixer_for_audio);
         audio_engine.set_master(master_for_audio);
+        audio_engine.set_drift_monitor(drift_for_audio);
         audio_engine.set_command_receiver(cmd_rx);
+        if let Err(e) = audio_engine.configure(&audio_config) {
+            warn!("Audio-Backend konnte nicht konfiguriert werden: {} (verwende Standard-Host)", e);
+        }
         
         // Audio-Geräte auflisten
         info!("Verfügbare Audio-Geräte:");
@@ -116,45 +140,66 @@ async fn main() -> Result<()> {
             }
         }
 
-        // AES67 Network Audio initialisieren
-        if aes67_enabled {
-            match audio_engine.init_aes67(None) {
+        // Netzwerk-Audio Backends initialisieren (AES67, virtuell, ...)
+        if !network_backend_names.is_empty() {
+            let aes67_config = Aes67Config {
+                interface: aes67_interface,
+                secondary_interface: aes67_secondary_interface,
+                multicast_groups: aes67_multicast_groups,
+                sample_rate: audio_sample_rate,
+                input_channels: audio_input_channels.min(u8::MAX as usize) as u8,
+                mixer_outputs: audio_output_channels,
+                streams: aes67_streams,
+                link_offset_us: aes67_link_offset_us,
+                ptp_master: aes67_ptp_master,
+                ptp_priority1: aes67_ptp_priority1,
+                ptp_timestamping: aes67_ptp_timestamping,
+                ptp_ports: aes67_ptp_ports,
+                buffer_size: audio_buffer_size,
+                subscriptions_file: Some(aes67_subscriptions_file.into()),
+                ..Default::default()
+            };
+            let context = BackendContext {
+                sample_rate: audio_sample_rate,
+                aes67: aes67_config,
+                virtual_channels,
+                drift_monitor: audio_engine.drift_monitor(),
+            };
+            match audio_engine.init_network(&BackendRegistry::with_defaults(), &network_backend_names, context) {
                 Ok(_) => {
-                    info!("🌐 AES67 Network Audio initialisiert");
+                    info!("🌐 Network Audio initialisiert: {}", audio_engine.network_backends().names().join(", "));
                 }
-                Err(e) => info!("AES67 konnte nicht initialisiert werden: {} (nur lokales Audio)", e),
+                Err(e) => info!("Network Audio konnte nicht initialisiert werden: {} (nur lokales Audio)", e),
             }
         }
         
-        // SAP/PTP Referenzen an Hauptthread senden
-        let _ = sap_tx.send(audio_engine.sap_discovery());
-        let _ = ptp_tx.send(audio_engine.ptp_clock());
+        // Netzwerk-Backends an Hauptthread senden
+        let _ = backends_tx.send(audio_engine.network_backends());
         
         // Command-Loop
         while audio_running_clone.load(std::sync::atomic::Ordering::Relaxed) {
             audio_engine.process_commands();
+            audio_engine.update_drift();
+            audio_engine.supervise_aes67();
             std::thread::sleep(std::time::Duration::from_millis(10));
         }
         
         info!("🔊 Audio-Thread beendet");
     });
     
-    // Warte auf SAP/PTP Referenzen vom Audio-Thread
-    let sap_discovery = sap_rx.recv().ok().flatten();
-    let ptp_clock = ptp_rx.recv().ok().flatten();
+    // Warte auf die Netzwerk-Backends vom Audio-Thread
+    let network_backends = backends_rx.recv().unwrap_or_default();
     
     // Kurz warten für AES67 Discovery
-    if sap_discovery.is_some() {
+    if let Some(sap) = network_backends.sap_discovery() {
         tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-        if let Some(ref sap) = sap_discovery {
-            let streams = sap.streams();
-            if streams.is_empty() {
-                info!("   Keine AES67 Geräte im Netzwerk gefunden (noch)");
-            } else {
-                info!("   {} AES67 Gerät(e) gefunden:", streams.len());
-                for stream in &streams {
-                    info!("     - {} ({} channels)", stream.name, stream.channels);
-                }
+        let streams = sap.streams();
+        if streams.is_empty() {
+            info!("   Keine AES67 Geräte im Netzwerk gefunden (noch)");
+        } else {
+            info!("   {} AES67 Gerät(e) gefunden:", streams.len());
+            for stream in &streams {
+                info!("     - {} ({} channels)", stream.name, stream.channels);
             }
         }
     }
@@ -171,7 +216,7 @@ async fn main() -> Result<()> {
     }
 
     // mDNS Discovery Service starten
-    let mut discovery_service = match discovery::DiscoveryService::new() {
+    let mut discovery_service = match DiscoveryService::new() {
         Ok(svc) => {
             info!("mDNS/DNS-SD Discovery Service gestartet");
             Some(svc)
@@ -192,21 +237,89 @@ async fn main() -> Result<()> {
         }
     }
 
+    // NMOS Node für Broadcast-Controller (bleibt bis zum Ende registriert)
+    let nmos = start_nmos(&config, &network_backends);
+    
     // API Server starten (blockiert)
     info!("API Server startet auf {}:{}", config.api.host, config.api.port);
-    start_api_server(
-        config.api.clone(),
-        mixer.clone(),
-        scene_manager.clone(),
-        master.clone(),
-        sap_discovery,
-        ptp_clock,
-        Some(audio_cmd),
-    ).await?;
+    start_api_server(config.api.clone(), ApiContext {
+        mixer: mixer.clone(),
+        scene_manager: scene_manager.clone(),
+        master: master.clone(),
+        network_backends,
+        nmos: nmos.as_ref().map(|(node, _)| node.clone()),
+        audio_cmd: Some(audio_cmd),
+        drift_monitor,
+    }).await?;
     
-    // Cleanup: Audio-Thread stoppen
+    // Cleanup: NMOS Ressourcen abmelden, Audio-Thread stoppen
+    drop(nmos);
     audio_running.store(false, std::sync::atomic::Ordering::Relaxed);
     let _ = audio_thread.join();
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// NMOS Node starten (IS-04 Registrierung, IS-05 Verbindungen)
+///
+/// Nur mit laufendem AES67 Backend. Controller und Registry erreichen den
+/// Node unter der API-Adresse, ohne feste Adresse unter der des
+/// AES67-Interfaces.
+fn start_nmos(config: &ServerConfig, backends: &NetworkBackends) -> Option<(Arc<NmosNode>, Registration)> {
+    if !config.nmos.enabled {
+        return None;
+    }
+    if backends.get("aes67").is_none() {
+        warn!("NMOS benötigt das AES67 Backend, NMOS Node nicht gestartet");
+        return None;
+    }
+
+    // Interfaces der Netzwerk-Wege als (Name, Adresse)
+    let named = |address: Ipv4Addr| (interface::interface_name(address).unwrap_or_else(|| address.to_string()), address);
+    let primary = match &config.network_audio.interface {
+        Some(name) => interface::ipv4_address(name).ok(),
+        None => interface::default_ipv4_address(),
+    };
+    let Some(primary) = primary.map(named) else {
+        warn!("Keine IPv4-Adresse für den NMOS Node gefunden");
+        return None;
+    };
+    let mut interfaces = vec![primary.clone()];
+    if let Some(secondary) = &config.network_audio.secondary_interface {
+        match interface::ipv4_address(secondary) {
+            Ok(address) => interfaces.push(named(address)),
+            Err(e) => warn!("NMOS: zweites Interface nicht verfügbar: {}", e),
+        }
+    }
+    let address = config.api.host.parse::<Ipv4Addr>().ok()
+        .filter(|address| !address.is_unspecified())
+        .unwrap_or(primary.1);
+    let label = config.nmos.label.clone().unwrap_or_else(|| {
+        hostname::get()
+            .map(|h| h.to_string_lossy().to_string())
+            .unwrap_or_else(|_| "AudioMultiverse".to_string())
+    });
+
+    let node = Arc::new(NmosNode::new(NodeConfig {
+        label,
+        address,
+        port: config.api.port,
+        legs: interfaces.len(),
+        interfaces,
+        receivers: config.nmos.receivers,
+        receiver_channels: config.nmos.receiver_channels,
+        first_input: config.nmos.first_input - 1,
+        registry: config.nmos.registry.clone(),
+        heartbeat_interval: Duration::from_secs(config.nmos.heartbeat_interval_s),
+    }, backends.clone()));
+    match Registration::start(node.clone()) {
+        Ok(registration) => {
+            info!("📇 NMOS Node {} gestartet ({} Receiver)", node.node_id(), config.nmos.receivers);
+            Some((node, registration))
+        }
+        Err(e) => {
+            warn!("NMOS Registrierung konnte nicht starten: {}", e);
+            None
+        }
+    }
+}
@@ -3,8 +3,24 @@
 //! Repräsentiert einen Eingangskanal mit allen Parametern
 
 use audiomultiverse_protocol::ChannelState;
+use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicU32, Ordering};
 
+use super::matrix::{AUX_BUS_COUNT, GROUP_BUS_COUNT};
+
+/// Bus-Sends eines Kanals (für API/UI)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelBusSends {
+    /// Kanal-ID (0-basiert)
+    pub id: u32,
+
+    /// Aux-Send-Pegel pro Aux-Bus (0.0 - 1.0, linear, post-fader)
+    pub aux: Vec<f32>,
+
+    /// Zuweisung auf die Gruppen-Busse
+    pub groups: Vec<bool>,
+}
+
 /// Ein Mixer-Kanal
 pub struct Channel {
     /// Kanal-ID (0-basiert)
@@ -34,6 +50,12 @@ pub struct Channel {
     /// Kanal-Farbe (RGB Hex)
     color: String,
     
+    /// Aux-Send-Pegel (0.0 - 1.0, linear, post-fader)
+    aux_sends: [f32; AUX_BUS_COUNT],
+    
+    /// Zuweisung auf die Gruppen-Busse
+    groups: [bool; GROUP_BUS_COUNT],
+    
     /// Aktueller Peak-Meter Wert (0.0 - 1.0)
     /// Atomic für lock-free Audio-Thread Updates
     meter_peak: AtomicU32,
@@ -55,6 +77,8 @@ impl Channel {
             gain: 0.0,
             phase_invert: false,
             color: "#3B82F6".to_string(), // Blau
+            aux_sends: [0.0; AUX_BUS_COUNT],
+            groups: [false; GROUP_BUS_COUNT],
             meter_peak: AtomicU32::new(0),
             meter_peak_hold: AtomicU32::new(0),
         }
@@ -76,6 +100,15 @@ impl Channel {
         }
     }
 
+    /// Bus-Sends für API/UI
+    pub fn bus_sends(&self) -> ChannelBusSends {
+        ChannelBusSends {
+            id: self.id,
+            aux: self.aux_sends.to_vec(),
+            groups: self.groups.to_vec(),
+        }
+    }
+
     // === Setter ===
 
     pub fn set_fader(&mut self, value: f32) {
@@ -110,6 +143,38 @@ impl Channel {
         self.color = color;
     }
 
+    /// Aux-Send setzen (false bei ungültigem Bus)
+    pub fn set_aux_send(&mut self, bus: usize, level: f32) -> bool {
+        match self.aux_sends.get_mut(bus) {
+            Some(send) => {
+                *send = level.clamp(0.0, 1.0);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gruppen-Zuweisung setzen (false bei ungültiger Gruppe)
+    pub fn set_group(&mut self, group: usize, assigned: bool) -> bool {
+        match self.groups.get_mut(group) {
+            Some(assign) => {
+                *assign = assigned;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Aux-Send-Pegel (für Audio-Processing)
+    pub fn aux_sends(&self) -> &[f32; AUX_BUS_COUNT] {
+        &self.aux_sends
+    }
+
+    /// Gruppen-Zuweisung (für Audio-Processing)
+    pub fn groups(&self) -> &[bool; GROUP_BUS_COUNT] {
+        &self.groups
+    }
+
     // === Metering ===
 
     /// Aktuellen Peak-Wert abrufen
@@ -226,4 +291,19 @@ mod tests {
         assert!(l > 0.9);
         assert!(r < 0.1);
     }
+
+    #[test]
+    fn test_bus_sends() {
+        let mut ch = Channel::new(3, "Test".to_string());
+        assert!(ch.set_aux_send(1, 1.5));
+        assert!(ch.set_group(0, true));
+        assert!(!ch.set_aux_send(AUX_BUS_COUNT, 0.5));
+        assert!(!ch.set_group(GROUP_BUS_COUNT, true));
+
+        let sends = ch.bus_sends();
+        assert_eq!(sends.id, 3);
+        assert_eq!(sends.aux[1], 1.0);
+        assert!(sends.groups[0]);
+        assert!(!sends.groups[1]);
+    }
 }
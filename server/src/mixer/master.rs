@@ -403,7 +403,7 @@ fn fader_to_db(fader: f32) -> f32 {
 }
 
 /// dB zu linearem Gain
-fn db_to_linear(db: f32) -> f32 {
+pub(crate) fn db_to_linear(db: f32) -> f32 {
     if db <= -100.0 {
         0.0
     } else {
@@ -0,0 +1,531 @@
+//! Matrix-Ausgänge
+//!
+//! Bus-Schicht zwischen Mixer und physischen/AES67 Ausgängen:
+//! Main L/R, Aux- und Gruppen-Busse werden pro Ausgang gemischt,
+//! anschließend folgen Pegel, Mute und das Lautsprecher-Processing
+//! (Crossover, EQ, Gain/Polarität, Limiter, Delay) pro Ausgang.
+//!
+//! Die API ändert die Parameter in `OutputMatrix`, gerechnet wird im
+//! Audio-Thread mit `MatrixDsp`, der Änderungen zu Blockbeginn übernimmt.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+
+use crate::audio::{EqBandParams, OutputProcessingParams, OutputProcessor};
+use super::master::db_to_linear;
+
+/// Anzahl Stereo Aux-Busse
+pub const AUX_BUS_COUNT: usize = 8;
+
+/// Anzahl Stereo Gruppen-Busse
+pub const GROUP_BUS_COUNT: usize = 4;
+
+/// Quelle für einen Matrix-Send
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MatrixSource {
+    /// Main Bus links
+    MainLeft,
+    /// Main Bus rechts
+    MainRight,
+    /// Aux Bus (Index) links
+    AuxLeft(u32),
+    /// Aux Bus (Index) rechts
+    AuxRight(u32),
+    /// Gruppe (Index) links
+    GroupLeft(u32),
+    /// Gruppe (Index) rechts
+    GroupRight(u32),
+}
+
+impl MatrixSource {
+    /// Prüfen ob der Bus-Index existiert
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            MatrixSource::MainLeft | MatrixSource::MainRight => true,
+            MatrixSource::AuxLeft(i) | MatrixSource::AuxRight(i) => (i as usize) < AUX_BUS_COUNT,
+            MatrixSource::GroupLeft(i) | MatrixSource::GroupRight(i) => (i as usize) < GROUP_BUS_COUNT,
+        }
+    }
+}
+
+/// Ein Send von einem Bus auf einen Matrix-Ausgang
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MatrixSend {
+    /// Quelle
+    pub source: MatrixSource,
+
+    /// Send-Pegel (0.0 - 1.0, linear)
+    pub level: f32,
+}
+
+/// Zustand eines Matrix-Ausgangs (für API/UI)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixOutputState {
+    /// Ausgangs-ID (0-basiert)
+    pub id: u32,
+
+    /// Name
+    pub name: String,
+
+    /// Ausgangspegel in dB (-100 = -∞ bis +10)
+    pub level_db: f32,
+
+    /// Mute
+    pub mute: bool,
+
+    /// Pegel folgt dem Master-Fader
+    pub follow_main: bool,
+
+    /// Bus-Sends
+    pub sends: Vec<MatrixSend>,
+
+    /// Lautsprecher-Processing
+    pub processing: OutputProcessingParams,
+
+    /// Aktuelle Limiter Gain Reduction in dB (nur lesend)
+    pub limiter_gr_db: f32,
+}
+
+/// Ein Bus-Frame (ein Sample pro Bus-Seite)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BusFrame {
+    /// Main L/R
+    pub main: (f32, f32),
+
+    /// Aux-Busse L/R
+    pub aux: [(f32, f32); AUX_BUS_COUNT],
+
+    /// Gruppen-Busse L/R
+    pub groups: [(f32, f32); GROUP_BUS_COUNT],
+}
+
+impl BusFrame {
+    /// Alle Busse auf 0 setzen
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Sample einer Quelle abrufen
+    pub fn source(&self, source: MatrixSource) -> f32 {
+        match source {
+            MatrixSource::MainLeft => self.main.0,
+            MatrixSource::MainRight => self.main.1,
+            MatrixSource::AuxLeft(i) => self.aux.get(i as usize).map_or(0.0, |b| b.0),
+            MatrixSource::AuxRight(i) => self.aux.get(i as usize).map_or(0.0, |b| b.1),
+            MatrixSource::GroupLeft(i) => self.groups.get(i as usize).map_or(0.0, |b| b.0),
+            MatrixSource::GroupRight(i) => self.groups.get(i as usize).map_or(0.0, |b| b.1),
+        }
+    }
+}
+
+/// Anzahl möglicher Send-Quellen (Main, Aux und Gruppen, je L/R)
+const SOURCE_COUNT: usize = 2 + 2 * AUX_BUS_COUNT + 2 * GROUP_BUS_COUNT;
+
+/// Sends, Pegel und Mute eines Ausgangs für den Audio-Thread
+///
+/// Feste Größe, damit der Audio-Thread die Parameter ohne Allokation
+/// übernehmen kann.
+#[derive(Debug, Clone, Copy)]
+struct OutputMix {
+    sends: [(MatrixSource, f32); SOURCE_COUNT],
+    send_count: usize,
+    gain: f32,
+    mute: bool,
+    follow_main: bool,
+}
+
+impl OutputMix {
+    fn of(state: &MatrixOutputState) -> Self {
+        let mut mix = Self {
+            sends: [(MatrixSource::MainLeft, 0.0); SOURCE_COUNT],
+            send_count: 0,
+            gain: db_to_linear(state.level_db),
+            mute: state.mute,
+            follow_main: state.follow_main,
+        };
+        for send in state.sends.iter().take(SOURCE_COUNT) {
+            mix.sends[mix.send_count] = (send.source, send.level);
+            mix.send_count += 1;
+        }
+        mix
+    }
+}
+
+/// Neue Processoren für den Audio-Thread und ersetzte zum Freigeben
+#[derive(Default)]
+struct ProcessorHandoff {
+    /// Vom API-Thread gebaut, vom Audio-Thread übernommen
+    incoming: Vec<Option<OutputProcessor>>,
+    /// Vom Audio-Thread ersetzt, im API-Thread freigegeben
+    retired: Vec<Option<OutputProcessor>>,
+}
+
+/// Übergabe zwischen API und Audio-Thread
+///
+/// Der API-Thread sperrt blockierend, der Audio-Thread nur mit `try_lock`
+/// und behält bei Konkurrenz die Parameter des letzten Blocks.
+struct MatrixShared {
+    mixes: Mutex<Vec<OutputMix>>,
+    processors: Mutex<ProcessorHandoff>,
+    /// Limiter Gain Reduction pro Ausgang (als Bits)
+    limiter_gr_bits: Vec<AtomicU32>,
+}
+
+/// Ein Matrix-Ausgang (Parameter und Processor-Vorlage)
+struct MatrixOutput {
+    state: MatrixOutputState,
+    processor: OutputProcessor,
+}
+
+/// Matrix aller Ausgänge (Parameter für die API)
+///
+/// Gerechnet wird im Audio-Thread mit `MatrixDsp`, Änderungen werden
+/// dorthin übergeben.
+pub struct OutputMatrix {
+    outputs: Vec<MatrixOutput>,
+    shared: Arc<MatrixShared>,
+}
+
+impl OutputMatrix {
+    /// Neue Matrix erstellen (ohne Sends, alle Ausgänge auf 0dB)
+    pub fn new(output_count: usize, sample_rate: f32) -> Self {
+        let outputs: Vec<MatrixOutput> = (0..output_count)
+            .map(|i| {
+                let processor = OutputProcessor::new(sample_rate);
+                MatrixOutput {
+                    state: MatrixOutputState {
+                        id: i as u32,
+                        name: format!("OUT {}", i + 1),
+                        level_db: 0.0,
+                        mute: false,
+                        follow_main: false,
+                        sends: vec![],
+                        processing: processor.params().clone(),
+                        limiter_gr_db: 0.0,
+                    },
+                    processor,
+                }
+            })
+            .collect();
+
+        let shared = Arc::new(MatrixShared {
+            mixes: Mutex::new(outputs.iter().map(|o| OutputMix::of(&o.state)).collect()),
+            processors: Mutex::new(ProcessorHandoff {
+                incoming: (0..output_count).map(|_| None).collect(),
+                retired: (0..output_count).map(|_| None).collect(),
+            }),
+            limiter_gr_bits: (0..output_count).map(|_| AtomicU32::new(0.0f32.to_bits())).collect(),
+        });
+
+        Self { outputs, shared }
+    }
+
+    /// Verarbeitung für den Audio-Thread (einmal anlegen)
+    pub fn dsp(&self) -> MatrixDsp {
+        MatrixDsp {
+            mixes: self.outputs.iter().map(|o| OutputMix::of(&o.state)).collect(),
+            processors: self.outputs.iter().map(|o| o.processor.clone()).collect(),
+            shared: self.shared.clone(),
+        }
+    }
+
+    fn state(&self, id: usize) -> MatrixOutputState {
+        let output = &self.outputs[id];
+        MatrixOutputState {
+            limiter_gr_db: f32::from_bits(self.shared.limiter_gr_bits[id].load(Ordering::Relaxed)),
+            ..output.state.clone()
+        }
+    }
+
+    /// Zustand eines Ausgangs
+    pub fn get(&self, id: u32) -> Option<MatrixOutputState> {
+        ((id as usize) < self.outputs.len()).then(|| self.state(id as usize))
+    }
+
+    /// Zustand aller Ausgänge
+    pub fn get_all(&self) -> Vec<MatrixOutputState> {
+        (0..self.outputs.len()).map(|id| self.state(id)).collect()
+    }
+
+    /// Sends, Pegel und Mute an den Audio-Thread übergeben
+    fn publish_mix(&self, id: usize) -> MatrixOutputState {
+        let mix = OutputMix::of(&self.outputs[id].state);
+        self.shared.mixes.lock().unwrap()[id] = mix;
+        self.state(id)
+    }
+
+    /// Processor an den Audio-Thread übergeben (Kopie mit leerem Zustand)
+    fn publish_processor(&self, id: usize) -> MatrixOutputState {
+        let processor = self.outputs[id].processor.clone();
+        let retired = {
+            let mut handoff = self.shared.processors.lock().unwrap();
+            handoff.incoming[id] = Some(processor);
+            handoff.retired.iter_mut().map(Option::take).collect::<Vec<_>>()
+        };
+        // Außerhalb des Locks freigeben
+        drop(retired);
+        self.state(id)
+    }
+
+    /// Send setzen (Pegel 0 entfernt den Send)
+    pub fn set_send(&mut self, id: u32, source: MatrixSource, level: f32) -> Option<MatrixOutputState> {
+        if !source.is_valid() {
+            return None;
+        }
+
+        let output = self.outputs.get_mut(id as usize)?;
+        let level = level.clamp(0.0, 1.0);
+        let sends = &mut output.state.sends;
+
+        if level <= 0.0 {
+            sends.retain(|s| s.source != source);
+        } else if let Some(send) = sends.iter_mut().find(|s| s.source == source) {
+            send.level = level;
+        } else {
+            sends.push(MatrixSend { source, level });
+        }
+
+        Some(self.publish_mix(id as usize))
+    }
+
+    /// Ausgangspegel in dB setzen
+    pub fn set_level(&mut self, id: u32, level_db: f32) -> Option<MatrixOutputState> {
+        let output = self.outputs.get_mut(id as usize)?;
+        output.state.level_db = level_db.clamp(-100.0, 10.0);
+        Some(self.publish_mix(id as usize))
+    }
+
+    /// Mute setzen
+    pub fn set_mute(&mut self, id: u32, mute: bool) -> Option<MatrixOutputState> {
+        let output = self.outputs.get_mut(id as usize)?;
+        output.state.mute = mute;
+        Some(self.publish_mix(id as usize))
+    }
+
+    /// "Follow Main" setzen
+    pub fn set_follow_main(&mut self, id: u32, follow: bool) -> Option<MatrixOutputState> {
+        let output = self.outputs.get_mut(id as usize)?;
+        output.state.follow_main = follow;
+        Some(self.publish_mix(id as usize))
+    }
+
+    /// Name setzen
+    pub fn set_name(&mut self, id: u32, name: String) -> Option<MatrixOutputState> {
+        let output = self.outputs.get_mut(id as usize)?;
+        output.state.name = name;
+        Some(self.state(id as usize))
+    }
+
+    /// Lautsprecher-Processing setzen
+    pub fn set_processing(&mut self, id: u32, params: OutputProcessingParams) -> Option<MatrixOutputState> {
+        let output = self.outputs.get_mut(id as usize)?;
+        output.processor.set_params(params);
+        output.state.processing = output.processor.params().clone();
+        Some(self.publish_processor(id as usize))
+    }
+
+    /// EQ-Band des Processings setzen
+    pub fn set_eq_band(&mut self, id: u32, band: usize, params: EqBandParams) -> Option<MatrixOutputState> {
+        let output = self.outputs.get_mut(id as usize)?;
+        if !output.processor.set_eq_band(band, params) {
+            return None;
+        }
+        output.state.processing = output.processor.params().clone();
+        Some(self.publish_processor(id as usize))
+    }
+}
+
+/// Verarbeitung der Matrix-Ausgänge im Audio-Thread
+///
+/// Hält eigene Kopien der Parameter und die Processoren mit ihrem
+/// Filter- und Delay-Zustand. `update` übernimmt Änderungen der API,
+/// ohne zu blockieren oder zu allokieren.
+pub struct MatrixDsp {
+    mixes: Vec<OutputMix>,
+    processors: Vec<OutputProcessor>,
+    shared: Arc<MatrixShared>,
+}
+
+impl MatrixDsp {
+    /// Änderungen übernehmen und Limiter-Werte veröffentlichen (einmal pro Block)
+    pub fn update(&mut self) {
+        if let Ok(mixes) = self.shared.mixes.try_lock() {
+            self.mixes.copy_from_slice(&mixes);
+        }
+        if let Ok(mut handoff) = self.shared.processors.try_lock() {
+            let ProcessorHandoff { incoming, retired } = &mut *handoff;
+            for ((processor, incoming), retired) in self.processors.iter_mut().zip(incoming.iter_mut()).zip(retired.iter_mut()) {
+                // Ein ersetzter Processor wird nie im Audio-Thread freigegeben
+                if retired.is_none() {
+                    if let Some(new) = incoming.take() {
+                        *retired = Some(std::mem::replace(processor, new));
+                    }
+                }
+            }
+        }
+        for (processor, gr) in self.processors.iter().zip(self.shared.limiter_gr_bits.iter()) {
+            gr.store(processor.limiter_gr_db().to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Einen Frame verarbeiten
+    ///
+    /// `outputs` enthält bereits das Direkt-Routing aus der `RoutingMatrix`,
+    /// die Bus-Sends werden hinzugemischt und das Ergebnis durch das
+    /// Processing des Ausgangs geschickt. `main_gain` ist der effektive
+    /// Master-Gain für Ausgänge mit "Follow Main".
+    pub fn process_frame(&mut self, buses: &BusFrame, outputs: &mut [f32], main_gain: f32) {
+        for ((mix, processor), sample) in self.mixes.iter().zip(self.processors.iter_mut()).zip(outputs.iter_mut()) {
+            let mut value = *sample;
+            for &(source, level) in &mix.sends[..mix.send_count] {
+                value += buses.source(source) * level;
+            }
+
+            if mix.mute {
+                value = 0.0;
+            } else {
+                let mut gain = mix.gain;
+                if mix.follow_main {
+                    gain *= main_gain;
+                }
+                value *= gain;
+            }
+
+            *sample = processor.process(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with_main(left: f32, right: f32) -> BusFrame {
+        BusFrame {
+            main: (left, right),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matrix_defaults() {
+        let matrix = OutputMatrix::new(4, 48000.0);
+        assert_eq!(matrix.get_all().len(), 4);
+
+        let state = matrix.get(0).unwrap();
+        assert_eq!(state.level_db, 0.0);
+        assert!(state.sends.is_empty());
+        assert!(!state.follow_main);
+    }
+
+    #[test]
+    fn test_direct_feed_passthrough() {
+        let matrix = OutputMatrix::new(2, 48000.0);
+        let mut outputs = [0.5, -0.25];
+        matrix.dsp().process_frame(&BusFrame::default(), &mut outputs, 1.0);
+        assert_eq!(outputs, [0.5, -0.25]);
+    }
+
+    #[test]
+    fn test_bus_sends() {
+        let mut matrix = OutputMatrix::new(2, 48000.0);
+        matrix.set_send(0, MatrixSource::MainLeft, 1.0);
+        matrix.set_send(1, MatrixSource::MainLeft, 0.5);
+        matrix.set_send(1, MatrixSource::AuxRight(2), 1.0);
+
+        let mut buses = frame_with_main(0.4, 0.0);
+        buses.aux[2] = (0.0, 0.2);
+
+        let mut outputs = [0.0, 0.0];
+        matrix.dsp().process_frame(&buses, &mut outputs, 1.0);
+        assert!((outputs[0] - 0.4).abs() < 1e-6);
+        assert!((outputs[1] - 0.4).abs() < 1e-6);
+
+        // Pegel 0 entfernt den Send
+        let state = matrix.set_send(1, MatrixSource::AuxRight(2), 0.0).unwrap();
+        assert_eq!(state.sends.len(), 1);
+
+        // Ungültiger Bus
+        assert!(matrix.set_send(0, MatrixSource::GroupLeft(99), 1.0).is_none());
+    }
+
+    #[test]
+    fn test_follow_main_and_mute() {
+        let mut matrix = OutputMatrix::new(2, 48000.0);
+        matrix.set_send(0, MatrixSource::MainLeft, 1.0);
+        matrix.set_send(1, MatrixSource::MainLeft, 1.0);
+        matrix.set_follow_main(1, true);
+        let mut dsp = matrix.dsp();
+
+        let mut outputs = [0.0, 0.0];
+        dsp.process_frame(&frame_with_main(1.0, 1.0), &mut outputs, 0.5);
+        assert!((outputs[0] - 1.0).abs() < 1e-6);
+        assert!((outputs[1] - 0.5).abs() < 1e-6);
+
+        matrix.set_mute(0, true);
+        dsp.update();
+        let mut outputs = [0.0, 0.0];
+        dsp.process_frame(&frame_with_main(1.0, 1.0), &mut outputs, 0.5);
+        assert_eq!(outputs[0], 0.0);
+    }
+
+    #[test]
+    fn test_output_level_and_delay() {
+        let mut matrix = OutputMatrix::new(1, 48000.0);
+        matrix.set_level(0, -6.0);
+        let mut params = matrix.get(0).unwrap().processing;
+        params.delay_ms = 0.0625; // 3 Samples
+        let state = matrix.set_processing(0, params).unwrap();
+        assert_eq!(state.processing.delay_ms, 0.0625);
+
+        let mut dsp = matrix.dsp();
+        let mut results = vec![];
+        for i in 0..5 {
+            let mut outputs = [if i == 0 { 1.0 } else { 0.0 }];
+            dsp.process_frame(&BusFrame::default(), &mut outputs, 1.0);
+            results.push(outputs[0]);
+        }
+
+        assert_eq!(results[0], 0.0);
+        assert!((results[3] - 0.5012).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_processing_eq_band() {
+        let mut matrix = OutputMatrix::new(1, 48000.0);
+        let mut band = matrix.get(0).unwrap().processing.eq[2].clone();
+        band.gain = 6.0;
+        let state = matrix.set_eq_band(0, 2, band).unwrap();
+        assert_eq!(state.processing.eq[2].gain, 6.0);
+        assert!(matrix.set_eq_band(0, 99, state.processing.eq[0].clone()).is_none());
+    }
+
+    #[test]
+    fn test_processing_reaches_dsp() {
+        let mut matrix = OutputMatrix::new(1, 48000.0);
+        let mut dsp = matrix.dsp();
+        let mut params = matrix.get(0).unwrap().processing;
+        params.polarity_invert = true;
+        matrix.set_processing(0, params.clone()).unwrap();
+
+        // Erst mit dem nächsten Block
+        let mut outputs = [0.5];
+        dsp.process_frame(&BusFrame::default(), &mut outputs, 1.0);
+        assert_eq!(outputs[0], 0.5);
+
+        dsp.update();
+        let mut outputs = [0.5];
+        dsp.process_frame(&BusFrame::default(), &mut outputs, 1.0);
+        assert_eq!(outputs[0], -0.5);
+
+        // Der ersetzte Processor wird bei der nächsten Änderung freigegeben
+        params.polarity_invert = false;
+        matrix.set_processing(0, params).unwrap();
+        assert!(matrix.shared.processors.lock().unwrap().retired[0].is_none());
+        dsp.update();
+        let mut outputs = [0.5];
+        dsp.process_frame(&BusFrame::default(), &mut outputs, 1.0);
+        assert_eq!(outputs[0], 0.5);
+    }
+}
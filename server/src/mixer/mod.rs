@@ -6,15 +6,57 @@ mod channel;
 mod routing;
 pub mod scenes;
 pub mod master;
+pub mod matrix;
 
-pub use channel::Channel;
+pub use channel::{Channel, ChannelBusSends};
 pub use audiomultiverse_protocol::ChannelState;
 pub use routing::RoutingMatrix;
 pub use scenes::{Scene, SceneManager, SceneMetadata, RecallFilter};
 pub use master::{MasterSection, MasterState};
+pub use matrix::{OutputMatrix, MatrixDsp, MatrixOutputState, MatrixSource, BusFrame};
 
-use std::sync::RwLock;
+use std::sync::{Mutex, RwLock};
 use audiomultiverse_protocol::{ChannelId, MixerState};
+use crate::audio::{EqBandParams, OutputProcessingParams};
+use matrix::{AUX_BUS_COUNT, GROUP_BUS_COUNT};
+
+/// Gains eines Kanals für einen Block
+#[derive(Debug, Clone, Copy, Default)]
+struct ChannelGains {
+    /// Effektiver Gain (Fader, Trim, Mute, Phase)
+    gain: f32,
+
+    /// Gains für L/R inkl. Pan
+    stereo: (f32, f32),
+
+    /// Aux-Send-Pegel
+    aux: [f32; AUX_BUS_COUNT],
+
+    /// Gruppen-Zuweisung
+    groups: [bool; GROUP_BUS_COUNT],
+}
+
+impl ChannelGains {
+    fn of(channel: &Channel) -> Self {
+        Self {
+            gain: channel.effective_gain(),
+            stereo: channel.stereo_gains(),
+            aux: *channel.aux_sends(),
+            groups: *channel.groups(),
+        }
+    }
+}
+
+/// Zustand des Audio-Threads (einmal allokiert, pro Block wiederverwendet)
+///
+/// Gains und Routing sind Kopien vom letzten Block, an dem die API keinen
+/// Lock hielt.
+struct BlockBuffers {
+    gains: Vec<ChannelGains>,
+    routing: Vec<Vec<f32>>,
+    peaks: Vec<f32>,
+    matrix: MatrixDsp,
+}
 
 /// Haupt-Mixer Struktur
 pub struct Mixer {
@@ -24,31 +66,55 @@ pub struct Mixer {
     /// Anzahl Ausgänge
     pub output_count: usize,
     
+    /// Sample Rate der Engine in Hz
+    pub sample_rate: u32,
+    
     /// Eingangskanäle
     channels: RwLock<Vec<Channel>>,
     
     /// Routing-Matrix
     routing: RwLock<RoutingMatrix>,
     
+    /// Matrix-Ausgänge (Bus-Sends, Pegel und Lautsprecher-Processing pro Ausgang)
+    matrix: RwLock<OutputMatrix>,
+    
     /// Solo-Modus aktiv (welche Kanäle)
     solo_active: RwLock<Vec<ChannelId>>,
+    
+    /// Zustand für `process_block` (nur der Audio-Thread sperrt ihn)
+    block: Mutex<BlockBuffers>,
 }
 
 impl Mixer {
-    /// Neuen Mixer erstellen
+    /// Neuen Mixer erstellen (48 kHz)
     pub fn new(input_count: usize, output_count: usize) -> Self {
+        Self::with_sample_rate(input_count, output_count, 48000)
+    }
+
+    /// Neuen Mixer mit Sample Rate erstellen
+    pub fn with_sample_rate(input_count: usize, output_count: usize, sample_rate: u32) -> Self {
         let channels: Vec<Channel> = (0..input_count)
             .map(|i| Channel::new(i as u32, format!("CH {}", i + 1)))
             .collect();
 
         let routing = RoutingMatrix::new(input_count, output_count);
+        let matrix = OutputMatrix::new(output_count, sample_rate as f32);
+        let block = BlockBuffers {
+            gains: channels.iter().map(ChannelGains::of).collect(),
+            routing: routing.matrix.clone(),
+            peaks: vec![0.0; input_count],
+            matrix: matrix.dsp(),
+        };
 
         Self {
             input_count,
             output_count,
+            sample_rate,
             channels: RwLock::new(channels),
             routing: RwLock::new(routing),
+            matrix: RwLock::new(matrix),
             solo_active: RwLock::new(vec![]),
+            block: Mutex::new(block),
         }
     }
 
@@ -130,6 +196,26 @@ impl Mixer {
         }
     }
 
+    /// Bus-Sends eines Kanals abrufen
+    pub fn get_channel_sends(&self, id: ChannelId) -> Option<ChannelBusSends> {
+        let channels = self.channels.read().unwrap();
+        channels.get(id as usize).map(|c| c.bus_sends())
+    }
+
+    /// Aux-Send eines Kanals setzen
+    pub fn set_channel_aux_send(&self, id: ChannelId, bus: usize, level: f32) -> Option<ChannelBusSends> {
+        let mut channels = self.channels.write().unwrap();
+        let channel = channels.get_mut(id as usize)?;
+        channel.set_aux_send(bus, level).then(|| channel.bus_sends())
+    }
+
+    /// Kanal einer Gruppe zuweisen oder entfernen
+    pub fn set_channel_group(&self, id: ChannelId, group: usize, assigned: bool) -> Option<ChannelBusSends> {
+        let mut channels = self.channels.write().unwrap();
+        let channel = channels.get_mut(id as usize)?;
+        channel.set_group(group, assigned).then(|| channel.bus_sends())
+    }
+
     /// Routing-Punkt setzen
     pub fn set_routing(&self, input: usize, output: usize, gain: f32) -> bool {
         let mut routing = self.routing.write().unwrap();
@@ -142,6 +228,143 @@ impl Mixer {
         routing.matrix.clone()
     }
 
+    // === Matrix-Ausgänge ===
+
+    /// Alle Matrix-Ausgänge abrufen
+    pub fn get_matrix_outputs(&self) -> Vec<MatrixOutputState> {
+        self.matrix.read().unwrap().get_all()
+    }
+
+    /// Einzelnen Matrix-Ausgang abrufen
+    pub fn get_matrix_output(&self, id: u32) -> Option<MatrixOutputState> {
+        self.matrix.read().unwrap().get(id)
+    }
+
+    /// Bus-Send auf einen Ausgang setzen (Pegel 0 entfernt den Send)
+    pub fn set_output_send(&self, id: u32, source: MatrixSource, level: f32) -> Option<MatrixOutputState> {
+        self.matrix.write().unwrap().set_send(id, source, level)
+    }
+
+    /// Ausgangspegel in dB setzen
+    pub fn set_output_level(&self, id: u32, level_db: f32) -> Option<MatrixOutputState> {
+        self.matrix.write().unwrap().set_level(id, level_db)
+    }
+
+    /// Ausgangs-Mute setzen
+    pub fn set_output_mute(&self, id: u32, mute: bool) -> Option<MatrixOutputState> {
+        self.matrix.write().unwrap().set_mute(id, mute)
+    }
+
+    /// "Follow Main" für einen Ausgang setzen
+    pub fn set_output_follow_main(&self, id: u32, follow: bool) -> Option<MatrixOutputState> {
+        self.matrix.write().unwrap().set_follow_main(id, follow)
+    }
+
+    /// Ausgangsname setzen
+    pub fn set_output_name(&self, id: u32, name: String) -> Option<MatrixOutputState> {
+        self.matrix.write().unwrap().set_name(id, name)
+    }
+
+    /// EQ-Band des Ausgangs-Processings setzen
+    pub fn set_output_eq_band(&self, id: u32, band: usize, params: EqBandParams) -> Option<MatrixOutputState> {
+        self.matrix.write().unwrap().set_eq_band(id, band, params)
+    }
+
+    /// Lautsprecher-Processing eines Ausgangs setzen
+    pub fn set_output_processing(&self, id: u32, params: OutputProcessingParams) -> Option<MatrixOutputState> {
+        self.matrix.write().unwrap().set_processing(id, params)
+    }
+
+    // === Audio-Processing ===
+
+    /// Einen Audio-Block verarbeiten
+    ///
+    /// `inputs` ist interleaved mit `input_count` Kanälen, `outputs` interleaved
+    /// mit `output_count` Kanälen. Pro Frame werden Direkt-Routing, Main-Bus
+    /// (Kanal-Gain + Pan), Aux-Busse (Sends) und Gruppen-Busse (Zuweisung)
+    /// berechnet, danach die Matrix-Ausgänge mit ihrem Lautsprecher-Processing.
+    /// `main_gain` ist der effektive Master-Gain für "Follow Main".
+    ///
+    /// Läuft im Audio-Thread: blockiert und allokiert nicht. Hält die API
+    /// gerade einen Lock, gelten die Parameter des letzten Blocks weiter.
+    pub fn process_block(&self, inputs: &[f32], outputs: &mut [f32], frames: usize, main_gain: f32) {
+        // Nur bei gleichzeitigem Aufruf aus zwei Audio-Threads belegt
+        let Ok(mut block) = self.block.try_lock() else {
+            outputs.iter_mut().for_each(|s| *s = 0.0);
+            return;
+        };
+        let BlockBuffers { gains, routing, peaks, matrix } = &mut *block;
+
+        let channels = self.channels.try_read().ok();
+        if let Some(channels) = &channels {
+            for (gains, channel) in gains.iter_mut().zip(channels.iter()) {
+                *gains = ChannelGains::of(channel);
+            }
+        }
+        if let Ok(current) = self.routing.try_read() {
+            for (row, current) in routing.iter_mut().zip(current.matrix.iter()) {
+                row.copy_from_slice(current);
+            }
+        }
+        matrix.update();
+        peaks.iter_mut().for_each(|p| *p = 0.0);
+        let mut buses = BusFrame::default();
+
+        let frames = frames
+            .min(inputs.len() / self.input_count.max(1))
+            .min(outputs.len() / self.output_count.max(1));
+
+        for frame in 0..frames {
+            let input = &inputs[frame * self.input_count..(frame + 1) * self.input_count];
+            let output = &mut outputs[frame * self.output_count..(frame + 1) * self.output_count];
+
+            output.iter_mut().for_each(|s| *s = 0.0);
+            buses.clear();
+
+            for (ch, (&sample, channel)) in input.iter().zip(gains.iter()).enumerate() {
+                if channel.gain == 0.0 || sample == 0.0 {
+                    continue;
+                }
+
+                let value = sample * channel.gain;
+                peaks[ch] = peaks[ch].max(value.abs());
+
+                for (out, &routing_gain) in output.iter_mut().zip(routing[ch].iter()) {
+                    if routing_gain > 0.0 {
+                        *out += value * routing_gain;
+                    }
+                }
+
+                let (left, right) = (sample * channel.stereo.0, sample * channel.stereo.1);
+                buses.main.0 += left;
+                buses.main.1 += right;
+
+                for (bus, &level) in buses.aux.iter_mut().zip(channel.aux.iter()) {
+                    if level > 0.0 {
+                        bus.0 += left * level;
+                        bus.1 += right * level;
+                    }
+                }
+
+                for (bus, &assigned) in buses.groups.iter_mut().zip(channel.groups.iter()) {
+                    if assigned {
+                        bus.0 += left;
+                        bus.1 += right;
+                    }
+                }
+            }
+
+            matrix.process_frame(&buses, output, main_gain);
+        }
+
+        // Ohne Lock fehlt den Metern ein Block
+        if let Some(channels) = &channels {
+            for (channel, &peak) in channels.iter().zip(peaks.iter()) {
+                channel.update_meter(peak);
+            }
+        }
+    }
+
     /// Kompletten Mixer-State abrufen
     pub fn get_state(&self) -> MixerState {
         MixerState {
@@ -197,4 +420,71 @@ mod tests {
         let state = mixer.set_mute(0, false).unwrap();
         assert!(!state.mute);
     }
+
+    #[test]
+    fn test_process_block_routing_and_matrix() {
+        let mixer = Mixer::new(2, 3);
+        mixer.set_fader(0, 0.75); // 0dB
+        mixer.set_fader(1, 0.75);
+        mixer.set_output_send(2, MatrixSource::MainLeft, 1.0);
+        mixer.set_output_send(2, MatrixSource::MainRight, 1.0);
+        mixer.set_output_follow_main(2, true);
+
+        let inputs = [0.5, 0.25];
+        let mut outputs = [0.0; 3];
+        mixer.process_block(&inputs, &mut outputs, 1, 0.5);
+
+        // 1:1 Direkt-Routing
+        assert!((outputs[0] - 0.5).abs() < 0.01);
+        assert!((outputs[1] - 0.25).abs() < 0.01);
+
+        // Main L+R (Center-Pan, -3dB) mit Follow Main
+        let expected = (0.5 + 0.25) * std::f32::consts::FRAC_1_SQRT_2 * 2.0 * 0.5;
+        assert!((outputs[2] - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_process_block_aux_and_group_buses() {
+        let mixer = Mixer::new(2, 4);
+        mixer.set_fader(0, 0.75); // 0dB
+        mixer.set_fader(1, 0.75);
+        mixer.set_pan(0, -1.0);
+        mixer.set_pan(1, 1.0);
+        mixer.set_channel_aux_send(0, 3, 0.5);
+        mixer.set_channel_group(1, 2, true);
+        assert!(mixer.set_channel_aux_send(0, AUX_BUS_COUNT, 1.0).is_none());
+
+        mixer.set_output_send(2, MatrixSource::AuxLeft(3), 1.0);
+        mixer.set_output_send(3, MatrixSource::GroupRight(2), 1.0);
+        // Direkt-Routing aus, nur die Busse
+        mixer.set_routing(0, 0, 0.0);
+        mixer.set_routing(1, 1, 0.0);
+
+        let inputs = [0.5, 0.25];
+        let mut outputs = [0.0; 4];
+        mixer.process_block(&inputs, &mut outputs, 1, 1.0);
+
+        assert!((outputs[2] - 0.25).abs() < 0.01);
+        assert!((outputs[3] - 0.25).abs() < 0.01);
+        assert!(outputs[0].abs() < 1e-6);
+        assert!(outputs[1].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_process_block_during_api_lock() {
+        let mixer = Mixer::new(2, 2);
+        mixer.set_fader(0, 0.75); // 0dB
+        let inputs = [0.5, 0.0];
+        let mut outputs = [0.0; 2];
+        mixer.process_block(&inputs, &mut outputs, 1, 1.0);
+        assert!((outputs[0] - 0.5).abs() < 0.01);
+
+        // Die API hält alle Locks: Parameter des letzten Blocks gelten weiter
+        let _channels = mixer.channels.write().unwrap();
+        let _routing = mixer.routing.write().unwrap();
+        let _matrix = mixer.matrix.write().unwrap();
+        let mut outputs = [0.0; 2];
+        mixer.process_block(&inputs, &mut outputs, 1, 1.0);
+        assert!((outputs[0] - 0.5).abs() < 0.01);
+    }
 }
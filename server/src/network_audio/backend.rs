@@ -1,20 +1,32 @@
 //! Audio Network Backend Abstraktion
 //! 
 //! Ermöglicht verschiedene Backends: AES67, DANTE, etc.
+//! Welche laufen, entscheidet die Registry (`registry`).
 
-use std::net::Ipv4Addr;
+use std::any::Any;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use anyhow::{Result, anyhow};
-use parking_lot::RwLock;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 use tracing::{info, warn, error, debug};
 
 // Use PtpClock from parent module (either real or stub depending on platform)
 use super::PtpClock;
-use super::rtp::{RtpSender, RtpReceiver, Aes67Format};
-use super::sap::{SapDiscovery, Aes67Stream, StreamDirection};
+use super::interface;
+use super::rtcp::RtcpSession;
+use super::rtp::{RtpReceiver, Aes67Format, RtpEncoding};
+use super::health::HealthState;
+use super::sap::{SapDiscovery, SapEvent, Aes67Stream, StreamDirection, DEFAULT_PAYLOAD_TYPE};
+use super::streams::{Aes67TxStreams, TxStreamConfig};
+use super::subscription::{Aes67Subscription, Aes67Subscriptions, ChannelPatch};
+use crate::audio::DriftMonitor;
 
 /// Trait für Audio-Netzwerk Backends
-pub trait AudioNetworkBackend: Send + Sync {
+pub trait AudioNetworkBackend: Any + Send + Sync {
     /// Backend-Name
     fn name(&self) -> &'static str;
     
@@ -41,10 +53,16 @@ pub trait AudioNetworkBackend: Send + Sync {
     
     /// Ist verbunden?
     fn is_connected(&self) -> bool;
+    
+    /// Für backend-spezifische Funktionen (z.B. AES67 Subscriptions)
+    fn as_any(&self) -> &dyn Any;
+    
+    /// Für backend-spezifische Funktionen (veränderlich)
+    fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
 /// Ein Netzwerk-Audio Gerät
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct NetworkDevice {
     /// Eindeutige ID
     pub id: String,
@@ -66,10 +84,14 @@ pub struct NetworkDevice {
     
     /// Multicast-Gruppe (für AES67)
     pub multicast_group: Option<String>,
+    
+    /// RTP-Port (None = AES67-Standard 5004)
+    pub port: Option<u16>,
 }
 
 /// Gerätetyp
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NetworkDeviceType {
     Transmitter,
     Receiver,
@@ -83,35 +105,112 @@ pub enum NetworkDeviceType {
 /// AES67 Backend configuration
 #[derive(Debug, Clone)]
 pub struct Aes67Config {
-    /// Network interface to use
-    pub interface: String,
-    /// Number of input channels to receive
+    /// Network interface for PTP, SAP, mDNS and RTP (name or IPv4 address,
+    /// None = all interfaces)
+    pub interface: Option<String>,
+    /// Interface of the secondary network for SMPTE ST 2022-7 redundancy
+    /// (None = single network)
+    pub secondary_interface: Option<String>,
+    /// Number of mixer inputs available for received streams
     pub input_channels: u8,
-    /// Number of output channels to send
+    /// Number of mixer outputs available for outgoing streams
+    pub mixer_outputs: usize,
+    /// Number of channels of the default output stream
     pub output_channels: u8,
-    /// Sample rate (48000 for AES67)
+    /// Engine sample rate (44100, 48000 or 96000)
     pub sample_rate: u32,
-    /// Multicast address for our output stream
+    /// Multicast address of the default output stream
     pub output_multicast: Ipv4Addr,
-    /// RTP port for our output stream
+    /// Multicast groups of the default output streams, one stream of
+    /// `output_channels` per group (empty = `output_multicast` only)
+    pub multicast_groups: Vec<Ipv4Addr>,
+    /// RTP port of the default output stream
     pub output_port: u16,
-    /// Stream name
+    /// Name of the default output stream
     pub stream_name: String,
     /// PTP domain (0-127)
     pub ptp_domain: u8,
+    /// Act as PTP master when no better clock is on the network
+    pub ptp_master: bool,
+    /// priority1 announced as PTP master (lower wins)
+    pub ptp_priority1: u8,
+    /// Timestamp source for PTP event messages
+    pub ptp_timestamping: PtpTimestamping,
+    /// UDP ports of PTP event and general messages (319/320)
+    pub ptp_ports: (u16, u16),
+    /// Outgoing streams (empty = the default output stream)
+    pub streams: Vec<TxStreamConfig>,
+    /// Engine block size in frames (buffered before transmitting)
+    pub buffer_size: usize,
+    /// Link offset for received streams in µs (playout delay behind PTP time)
+    pub link_offset_us: u32,
+    /// File the subscriptions are saved to and restored from at startup
+    /// (None = not saved)
+    pub subscriptions_file: Option<PathBuf>,
+}
+
+/// Timestamp source for PTP event messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PtpTimestamping {
+    /// Best available: hardware, then kernel software, then user space
+    #[default]
+    Auto,
+    /// NIC hardware timestamps (PTP hardware clock)
+    Hardware,
+    /// Kernel software timestamps (SO_TIMESTAMPING)
+    Software,
+    /// Taken in user space after receive/before send
+    User,
+}
+
+impl Aes67Config {
+    /// Outgoing streams to create on startup
+    pub fn tx_streams(&self) -> Vec<TxStreamConfig> {
+        if !self.streams.is_empty() {
+            return self.streams.clone();
+        }
+        if self.multicast_groups.is_empty() {
+            let mut stream = TxStreamConfig::new(&self.stream_name, self.output_multicast, self.output_channels);
+            stream.port = self.output_port;
+            return vec![stream];
+        }
+
+        // Consecutive mixer outputs, e.g. 1-8 to the first group, 9-16 to the second
+        let channels = self.output_channels as usize;
+        self.multicast_groups.iter().enumerate().map(|(i, group)| {
+            let first = i * channels;
+            let name = format!("{} {}-{}", self.stream_name, first + 1, first + channels);
+            let mut stream = TxStreamConfig::new(&name, *group, self.output_channels);
+            stream.port = self.output_port;
+            stream.outputs = (first..first + channels).collect();
+            stream
+        }).collect()
+    }
 }
 
 impl Default for Aes67Config {
     fn default() -> Self {
         Self {
-            interface: "eth0".to_string(),
+            interface: None,
+            secondary_interface: None,
             input_channels: 8,
+            mixer_outputs: 32,
             output_channels: 8,
             sample_rate: 48000,
             output_multicast: Ipv4Addr::new(239, 69, 1, 100),
+            multicast_groups: Vec::new(),
             output_port: 5004,
             stream_name: "AudioMultiverse".to_string(),
             ptp_domain: 0,
+            ptp_master: true,
+            ptp_priority1: 250,
+            ptp_timestamping: PtpTimestamping::Auto,
+            ptp_ports: (319, 320),
+            streams: Vec::new(),
+            buffer_size: 256,
+            link_offset_us: 3000,
+            subscriptions_file: None,
         }
     }
 }
@@ -120,43 +219,108 @@ impl Default for Aes67Config {
 pub struct Aes67Backend {
     /// Configuration
     config: Aes67Config,
-    /// Connection state
-    connected: bool,
     /// PTP Clock for synchronization
     ptp_clock: Arc<PtpClock>,
     /// SAP/SDP Discovery
     sap_discovery: Arc<SapDiscovery>,
-    /// RTP Sender (for output)
-    rtp_sender: Option<RtpSender>,
-    /// RTP Receiver (for input)  
-    rtp_receiver: Option<RtpReceiver>,
-    /// Connected device
-    connected_device: Option<NetworkDevice>,
-    /// Our announced stream info
-    our_stream: Option<Aes67Stream>,
+    /// Outgoing streams (shared with engine and API)
+    tx_streams: Arc<Aes67TxStreams>,
+    /// Received streams (shared with engine and API)
+    subscriptions: Arc<Aes67Subscriptions>,
+    /// Clock drift monitor (local device vs. PTP) for adaptive resampling
+    drift_monitor: Option<Arc<DriftMonitor>>,
+    /// Address of the configured interface (None = all interfaces)
+    interface_addr: Option<Ipv4Addr>,
+    /// Announcements, watched to resubscribe streams that reappear
+    sap_events: broadcast::Receiver<SapEvent>,
+    /// Last health check of the subscriptions
+    last_supervision: Option<Instant>,
+    /// Stream samples between the tables and `read_samples`/`write_samples`,
+    /// allocated once
+    read_scratch: Mutex<Vec<f32>>,
+    write_scratch: Mutex<Vec<f32>>,
 }
 
+/// Interval of the subscription health checks
+const SUPERVISION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Size of the scratch buffers (samples), larger blocks are copied in pieces
+const SCRATCH_SAMPLES: usize = 16 * 1024;
+
 impl Aes67Backend {
-    /// Create a new AES67 backend with default config
+    /// Create a new AES67 backend with default config (all interfaces)
     pub fn new() -> Self {
         Self::with_config(Aes67Config::default())
+            .expect("the default config does not name an interface")
     }
     
     /// Create a new AES67 backend with custom config
-    pub fn with_config(config: Aes67Config) -> Self {
-        let mut ptp_clock = PtpClock::new(&config.interface);
+    ///
+    /// Fails if a configured interface does not exist or has no IPv4 address.
+    pub fn with_config(config: Aes67Config) -> Result<Self> {
+        let interface_addr = config.interface.as_deref()
+            .map(|name| interface::ipv4_address(name).map_err(|e| anyhow!("AES67 interface: {}", e)))
+            .transpose()?;
+        if let Some(name) = &config.secondary_interface {
+            interface::ipv4_address(name).map_err(|e| anyhow!("AES67 secondary interface: {}", e))?;
+        }
+        
+        // PTP needs the interface name for its clock identity (MAC) and hardware timestamps
+        let ptp_interface = match (&config.interface, interface_addr) {
+            (Some(name), Some(address)) if name.parse::<Ipv4Addr>().is_ok() => {
+                interface::interface_name(address).unwrap_or_else(|| name.clone())
+            }
+            (Some(name), _) => name.clone(),
+            (None, _) => interface::default_ipv4_address()
+                .and_then(interface::interface_name)
+                .unwrap_or_default(),
+        };
+        let mut ptp_clock = PtpClock::new(&ptp_interface);
+        ptp_clock.set_interface_address(interface_addr);
         ptp_clock.set_domain(config.ptp_domain);
+        ptp_clock.set_master_capable(config.ptp_master, config.ptp_priority1);
+        ptp_clock.set_timestamping(config.ptp_timestamping);
+        ptp_clock.set_ports(config.ptp_ports.0, config.ptp_ports.1);
         
-        Self {
-            config,
-            connected: false,
-            ptp_clock: Arc::new(ptp_clock),
-            sap_discovery: Arc::new(SapDiscovery::new()),
-            rtp_sender: None,
-            rtp_receiver: None,
-            connected_device: None,
-            our_stream: None,
+        let ptp_clock = Arc::new(ptp_clock);
+        let mut sap_discovery = SapDiscovery::with_ptp_clock(ptp_clock.clone());
+        sap_discovery.set_interface(interface_addr);
+        let sap_discovery = Arc::new(sap_discovery);
+        let sap_events = sap_discovery.subscribe();
+        let mut subscriptions = Aes67Subscriptions::with_ptp_clock(config.input_channels as usize, ptp_clock.clone());
+        if let Some(path) = &config.subscriptions_file {
+            subscriptions.set_store(path.clone());
         }
+        let subscriptions = Arc::new(subscriptions);
+        let tx_streams = Arc::new(Aes67TxStreams::new(
+            ptp_clock.clone(),
+            sap_discovery.clone(),
+            config.sample_rate,
+            config.mixer_outputs,
+            config.buffer_size * 2,
+            interface_addr,
+            config.secondary_interface.clone(),
+        ));
+        
+        Ok(Self {
+            config,
+            ptp_clock,
+            sap_discovery,
+            tx_streams,
+            subscriptions,
+            drift_monitor: None,
+            interface_addr,
+            sap_events,
+            last_supervision: None,
+            read_scratch: Mutex::new(vec![0.0; SCRATCH_SAMPLES]),
+            write_scratch: Mutex::new(vec![0.0; SCRATCH_SAMPLES]),
+        })
+    }
+    
+    /// Enable drift compensation for received and sent streams
+    pub fn set_drift_monitor(&mut self, monitor: Arc<DriftMonitor>) {
+        self.tx_streams.set_drift_monitor(monitor.clone());
+        self.drift_monitor = Some(monitor);
     }
     
     /// Get PTP clock reference
@@ -174,12 +338,215 @@ impl Aes67Backend {
         self.ptp_clock.is_synchronized()
     }
     
-    /// Get our output stream info
-    pub fn our_stream(&self) -> Option<&Aes67Stream> {
-        self.our_stream.as_ref()
+    /// Get outgoing stream table reference
+    pub fn tx_streams(&self) -> Arc<Aes67TxStreams> {
+        self.tx_streams.clone()
+    }
+    
+    /// Get subscription table reference
+    pub fn subscriptions(&self) -> Arc<Aes67Subscriptions> {
+        self.subscriptions.clone()
+    }
+    
+    /// Subscribe to a stream and patch its channels onto mixer inputs
+    ///
+    /// Streams are received concurrently; subscribing to a stream that is
+    /// already subscribed fails.
+    pub fn subscribe(&mut self, stream: &Aes67Stream, patch: ChannelPatch) -> Result<()> {
+        self.add_subscription(stream, patch, true)
+    }
+    
+    /// Subscribe like `subscribe`, without saving the subscription
+    ///
+    /// For NMOS receivers: they start inactive after a restart, so their
+    /// streams must not be restored either.
+    pub fn subscribe_unsaved(&mut self, stream: &Aes67Stream, patch: ChannelPatch) -> Result<()> {
+        self.add_subscription(stream, patch, false)
+    }
+    
+    fn add_subscription(&mut self, stream: &Aes67Stream, patch: ChannelPatch, persistent: bool) -> Result<()> {
+        if self.subscriptions.contains(&stream.session_id) {
+            return Err(anyhow!("Stream '{}' is already subscribed", stream.name));
+        }
+        
+        info!("🔌 Subscribing to AES67 stream: {}", stream.name);
+        let mut subscription = self.open_subscription(stream, patch)?;
+        subscription.set_persistent(persistent);
+        self.subscriptions.add(subscription)?;
+        
+        info!("✅ Subscribed to {} ({}:{}, {} channels, {}, {} µs, {} active)", 
+            stream.name, stream.multicast_addr, stream.port, stream.channels,
+            stream.encoding.name(), stream.ptime_us, self.subscriptions.len());
+        
+        Ok(())
+    }
+    
+    /// Start receiving a stream (receiver and RTCP), not yet in the table
+    fn open_subscription(&self, stream: &Aes67Stream, patch: ChannelPatch) -> Result<Aes67Subscription> {
+        let format = stream_format(stream);
+        
+        // Create RTP receiver for this stream, converting to the engine rate if needed
+        let mut receiver = RtpReceiver::with_options(
+            stream.multicast_addr, stream.port, format, stream.source_address, self.interface_addr,
+        )?;
+        receiver.set_expected_payload_type(stream.payload_type);
+        if !stream.multicast_addr.is_multicast() {
+            info!("   Unicast stream to {}:{}", stream.multicast_addr, stream.port);
+        } else if let Some(source) = stream.source_address {
+            info!("   Source-specific multicast from {}", source);
+        }
+        match (stream.secondary, &self.config.secondary_interface) {
+            (Some(secondary), Some(interface)) => {
+                receiver.add_secondary(*secondary.ip(), secondary.port(), interface::ipv4_address(interface)?)?;
+                info!("   ST 2022-7 secondary path: {} via {}", secondary, interface);
+            }
+            (Some(secondary), None) => {
+                info!("   Stream is also sent on {}, no secondary interface configured", secondary);
+            }
+            _ => {}
+        }
+        receiver.set_output_rate(self.config.sample_rate);
+        receiver.set_ptp_clock(self.ptp_clock.clone());
+        receiver.set_link_offset(self.link_offset_frames(stream));
+        receiver.set_media_clock_offset(stream.media_clock_offset);
+        if let Some(monitor) = &self.drift_monitor {
+            receiver.set_drift_monitor(monitor.clone());
+        }
+        receiver.start()?;
+        
+        // Receiver reports to the sender on RTP port + 1
+        let local = self.interface_addr.map(|a| a.to_string())
+            .or_else(get_local_ip)
+            .unwrap_or_else(|| "0.0.0.0".to_string());
+        let rtcp = RtcpSession::for_receiver(
+            receiver.reception(),
+            SocketAddrV4::new(stream.multicast_addr, stream.port),
+            self.interface_addr,
+            format!("audiomultiverse@{}", local),
+        );
+        let mut subscription = Aes67Subscription::new(stream.clone(), patch, receiver);
+        match rtcp {
+            Ok(rtcp) => subscription.set_rtcp(rtcp),
+            Err(e) => warn!("No RTCP for stream '{}': {}", stream.name, e),
+        }
+        Ok(subscription)
+    }
+    
+    /// Watch the subscriptions: check their health and resubscribe streams
+    /// that reappear
+    ///
+    /// A stalled stream that is announced again, a subscribed stream whose
+    /// description changed and a saved subscription whose stream shows up
+    /// are subscribed again with the new description. Call regularly from
+    /// the engine thread; checks run every 250 ms.
+    pub fn supervise(&mut self) {
+        let now = Instant::now();
+        if self.last_supervision.is_some_and(|last| now.duration_since(last) < SUPERVISION_INTERVAL) {
+            return;
+        }
+        self.last_supervision = Some(now);
+        self.subscriptions.update_health();
+        
+        loop {
+            match self.sap_events.try_recv() {
+                Ok(SapEvent::Discovered(stream) | SapEvent::Updated(stream)) => self.stream_announced(&stream),
+                Ok(SapEvent::Removed { .. }) => {}
+                Err(broadcast::error::TryRecvError::Lagged(_)) => {
+                    for stream in self.sap_discovery.streams() {
+                        self.stream_announced(&stream);
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    }
+    
+    /// Resubscribe or restore a subscription for an announced stream
+    fn stream_announced(&mut self, stream: &Aes67Stream) {
+        if let Some(saved) = self.subscriptions.pending_for(stream) {
+            let patch = fit_patch(saved.patch.clone(), stream.channels);
+            let result = self.open_subscription(stream, patch)
+                .and_then(|subscription| self.subscriptions.restore(&saved, subscription));
+            match result {
+                Ok(()) => info!("🔁 Restored saved subscription to AES67 stream: {}", stream.name),
+                Err(e) => warn!("Could not restore subscription to AES67 stream '{}': {}", stream.name, e),
+            }
+            return;
+        }
+        
+        let announced = self.sap_discovery.streams();
+        let is_announced = |id: &str| announced.iter().any(|s| s.session_id == id);
+        let Some((current, patch, state)) = self.subscriptions.subscribed_stream(stream, is_announced) else {
+            return;
+        };
+        if state != HealthState::Stalled && same_description(&current, stream) {
+            return;
+        }
+        let patch = fit_patch(patch, stream.channels);
+        let result = self.open_subscription(stream, patch)
+            .and_then(|subscription| self.subscriptions.replace(&current.session_id, subscription));
+        match result {
+            Ok(()) => info!("🔁 Resubscribed to AES67 stream: {} ({}:{})", stream.name, stream.multicast_addr, stream.port),
+            Err(e) => warn!("Could not resubscribe to AES67 stream '{}': {}", stream.name, e),
+        }
+    }
+    
+    /// Link offset for a stream in frames of its sample rate
+    ///
+    /// Playout needs at least one engine block plus one packet, shorter
+    /// offsets would run into concealment on every block.
+    fn link_offset_frames(&self, stream: &Aes67Stream) -> usize {
+        let configured = (stream.sample_rate as u64 * self.config.link_offset_us as u64 / 1_000_000) as usize;
+        let block = self.config.buffer_size * stream.sample_rate as usize / self.config.sample_rate.max(1) as usize;
+        let minimum = block + stream_format(stream).samples_per_packet as usize;
+        if configured < minimum {
+            warn!("Link offset {} µs is shorter than one audio block, using {} frames", self.config.link_offset_us, minimum);
+        }
+        configured.max(minimum)
+    }
+    
+    /// Stop receiving a stream
+    pub fn unsubscribe(&mut self, stream_id: &str) -> Result<()> {
+        // A saved subscription whose stream was not announced yet is forgotten
+        if self.subscriptions.remove_pending(stream_id) {
+            info!("🔌 Removed saved subscription to AES67 stream {}", stream_id);
+            return Ok(());
+        }
+        let stream = self.subscriptions.remove(stream_id)
+            .ok_or_else(|| anyhow!("Stream '{}' is not subscribed", stream_id))?;
+        info!("🔌 Unsubscribed from AES67 stream: {}", stream.name);
+        Ok(())
     }
 }
 
+/// The stream is received the same way under both descriptions
+fn same_description(a: &Aes67Stream, b: &Aes67Stream) -> bool {
+    a.session_id == b.session_id
+        && a.multicast_addr == b.multicast_addr
+        && a.port == b.port
+        && a.channels == b.channels
+        && a.sample_rate == b.sample_rate
+        && a.encoding == b.encoding
+        && a.ptime_us == b.ptime_us
+        && a.source_address == b.source_address
+        && a.secondary == b.secondary
+        && a.media_clock_offset == b.media_clock_offset
+}
+
+/// Keep a patch for a stream that may now have fewer or more channels
+fn fit_patch(mut patch: ChannelPatch, channels: u8) -> ChannelPatch {
+    patch.resize(channels as usize, None);
+    patch
+}
+
+/// Standard RTP port of AES67 streams
+const DEFAULT_RTP_PORT: u16 = 5004;
+
+/// RTP format of a stream as described by its SDP
+fn stream_format(stream: &Aes67Stream) -> Aes67Format {
+    Aes67Format::with_ptime(stream.sample_rate, stream.channels, stream.encoding, stream.ptime_us)
+}
+
 impl AudioNetworkBackend for Aes67Backend {
     fn name(&self) -> &'static str {
         "AES67"
@@ -187,8 +554,14 @@ impl AudioNetworkBackend for Aes67Backend {
     
     fn init(&mut self) -> Result<()> {
         info!("🌐 AES67 Backend initialisiert");
-        info!("   Interface: {}", self.config.interface);
-        info!("   Channels: {} in / {} out", self.config.input_channels, self.config.output_channels);
+        match (&self.config.interface, self.interface_addr) {
+            (Some(name), Some(address)) => info!("   Interface: {} ({})", name, address),
+            _ => info!("   Interface: all"),
+        }
+        if let Some(secondary) = &self.config.secondary_interface {
+            info!("   Secondary interface (ST 2022-7): {}", secondary);
+        }
+        info!("   Channels: {} in / {} out", self.config.input_channels, self.config.mixer_outputs);
         info!("   Sample Rate: {} Hz", self.config.sample_rate);
         info!("   PTP Domain: {}", self.config.ptp_domain);
         
@@ -200,47 +573,12 @@ impl AudioNetworkBackend for Aes67Backend {
         info!("📢 Starting SAP/SDP discovery...");
         self.sap_discovery.start()?;
         
-        // Create output stream
-        let format = Aes67Format {
-            sample_rate: self.config.sample_rate,
-            channels: self.config.output_channels,
-            bits_per_sample: 24,
-            samples_per_packet: 48, // 1ms at 48kHz
-        };
-        
-        // Create RTP sender for output
-        let mut sender = RtpSender::new(
-            self.config.output_multicast,
-            self.config.output_port,
-            format,
-        )?;
-        sender.set_ptp_clock(self.ptp_clock.clone());
-        
-        // Announce our output stream via SAP
-        let our_stream = Aes67Stream {
-            name: self.config.stream_name.clone(),
-            session_id: format!("audiomultiverse_{}", sender.ssrc()),
-            origin: get_local_ip().unwrap_or_else(|| "0.0.0.0".to_string()),
-            multicast_addr: self.config.output_multicast,
-            port: self.config.output_port,
-            channels: self.config.output_channels,
-            sample_rate: self.config.sample_rate,
-            bits_per_sample: 24,
-            ptime_us: 1000, // 1ms
-            direction: StreamDirection::Send,
-            sdp: String::new(),
-        };
-        
-        self.sap_discovery.announce(our_stream.clone())?;
-        self.our_stream = Some(our_stream);
-        self.rtp_sender = Some(sender);
+        // Start all outgoing streams, each on its own paced transmitter thread
+        for stream in self.config.tx_streams() {
+            self.tx_streams.add(stream)?;
+        }
         
-        info!("✅ AES67 Backend ready");
-        info!("   Output: {}:{} ({} channels)", 
-            self.config.output_multicast, 
-            self.config.output_port,
-            self.config.output_channels
-        );
+        info!("✅ AES67 Backend ready ({} output stream(s))", self.tx_streams.count());
         
         Ok(())
     }
@@ -259,90 +597,90 @@ impl AudioNetworkBackend for Aes67Backend {
     }
     
     fn connect(&mut self, device: &NetworkDevice) -> Result<()> {
-        info!("🔌 Connecting to AES67 device: {}", device.name);
-        
-        // Get multicast address from device
-        let multicast_str = device.multicast_group.as_ref()
-            .ok_or_else(|| anyhow!("Device has no multicast group"))?;
-        let multicast_addr: Ipv4Addr = multicast_str.parse()
-            .map_err(|_| anyhow!("Invalid multicast address"))?;
-        
-        // Assume port 5004 if not specified (standard AES67 port)
-        let port = 5004;
-        
-        // Create format based on device info
-        let format = Aes67Format {
-            sample_rate: device.sample_rate,
-            channels: device.channels as u8,
-            bits_per_sample: 24,
-            samples_per_packet: 48,
+        // Prefer the full SAP description (carries the RTP port)
+        let stream = match self.sap_discovery.streams().into_iter().find(|s| s.session_id == device.id) {
+            Some(stream) => stream,
+            None => {
+                let multicast_str = device.multicast_group.as_ref()
+                    .ok_or_else(|| anyhow!("Device has no multicast group or unicast address"))?;
+                let multicast_addr: Ipv4Addr = multicast_str.parse()
+                    .map_err(|_| anyhow!("Invalid stream address '{}'", multicast_str))?;
+                
+                Aes67Stream {
+                    name: device.name.clone(),
+                    session_id: device.id.clone(),
+                    origin: device.ip_address.clone().unwrap_or_default(),
+                    multicast_addr,
+                    port: device.port.unwrap_or(DEFAULT_RTP_PORT),
+                    channels: device.channels as u8,
+                    sample_rate: device.sample_rate,
+                    encoding: RtpEncoding::L24,
+                    ptime_us: 1000,
+                    direction: StreamDirection::Send,
+                    payload_type: DEFAULT_PAYLOAD_TYPE,
+                    media_clock_offset: 0,
+                    ref_clock: None,
+                    source_address: None,
+                    secondary: None,
+                    sdp: String::new(),
+                }
+            }
         };
         
-        // Create RTP receiver for this stream
-        let receiver = RtpReceiver::new(multicast_addr, port, format)?;
-        receiver.start()?;
-        
-        self.rtp_receiver = Some(receiver);
-        self.connected_device = Some(device.clone());
-        self.connected = true;
-        
-        info!("✅ Connected to {} ({}:{}, {} channels)", 
-            device.name, multicast_addr, port, device.channels);
-        
-        Ok(())
+        // Patch onto the next free mixer inputs
+        let patch = self.subscriptions.consecutive_patch(self.subscriptions.next_free_input(), stream.channels)?;
+        self.subscribe(&stream, patch)
     }
     
     fn disconnect(&mut self) -> Result<()> {
-        if let Some(device) = &self.connected_device {
-            info!("🔌 Disconnecting from AES67 device: {}", device.name);
-        }
-        
-        if let Some(receiver) = &self.rtp_receiver {
-            receiver.stop();
+        if !self.subscriptions.is_empty() {
+            info!("🔌 Unsubscribing from {} AES67 stream(s)", self.subscriptions.len());
         }
-        
-        self.rtp_receiver = None;
-        self.connected_device = None;
-        self.connected = false;
-        
+        self.subscriptions.clear();
         Ok(())
     }
     
-    fn read_samples(&self, buffer: &mut [f32], _channels: usize) -> usize {
-        if let Some(receiver) = &self.rtp_receiver {
-            receiver.read(buffer)
-        } else {
-            // No receiver connected, return silence
-            for sample in buffer.iter_mut() {
-                *sample = 0.0;
-            }
-            buffer.len()
-        }
+    fn read_samples(&self, buffer: &mut [f32], channels: usize) -> usize {
+        // Buffer holds interleaved mixer inputs; unpatched inputs stay silent
+        buffer.fill(0.0);
+        // Only contended if two threads read at once, the second one gets silence
+        let Some(mut scratch) = self.read_scratch.try_lock() else {
+            return 0;
+        };
+        self.subscriptions.read_into(buffer, channels, &mut scratch);
+        buffer.len()
     }
     
-    fn write_samples(&self, buffer: &[f32], _channels: usize) -> usize {
-        if let Some(sender) = &self.rtp_sender {
-            match sender.send(buffer) {
-                Ok(_) => buffer.len(),
-                Err(e) => {
-                    warn!("RTP send error: {}", e);
-                    0
-                }
-            }
-        } else {
-            0
+    fn write_samples(&self, buffer: &[f32], channels: usize) -> usize {
+        // Buffer holds interleaved mixer outputs, every stream takes its channels
+        if channels == 0 {
+            return 0;
         }
+        let Some(mut scratch) = self.write_scratch.try_lock() else {
+            return 0;
+        };
+        self.tx_streams.write_outputs(buffer, channels, buffer.len() / channels, &mut scratch);
+        buffer.len()
     }
     
     fn latency(&self) -> usize {
-        // AES67 typical latency: 1ms packet time + jitter buffer (~3-4ms total)
-        // At 48kHz: 48 samples = 1ms
-        // We use ~4ms jitter buffer = 192 samples
-        48 * 4
+        // Measured end-to-end latency of the slowest stream, the link offset until measured
+        let latency_us = self.subscriptions.max_latency_us()
+            .unwrap_or(self.config.link_offset_us as i64)
+            .max(0) as u64;
+        (latency_us * self.config.sample_rate as u64 / 1_000_000) as usize
     }
     
     fn is_connected(&self) -> bool {
-        self.connected
+        !self.subscriptions.is_empty()
+    }
+    
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
     }
 }
 
@@ -354,30 +692,21 @@ impl Default for Aes67Backend {
 
 impl Drop for Aes67Backend {
     fn drop(&mut self) {
+        // Stop outgoing streams and withdraw their announcements
+        self.tx_streams.clear();
+        
         // Stop services
         self.ptp_clock.stop();
         self.sap_discovery.stop();
         
-        // Remove our SAP announcement
-        if let Some(stream) = &self.our_stream {
-            let _ = self.sap_discovery.remove_announcement(&stream.session_id);
-        }
-        
-        // Disconnect from any connected device
+        // Stop all receivers
         let _ = self.disconnect();
     }
 }
 
-/// Get local IP address
-fn get_local_ip() -> Option<String> {
-    use std::net::UdpSocket;
-    
-    // Create a UDP socket and connect to a public address
-    // This doesn't actually send anything, just determines the local IP
-    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
-    socket.connect("8.8.8.8:80").ok()?;
-    let addr = socket.local_addr().ok()?;
-    Some(addr.ip().to_string())
+/// Get local IP address (used when no interface is configured)
+pub fn get_local_ip() -> Option<String> {
+    interface::default_ipv4_address().map(|address| address.to_string())
 }
 
 // ============================================================
@@ -441,7 +770,7 @@ impl AudioNetworkBackend for DanteBackend {
         buffer.len()
     }
     
-    fn write_samples(&self, buffer: &[f32], _channels: usize) -> usize {
+    fn write_samples(&self, _buffer: &[f32], _channels: usize) -> usize {
         // TODO: dante_sdk::write()
         0
     }
@@ -454,6 +783,14 @@ impl AudioNetworkBackend for DanteBackend {
     fn is_connected(&self) -> bool {
         self.connected
     }
+    
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 #[cfg(test)]
@@ -469,6 +806,46 @@ mod tests {
         assert_eq!(config.ptp_domain, 0);
     }
     
+    #[test]
+    fn test_default_tx_stream() {
+        let config = Aes67Config::default();
+        let streams = config.tx_streams();
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].destination, Ipv4Addr::new(239, 69, 1, 100));
+        assert_eq!(streams[0].channels, 8);
+        assert!(streams[0].validate(config.sample_rate, config.mixer_outputs).is_ok());
+    }
+    
+    #[test]
+    fn test_multicast_group_streams() {
+        let config = Aes67Config {
+            multicast_groups: vec![Ipv4Addr::new(239, 69, 1, 1), Ipv4Addr::new(239, 69, 1, 2)],
+            ..Default::default()
+        };
+        let streams = config.tx_streams();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[1].destination, Ipv4Addr::new(239, 69, 1, 2));
+        assert_eq!(streams[1].outputs, (8..16).collect::<Vec<_>>());
+        assert_eq!(streams[1].name, "AudioMultiverse 9-16");
+    }
+    
+    #[test]
+    fn test_missing_interface() {
+        let config = Aes67Config {
+            interface: Some("does-not-exist0".to_string()),
+            ..Default::default()
+        };
+        let error = Aes67Backend::with_config(config).err().unwrap().to_string();
+        assert!(error.contains("AES67 interface") && error.contains("does-not-exist0"), "{}", error);
+        
+        let config = Aes67Config {
+            interface: Some("127.0.0.1".to_string()),
+            ..Default::default()
+        };
+        let backend = Aes67Backend::with_config(config).unwrap();
+        assert_eq!(backend.interface_addr, Some(Ipv4Addr::LOCALHOST));
+    }
+    
     #[test]
     fn test_get_local_ip() {
         let ip = get_local_ip();
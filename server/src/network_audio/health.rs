@@ -0,0 +1,218 @@
+//! Health of received streams
+//!
+//! Every subscription watches the packets of its stream: rate, time since
+//! the last packet, loss, late packets, SSRC changes (sender restarted) and
+//! whether the sender is locked to our PTP grandmaster. A stream without
+//! packets for [`STALL_TIMEOUT`] is stalled; clients get an event when a
+//! stream stalls, recovers or was resubscribed automatically.
+
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+use super::rtp::JitterStats;
+use super::sdp::RefClock;
+
+/// A stream without packets for this long is stalled
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The packet rate is measured over windows of this length
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Packet arrival of a received stream, fed by its receive threads
+#[derive(Debug)]
+pub struct StreamActivity {
+    /// Subscribed at (counts as the last packet until one arrives)
+    started: Instant,
+    packets: u64,
+    last_packet: Option<Instant>,
+    ssrc: Option<u32>,
+    ssrc_changes: u64,
+    window_start: Instant,
+    window_packets: u64,
+    packet_rate: f64,
+}
+
+impl StreamActivity {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            packets: 0,
+            last_packet: None,
+            ssrc: None,
+            ssrc_changes: 0,
+            window_start: now,
+            window_packets: 0,
+            packet_rate: 0.0,
+        }
+    }
+
+    /// Count a packet (duplicates from the second path are not counted)
+    pub fn record(&mut self, ssrc: u32, now: Instant) {
+        if self.ssrc.is_some_and(|last| last != ssrc) {
+            self.ssrc_changes += 1;
+        }
+        self.ssrc = Some(ssrc);
+        self.packets += 1;
+        self.last_packet = Some(now);
+        self.update_rate(now);
+    }
+
+    /// Packets per second over the last complete window
+    pub fn packet_rate(&mut self, now: Instant) -> f64 {
+        self.update_rate(now);
+        self.packet_rate
+    }
+
+    /// Time since the last packet, or since subscribing if none arrived yet
+    pub fn silence(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_packet.unwrap_or(self.started))
+    }
+
+    fn update_rate(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= RATE_WINDOW {
+            self.packet_rate = (self.packets - self.window_packets) as f64 / elapsed.as_secs_f64();
+            self.window_start = now;
+            self.window_packets = self.packets;
+        }
+    }
+}
+
+/// State of a received stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    /// Subscribed, no packet yet
+    Waiting,
+    /// Packets arriving
+    Ok,
+    /// No packets for [`STALL_TIMEOUT`]
+    Stalled,
+}
+
+/// Health of a received stream for API and clients
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamHealth {
+    pub state: HealthState,
+    pub packets_per_second: f64,
+    /// Milliseconds since the last packet (None = nothing received yet)
+    pub last_packet_ms: Option<u64>,
+    /// Packets missing from the sequence
+    pub lost: u64,
+    /// Packets that arrived after their playout time
+    pub late: u64,
+    /// Sender changed its SSRC (restarted)
+    pub ssrc_changes: u64,
+    /// Sender is not on our PTP grandmaster (other or unknown reference
+    /// clock, or timestamps far from our media clock)
+    pub ptp_mismatch: bool,
+    /// Automatic resubscriptions (stream reappeared or changed)
+    pub reconnects: u64,
+}
+
+impl StreamHealth {
+    /// Evaluate a stream from its packet activity and playout statistics
+    ///
+    /// `ref_clock` is the sender's reference clock from its SDP,
+    /// `grandmaster` our current PTP grandmaster (None = not synchronized).
+    pub fn evaluate(
+        activity: &mut StreamActivity,
+        jitter: &JitterStats,
+        ref_clock: Option<&RefClock>,
+        grandmaster: Option<&str>,
+        reconnects: u64,
+        now: Instant,
+    ) -> Self {
+        let state = if activity.silence(now) >= STALL_TIMEOUT {
+            HealthState::Stalled
+        } else if activity.packets == 0 {
+            HealthState::Waiting
+        } else {
+            HealthState::Ok
+        };
+        // The last window may still hold packets from before the stall
+        let packet_rate = activity.packet_rate(now);
+
+        Self {
+            state,
+            packets_per_second: if state == HealthState::Stalled { 0.0 } else { packet_rate },
+            last_packet_ms: activity.last_packet.map(|t| now.saturating_duration_since(t).as_millis() as u64),
+            lost: jitter.lost,
+            late: jitter.late,
+            ssrc_changes: activity.ssrc_changes,
+            ptp_mismatch: state == HealthState::Ok && ptp_mismatch(ref_clock, grandmaster, jitter.ptp_aligned),
+            reconnects,
+        }
+    }
+}
+
+/// Sender not locked to our grandmaster
+fn ptp_mismatch(ref_clock: Option<&RefClock>, grandmaster: Option<&str>, aligned: bool) -> bool {
+    if !aligned {
+        return true;
+    }
+    match (ref_clock, grandmaster) {
+        (Some(RefClock::Ptp { grandmaster: Some(theirs), .. }), Some(ours)) => !theirs.eq_ignore_ascii_case(ours),
+        (Some(RefClock::LocalMac(_)), _) => true,
+        _ => false,
+    }
+}
+
+/// Change of a subscription's health
+#[derive(Debug, Clone)]
+pub enum HealthEvent {
+    /// No packets for [`STALL_TIMEOUT`]
+    Stalled { stream_id: String, name: String },
+    /// Packets arriving again
+    Recovered { stream_id: String, name: String },
+    /// Subscribed again with a new description (stream reappeared, changed,
+    /// or a saved subscription was restored)
+    Resubscribed { previous_id: String, stream_id: String, name: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ptp(grandmaster: &str) -> RefClock {
+        RefClock::Ptp { version: "IEEE1588-2008".to_string(), grandmaster: Some(grandmaster.to_string()), domain: Some(0) }
+    }
+
+    #[test]
+    fn test_stream_health() {
+        let start = Instant::now();
+        let mut activity = StreamActivity::new(start);
+        let jitter = JitterStats { ptp_aligned: true, ..Default::default() };
+        let gm = "00-1D-C1-FF-FE-12-34-56";
+        let health = |activity: &mut StreamActivity, at: Duration| {
+            StreamHealth::evaluate(activity, &jitter, Some(&ptp(gm)), Some(gm), 0, start + at)
+        };
+
+        assert_eq!(health(&mut activity, Duration::from_millis(500)).state, HealthState::Waiting);
+        assert_eq!(health(&mut activity, Duration::from_millis(1500)).state, HealthState::Stalled);
+
+        // 1000 packets per second from one sender, then it restarts
+        for i in 0..2000 {
+            activity.record(if i < 1500 { 1 } else { 2 }, start + Duration::from_millis(2000 + i));
+        }
+        let ok = health(&mut activity, Duration::from_millis(4000));
+        assert_eq!(ok.state, HealthState::Ok);
+        assert!((ok.packets_per_second - 1000.0).abs() < 10.0, "{}", ok.packets_per_second);
+        assert_eq!((ok.ssrc_changes, ok.last_packet_ms, ok.ptp_mismatch), (1, Some(1), false));
+
+        let stalled = health(&mut activity, Duration::from_millis(5500));
+        assert_eq!(stalled.state, HealthState::Stalled);
+        assert_eq!(stalled.packets_per_second, 0.0);
+    }
+
+    #[test]
+    fn test_ptp_mismatch() {
+        let ours = "00-1D-C1-FF-FE-12-34-56";
+        assert!(!ptp_mismatch(Some(&ptp(&ours.to_lowercase())), Some(ours), true));
+        assert!(ptp_mismatch(Some(&ptp("00-1D-C1-FF-FE-AB-CD-EF")), Some(ours), true));
+        assert!(ptp_mismatch(Some(&RefClock::LocalMac("00-1D-C1-12-34-56".to_string())), Some(ours), true));
+        assert!(ptp_mismatch(None, Some(ours), false));
+        // Nothing to compare
+        assert!(!ptp_mismatch(None, None, true));
+    }
+}
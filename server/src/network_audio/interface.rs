@@ -0,0 +1,166 @@
+//! Network interface addresses
+//!
+//! Resolves the configured interface names to the IPv4 address multicast
+//! traffic is sent from and received on, and binds multicast sockets (PTP,
+//! SAP, RTP, RTCP) to that interface.
+
+use std::net::Ipv4Addr;
+use anyhow::{Result, anyhow};
+use socket2::Socket;
+
+/// IPv4 address of a network interface (name or address literal)
+pub fn ipv4_address(interface: &str) -> Result<Ipv4Addr> {
+    if let Ok(address) = interface.parse() {
+        return Ok(address);
+    }
+
+    let addresses = interface_addresses()?;
+    if let Some((_, address)) = addresses.iter().find(|(name, _)| name == interface) {
+        return Ok(*address);
+    }
+
+    let mut names: Vec<&str> = addresses.iter().map(|(name, _)| name.as_str()).collect();
+    names.dedup();
+    Err(anyhow!(
+        "Network interface '{}' not found or without IPv4 address (available: {})",
+        interface,
+        if names.is_empty() { "none".to_string() } else { names.join(", ") }
+    ))
+}
+
+/// Name of the interface that has `address`
+pub fn interface_name(address: Ipv4Addr) -> Option<String> {
+    interface_addresses().ok()?
+        .into_iter()
+        .find(|(_, a)| *a == address)
+        .map(|(name, _)| name)
+}
+
+/// Our address when no interface is configured
+///
+/// The first interface that is not loopback, preferring routable addresses
+/// over link-local ones.
+#[cfg(target_os = "linux")]
+pub fn default_ipv4_address() -> Option<Ipv4Addr> {
+    let addresses: Vec<Ipv4Addr> = interface_addresses().ok()?
+        .into_iter()
+        .map(|(_, address)| address)
+        .filter(|address| !address.is_loopback())
+        .collect();
+    addresses.iter().find(|a| !a.is_link_local()).or(addresses.first()).copied()
+}
+
+/// Our address when no interface is configured (source of the default route)
+#[cfg(not(target_os = "linux"))]
+pub fn default_ipv4_address() -> Option<Ipv4Addr> {
+    // Connecting a UDP socket sends nothing, it only selects the route
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    match socket.local_addr().ok()?.ip() {
+        std::net::IpAddr::V4(address) => Some(address),
+        std::net::IpAddr::V6(_) => None,
+    }
+}
+
+/// Join `group` on `interface` (None = the system's choice) and send
+/// multicast from there
+///
+/// With an interface, the socket only receives groups it joined itself on
+/// that interface.
+pub fn join_multicast(socket: &Socket, group: Ipv4Addr, interface: Option<Ipv4Addr>) -> Result<()> {
+    let address = interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+    socket.join_multicast_v4(&group, &address)
+        .map_err(|e| anyhow!("Cannot join multicast group {} on {}: {}", group, address, e))?;
+    if let Some(interface) = interface {
+        socket.set_multicast_if_v4(&interface)?;
+        only_joined_groups(socket)?;
+    }
+    Ok(())
+}
+
+/// Deliver only multicast groups joined on this socket
+///
+/// Linux otherwise hands a socket bound to the wildcard address every group
+/// any socket on the host joined, on any interface.
+#[cfg(target_os = "linux")]
+pub fn only_joined_groups(socket: &Socket) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let disabled: libc::c_int = 0;
+    // SAFETY: valid socket and an option value of the given size
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_MULTICAST_ALL,
+            &disabled as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if result != 0 {
+        return Err(anyhow!("IP_MULTICAST_ALL: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Deliver only multicast groups joined on this socket (the default outside Linux)
+#[cfg(not(target_os = "linux"))]
+pub fn only_joined_groups(_socket: &Socket) -> Result<()> {
+    Ok(())
+}
+
+/// All IPv4 interface addresses as (interface name, address)
+#[cfg(target_os = "linux")]
+pub fn interface_addresses() -> Result<Vec<(String, Ipv4Addr)>> {
+    let mut list: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: getifaddrs allocates the list, it is released below
+    if unsafe { libc::getifaddrs(&mut list) } != 0 {
+        return Err(anyhow!("getifaddrs failed: {}", std::io::Error::last_os_error()));
+    }
+
+    let mut addresses = Vec::new();
+    let mut entry = list;
+    while !entry.is_null() {
+        // SAFETY: entries of the list are valid until freeifaddrs
+        let ifa = unsafe { &*entry };
+        entry = ifa.ifa_next;
+        if ifa.ifa_addr.is_null() || ifa.ifa_name.is_null() {
+            continue;
+        }
+        // SAFETY: ifa_addr is non-null and its family is checked before the cast
+        let family = unsafe { (*ifa.ifa_addr).sa_family } as i32;
+        if family != libc::AF_INET {
+            continue;
+        }
+        let sin = unsafe { &*(ifa.ifa_addr as *const libc::sockaddr_in) };
+        let name = unsafe { std::ffi::CStr::from_ptr(ifa.ifa_name) }.to_string_lossy().into_owned();
+        addresses.push((name, Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr))));
+    }
+
+    // SAFETY: list was returned by getifaddrs
+    unsafe { libc::freeifaddrs(list) };
+    Ok(addresses)
+}
+
+/// All IPv4 interface addresses (interface names need Linux)
+#[cfg(not(target_os = "linux"))]
+pub fn interface_addresses() -> Result<Vec<(String, Ipv4Addr)>> {
+    Err(anyhow!("Interface names are only supported on Linux, configure the interface's IPv4 address"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipv4_address() {
+        assert_eq!(ipv4_address("192.168.10.5").unwrap(), Ipv4Addr::new(192, 168, 10, 5));
+        #[cfg(target_os = "linux")]
+        assert_eq!(ipv4_address("lo").unwrap(), Ipv4Addr::LOCALHOST);
+        let error = ipv4_address("does-not-exist0").unwrap_err().to_string();
+        assert!(error.contains("does-not-exist0"), "{}", error);
+        #[cfg(target_os = "linux")]
+        assert_eq!(interface_name(Ipv4Addr::LOCALHOST).as_deref(), Some("lo"));
+        assert!(default_ipv4_address().is_none_or(|address| !address.is_loopback()));
+    }
+}
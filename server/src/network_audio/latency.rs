@@ -0,0 +1,188 @@
+//! Latency of received streams
+//!
+//! Both measurements are taken against our PTP media clock: transit is how
+//! long after its RTP timestamp a packet arrives (sender buffering plus
+//! network), playout how long after its RTP timestamp a frame is played
+//! (the end-to-end latency). Transit has to stay below the link offset,
+//! otherwise packets arrive too late to be played.
+//!
+//! Streams from different devices are in phase in the mix when they play
+//! at the same latency; [`align_delays`] finds the extra delay per stream.
+
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+/// Transit is reported over windows of this length
+const TRANSIT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Packets further than this from our media clock are not on our PTP time
+const MAX_TRANSIT: Duration = Duration::from_secs(1);
+
+/// Minimum, maximum and sum of transit times in frames
+#[derive(Debug, Clone, Copy)]
+struct Transit {
+    min: i64,
+    max: i64,
+    sum: i64,
+    count: i64,
+}
+
+impl Transit {
+    fn new(frames: i64) -> Self {
+        Self { min: frames, max: frames, sum: frames, count: 1 }
+    }
+
+    fn add(&mut self, frames: i64) {
+        self.min = self.min.min(frames);
+        self.max = self.max.max(frames);
+        self.sum += frames;
+        self.count += 1;
+    }
+}
+
+/// Latency measurements of a received stream, fed by its receive threads
+/// and its playout
+#[derive(Debug)]
+pub struct LatencyMonitor {
+    sample_rate: u32,
+    window_start: Instant,
+    /// Transit of the current window
+    window: Option<Transit>,
+    /// Transit of the last complete window
+    transit: Option<Transit>,
+    /// Frames between PTP media time and the frame played last (None = not on PTP time)
+    playout: Option<usize>,
+}
+
+impl LatencyMonitor {
+    pub fn new(sample_rate: u32, now: Instant) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            window_start: now,
+            window: None,
+            transit: None,
+            playout: None,
+        }
+    }
+
+    /// Record a packet that arrived `frames` after its RTP timestamp
+    /// (media time at arrival minus the timestamp)
+    pub fn record_transit(&mut self, frames: i32, now: Instant) {
+        if now.saturating_duration_since(self.window_start) >= TRANSIT_WINDOW {
+            self.transit = self.window.take();
+            self.window_start = now;
+        }
+        if frames.unsigned_abs() as u64 > self.sample_rate as u64 * MAX_TRANSIT.as_secs() {
+            return;
+        }
+        match self.window.as_mut() {
+            Some(window) => window.add(frames as i64),
+            None => self.window = Some(Transit::new(frames as i64)),
+        }
+    }
+
+    /// Record the playout delay at a read (None = playout not on PTP time)
+    pub fn record_playout(&mut self, frames: Option<usize>) {
+        self.playout = frames;
+    }
+
+    /// Measured latency, with the stream's link offset in frames
+    pub fn stats(&self, link_offset: usize) -> LatencyStats {
+        let us = |frames: i64| frames * 1_000_000 / self.sample_rate as i64;
+        // Until the first window is complete, the current one
+        let transit = self.transit.or(self.window);
+        LatencyStats {
+            transit_min_us: transit.map(|t| us(t.min)),
+            transit_avg_us: transit.map(|t| us(t.sum / t.count)),
+            transit_max_us: transit.map(|t| us(t.max)),
+            playout_us: self.playout.map(|frames| us(frames as i64)),
+            link_offset_us: us(link_offset as i64),
+        }
+    }
+}
+
+/// Latency of a received stream for API and clients (µs)
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct LatencyStats {
+    /// Packet arrival after the RTP timestamp over the last second
+    /// (None = timestamps not on our PTP time)
+    pub transit_min_us: Option<i64>,
+    pub transit_avg_us: Option<i64>,
+    pub transit_max_us: Option<i64>,
+    /// End-to-end latency: PTP time minus the RTP timestamp of the frame
+    /// being played (None = playout not on PTP time)
+    pub playout_us: Option<i64>,
+    /// Playout target behind PTP time, including the alignment delay
+    pub link_offset_us: i64,
+}
+
+/// A received stream to align: its link offset without alignment delay,
+/// its measured worst transit and its packet time (µs)
+#[derive(Debug, Clone, Copy)]
+pub struct AlignInput {
+    pub link_offset_us: u32,
+    pub transit_max_us: Option<i64>,
+    pub ptime_us: u32,
+}
+
+/// Extra delay per stream so that all streams play at the same latency
+///
+/// Every stream plays at least at its own link offset and not before its
+/// latest packet is complete (worst transit plus one packet). The common
+/// latency is the largest of these; streams without transit measurement
+/// (not on our PTP time) cannot be aligned and get None.
+pub fn align_delays(streams: &[AlignInput]) -> Vec<Option<u32>> {
+    let needed = |s: &AlignInput| {
+        s.transit_max_us.map(|transit| (transit + s.ptime_us as i64).max(s.link_offset_us as i64))
+    };
+    let Some(target) = streams.iter().filter_map(needed).max() else {
+        return vec![None; streams.len()];
+    };
+    streams.iter()
+        .map(|s| s.transit_max_us.map(|_| (target - s.link_offset_us as i64).max(0) as u32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_monitor() {
+        let start = Instant::now();
+        let mut monitor = LatencyMonitor::new(48000, start);
+        assert!(monitor.stats(144).transit_max_us.is_none());
+
+        for (i, transit) in [48, 96, 72].into_iter().enumerate() {
+            monitor.record_transit(transit, start + Duration::from_millis(i as u64));
+        }
+        // Not on our PTP time: ignored
+        monitor.record_transit(i32::MAX, start);
+        monitor.record_playout(Some(144));
+
+        let stats = monitor.stats(144);
+        assert_eq!((stats.transit_min_us, stats.transit_avg_us, stats.transit_max_us), (Some(1000), Some(1500), Some(2000)));
+        assert_eq!((stats.playout_us, stats.link_offset_us), (Some(3000), 3000));
+
+        // A new window replaces the old one once it is complete
+        monitor.record_transit(480, start + Duration::from_millis(1500));
+        assert_eq!(monitor.stats(144).transit_max_us, Some(2000));
+        monitor.record_transit(480, start + Duration::from_millis(2500));
+        assert_eq!(monitor.stats(144).transit_min_us, Some(10_000));
+    }
+
+    #[test]
+    fn test_align_delays() {
+        let stream = |link_offset_us, transit_max_us| AlignInput { link_offset_us, transit_max_us, ptime_us: 1000 };
+
+        // Packets of the second stream take up to 4 ms, both play at 5 ms
+        let delays = align_delays(&[stream(3000, Some(1200)), stream(3000, Some(4000)), stream(4000, None)]);
+        assert_eq!(delays, vec![Some(2000), Some(2000), None]);
+
+        // All within their link offset: the longest link offset wins
+        let delays = align_delays(&[stream(3000, Some(500)), stream(6000, Some(500))]);
+        assert_eq!(delays, vec![Some(3000), Some(0)]);
+
+        assert_eq!(align_delays(&[stream(3000, None)]), vec![None]);
+    }
+}
@@ -0,0 +1,176 @@
+//! Virtual loopback backend
+//!
+//! A network backend without a network, registered as "virtual": once its
+//! device is connected, the first mixer outputs come back on the last mixer
+//! inputs one block later. Lets routing, the backend registry and the API
+//! be tested without AES67 hardware.
+
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use anyhow::{Result, anyhow};
+use parking_lot::Mutex;
+use tracing::info;
+
+use super::backend::{AudioNetworkBackend, NetworkDevice, NetworkDeviceType};
+
+/// ID of the only device of the loopback backend
+pub const LOOPBACK_DEVICE_ID: &str = "loopback";
+
+/// Loops mixer outputs back to mixer inputs
+pub struct LoopbackBackend {
+    /// Looped channels
+    channels: usize,
+    sample_rate: u32,
+    connected: AtomicBool,
+    /// Outputs of the last block, `channels` per frame
+    block: Mutex<Vec<f32>>,
+    /// Frames of the last block (the loop delays by one block)
+    block_frames: AtomicUsize,
+}
+
+impl LoopbackBackend {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        Self {
+            channels: channels.max(1),
+            sample_rate,
+            connected: AtomicBool::new(false),
+            block: Mutex::new(Vec::new()),
+            block_frames: AtomicUsize::new(0),
+        }
+    }
+
+    fn device(&self) -> NetworkDevice {
+        NetworkDevice {
+            id: LOOPBACK_DEVICE_ID.to_string(),
+            name: "Virtual Loopback".to_string(),
+            device_type: NetworkDeviceType::Both,
+            channels: self.channels as u32,
+            sample_rate: self.sample_rate,
+            ip_address: None,
+            multicast_group: None,
+            port: None,
+        }
+    }
+}
+
+impl AudioNetworkBackend for LoopbackBackend {
+    fn name(&self) -> &'static str {
+        "Virtual"
+    }
+
+    fn init(&mut self) -> Result<()> {
+        info!("🔁 Virtual loopback backend ready ({} channels)", self.channels);
+        Ok(())
+    }
+
+    fn discover(&self) -> Result<Vec<NetworkDevice>> {
+        Ok(vec![self.device()])
+    }
+
+    fn connect(&mut self, device: &NetworkDevice) -> Result<()> {
+        if device.id != LOOPBACK_DEVICE_ID {
+            return Err(anyhow!("Unknown virtual device '{}'", device.id));
+        }
+        self.connected.store(true, Ordering::Relaxed);
+        info!("🔁 Virtual loopback connected: outputs 1-{} -> last {} inputs", self.channels, self.channels);
+        Ok(())
+    }
+
+    fn disconnect(&mut self) -> Result<()> {
+        self.connected.store(false, Ordering::Relaxed);
+        self.block.lock().clear();
+        self.block_frames.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn read_samples(&self, buffer: &mut [f32], channels: usize) -> usize {
+        // Buffer holds interleaved mixer inputs, the loop feeds the last ones
+        buffer.fill(0.0);
+        if channels == 0 || !self.is_connected() {
+            return 0;
+        }
+        let Some(block) = self.block.try_lock() else {
+            return 0;
+        };
+        let looped = self.channels.min(channels);
+        let first = channels - looped;
+        let frames = (buffer.len() / channels).min(block.len() / self.channels);
+        for frame in 0..frames {
+            let source = &block[frame * self.channels..frame * self.channels + looped];
+            buffer[frame * channels + first..frame * channels + channels].copy_from_slice(source);
+        }
+        frames * channels
+    }
+
+    fn write_samples(&self, buffer: &[f32], channels: usize) -> usize {
+        // Buffer holds interleaved mixer outputs, the loop takes the first ones
+        if channels == 0 || !self.is_connected() {
+            return 0;
+        }
+        let Some(mut block) = self.block.try_lock() else {
+            return 0;
+        };
+        let frames = buffer.len() / channels;
+        let looped = self.channels.min(channels);
+        block.resize(frames * self.channels, 0.0);
+        for frame in 0..frames {
+            let target = &mut block[frame * self.channels..(frame + 1) * self.channels];
+            target[..looped].copy_from_slice(&buffer[frame * channels..frame * channels + looped]);
+            target[looped..].fill(0.0);
+        }
+        self.block_frames.store(frames, Ordering::Relaxed);
+        frames * channels
+    }
+
+    fn latency(&self) -> usize {
+        self.block_frames.load(Ordering::Relaxed)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback() {
+        let mut backend = LoopbackBackend::new(2, 48000);
+        backend.init().unwrap();
+        let device = backend.discover().unwrap().remove(0);
+        assert_eq!((device.id.as_str(), device.channels), (LOOPBACK_DEVICE_ID, 2));
+
+        // 3 outputs, 2 frames; 4 inputs
+        let outputs = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        let mut inputs = [1.0; 8];
+
+        // Silent until connected
+        assert_eq!(backend.write_samples(&outputs, 3), 0);
+        backend.read_samples(&mut inputs, 4);
+        assert!(inputs.iter().all(|s| *s == 0.0));
+
+        backend.connect(&device).unwrap();
+        assert!(backend.is_connected());
+        backend.write_samples(&outputs, 3);
+        backend.read_samples(&mut inputs, 4);
+        assert_eq!(inputs, [0.0, 0.0, 0.1, 0.2, 0.0, 0.0, 0.4, 0.5]);
+        assert_eq!(backend.latency(), 2);
+
+        let other = NetworkDevice { id: "other".to_string(), ..device };
+        assert!(backend.connect(&other).is_err());
+
+        backend.disconnect().unwrap();
+        backend.read_samples(&mut inputs, 4);
+        assert!(inputs.iter().all(|s| *s == 0.0));
+    }
+}
@@ -4,28 +4,63 @@
 //!
 //! ## Modules
 //! - `ptp` - PTP (IEEE 1588) Clock Synchronization (Linux only)
-//! - `rtp` - RTP Audio Streaming (L24 format)
+//! - `rtp` - RTP Audio Streaming (L16/L24/AM824 format)
+//! - `rtcp` - RTCP sender/receiver reports (loss, jitter, round-trip)
 //! - `sap` - SAP/SDP Stream Discovery
+//! - `sdp` - Session descriptions (RFC 4566, AES67/RAVENNA attributes)
+//! - `ravenna` - RAVENNA session discovery and publication (mDNS/RTSP)
+//! - `subscription` - Received streams and their channel patch
+//! - `health` - Stream health (packet rate, stalls, PTP lock) and its events
+//! - `latency` - Transit and end-to-end latency, alignment of streams
+//! - `sender` - Paced real-time RTP transmitter
+//! - `streams` - Outgoing streams (config, SAP announcement, output map)
+//! - `interface` - Network interface addresses and multicast binding (primary/secondary network)
 //! - `backend` - High-level backend abstraction
+//! - `registry` - Backends selected by name, several running at once
+//! - `loopback` - Virtual loopback backend (mixer outputs back to inputs)
+//! - `nmos` - NMOS node: IS-04 registration, IS-05 connection management
+//! - `simulator` - Simulated AES67 device for loopback tests (tests only)
 //!
 //! Note: Full AES67 support (PTP synchronization) requires Linux.
 //! On Windows, a limited implementation without hardware timestamping is used.
 
 // Common modules (all platforms)
 pub mod rtp;
+pub mod rtcp;
 pub mod sap;
+pub mod sdp;
+pub mod ravenna;
+pub mod subscription;
+pub mod health;
+pub mod latency;
+pub mod sender;
+pub mod streams;
+pub mod interface;
 mod backend;
+pub mod registry;
+pub mod loopback;
+pub mod nmos;
 
-// PTP requires statime which is Linux-only
+// In-process AES67 device for the interoperability tests (`tests/`)
+#[cfg(any(test, feature = "test-support"))]
+pub mod simulator;
+
+// PTP uses Linux socket timestamping (SO_TIMESTAMPING)
 #[cfg(target_os = "linux")]
 pub mod ptp;
 
 // Re-exports
-pub use backend::{AudioNetworkBackend, Aes67Backend, Aes67Config, NetworkDevice, NetworkDeviceType};
+pub use backend::{AudioNetworkBackend, Aes67Backend, Aes67Config, NetworkDevice, PtpTimestamping};
 #[cfg(target_os = "linux")]
 pub use ptp::{PtpClock, PtpState, PtpStats};
+pub use rtcp::RtcpStats;
 pub use rtp::{RtpSender, RtpReceiver, Aes67Format};
-pub use sap::{SapDiscovery, Aes67Stream, StreamDirection};
+pub use sap::{SapDiscovery, SapEvent, Aes67Stream, StreamDirection};
+pub use sender::MediaTicker;
+pub use streams::{Aes67TxStreams, TxStreamConfig, TxStreamInfo};
+pub use subscription::{Aes67Subscriptions, ChannelPatch, SavedSubscription, SubscriptionInfo};
+pub use health::HealthEvent;
+pub use registry::{BackendContext, BackendInfo, BackendRegistry, NetworkBackends};
 
 // Stub types for non-Linux platforms
 #[cfg(not(target_os = "linux"))]
@@ -34,7 +69,7 @@ pub mod ptp_stub {
     use std::sync::Arc;
     
     /// PTP Clock state
-    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
     pub enum PtpState {
         Initializing,
         Listening,
@@ -44,13 +79,23 @@ pub mod ptp_stub {
     }
     
     /// PTP Clock statistics
-    #[derive(Debug, Clone, Default)]
+    #[derive(Debug, Clone, Default, serde::Serialize)]
     pub struct PtpStats {
         pub offset_ns: i64,
         pub path_delay_ns: i64,
         pub sync_count: u64,
         pub clock_accuracy_ns: u64,
         pub steps_removed: u16,
+        pub frequency_ppb: f64,
+        pub delay_req_count: u64,
+        pub grandmaster_identity: String,
+        pub parent_port_identity: String,
+        pub grandmaster_priority1: u8,
+        pub grandmaster_priority2: u8,
+        pub clock_class: u8,
+        pub clock_accuracy: u8,
+        pub timestamping: super::PtpTimestamping,
+        pub utc_offset_s: i16,
     }
     
     /// Stub PTP Clock for non-Linux platforms
@@ -68,6 +113,18 @@ pub mod ptp_stub {
         
         pub fn set_domain(&mut self, _domain: u8) {}
         
+        pub fn set_master_capable(&mut self, _enabled: bool, _priority1: u8) {}
+        
+        pub fn set_timestamping(&mut self, _mode: super::PtpTimestamping) {}
+        
+        pub fn set_ports(&mut self, _event_port: u16, _general_port: u16) {}
+        
+        pub fn set_interface_address(&mut self, _address: Option<std::net::Ipv4Addr>) {}
+        
+        pub fn domain(&self) -> u8 {
+            0
+        }
+        
         pub fn state(&self) -> PtpState {
             if self.running.load(Ordering::Relaxed) {
                 PtpState::Master // Pretend to be master on Windows
@@ -98,13 +155,22 @@ pub mod ptp_stub {
             self.running.store(false, Ordering::Relaxed);
         }
         
-        pub fn media_timestamp(&self) -> u32 {
+        pub fn now_ns(&self) -> u64 {
             use std::time::{SystemTime, UNIX_EPOCH};
-            let now = SystemTime::now()
+            SystemTime::now()
                 .duration_since(UNIX_EPOCH)
-                .unwrap_or_default();
-            // 48kHz sample clock
-            ((now.as_nanos() / 20833) as u32) & 0xFFFFFFFF
+                .unwrap_or_default()
+                .as_nanos() as u64
+        }
+        
+        /// The system clock is UTC, no offset
+        pub fn utc_ns(&self) -> u64 {
+            self.now_ns()
+        }
+        
+        pub fn media_timestamp(&self, sample_rate: u32) -> u32 {
+            // Media clock at the stream's sample rate
+            ((self.now_ns() as u128 * sample_rate as u128 / 1_000_000_000) & 0xFFFFFFFF) as u32
         }
     }
 }
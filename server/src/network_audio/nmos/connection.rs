@@ -0,0 +1,413 @@
+//! IS-05 connection management
+//!
+//! Every receiver has staged and active parameters. A controller PATCHes
+//! the staged ones (a JSON merge, transport parameters per leg) and
+//! activates them immediately or at a TAI time; the activated receiver
+//! subscribes to the stream its transport file (SDP) describes, with the
+//! transport parameters applied on top. Senders only accept their current
+//! parameters, their streams are changed through the AES67 API.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+use super::NmosError;
+use crate::network_audio::Aes67Stream;
+
+/// TAI is ahead of UTC by the leap seconds since 1972
+const TAI_OFFSET: Duration = Duration::from_secs(37);
+
+/// Transport of our receivers (senders are rtp.mcast or rtp.ucast)
+pub const TRANSPORT_RTP: &str = "urn:x-nmos:transport:rtp";
+
+/// Transport parameters of a receiver leg
+const RECEIVER_PARAMS: [&str; 5] = ["source_ip", "multicast_ip", "interface_ip", "destination_port", "rtp_enabled"];
+
+/// Transport parameters of a sender leg
+const SENDER_PARAMS: [&str; 5] = ["source_ip", "destination_ip", "source_port", "destination_port", "rtp_enabled"];
+
+/// When staged parameters take effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActivationMode {
+    #[serde(rename = "activate_immediate")]
+    Immediate,
+    #[serde(rename = "activate_scheduled_absolute")]
+    ScheduledAbsolute,
+    #[serde(rename = "activate_scheduled_relative")]
+    ScheduledRelative,
+}
+
+/// Activation of staged parameters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Activation {
+    pub mode: Option<ActivationMode>,
+    /// TAI time (absolute) or offset (relative) as "<seconds>:<nanoseconds>"
+    pub requested_time: Option<String>,
+    /// TAI time the parameters took or will take effect
+    pub activation_time: Option<String>,
+}
+
+/// SDP describing the stream to receive
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransportFile {
+    pub data: Option<String>,
+    #[serde(rename = "type")]
+    pub media_type: Option<String>,
+}
+
+/// Staged or active parameters of a receiver
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceiverParams {
+    /// Sender the receiver is connected to (informational)
+    pub sender_id: Option<String>,
+    pub master_enable: bool,
+    pub activation: Activation,
+    pub transport_file: TransportFile,
+    /// One set of transport parameters per network leg
+    pub transport_params: Vec<Map<String, Value>>,
+}
+
+impl ReceiverParams {
+    /// Disabled receiver with `legs` network legs (two with ST 2022-7)
+    pub fn new(legs: usize) -> Self {
+        let leg = json!({
+            "source_ip": null,
+            "multicast_ip": null,
+            "interface_ip": "auto",
+            "destination_port": "auto",
+            "rtp_enabled": true,
+        });
+        Self {
+            sender_id: None,
+            master_enable: false,
+            activation: Activation::default(),
+            transport_file: TransportFile::default(),
+            transport_params: vec![leg.as_object().cloned().unwrap_or_default(); legs],
+        }
+    }
+
+    /// Staged parameters with a PATCH applied
+    ///
+    /// Objects are merged key by key, transport parameters leg by leg; the
+    /// result is checked against the allowed interface addresses.
+    pub fn patched(&self, patch: &Value, interfaces: &[Ipv4Addr]) -> Result<Self, NmosError> {
+        let mut staged = serde_json::to_value(self).map_err(|e| NmosError::internal(e.to_string()))?;
+        merge_staged(&mut staged, patch, &["sender_id", "master_enable", "activation", "transport_file", "transport_params"], &RECEIVER_PARAMS)?;
+        let params: Self = serde_json::from_value(staged).map_err(|e| NmosError::bad_request(e.to_string()))?;
+        for leg in &params.transport_params {
+            check_ip(leg, "source_ip", false)?;
+            check_ip(leg, "multicast_ip", false)?;
+            check_port(leg, "destination_port")?;
+            check_bool(leg, "rtp_enabled")?;
+            match leg.get("interface_ip") {
+                Some(Value::String(auto)) if auto == "auto" => {}
+                _ => {
+                    let address = check_ip(leg, "interface_ip", true)?;
+                    if address.is_some_and(|a| !interfaces.contains(&a)) {
+                        return Err(NmosError::bad_request("interface_ip is not one of our interfaces"));
+                    }
+                }
+            }
+        }
+        params.activation.schedule(tai_now())?;
+        Ok(params)
+    }
+
+    /// The transport parameters applied to the stream from the transport file
+    ///
+    /// Set multicast/source addresses and ports override the SDP; a second
+    /// leg with RTP disabled drops the ST 2022-7 secondary path.
+    pub fn apply_to(&self, stream: &mut Aes67Stream) {
+        if let Some(primary) = self.transport_params.first() {
+            if let Some(address) = ip_param(primary, "multicast_ip") {
+                stream.multicast_addr = address;
+            }
+            if let Some(address) = ip_param(primary, "source_ip") {
+                stream.source_address = Some(address);
+            }
+            if let Some(port) = port_param(primary, "destination_port") {
+                stream.port = port;
+            }
+        }
+        if let Some(secondary) = self.transport_params.get(1) {
+            if !rtp_enabled(secondary) {
+                stream.secondary = None;
+            } else if let Some(current) = stream.secondary {
+                let address = ip_param(secondary, "multicast_ip").unwrap_or(*current.ip());
+                let port = port_param(secondary, "destination_port").unwrap_or(current.port());
+                stream.secondary = Some(SocketAddrV4::new(address, port));
+            }
+        }
+    }
+
+    /// Active parameters for a subscribed stream: "auto" and unset values
+    /// resolved to what is received (`interfaces` per leg)
+    pub fn resolved(&self, stream: &Aes67Stream, interfaces: &[Ipv4Addr]) -> Self {
+        let mut active = self.clone();
+        let paths = [Some(SocketAddrV4::new(stream.multicast_addr, stream.port)), stream.secondary];
+        for (index, (leg, path)) in active.transport_params.iter_mut().zip(paths).enumerate() {
+            let Some(path) = path else {
+                leg.insert("rtp_enabled".to_string(), json!(false));
+                continue;
+            };
+            leg.insert("multicast_ip".to_string(), json!(path.ip().is_multicast().then(|| path.ip().to_string())));
+            leg.insert("destination_port".to_string(), json!(path.port()));
+            leg.insert("source_ip".to_string(), json!(stream.source_address.map(|a| a.to_string())));
+            if leg.get("interface_ip") == Some(&json!("auto")) {
+                let interface = interfaces.get(index).or(interfaces.first());
+                leg.insert("interface_ip".to_string(), json!(interface.map(|a| a.to_string())));
+            }
+        }
+        active
+    }
+
+    /// Receiving is enabled (master enable and RTP on the primary leg)
+    pub fn enabled(&self) -> bool {
+        self.master_enable && self.transport_params.first().is_none_or(rtp_enabled)
+    }
+}
+
+impl Activation {
+    /// Delay and TAI time of the activation (None = only staged)
+    pub fn schedule(&self, now: Duration) -> Result<Option<(Duration, String)>, NmosError> {
+        let requested = || {
+            self.requested_time.as_deref()
+                .and_then(parse_tai)
+                .ok_or_else(|| NmosError::bad_request("requested_time must be \"<seconds>:<nanoseconds>\""))
+        };
+        let delay = match self.mode {
+            None => return Ok(None),
+            Some(ActivationMode::Immediate) => Duration::ZERO,
+            Some(ActivationMode::ScheduledAbsolute) => requested()?.saturating_sub(now),
+            Some(ActivationMode::ScheduledRelative) => requested()?,
+        };
+        Ok(Some((delay, format_tai(now + delay))))
+    }
+}
+
+/// Constraints of a receiver leg
+pub fn receiver_constraints(legs: usize, interfaces: &[Ipv4Addr]) -> Value {
+    let interfaces: Vec<String> = interfaces.iter().map(|a| a.to_string()).collect();
+    let leg = json!({
+        "source_ip": {},
+        "multicast_ip": {},
+        "interface_ip": { "enum": interfaces },
+        "destination_port": {},
+        "rtp_enabled": {},
+    });
+    Value::Array(vec![leg; legs])
+}
+
+/// Sender parameters: enabled, with the stream's transport parameters
+pub fn sender_params(transport_params: Vec<Map<String, Value>>, activation: Activation) -> Value {
+    json!({
+        "receiver_id": null,
+        "master_enable": true,
+        "activation": activation,
+        "transport_params": transport_params,
+    })
+}
+
+/// Constraints of a sender: only its current values
+pub fn sender_constraints(transport_params: &[Map<String, Value>]) -> Value {
+    let legs = transport_params.iter().map(|leg| {
+        let constraints: Map<String, Value> = leg.iter()
+            .map(|(key, value)| (key.clone(), json!({ "enum": [value] })))
+            .collect();
+        Value::Object(constraints)
+    });
+    Value::Array(legs.collect())
+}
+
+/// Check a PATCH to a sender: its current parameters may be staged and
+/// activated again, nothing else
+pub fn check_sender_patch(patch: &Value, transport_params: &[Map<String, Value>]) -> Result<Activation, NmosError> {
+    let mut staged = sender_params(transport_params.to_vec(), Activation::default());
+    merge_staged(&mut staged, patch, &["receiver_id", "master_enable", "activation", "transport_params"], &SENDER_PARAMS)?;
+    if staged["master_enable"] != json!(true) {
+        return Err(NmosError::bad_request("Senders cannot be disabled, remove the stream via /api/aes67/tx-streams"));
+    }
+    if staged["transport_params"] != json!(transport_params) {
+        return Err(NmosError::bad_request("Sender transport parameters are fixed, change the stream via /api/aes67/tx-streams"));
+    }
+    let activation: Activation = serde_json::from_value(staged["activation"].clone())
+        .map_err(|e| NmosError::bad_request(e.to_string()))?;
+    activation.schedule(tai_now())?;
+    Ok(activation)
+}
+
+/// Merge a PATCH into staged parameters
+fn merge_staged(staged: &mut Value, patch: &Value, keys: &[&str], leg_keys: &[&str]) -> Result<(), NmosError> {
+    let patch = patch.as_object().ok_or_else(|| NmosError::bad_request("PATCH body must be an object"))?;
+    for (key, value) in patch {
+        if !keys.contains(&key.as_str()) {
+            return Err(NmosError::bad_request(format!("Unknown parameter '{}'", key)));
+        }
+        match (key.as_str(), value) {
+            ("transport_params", Value::Array(legs)) => {
+                let staged_legs = staged[key].as_array_mut().ok_or_else(|| NmosError::internal("no transport_params"))?;
+                if legs.len() != staged_legs.len() {
+                    return Err(NmosError::bad_request(format!("transport_params must have {} leg(s)", staged_legs.len())));
+                }
+                for (staged_leg, leg) in staged_legs.iter_mut().zip(legs) {
+                    let leg = leg.as_object().ok_or_else(|| NmosError::bad_request("transport_params legs must be objects"))?;
+                    if let Some(unknown) = leg.keys().find(|k| !leg_keys.contains(&k.as_str())) {
+                        return Err(NmosError::bad_request(format!("Unknown transport parameter '{}'", unknown)));
+                    }
+                    merge_object(staged_leg, leg);
+                }
+            }
+            ("activation" | "transport_file", Value::Object(object)) => merge_object(&mut staged[key], object),
+            _ => staged[key] = value.clone(),
+        }
+    }
+    Ok(())
+}
+
+fn merge_object(target: &mut Value, patch: &Map<String, Value>) {
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+fn check_ip(leg: &Map<String, Value>, key: &str, required: bool) -> Result<Option<Ipv4Addr>, NmosError> {
+    match leg.get(key) {
+        None | Some(Value::Null) if !required => Ok(None),
+        Some(Value::String(address)) => address.parse().map(Some)
+            .map_err(|_| NmosError::bad_request(format!("{} '{}' is not an IPv4 address", key, address))),
+        _ => Err(NmosError::bad_request(format!("{} must be an IPv4 address", key))),
+    }
+}
+
+fn check_port(leg: &Map<String, Value>, key: &str) -> Result<(), NmosError> {
+    match leg.get(key) {
+        None => Ok(()),
+        Some(Value::String(auto)) if auto == "auto" => Ok(()),
+        Some(Value::Number(port)) if port.as_u64().is_some_and(|p| (1..=65535).contains(&p)) => Ok(()),
+        _ => Err(NmosError::bad_request(format!("{} must be \"auto\" or a port", key))),
+    }
+}
+
+fn check_bool(leg: &Map<String, Value>, key: &str) -> Result<(), NmosError> {
+    match leg.get(key) {
+        None | Some(Value::Bool(_)) => Ok(()),
+        _ => Err(NmosError::bad_request(format!("{} must be true or false", key))),
+    }
+}
+
+fn ip_param(leg: &Map<String, Value>, key: &str) -> Option<Ipv4Addr> {
+    leg.get(key)?.as_str()?.parse().ok()
+}
+
+fn port_param(leg: &Map<String, Value>, key: &str) -> Option<u16> {
+    leg.get(key)?.as_u64().and_then(|port| u16::try_from(port).ok())
+}
+
+fn rtp_enabled(leg: &Map<String, Value>) -> bool {
+    leg.get("rtp_enabled").and_then(Value::as_bool).unwrap_or(true)
+}
+
+/// Current TAI time since the epoch (system clock plus leap seconds)
+pub fn tai_now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default() + TAI_OFFSET
+}
+
+/// "<seconds>:<nanoseconds>" as used by IS-04 versions and IS-05 activations
+pub fn format_tai(time: Duration) -> String {
+    format!("{}:{}", time.as_secs(), time.subsec_nanos())
+}
+
+pub fn parse_tai(text: &str) -> Option<Duration> {
+    let (seconds, nanos) = text.split_once(':')?;
+    let nanos: u32 = nanos.parse().ok()?;
+    if nanos >= 1_000_000_000 {
+        return None;
+    }
+    Some(Duration::new(seconds.parse().ok()?, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERFACE: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+
+    #[test]
+    fn test_receiver_patch() {
+        let params = ReceiverParams::new(2);
+
+        // Staging only, legs merged one by one
+        let patch = json!({
+            "sender_id": "0b9f4e0e-7a6c-4a0a-9d11-5bd39e12f8e1",
+            "master_enable": true,
+            "transport_file": { "data": "v=0", "type": "application/sdp" },
+            "transport_params": [{ "multicast_ip": "239.69.1.1", "destination_port": 5004 }, {}],
+        });
+        let staged = params.patched(&patch, &[INTERFACE]).unwrap();
+        assert!(staged.enabled());
+        assert_eq!(staged.transport_params[0]["multicast_ip"], json!("239.69.1.1"));
+        assert_eq!(staged.transport_params[0]["interface_ip"], json!("auto"));
+        assert_eq!(staged.transport_params[1]["multicast_ip"], Value::Null);
+        assert_eq!(staged.activation.schedule(Duration::ZERO).unwrap(), None);
+
+        let now = Duration::from_secs(1000);
+        let relative = json!({ "activation": { "mode": "activate_scheduled_relative", "requested_time": "2:500000000" } });
+        let (delay, at) = staged.patched(&relative, &[INTERFACE]).unwrap().activation.schedule(now).unwrap().unwrap();
+        assert_eq!((delay, at.as_str()), (Duration::from_millis(2500), "1002:500000000"));
+        let absolute = json!({ "activation": { "mode": "activate_scheduled_absolute", "requested_time": "999:0" } });
+        let (delay, _) = staged.patched(&absolute, &[INTERFACE]).unwrap().activation.schedule(now).unwrap().unwrap();
+        assert_eq!(delay, Duration::ZERO);
+
+        for bad in [
+            json!({ "transport_params": [{ "multicast_ip": "239.69.1.1" }] }),
+            json!({ "transport_params": [{ "destination_ip": "239.69.1.1" }, {}] }),
+            json!({ "transport_params": [{ "destination_port": 0 }, {}] }),
+            json!({ "transport_params": [{ "interface_ip": "10.0.0.1" }, {}] }),
+            json!({ "activation": { "mode": "activate_scheduled_relative", "requested_time": "soon" } }),
+            json!({ "master_enable": "yes" }),
+            json!({ "enabled": true }),
+        ] {
+            assert_eq!(params.patched(&bad, &[INTERFACE]).unwrap_err().code, 400, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_transport_params_on_stream() {
+        let mut stream = Aes67Stream::from_sdp(concat!(
+            "v=0\r\no=- 1 1 IN IP4 192.168.1.20\r\ns=Stage box\r\nc=IN IP4 239.69.1.1/32\r\nt=0 0\r\n",
+            "m=audio 5004 RTP/AVP 98\r\na=rtpmap:98 L24/48000/2\r\na=ptime:1\r\n",
+        )).unwrap();
+        let params = ReceiverParams::new(1)
+            .patched(&json!({ "transport_params": [{ "multicast_ip": "239.69.2.2", "source_ip": "192.168.1.20" }] }), &[INTERFACE])
+            .unwrap();
+        params.apply_to(&mut stream);
+        assert_eq!((stream.multicast_addr, stream.port), (Ipv4Addr::new(239, 69, 2, 2), 5004));
+        assert_eq!(stream.source_address, Some(Ipv4Addr::new(192, 168, 1, 20)));
+
+        let active = params.resolved(&stream, &[INTERFACE]);
+        assert_eq!(active.transport_params[0]["destination_port"], json!(5004));
+        assert_eq!(active.transport_params[0]["interface_ip"], json!("192.168.1.10"));
+    }
+
+    #[test]
+    fn test_sender_patch() {
+        let leg = json!({ "destination_ip": "239.69.1.100", "destination_port": 5004, "rtp_enabled": true });
+        let legs = vec![leg.as_object().unwrap().clone()];
+        assert_eq!(sender_constraints(&legs)[0]["destination_ip"], json!({ "enum": ["239.69.1.100"] }));
+
+        let same = json!({ "transport_params": [{ "destination_port": 5004 }], "activation": { "mode": "activate_immediate" } });
+        assert_eq!(check_sender_patch(&same, &legs).unwrap().mode, Some(ActivationMode::Immediate));
+        assert!(check_sender_patch(&json!({ "transport_params": [{ "destination_port": 5006 }] }), &legs).is_err());
+        assert!(check_sender_patch(&json!({ "master_enable": false }), &legs).is_err());
+    }
+
+    #[test]
+    fn test_tai_time() {
+        assert_eq!(parse_tai("1700000000:5"), Some(Duration::new(1_700_000_000, 5)));
+        assert_eq!(parse_tai("1:1000000000"), None);
+        assert_eq!(format_tai(Duration::new(12, 34)), "12:34");
+    }
+}
@@ -0,0 +1,145 @@
+//! Mock NMOS registry for the registration tests
+//!
+//! Implements the parts of the IS-04 Registration API a node uses:
+//! registering resources, deleting them and heartbeats. An axum server on
+//! its own runtime; resources are kept in memory, `forget` drops them like
+//! a restarting registry.
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, post},
+    Json, Router,
+};
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use tokio::runtime::Runtime;
+
+use super::{NODE_API_VERSION, ResourceType};
+
+#[derive(Default)]
+struct RegistryState {
+    /// Resources by type name and id
+    resources: HashMap<String, HashMap<String, Value>>,
+    /// Registrations in order (type name, id)
+    posted: Vec<(String, String)>,
+    heartbeats: usize,
+    /// Answer every heartbeat with 404
+    reject_heartbeats: bool,
+}
+
+type SharedState = Arc<Mutex<RegistryState>>;
+
+/// Registry on a loopback port
+pub struct MockRegistry {
+    port: u16,
+    state: SharedState,
+    runtime: Option<Runtime>,
+}
+
+impl MockRegistry {
+    pub fn start() -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("nmos-mock-registry")
+            .enable_all()
+            .build()?;
+        let listener = runtime.block_on(tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)))?;
+        let port = listener.local_addr()?.port();
+        let state = SharedState::default();
+
+        let base = format!("/x-nmos/registration/{}", NODE_API_VERSION);
+        let app = Router::new()
+            .route(&format!("{}/resource", base), post(register))
+            .route(&format!("{}/resource/:resources/:id", base), delete(unregister))
+            .route(&format!("{}/health/nodes/:id", base), post(heartbeat))
+            .with_state(state.clone());
+        runtime.spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Ok(Self { port, state, runtime: Some(runtime) })
+    }
+
+    /// Base URL for the node's config
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// Registered resources of a type ("node", "receiver", ...)
+    pub fn resources(&self, kind: &str) -> Vec<Value> {
+        self.state.lock().resources.get(kind)
+            .map(|resources| resources.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// All registrations in order (type name, id)
+    pub fn posted(&self) -> Vec<(String, String)> {
+        self.state.lock().posted.clone()
+    }
+
+    pub fn heartbeats(&self) -> usize {
+        self.state.lock().heartbeats
+    }
+
+    /// Drop all resources, like a registry that restarted
+    pub fn forget(&self) {
+        self.state.lock().resources.clear();
+    }
+
+    /// Answer heartbeats with 404 even for registered nodes
+    pub fn reject_heartbeats(&self, reject: bool) {
+        self.state.lock().reject_heartbeats = reject;
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Drop for MockRegistry {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn error(code: u16, error: &str) -> (StatusCode, Json<Value>) {
+    let status = StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, Json(json!({ "code": code, "error": error, "debug": null })))
+}
+
+async fn register(State(state): State<SharedState>, Json(body): Json<Value>) -> (StatusCode, Json<Value>) {
+    let kind = body["type"].as_str().unwrap_or_default().to_string();
+    let id = body["data"]["id"].as_str().unwrap_or_default().to_string();
+    let mut state = state.lock();
+    state.posted.push((kind.clone(), id.clone()));
+    let existed = state.resources.entry(kind).or_default().insert(id, body["data"].clone()).is_some();
+    let status = if existed { StatusCode::OK } else { StatusCode::CREATED };
+    (status, Json(body["data"].clone()))
+}
+
+async fn unregister(
+    State(state): State<SharedState>,
+    Path((resources, id)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+    let kind = ResourceType::from_plural(&resources).map(ResourceType::name).unwrap_or_default();
+    match state.lock().resources.get_mut(kind).and_then(|resources| resources.remove(&id)) {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(error(404, "not found")),
+    }
+}
+
+async fn heartbeat(State(state): State<SharedState>, Path(id): Path<String>) -> (StatusCode, Json<Value>) {
+    let mut state = state.lock();
+    state.heartbeats += 1;
+    if !state.reject_heartbeats && state.resources.get("node").is_some_and(|nodes| nodes.contains_key(&id)) {
+        (StatusCode::OK, Json(json!({ "health": "0" })))
+    } else {
+        error(404, "node not registered")
+    }
+}
@@ -0,0 +1,834 @@
+//! NMOS node (AMWA IS-04/IS-05)
+//!
+//! Our outgoing AES67 streams are offered as NMOS senders (each with its
+//! source and flow), a fixed set of NMOS receivers patches incoming streams
+//! onto consecutive mixer inputs. The node registers its resources with a
+//! registry (IS-04) and lets controllers connect receivers to senders by
+//! their SDP transport files (IS-05).
+//!
+//! ## Modules
+//! - `connection` - IS-05 staged/active parameters and activations
+//! - `registration` - Registry discovery (DNS-SD), registration and heartbeats
+//! - `mock_registry` - Minimal registry for the registration tests (tests only)
+
+pub mod connection;
+pub mod registration;
+
+#[cfg(test)]
+pub mod mock_registry;
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use tracing::{debug, info, warn};
+
+use super::{Aes67Backend, Aes67Stream, Aes67TxStreams, NetworkBackends, PtpClock, SapDiscovery, TxStreamInfo};
+use super::rtp::RtpEncoding;
+use connection::{Activation, ActivationMode, ReceiverParams, format_tai, tai_now};
+
+pub use registration::Registration;
+
+/// IS-04 Node API and Registration API version
+pub const NODE_API_VERSION: &str = "v1.3";
+
+/// IS-05 Connection API version
+pub const CONNECTION_API_VERSION: &str = "v1.1";
+
+/// Node settings
+#[derive(Debug, Clone)]
+pub struct NodeConfig {
+    pub label: String,
+    /// Address and port controllers and the registry reach our API on
+    pub address: Ipv4Addr,
+    pub port: u16,
+    /// Network interfaces (name, address), the secondary network second
+    pub interfaces: Vec<(String, Ipv4Addr)>,
+    /// Network legs of senders and receivers (2 with ST 2022-7)
+    pub legs: usize,
+    /// Number of receivers
+    pub receivers: usize,
+    /// Channels per receiver
+    pub receiver_channels: usize,
+    /// Mixer input of the first receiver's first channel (0-based)
+    pub first_input: usize,
+    /// Registry base URL ("http://host:port", None = DNS-SD)
+    pub registry: Option<String>,
+    pub heartbeat_interval: Duration,
+}
+
+/// IS-04 resource types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceType {
+    Node,
+    Device,
+    Source,
+    Flow,
+    Sender,
+    Receiver,
+}
+
+impl ResourceType {
+    /// In registration order, resources after those they refer to
+    pub const ALL: [ResourceType; 6] = [
+        ResourceType::Node, ResourceType::Device, ResourceType::Source,
+        ResourceType::Flow, ResourceType::Sender, ResourceType::Receiver,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResourceType::Node => "node",
+            ResourceType::Device => "device",
+            ResourceType::Source => "source",
+            ResourceType::Flow => "flow",
+            ResourceType::Sender => "sender",
+            ResourceType::Receiver => "receiver",
+        }
+    }
+
+    /// Path segment in the Node and Registration APIs
+    pub fn plural(self) -> &'static str {
+        match self {
+            ResourceType::Node => "nodes",
+            ResourceType::Device => "devices",
+            ResourceType::Source => "sources",
+            ResourceType::Flow => "flows",
+            ResourceType::Sender => "senders",
+            ResourceType::Receiver => "receivers",
+        }
+    }
+
+    pub fn from_plural(plural: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.plural() == plural)
+    }
+}
+
+/// A resource with its current version
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    pub kind: ResourceType,
+    pub id: String,
+    pub data: Value,
+}
+
+impl Resource {
+    pub fn version(&self) -> &str {
+        self.data["version"].as_str().unwrap_or_default()
+    }
+}
+
+/// Error of the NMOS APIs, answered as `{code, error, debug}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NmosError {
+    /// HTTP status
+    pub code: u16,
+    pub error: String,
+}
+
+impl NmosError {
+    pub fn bad_request(error: impl Into<String>) -> Self {
+        Self { code: 400, error: error.into() }
+    }
+
+    pub fn not_found(error: impl Into<String>) -> Self {
+        Self { code: 404, error: error.into() }
+    }
+
+    /// Staged parameters are locked by a scheduled activation
+    pub fn locked(error: impl Into<String>) -> Self {
+        Self { code: 423, error: error.into() }
+    }
+
+    pub fn internal(error: impl Into<String>) -> Self {
+        Self { code: 500, error: error.into() }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({ "code": self.code, "error": self.error, "debug": null })
+    }
+}
+
+/// AES67 parts the node describes
+struct Aes67Handles {
+    tx_streams: Arc<Aes67TxStreams>,
+    sap: Arc<SapDiscovery>,
+    ptp: Arc<PtpClock>,
+}
+
+/// A receiver patching one stream onto consecutive mixer inputs
+struct NmosReceiver {
+    id: String,
+    label: String,
+    /// Mixer input of the first channel (0-based)
+    first_input: usize,
+    channels: usize,
+    state: Mutex<ReceiverState>,
+}
+
+struct ReceiverState {
+    staged: ReceiverParams,
+    active: ReceiverParams,
+    /// Session id of the subscribed stream
+    stream_id: Option<String>,
+    /// Counts stagings; a scheduled activation only runs if nothing was staged since
+    generation: u64,
+}
+
+impl NmosReceiver {
+    /// Patch of a stream's channels, channels beyond the receiver's are dropped
+    fn patch(&self, stream_channels: u8) -> Vec<Option<usize>> {
+        (0..stream_channels as usize)
+            .map(|channel| (channel < self.channels).then_some(self.first_input + channel))
+            .collect()
+    }
+}
+
+/// One of our outgoing streams as NMOS sender
+struct SenderEntry {
+    info: TxStreamInfo,
+    sender_id: String,
+    flow_id: String,
+    source_id: String,
+}
+
+/// Last version of every resource, bumped when its content changes
+#[derive(Default)]
+struct Versions {
+    last: Duration,
+    resources: HashMap<String, (Value, String)>,
+}
+
+impl Versions {
+    fn stamp(&mut self, id: &str, data: &Value) -> String {
+        if let Some((known, version)) = self.resources.get(id) {
+            if known == data {
+                return version.clone();
+            }
+        }
+        // Strictly increasing, also for changes within one clock tick
+        let now = tai_now().max(self.last + Duration::from_nanos(1));
+        self.last = now;
+        let version = format_tai(now);
+        self.resources.insert(id.to_string(), (data.clone(), version.clone()));
+        version
+    }
+}
+
+/// The node: resources for IS-04, connection management for IS-05
+pub struct NmosNode {
+    config: NodeConfig,
+    hostname: String,
+    /// Seed of the stable resource IDs
+    seed: String,
+    node_id: String,
+    device_id: String,
+    backends: NetworkBackends,
+    aes67: Option<Aes67Handles>,
+    receivers: Vec<NmosReceiver>,
+    versions: Mutex<Versions>,
+}
+
+impl NmosNode {
+    /// Node for the running backends; senders and receivers need AES67
+    ///
+    /// Resource IDs derive from host name and API port, so they stay the
+    /// same across restarts.
+    pub fn new(config: NodeConfig, backends: NetworkBackends) -> Self {
+        let hostname = hostname::get()
+            .map(|h| h.to_string_lossy().to_string())
+            .unwrap_or_else(|_| "audiomultiverse".to_string());
+        let seed = format!("{}:{}", hostname, config.port);
+        let aes67 = backends.with(|backend: &mut Aes67Backend| Aes67Handles {
+            tx_streams: backend.tx_streams(),
+            sap: backend.sap_discovery(),
+            ptp: backend.ptp_clock(),
+        });
+        let receivers = (0..config.receivers).map(|index| {
+            let first_input = config.first_input + index * config.receiver_channels;
+            NmosReceiver {
+                id: stable_id(&seed, &format!("receiver:{}", index)),
+                label: format!("Inputs {}-{}", first_input + 1, first_input + config.receiver_channels),
+                first_input,
+                channels: config.receiver_channels,
+                state: Mutex::new(ReceiverState {
+                    staged: ReceiverParams::new(config.legs),
+                    active: ReceiverParams::new(config.legs),
+                    stream_id: None,
+                    generation: 0,
+                }),
+            }
+        }).collect();
+
+        Self {
+            node_id: stable_id(&seed, "node"),
+            device_id: stable_id(&seed, "device"),
+            hostname,
+            seed,
+            config,
+            backends,
+            aes67,
+            receivers,
+            versions: Mutex::new(Versions::default()),
+        }
+    }
+
+    pub fn config(&self) -> &NodeConfig {
+        &self.config
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// All resources in registration order, with current versions
+    pub fn resources(&self) -> Vec<Resource> {
+        let senders = self.senders();
+        let mut resources = vec![
+            (ResourceType::Node, self.node_resource()),
+            (ResourceType::Device, self.device_resource(&senders)),
+        ];
+        for sender in &senders {
+            resources.push((ResourceType::Source, self.source_resource(sender)));
+        }
+        for sender in &senders {
+            resources.push((ResourceType::Flow, self.flow_resource(sender)));
+        }
+        for sender in &senders {
+            resources.push((ResourceType::Sender, self.sender_resource(sender)));
+        }
+        for receiver in &self.receivers {
+            resources.push((ResourceType::Receiver, self.receiver_resource(receiver)));
+        }
+
+        let mut versions = self.versions.lock();
+        resources.into_iter().map(|(kind, mut data)| {
+            let id = data["id"].as_str().unwrap_or_default().to_string();
+            let version = versions.stamp(&id, &data);
+            data["version"] = json!(version);
+            Resource { kind, id, data }
+        }).collect()
+    }
+
+    /// Resources of one type (Node API lists)
+    pub fn resources_of(&self, kind: ResourceType) -> Vec<Value> {
+        self.resources().into_iter().filter(|r| r.kind == kind).map(|r| r.data).collect()
+    }
+
+    /// One resource (Node API)
+    pub fn resource(&self, kind: ResourceType, id: &str) -> Option<Value> {
+        self.resources().into_iter().find(|r| r.kind == kind && r.id == id).map(|r| r.data)
+    }
+
+    fn node_resource(&self) -> Value {
+        let address = self.config.address.to_string();
+        let interfaces: Vec<Value> = self.config.interfaces.iter().map(|(name, _)| json!({
+            "name": name,
+            "chassis_id": null,
+            "port_id": self.port_id(name),
+        })).collect();
+        json!({
+            "id": self.node_id,
+            "label": self.config.label,
+            "description": format!("AudioMultiverse on {}", self.hostname),
+            "tags": {},
+            "href": format!("http://{}:{}/", address, self.config.port),
+            "hostname": self.hostname,
+            "api": {
+                "versions": [NODE_API_VERSION],
+                "endpoints": [{ "host": address, "port": self.config.port, "protocol": "http" }],
+            },
+            "caps": {},
+            "services": [],
+            "clocks": [self.clock()],
+            "interfaces": interfaces,
+        })
+    }
+
+    /// clk0: the PTP grandmaster we follow, the internal clock without one
+    fn clock(&self) -> Value {
+        let Some(aes67) = &self.aes67 else {
+            return json!({ "name": "clk0", "ref_type": "internal" });
+        };
+        let stats = aes67.ptp.stats();
+        if stats.grandmaster_identity.is_empty() {
+            return json!({ "name": "clk0", "ref_type": "internal" });
+        }
+        json!({
+            "name": "clk0",
+            "ref_type": "ptp",
+            "traceable": false,
+            "version": "IEEE1588-2008",
+            "gmid": stats.grandmaster_identity.to_lowercase(),
+            "locked": aes67.ptp.is_synchronized(),
+        })
+    }
+
+    /// MAC address of an interface as IS-04 port id ("xx-xx-xx-xx-xx-xx")
+    fn port_id(&self, interface: &str) -> String {
+        std::fs::read_to_string(format!("/sys/class/net/{}/address", interface))
+            .ok()
+            .map(|mac| mac.trim().replace(':', "-").to_lowercase())
+            .filter(|mac| mac.len() == 17)
+            .unwrap_or_else(|| {
+                // Locally administered address from the interface name
+                let id = stable_id(&self.seed, interface).replace('-', "");
+                format!("02-{}-{}-{}-{}-{}", &id[0..2], &id[2..4], &id[4..6], &id[6..8], &id[8..10])
+            })
+    }
+
+    fn device_resource(&self, senders: &[SenderEntry]) -> Value {
+        let connection = format!(
+            "http://{}:{}/x-nmos/connection/{}/",
+            self.config.address, self.config.port, CONNECTION_API_VERSION
+        );
+        json!({
+            "id": self.device_id,
+            "label": self.config.label,
+            "description": "Mixer inputs and outputs as AES67 streams",
+            "tags": {},
+            "type": "urn:x-nmos:device:generic",
+            "node_id": self.node_id,
+            "senders": senders.iter().map(|s| s.sender_id.clone()).collect::<Vec<_>>(),
+            "receivers": self.receivers.iter().map(|r| r.id.clone()).collect::<Vec<_>>(),
+            "controls": [{ "href": connection, "type": format!("urn:x-nmos:control:sr-ctrl/{}", CONNECTION_API_VERSION) }],
+        })
+    }
+
+    fn source_resource(&self, sender: &SenderEntry) -> Value {
+        let channels: Vec<Value> = sender.info.config.outputs.iter()
+            .map(|output| json!({ "label": format!("Output {}", output + 1) }))
+            .collect();
+        json!({
+            "id": sender.source_id,
+            "label": sender.info.config.name,
+            "description": "Mixer outputs",
+            "tags": {},
+            "format": "urn:x-nmos:format:audio",
+            "caps": {},
+            "device_id": self.device_id,
+            "parents": [],
+            "clock_name": "clk0",
+            "channels": channels,
+        })
+    }
+
+    fn flow_resource(&self, sender: &SenderEntry) -> Value {
+        let encoding = sender.info.config.encoding;
+        let mut flow = json!({
+            "id": sender.flow_id,
+            "label": sender.info.config.name,
+            "description": "Mixer outputs",
+            "tags": {},
+            "format": "urn:x-nmos:format:audio",
+            "source_id": sender.source_id,
+            "device_id": self.device_id,
+            "parents": [],
+            "media_type": format!("audio/{}", encoding.name()),
+            "sample_rate": { "numerator": sender.info.sample_rate },
+        });
+        match encoding {
+            RtpEncoding::L16 => flow["bit_depth"] = json!(16),
+            RtpEncoding::L24 => flow["bit_depth"] = json!(24),
+            RtpEncoding::Am824 => {}
+        }
+        flow
+    }
+
+    fn sender_resource(&self, sender: &SenderEntry) -> Value {
+        let transport = if sender.info.config.destination.is_multicast() { "rtp.mcast" } else { "rtp.ucast" };
+        json!({
+            "id": sender.sender_id,
+            "label": sender.info.config.name,
+            "description": format!("AES67 stream to {}:{}", sender.info.config.destination, sender.info.config.port),
+            "tags": {},
+            "flow_id": sender.flow_id,
+            "transport": format!("urn:x-nmos:transport:{}", transport),
+            "device_id": self.device_id,
+            "manifest_href": format!(
+                "http://{}:{}/x-nmos/connection/{}/single/senders/{}/transportfile",
+                self.config.address, self.config.port, CONNECTION_API_VERSION, sender.sender_id
+            ),
+            "interface_bindings": self.interface_bindings(),
+            "subscription": { "receiver_id": null, "active": true },
+        })
+    }
+
+    fn receiver_resource(&self, receiver: &NmosReceiver) -> Value {
+        let state = receiver.state.lock();
+        let active = state.active.master_enable;
+        json!({
+            "id": receiver.id,
+            "label": receiver.label,
+            "description": format!("{} channels onto mixer inputs {}-{}", receiver.channels, receiver.first_input + 1, receiver.first_input + receiver.channels),
+            "tags": {},
+            "format": "urn:x-nmos:format:audio",
+            "caps": { "media_types": ["audio/L24", "audio/L16", "audio/AM824"] },
+            "device_id": self.device_id,
+            "transport": connection::TRANSPORT_RTP,
+            "interface_bindings": self.interface_bindings(),
+            "subscription": {
+                "sender_id": if active { state.active.sender_id.clone() } else { None },
+                "active": active,
+            },
+        })
+    }
+
+    /// Interfaces of the legs
+    fn interface_bindings(&self) -> Vec<String> {
+        self.config.interfaces.iter().take(self.config.legs).map(|(name, _)| name.clone()).collect()
+    }
+
+    fn interface_addresses(&self) -> Vec<Ipv4Addr> {
+        self.config.interfaces.iter().map(|(_, address)| *address).collect()
+    }
+
+    fn senders(&self) -> Vec<SenderEntry> {
+        let Some(aes67) = &self.aes67 else {
+            return Vec::new();
+        };
+        aes67.tx_streams.infos().into_iter().map(|info| {
+            // Destinations are unique, and unlike the stream id survive restarts
+            let key = format!("{}:{}", info.config.destination, info.config.port);
+            SenderEntry {
+                sender_id: stable_id(&self.seed, &format!("sender:{}", key)),
+                flow_id: stable_id(&self.seed, &format!("flow:{}", key)),
+                source_id: stable_id(&self.seed, &format!("source:{}", key)),
+                info,
+            }
+        }).collect()
+    }
+
+    fn sender(&self, id: &str) -> Result<SenderEntry, NmosError> {
+        self.senders().into_iter().find(|s| s.sender_id == id)
+            .ok_or_else(|| NmosError::not_found(format!("Sender '{}' not found", id)))
+    }
+
+    fn receiver(&self, id: &str) -> Result<&NmosReceiver, NmosError> {
+        self.receivers.iter().find(|r| r.id == id)
+            .ok_or_else(|| NmosError::not_found(format!("Receiver '{}' not found", id)))
+    }
+
+    // === IS-05 receivers ===
+
+    pub fn receiver_ids(&self) -> Vec<String> {
+        self.receivers.iter().map(|r| r.id.clone()).collect()
+    }
+
+    pub fn receiver_constraints(&self, id: &str) -> Result<Value, NmosError> {
+        self.receiver(id)?;
+        Ok(connection::receiver_constraints(self.config.legs, &self.interface_addresses()))
+    }
+
+    pub fn receiver_staged(&self, id: &str) -> Result<Value, NmosError> {
+        Ok(json!(self.receiver(id)?.state.lock().staged))
+    }
+
+    pub fn receiver_active(&self, id: &str) -> Result<Value, NmosError> {
+        Ok(json!(self.receiver(id)?.state.lock().active))
+    }
+
+    /// Stage parameters, activating them now or at the requested time
+    ///
+    /// Returns the HTTP status (200, 202 for a scheduled activation) and
+    /// the staged parameters. While an activation is scheduled only its
+    /// cancellation (mode null) is accepted.
+    pub fn patch_receiver(self: &Arc<Self>, id: &str, patch: &Value) -> Result<(u16, Value), NmosError> {
+        let receiver = self.receiver(id)?;
+        let mut state = receiver.state.lock();
+        let scheduled = matches!(
+            state.staged.activation.mode,
+            Some(ActivationMode::ScheduledAbsolute | ActivationMode::ScheduledRelative)
+        );
+        let cancels = patch.pointer("/activation/mode") == Some(&Value::Null);
+        if scheduled && !cancels {
+            return Err(NmosError::locked("An activation is scheduled, cancel it first"));
+        }
+
+        let mut staged = state.staged.patched(patch, &self.interface_addresses())?;
+        state.generation += 1;
+        let Some((delay, activation_time)) = staged.activation.schedule(tai_now())? else {
+            staged.activation = Activation::default();
+            state.staged = staged;
+            return Ok((200, json!(state.staged)));
+        };
+        staged.activation.activation_time = Some(activation_time.clone());
+        state.staged = staged;
+
+        if state.staged.activation.mode == Some(ActivationMode::Immediate) {
+            let response = json!(state.staged);
+            let activated = self.activate(receiver, &mut state, activation_time);
+            state.staged.activation = Activation::default();
+            activated?;
+            return Ok((200, response));
+        }
+
+        let generation = state.generation;
+        let node = self.clone();
+        let id = id.to_string();
+        std::thread::Builder::new()
+            .name("nmos-activation".to_string())
+            .spawn(move || {
+                std::thread::sleep(delay);
+                node.run_scheduled(&id, generation, activation_time);
+            })
+            .map_err(|e| NmosError::internal(e.to_string()))?;
+        debug!("NMOS receiver {} activates in {:?}", receiver.label, delay);
+        Ok((202, json!(state.staged)))
+    }
+
+    /// Scheduled activation, unless staged parameters changed meanwhile
+    fn run_scheduled(&self, id: &str, generation: u64, activation_time: String) {
+        let Ok(receiver) = self.receiver(id) else {
+            return;
+        };
+        let mut state = receiver.state.lock();
+        if state.generation != generation {
+            return;
+        }
+        let activated = self.activate(receiver, &mut state, activation_time);
+        state.staged.activation = Activation::default();
+        if let Err(e) = activated {
+            warn!("Scheduled NMOS activation of {} failed: {}", receiver.label, e.error);
+        }
+    }
+
+    /// Make the staged parameters active: subscribe to the stream of the
+    /// transport file, replacing the receiver's previous stream
+    ///
+    /// The subscription is not saved, receivers start inactive after a restart.
+    fn activate(&self, receiver: &NmosReceiver, state: &mut ReceiverState, activation_time: String) -> Result<(), NmosError> {
+        let mut staged = state.staged.clone();
+        staged.activation.activation_time = Some(activation_time);
+        let stream = if staged.enabled() { Some(stream_for(&staged)?) } else { None };
+
+        let previous = state.stream_id.take();
+        let subscribed = self.backends.with(|backend: &mut Aes67Backend| {
+            if let Some(previous) = &previous {
+                if let Err(e) = backend.unsubscribe(previous) {
+                    debug!("NMOS receiver stream {} was already unsubscribed: {}", previous, e);
+                }
+            }
+            match &stream {
+                Some(stream) => backend.subscribe_unsaved(stream, receiver.patch(stream.channels)),
+                None => Ok(()),
+            }
+        }).ok_or_else(|| NmosError::internal("AES67 is not running"))?;
+
+        if let Err(e) = subscribed {
+            // The previous stream is gone either way
+            state.active.master_enable = false;
+            state.active.activation = staged.activation;
+            return Err(NmosError::internal(format!("Subscribing failed: {}", e)));
+        }
+        match &stream {
+            Some(stream) => {
+                info!("🎛️ NMOS receiver {} connected to '{}'", receiver.label, stream.name);
+                state.stream_id = Some(stream.session_id.clone());
+                state.active = staged.resolved(stream, &self.interface_addresses());
+            }
+            None => {
+                if previous.is_some() {
+                    info!("🎛️ NMOS receiver {} disconnected", receiver.label);
+                }
+                state.active = staged;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bulk PATCH of receivers: `[{id, params}]`, one result per entry
+    pub fn bulk_receivers(self: &Arc<Self>, body: &Value) -> Result<Value, NmosError> {
+        bulk(body, |id, params| self.patch_receiver(id, params).map(|(code, _)| code))
+    }
+
+    // === IS-05 senders (read-only, configured via the AES67 API) ===
+
+    pub fn sender_ids(&self) -> Vec<String> {
+        self.senders().into_iter().map(|s| s.sender_id).collect()
+    }
+
+    pub fn sender_constraints(&self, id: &str) -> Result<Value, NmosError> {
+        Ok(connection::sender_constraints(&self.sender_legs(&self.sender(id)?)))
+    }
+
+    pub fn sender_staged(&self, id: &str) -> Result<Value, NmosError> {
+        Ok(connection::sender_params(self.sender_legs(&self.sender(id)?), Activation::default()))
+    }
+
+    pub fn sender_active(&self, id: &str) -> Result<Value, NmosError> {
+        self.sender_staged(id)
+    }
+
+    /// Only the current parameters are accepted, activating them changes nothing
+    pub fn patch_sender(&self, id: &str, patch: &Value) -> Result<(u16, Value), NmosError> {
+        let legs = self.sender_legs(&self.sender(id)?);
+        let mut activation = connection::check_sender_patch(patch, &legs)?;
+        let code = match activation.schedule(tai_now())? {
+            Some((_, activation_time)) => {
+                activation.activation_time = Some(activation_time);
+                if activation.mode == Some(ActivationMode::Immediate) { 200 } else { 202 }
+            }
+            None => 200,
+        };
+        Ok((code, connection::sender_params(legs, activation)))
+    }
+
+    pub fn bulk_senders(&self, body: &Value) -> Result<Value, NmosError> {
+        bulk(body, |id, params| self.patch_sender(id, params).map(|(code, _)| code))
+    }
+
+    /// SDP of a sender, as announced via SAP
+    pub fn sender_transport_file(&self, id: &str) -> Result<String, NmosError> {
+        let sender = self.sender(id)?;
+        self.aes67.as_ref()
+            .and_then(|aes67| aes67.sap.announced_sdp(&sender.info.id))
+            .ok_or_else(|| NmosError::not_found(format!("Sender '{}' has no transport file", id)))
+    }
+
+    fn sender_legs(&self, sender: &SenderEntry) -> Vec<serde_json::Map<String, Value>> {
+        let config = &sender.info.config;
+        let secondary = sender.info.paths.len() > 1;
+        (0..self.config.legs).map(|leg| {
+            let source = self.config.interfaces.get(leg).map(|(_, address)| address.to_string());
+            let destination = match leg {
+                0 => config.destination,
+                _ => config.secondary_destination.unwrap_or(config.destination),
+            };
+            let params = json!({
+                "source_ip": source,
+                "destination_ip": destination.to_string(),
+                "source_port": "auto",
+                "destination_port": config.port,
+                "rtp_enabled": leg == 0 || secondary,
+            });
+            params.as_object().cloned().unwrap_or_default()
+        }).collect()
+    }
+}
+
+/// Stream of a receiver's transport file with its transport parameters applied
+fn stream_for(params: &ReceiverParams) -> Result<Aes67Stream, NmosError> {
+    let sdp = params.transport_file.data.as_deref()
+        .ok_or_else(|| NmosError::bad_request("The receiver needs a transport file to be enabled"))?;
+    let mut stream = Aes67Stream::from_sdp(sdp)
+        .map_err(|e| NmosError::bad_request(format!("Invalid transport file: {}", e)))?;
+    params.apply_to(&mut stream);
+    Ok(stream)
+}
+
+/// Run a bulk request entry by entry
+fn bulk(body: &Value, mut patch: impl FnMut(&str, &Value) -> Result<u16, NmosError>) -> Result<Value, NmosError> {
+    let entries = body.as_array().ok_or_else(|| NmosError::bad_request("Bulk requests are arrays of {id, params}"))?;
+    let results = entries.iter().map(|entry| {
+        let id = entry["id"].as_str().unwrap_or_default();
+        match patch(id, &entry["params"]) {
+            Ok(code) => json!({ "id": id, "code": code }),
+            Err(e) => json!({ "id": id, "code": e.code, "error": e.error, "debug": null }),
+        }
+    });
+    Ok(Value::Array(results.collect()))
+}
+
+/// Stable resource ID: FNV-1a hashes of seed and name as UUID (version 8)
+pub fn stable_id(seed: &str, name: &str) -> String {
+    let fnv = |basis: u64| format!("{}/{}", seed, name).bytes()
+        .fold(basis, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3));
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&fnv(0xcbf2_9ce4_8422_2325).to_be_bytes());
+    bytes[8..].copy_from_slice(&fnv(0x8422_2325_cbf2_9ce4).to_be_bytes());
+    uuid::Builder::from_custom_bytes(bytes).into_uuid().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network_audio::{Aes67Config, TxStreamConfig};
+
+    /// Node on a free loopback port
+    pub fn node_config() -> NodeConfig {
+        let port = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port();
+        NodeConfig {
+            label: "Test node".to_string(),
+            address: Ipv4Addr::LOCALHOST,
+            port,
+            interfaces: vec![("lo".to_string(), Ipv4Addr::LOCALHOST)],
+            legs: 1,
+            receivers: 2,
+            receiver_channels: 4,
+            first_input: 8,
+            registry: None,
+            heartbeat_interval: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_stable_ids() {
+        let id = stable_id("host:8080", "node");
+        assert_eq!(id, stable_id("host:8080", "node"));
+        assert_ne!(id, stable_id("host:8081", "node"));
+        assert_ne!(id, stable_id("host:8080", "device"));
+        let uuid = uuid::Uuid::parse_str(&id).unwrap();
+        assert_eq!(uuid.get_version_num(), 8);
+    }
+
+    #[test]
+    fn test_node_resources() {
+        // AES67 without PTP or network, one outgoing stream
+        let mut tx = TxStreamConfig::new("Main mix", Ipv4Addr::new(239, 69, 250, 10), 2);
+        tx.port = 15020;
+        let backend = Aes67Backend::with_config(Aes67Config {
+            interface: Some("127.0.0.1".to_string()),
+            input_channels: 16,
+            mixer_outputs: 8,
+            ..Default::default()
+        }).unwrap();
+        backend.tx_streams().add(tx).unwrap();
+        let mut backends = NetworkBackends::default();
+        backends.add("aes67", Box::new(backend));
+        let config = node_config();
+        let port = config.port;
+        let node = NmosNode::new(config, backends.clone());
+
+        let resources = node.resources();
+        let kinds: Vec<&str> = resources.iter().map(|r| r.kind.name()).collect();
+        assert_eq!(kinds, ["node", "device", "source", "flow", "sender", "receiver", "receiver"]);
+        assert_eq!(resources[0].data["api"]["endpoints"][0], json!({ "host": "127.0.0.1", "port": port, "protocol": "http" }));
+        assert_eq!(resources[3].data["media_type"], json!("audio/L24"));
+        assert_eq!(resources[4].data["flow_id"], resources[3].data["id"]);
+        assert_eq!(resources[5].data["label"], json!("Inputs 9-12"));
+
+        // Versions only change with the content
+        let again = node.resources();
+        assert_eq!(again[4].version(), resources[4].version());
+        let sender_id = resources[4].id.clone();
+        let sdp = node.sender_transport_file(&sender_id).unwrap();
+        assert!(sdp.contains("c=IN IP4 239.69.250.10"), "{}", sdp);
+        assert_eq!(node.sender_staged(&sender_id).unwrap()["transport_params"][0]["destination_port"], json!(15020));
+        assert_eq!(node.patch_sender(&sender_id, &json!({ "master_enable": false })).unwrap_err().code, 400);
+
+        // A receiver enabled with an invalid transport file stays disabled
+        let node = Arc::new(node);
+        let receiver = resources[5].id.clone();
+        let patch = json!({
+            "master_enable": true,
+            "transport_file": { "data": "not sdp", "type": "application/sdp" },
+            "activation": { "mode": "activate_immediate" },
+        });
+        assert_eq!(node.patch_receiver(&receiver, &patch).unwrap_err().code, 400);
+        assert_eq!(node.receiver_active(&receiver).unwrap()["master_enable"], json!(false));
+        assert_eq!(node.receiver_staged(&receiver).unwrap()["activation"]["mode"], Value::Null);
+
+        // Scheduled activations lock the staged parameters until cancelled
+        let scheduled = json!({ "activation": { "mode": "activate_scheduled_relative", "requested_time": "60:0" } });
+        assert_eq!(node.patch_receiver(&receiver, &scheduled).unwrap().0, 202);
+        assert_eq!(node.patch_receiver(&receiver, &json!({ "master_enable": false })).unwrap_err().code, 423);
+        let (code, staged) = node.patch_receiver(&receiver, &json!({ "activation": { "mode": null } })).unwrap();
+        assert_eq!((code, &staged["activation"]["mode"]), (200, &Value::Null));
+
+        let bulk = node.bulk_receivers(&json!([{ "id": receiver, "params": {} }, { "id": "unknown", "params": {} }])).unwrap();
+        assert_eq!((bulk[0]["code"].clone(), bulk[1]["code"].clone()), (json!(200), json!(404)));
+        backends.with(|b: &mut Aes67Backend| b.tx_streams().clear());
+    }
+}
@@ -0,0 +1,328 @@
+//! IS-04 registration
+//!
+//! The registry is given as URL or found via DNS-SD (`_nmos-register._tcp`,
+//! the one with the lowest `pri` that speaks our API version). All resources
+//! are registered parents first and kept current: changed resources are
+//! posted again, removed ones deleted. Heartbeats keep the node alive; a
+//! registry that forgot the node (404) gets everything again, an unreachable
+//! one is replaced by discovering anew. Stopping deletes the node's
+//! resources. The registration thread uses a blocking HTTP client.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::{Result, bail};
+use mdns_sd::{IfKind, ServiceDaemon, ServiceEvent};
+use reqwest::blocking::Client;
+use reqwest::header::ACCEPT;
+use reqwest::Method;
+use serde_json::{Value, json};
+use tracing::{debug, info, warn};
+
+use super::{NODE_API_VERSION, NmosNode, Resource, ResourceType};
+
+/// DNS-SD service type of registries (IS-04 v1.3)
+pub const REGISTRY_SERVICE: &str = "_nmos-register._tcp.local.";
+
+/// How long DNS-SD browsing collects registries
+const BROWSE_TIME: Duration = Duration::from_secs(2);
+
+/// Timeout for registry connections and requests
+const HTTP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A registry announced via DNS-SD
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistryService {
+    pub address: Ipv4Addr,
+    pub port: u16,
+    /// TXT records (api_proto, api_ver, pri)
+    pub txt: HashMap<String, String>,
+}
+
+/// Best registry: HTTP, our API version, lowest priority value
+pub fn select_registry(services: &[RegistryService]) -> Option<String> {
+    services.iter()
+        .filter(|s| s.txt.get("api_proto").is_none_or(|proto| proto == "http"))
+        .filter(|s| s.txt.get("api_ver").is_none_or(|versions| versions.split(',').any(|v| v.trim() == NODE_API_VERSION)))
+        .min_by_key(|s| s.txt.get("pri").and_then(|pri| pri.parse::<u32>().ok()).unwrap_or(u32::MAX))
+        .map(|s| format!("http://{}:{}", s.address, s.port))
+}
+
+/// Registration of a node, running in its own thread
+pub struct Registration {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Registration {
+    /// Find a registry and register the node's resources
+    pub fn start(node: Arc<NmosNode>) -> Result<Self> {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let running = running.clone();
+            std::thread::Builder::new()
+                .name("nmos-registration".to_string())
+                .spawn(move || run_registration(&node, &running))?
+        };
+        Ok(Self { running, thread: Some(thread) })
+    }
+
+    /// Stop heartbeats and delete the node's resources from the registry
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run_registration(node: &NmosNode, running: &AtomicBool) {
+    let interval = node.config().heartbeat_interval;
+    // Registered resources by id with their type and version
+    let mut registered: HashMap<String, (ResourceType, String)> = HashMap::new();
+    let mut registry: Option<Registry> = None;
+
+    while running.load(Ordering::Relaxed) {
+        if registry.is_none() {
+            registry = find_registry(node)
+                .and_then(|url| Registry::new(&url).map_err(|e| warn!("NMOS registry {}: {}", url, e)).ok());
+            registered.clear();
+            match &registry {
+                Some(registry) => info!("📇 NMOS registry: {}", registry.url),
+                None => debug!("No NMOS registry found"),
+            }
+        }
+
+        if let Some(active) = &registry {
+            let result = sync(active, node, &mut registered).and_then(|()| heartbeat(active, node));
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    // The registry forgot us (restart or missed heartbeats):
+                    // register again, the next heartbeat follows after the interval
+                    info!("📇 NMOS registry {} lost the node, registering again", active.url);
+                    registered.clear();
+                    if let Err(e) = sync(active, node, &mut registered) {
+                        warn!("NMOS registry {} failed: {}", active.url, e);
+                        registry = None;
+                    }
+                }
+                Err(e) => {
+                    warn!("NMOS registry {} failed: {}", active.url, e);
+                    registry = None;
+                }
+            }
+        }
+
+        let until = Instant::now() + interval;
+        while running.load(Ordering::Relaxed) && Instant::now() < until {
+            std::thread::sleep(Duration::from_millis(50).min(interval));
+        }
+    }
+
+    // Children first, the node last
+    if let Some(registry) = &registry {
+        let mut resources: Vec<_> = registered.into_iter().collect();
+        resources.sort_by_key(|(_, (kind, _))| std::cmp::Reverse(ResourceType::ALL.iter().position(|k| k == kind)));
+        for (id, (kind, _)) in resources {
+            if let Err(e) = registry.delete(kind, &id) {
+                debug!("Deleting NMOS {} {} failed: {}", kind.name(), id, e);
+            }
+        }
+        info!("📇 NMOS node unregistered from {}", registry.url);
+    }
+}
+
+/// Register new and changed resources, delete removed ones
+fn sync(registry: &Registry, node: &NmosNode, registered: &mut HashMap<String, (ResourceType, String)>) -> Result<()> {
+    let resources = node.resources();
+    for resource in &resources {
+        let version = resource.version().to_string();
+        if registered.get(&resource.id).is_some_and(|(_, known)| *known == version) {
+            continue;
+        }
+        registry.register(resource)?;
+        debug!("Registered NMOS {} {} ({})", resource.kind.name(), resource.id, version);
+        registered.insert(resource.id.clone(), (resource.kind, version));
+    }
+
+    let mut removed: Vec<(String, ResourceType)> = registered.iter()
+        .filter(|(id, _)| !resources.iter().any(|r| r.id == **id))
+        .map(|(id, (kind, _))| (id.clone(), *kind))
+        .collect();
+    removed.sort_by_key(|(_, kind)| std::cmp::Reverse(ResourceType::ALL.iter().position(|k| k == kind)));
+    for (id, kind) in removed {
+        registry.delete(kind, &id)?;
+        registered.remove(&id);
+    }
+    Ok(())
+}
+
+/// Heartbeat, false if the registry does not know the node
+fn heartbeat(registry: &Registry, node: &NmosNode) -> Result<bool> {
+    let (status, body) = registry.request(Method::POST, &format!("/health/nodes/{}", node.node_id()), None)?;
+    match status {
+        200 => Ok(true),
+        404 => Ok(false),
+        _ => bail!("heartbeat answered {}: {}", status, body),
+    }
+}
+
+/// Registry from the config, or the best one announced via DNS-SD
+fn find_registry(node: &NmosNode) -> Option<String> {
+    if let Some(url) = &node.config().registry {
+        return Some(url.clone());
+    }
+    // Only on the interface controllers reach us on
+    let interface = Some(node.config().address).filter(|a| !a.is_unspecified() && !a.is_loopback());
+    match browse_registries(interface) {
+        Ok(services) => select_registry(&services),
+        Err(e) => {
+            warn!("DNS-SD browsing for NMOS registries failed: {}", e);
+            None
+        }
+    }
+}
+
+fn browse_registries(interface: Option<Ipv4Addr>) -> Result<Vec<RegistryService>> {
+    let daemon = ServiceDaemon::new()?;
+    if let Some(interface) = interface {
+        daemon.disable_interface(IfKind::All)?;
+        daemon.enable_interface(IpAddr::V4(interface))?;
+    }
+    let receiver = daemon.browse(REGISTRY_SERVICE)?;
+    let mut services = Vec::new();
+    let deadline = Instant::now() + BROWSE_TIME;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let Ok(event) = receiver.recv_timeout(remaining) else {
+            break;
+        };
+        if let ServiceEvent::ServiceResolved(service) = event {
+            let Some(address) = service.get_addresses_v4().into_iter().next().copied() else {
+                continue;
+            };
+            let txt = service.get_properties().iter()
+                .map(|p| (p.key().to_string(), p.val_str().to_string()))
+                .collect();
+            services.push(RegistryService { address, port: service.get_port(), txt });
+        }
+    }
+    let _ = daemon.shutdown();
+    Ok(services)
+}
+
+/// Registration API of a registry
+struct Registry {
+    url: String,
+    client: Client,
+}
+
+impl Registry {
+    /// From a base URL "http://host[:port]"
+    fn new(url: &str) -> Result<Self> {
+        let url = url.trim_end_matches('/');
+        if !url.starts_with("http://") {
+            bail!("only http:// registries are supported");
+        }
+        let client = Client::builder()
+            .connect_timeout(HTTP_TIMEOUT)
+            .timeout(HTTP_TIMEOUT)
+            .build()?;
+        Ok(Self { url: url.to_string(), client })
+    }
+
+    fn register(&self, resource: &Resource) -> Result<()> {
+        let body = json!({ "type": resource.kind.name(), "data": resource.data });
+        let (status, response) = self.request(Method::POST, "/resource", Some(&body))?;
+        match status {
+            200 | 201 => Ok(()),
+            _ => bail!("registering {} {} answered {}: {}", resource.kind.name(), resource.id, status, response),
+        }
+    }
+
+    fn delete(&self, kind: ResourceType, id: &str) -> Result<()> {
+        let (status, response) = self.request(Method::DELETE, &format!("/resource/{}/{}", kind.plural(), id), None)?;
+        match status {
+            // Already gone is fine
+            204 | 404 => Ok(()),
+            _ => bail!("deleting {} {} answered {}: {}", kind.name(), id, status, response),
+        }
+    }
+
+    /// Request below the Registration API, returns status and body
+    fn request(&self, method: Method, path: &str, body: Option<&Value>) -> Result<(u16, String)> {
+        let url = format!("{}/x-nmos/registration/{}{}", self.url, NODE_API_VERSION, path);
+        let mut request = self.client.request(method, url).header(ACCEPT, "application/json");
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send()?;
+        let status = response.status().as_u16();
+        Ok((status, response.text()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::NetworkBackends;
+    use super::super::mock_registry::MockRegistry;
+    use super::super::tests::node_config;
+    use crate::network_audio::simulator::wait_for;
+
+    fn service(pri: &str, api_ver: &str, port: u16) -> RegistryService {
+        let txt = [("api_proto", "http"), ("api_ver", api_ver), ("pri", pri)].into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        RegistryService { address: Ipv4Addr::new(192, 168, 1, 5), port, txt }
+    }
+
+    #[test]
+    fn test_select_registry() {
+        assert_eq!(select_registry(&[]), None);
+        let services = [service("100", "v1.2,v1.3", 8010), service("10", "v1.3", 8020), service("0", "v1.2", 8030)];
+        assert_eq!(select_registry(&services).as_deref(), Some("http://192.168.1.5:8020"));
+    }
+
+    #[test]
+    fn test_registration() {
+        let registry = MockRegistry::start().unwrap();
+        let mut config = node_config();
+        config.registry = Some(registry.url());
+        config.heartbeat_interval = Duration::from_millis(100);
+        let node = Arc::new(NmosNode::new(config, NetworkBackends::default()));
+        let mut registration = Registration::start(node.clone()).unwrap();
+
+        // Parents first, then heartbeats
+        assert!(wait_for(Duration::from_secs(5), || registry.heartbeats() >= 2));
+        let order: Vec<String> = registry.posted().into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(order, ["node", "device", "receiver", "receiver"]);
+        assert_eq!(registry.resources("node")[0]["id"], json!(node.node_id()));
+        assert_eq!(registry.resources("receiver").len(), 2);
+
+        // A registry that restarts gets everything again
+        registry.forget();
+        assert!(wait_for(Duration::from_secs(5), || registry.resources("receiver").len() == 2));
+        assert_eq!(registry.resources("device")[0]["node_id"], json!(node.node_id()));
+
+        // A registry that keeps losing the node is not flooded
+        registry.reject_heartbeats(true);
+        let before = (registry.heartbeats(), registry.posted().len());
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(registry.heartbeats() - before.0 <= 8, "{} heartbeats", registry.heartbeats() - before.0);
+        assert!(registry.posted().len() - before.1 <= 8 * 4);
+        registry.reject_heartbeats(false);
+
+        registration.stop();
+        assert!(registry.resources("node").is_empty() && registry.resources("receiver").is_empty());
+    }
+}
@@ -0,0 +1,429 @@
+//! PTPv2 message encoding (IEEE 1588-2008, clause 13)
+//!
+//! Only the messages used by an ordinary clock with the delay
+//! request-response mechanism are handled: Sync, Follow_Up, Delay_Req,
+//! Delay_Resp and Announce.
+
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// PTP primary multicast group (all messages except peer delay)
+pub const PTP_PRIMARY_MULTICAST: Ipv4Addr = Ipv4Addr::new(224, 0, 1, 129);
+
+/// Event port (Sync, Delay_Req)
+pub const PTP_EVENT_PORT: u16 = 319;
+
+/// General port (Announce, Follow_Up, Delay_Resp)
+pub const PTP_GENERAL_PORT: u16 = 320;
+
+/// Common header length
+pub const HEADER_LEN: usize = 34;
+
+/// Two-step flag (flagField octet 0, bit 1)
+const FLAG_TWO_STEP: u16 = 0x0200;
+
+/// currentUtcOffsetValid flag (flagField octet 1, bit 2)
+pub const FLAG_UTC_OFFSET_VALID: u16 = 0x0004;
+
+/// ptpTimescale flag (flagField octet 1, bit 3)
+pub const FLAG_PTP_TIMESCALE: u16 = 0x0008;
+
+/// Message types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MessageType {
+    Sync = 0x0,
+    DelayReq = 0x1,
+    FollowUp = 0x8,
+    DelayResp = 0x9,
+    Announce = 0xB,
+}
+
+impl MessageType {
+    fn from_nibble(value: u8) -> Option<Self> {
+        match value & 0x0F {
+            0x0 => Some(Self::Sync),
+            0x1 => Some(Self::DelayReq),
+            0x8 => Some(Self::FollowUp),
+            0x9 => Some(Self::DelayResp),
+            0xB => Some(Self::Announce),
+            _ => None,
+        }
+    }
+
+    /// Value of the (deprecated) control field
+    fn control(self) -> u8 {
+        match self {
+            Self::Sync => 0,
+            Self::DelayReq => 1,
+            Self::FollowUp => 2,
+            Self::DelayResp => 3,
+            Self::Announce => 5,
+        }
+    }
+}
+
+/// Clock identity (EUI-64)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ClockIdentity(pub [u8; 8]);
+
+impl ClockIdentity {
+    /// EUI-64 derived from a MAC address (FF-FE inserted in the middle)
+    pub fn from_mac(mac: [u8; 6]) -> Self {
+        Self([mac[0], mac[1], mac[2], 0xFF, 0xFE, mac[3], mac[4], mac[5]])
+    }
+}
+
+impl fmt::Display for ClockIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "{}", parts.join("-"))
+    }
+}
+
+/// Port identity (clock identity + port number)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PortIdentity {
+    pub clock_identity: ClockIdentity,
+    pub port_number: u16,
+}
+
+impl PortIdentity {
+    fn parse(data: &[u8]) -> Self {
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&data[..8]);
+        Self {
+            clock_identity: ClockIdentity(id),
+            port_number: u16::from_be_bytes([data[8], data[9]]),
+        }
+    }
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.clock_identity.0);
+        buf.extend_from_slice(&self.port_number.to_be_bytes());
+    }
+}
+
+impl fmt::Display for PortIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.clock_identity, self.port_number)
+    }
+}
+
+/// Clock quality of a grandmaster
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClockQuality {
+    pub clock_class: u8,
+    pub clock_accuracy: u8,
+    pub offset_scaled_log_variance: u16,
+}
+
+/// Common message header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Header {
+    pub message_type: MessageType,
+    pub message_length: u16,
+    pub domain: u8,
+    pub flags: u16,
+    /// Correction field in nanoseconds * 2^16
+    pub correction: i64,
+    pub source_port: PortIdentity,
+    pub sequence_id: u16,
+    /// log2 of the message interval in seconds
+    pub log_interval: i8,
+}
+
+impl Header {
+    pub fn new(message_type: MessageType, domain: u8, source_port: PortIdentity, sequence_id: u16) -> Self {
+        Self {
+            message_type,
+            message_length: 0,
+            domain,
+            flags: 0,
+            correction: 0,
+            source_port,
+            sequence_id,
+            log_interval: 0x7F,
+        }
+    }
+
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < HEADER_LEN || data[1] & 0x0F != 2 {
+            return None;
+        }
+        Some(Self {
+            message_type: MessageType::from_nibble(data[0])?,
+            message_length: u16::from_be_bytes([data[2], data[3]]),
+            domain: data[4],
+            flags: u16::from_be_bytes([data[6], data[7]]),
+            correction: i64::from_be_bytes(data[8..16].try_into().ok()?),
+            source_port: PortIdentity::parse(&data[20..30]),
+            sequence_id: u16::from_be_bytes([data[30], data[31]]),
+            log_interval: data[33] as i8,
+        })
+    }
+
+    fn write(&self, buf: &mut Vec<u8>, message_length: usize) {
+        buf.push(self.message_type as u8);
+        buf.push(2); // versionPTP
+        buf.extend_from_slice(&(message_length as u16).to_be_bytes());
+        buf.push(self.domain);
+        buf.push(0);
+        buf.extend_from_slice(&self.flags.to_be_bytes());
+        buf.extend_from_slice(&self.correction.to_be_bytes());
+        buf.extend_from_slice(&[0; 4]);
+        self.source_port.write(buf);
+        buf.extend_from_slice(&self.sequence_id.to_be_bytes());
+        buf.push(self.message_type.control());
+        buf.push(self.log_interval as u8);
+    }
+
+    pub fn two_step(&self) -> bool {
+        self.flags & FLAG_TWO_STEP != 0
+    }
+
+    pub fn set_two_step(&mut self, two_step: bool) {
+        if two_step {
+            self.flags |= FLAG_TWO_STEP;
+        } else {
+            self.flags &= !FLAG_TWO_STEP;
+        }
+    }
+
+    /// Correction field in whole nanoseconds
+    pub fn correction_ns(&self) -> i64 {
+        self.correction >> 16
+    }
+}
+
+/// Announce message body
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Announce {
+    pub current_utc_offset: i16,
+    pub grandmaster_priority1: u8,
+    pub grandmaster_clock_quality: ClockQuality,
+    pub grandmaster_priority2: u8,
+    pub grandmaster_identity: ClockIdentity,
+    pub steps_removed: u16,
+    pub time_source: u8,
+}
+
+/// A parsed PTP message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Message {
+    Sync { header: Header, origin_timestamp: i64 },
+    FollowUp { header: Header, precise_origin_timestamp: i64 },
+    DelayReq { header: Header, origin_timestamp: i64 },
+    DelayResp { header: Header, receive_timestamp: i64, requesting_port: PortIdentity },
+    Announce { header: Header, announce: Announce },
+}
+
+impl Message {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let header = Header::parse(data)?;
+        let body = &data[HEADER_LEN..];
+
+        Some(match header.message_type {
+            MessageType::Sync => Message::Sync {
+                header,
+                origin_timestamp: parse_timestamp(body)?,
+            },
+            MessageType::FollowUp => Message::FollowUp {
+                header,
+                precise_origin_timestamp: parse_timestamp(body)?,
+            },
+            MessageType::DelayReq => Message::DelayReq {
+                header,
+                origin_timestamp: parse_timestamp(body)?,
+            },
+            MessageType::DelayResp => {
+                if body.len() < 20 {
+                    return None;
+                }
+                Message::DelayResp {
+                    header,
+                    receive_timestamp: parse_timestamp(body)?,
+                    requesting_port: PortIdentity::parse(&body[10..20]),
+                }
+            }
+            MessageType::Announce => {
+                if body.len() < 30 {
+                    return None;
+                }
+                let mut gm = [0u8; 8];
+                gm.copy_from_slice(&body[19..27]);
+                Message::Announce {
+                    header,
+                    announce: Announce {
+                        current_utc_offset: i16::from_be_bytes([body[10], body[11]]),
+                        grandmaster_priority1: body[13],
+                        grandmaster_clock_quality: ClockQuality {
+                            clock_class: body[14],
+                            clock_accuracy: body[15],
+                            offset_scaled_log_variance: u16::from_be_bytes([body[16], body[17]]),
+                        },
+                        grandmaster_priority2: body[18],
+                        grandmaster_identity: ClockIdentity(gm),
+                        steps_removed: u16::from_be_bytes([body[27], body[28]]),
+                        time_source: body[29],
+                    },
+                }
+            }
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        match self {
+            Message::Sync { header, .. }
+            | Message::FollowUp { header, .. }
+            | Message::DelayReq { header, .. }
+            | Message::DelayResp { header, .. }
+            | Message::Announce { header, .. } => header,
+        }
+    }
+
+    /// Serialize the message
+    pub fn to_bytes(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(64);
+        match self {
+            Message::Sync { header, origin_timestamp: ts }
+            | Message::FollowUp { header, precise_origin_timestamp: ts }
+            | Message::DelayReq { header, origin_timestamp: ts } => {
+                header.write(&mut buf, HEADER_LEN + 10);
+                write_timestamp(&mut buf, ts);
+            }
+            Message::DelayResp { header, receive_timestamp, requesting_port } => {
+                header.write(&mut buf, HEADER_LEN + 20);
+                write_timestamp(&mut buf, receive_timestamp);
+                requesting_port.write(&mut buf);
+            }
+            Message::Announce { header, announce } => {
+                header.write(&mut buf, HEADER_LEN + 30);
+                write_timestamp(&mut buf, 0);
+                buf.extend_from_slice(&announce.current_utc_offset.to_be_bytes());
+                buf.push(0);
+                buf.push(announce.grandmaster_priority1);
+                buf.push(announce.grandmaster_clock_quality.clock_class);
+                buf.push(announce.grandmaster_clock_quality.clock_accuracy);
+                buf.extend_from_slice(&announce.grandmaster_clock_quality.offset_scaled_log_variance.to_be_bytes());
+                buf.push(announce.grandmaster_priority2);
+                buf.extend_from_slice(&announce.grandmaster_identity.0);
+                buf.extend_from_slice(&announce.steps_removed.to_be_bytes());
+                buf.push(announce.time_source);
+            }
+        }
+        buf
+    }
+}
+
+/// Parse a PTP timestamp (48-bit seconds + 32-bit nanoseconds) to nanoseconds
+fn parse_timestamp(data: &[u8]) -> Option<i64> {
+    (data.len() >= 10).then(|| extract_timestamp(data))
+}
+
+/// Extract PTP timestamp from message (48-bit seconds + 32-bit nanoseconds)
+pub fn extract_timestamp(data: &[u8]) -> i64 {
+    if data.len() < 10 {
+        return 0;
+    }
+
+    // 48-bit seconds (6 bytes)
+    let seconds = u64::from_be_bytes([0, 0, data[0], data[1], data[2], data[3], data[4], data[5]]);
+
+    // 32-bit nanoseconds (4 bytes)
+    let nanos = u32::from_be_bytes([data[6], data[7], data[8], data[9]]);
+
+    (seconds as i64 * 1_000_000_000) + nanos as i64
+}
+
+fn write_timestamp(buf: &mut Vec<u8>, ns: i64) {
+    let ns = ns.max(0);
+    let seconds = (ns / 1_000_000_000) as u64;
+    let nanos = (ns % 1_000_000_000) as u32;
+    buf.extend_from_slice(&seconds.to_be_bytes()[2..]);
+    buf.extend_from_slice(&nanos.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_timestamp() {
+        // Test timestamp: 1704067200 seconds, 500000000 nanoseconds
+        let data: [u8; 10] = [
+            0x00, 0x00, 0x65, 0x92, 0x00, 0x80, // seconds (1704067200)
+            0x1D, 0xCD, 0x65, 0x00,             // nanoseconds (500000000)
+        ];
+
+        let ts = extract_timestamp(&data);
+        let expected = 1704067200_i64 * 1_000_000_000 + 500000000;
+        assert_eq!(ts, expected);
+    }
+
+    #[test]
+    fn test_delay_resp_roundtrip() {
+        let master = PortIdentity {
+            clock_identity: ClockIdentity::from_mac([0x00, 0x1D, 0xC1, 0x12, 0x34, 0x56]),
+            port_number: 1,
+        };
+        let requester = PortIdentity {
+            clock_identity: ClockIdentity([1, 2, 3, 4, 5, 6, 7, 8]),
+            port_number: 1,
+        };
+        let mut header = Header::new(MessageType::DelayResp, 0, master, 42);
+        header.correction = 1500 << 16;
+        header.log_interval = -3;
+
+        let message = Message::DelayResp {
+            header,
+            receive_timestamp: 1_704_067_200_123_456_789,
+            requesting_port: requester,
+        };
+        let bytes = message.to_bytes();
+        assert_eq!(bytes.len(), 54);
+        assert_eq!(&bytes[2..4], &54u16.to_be_bytes());
+
+        let parsed = Message::parse(&bytes).unwrap();
+        assert_eq!(parsed, Message::DelayResp {
+            header: Header { message_length: 54, ..header },
+            receive_timestamp: 1_704_067_200_123_456_789,
+            requesting_port: requester,
+        });
+        assert_eq!(parsed.header().correction_ns(), 1500);
+        assert_eq!(master.clock_identity.to_string(), "00-1D-C1-FF-FE-12-34-56");
+    }
+
+    #[test]
+    fn test_announce_and_two_step_sync() {
+        // Announce as sent by a typical AES67 grandmaster
+        let mut data = vec![0x0B, 0x02, 0x00, 0x40, 0x00, 0x00, 0x00, 0x08];
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[0xAA, 0xBB, 0xCC, 0xFF, 0xFE, 0x00, 0x11, 0x22, 0x00, 0x01]);
+        data.extend_from_slice(&[0x00, 0x07, 0x05, 0x01]);
+        data.extend_from_slice(&[0; 10]);
+        data.extend_from_slice(&[0x00, 0x25, 0x00, 128, 6, 0x21, 0x4E, 0x5D, 127]);
+        data.extend_from_slice(&[0xAA, 0xBB, 0xCC, 0xFF, 0xFE, 0x00, 0x11, 0x22]);
+        data.extend_from_slice(&[0x00, 0x00, 0x20]);
+
+        let Some(Message::Announce { header, announce }) = Message::parse(&data) else {
+            panic!("announce not parsed");
+        };
+        assert_eq!(header.sequence_id, 7);
+        assert_eq!(announce.current_utc_offset, 37);
+        assert_eq!(announce.grandmaster_priority1, 128);
+        assert_eq!(announce.grandmaster_clock_quality.clock_class, 6);
+        assert_eq!(announce.grandmaster_priority2, 127);
+        assert_eq!(announce.steps_removed, 0);
+        assert_eq!(announce.grandmaster_identity, header.source_port.clock_identity);
+
+        let roundtrip = Message::Announce { header, announce }.to_bytes();
+        assert_eq!(Message::parse(&roundtrip), Some(Message::Announce { header, announce }));
+
+        let mut sync = Header::new(MessageType::Sync, 0, header.source_port, 1);
+        sync.set_two_step(true);
+        let bytes = Message::Sync { header: sync, origin_timestamp: 0 }.to_bytes();
+        assert_eq!(bytes[6], 0x02);
+        assert!(Header::parse(&bytes).unwrap().two_step());
+    }
+}
@@ -0,0 +1,1146 @@
+//! PTP (IEEE 1588) Clock Synchronization for AES67
+//!
+//! Ordinary clock using the delay request-response mechanism (AES67 media
+//! profile). The best master is selected from the Announce messages;
+//! Sync/Follow_Up give the offset, Delay_Req/Delay_Resp the mean path delay,
+//! and a PI servo steers a virtual PTP clock on top of the system clock.
+//! When master capable and no better clock announces itself, the port takes
+//! the master role (Announce, two-step Sync, Delay_Resp) and yields again as
+//! soon as a better master appears.
+//!
+//! Event messages are timestamped by the kernel or the NIC where possible
+//! (see `timestamping`).
+//!
+//! AES67 requires PTP for sample-accurate synchronization across devices.
+
+mod message;
+mod servo;
+mod timestamping;
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+use parking_lot::RwLock;
+use serde::Serialize;
+use tracing::{info, warn, debug};
+
+pub use message::{ClockIdentity, PortIdentity};
+use message::{
+    Announce, ClockQuality, Header, Message, MessageType, FLAG_PTP_TIMESCALE, FLAG_UTC_OFFSET_VALID,
+    PTP_EVENT_PORT, PTP_GENERAL_PORT, PTP_PRIMARY_MULTICAST,
+};
+use servo::{PiServo, ServoAction, VirtualClock};
+use timestamping::Timestamper;
+use super::PtpTimestamping;
+
+/// Sync messages missing for this long: holdover
+const SYNC_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Announce intervals without an Announce before a master is dropped
+const ANNOUNCE_RECEIPT_TIMEOUT: u32 = 3;
+
+/// Announces needed before a foreign master is considered
+const FOREIGN_MASTER_THRESHOLD: u32 = 2;
+
+/// Path delay samples in the median filter
+const DELAY_FILTER_LENGTH: usize = 7;
+
+/// Master message intervals (log2 seconds, AES67 media profile defaults)
+const LOG_ANNOUNCE_INTERVAL: i8 = 1;
+const LOG_SYNC_INTERVAL: i8 = -3;
+const LOG_MIN_DELAY_REQ_INTERVAL: i8 = 0;
+
+/// TAI - UTC in seconds (the PTP timescale is TAI)
+const UTC_OFFSET_S: i16 = 37;
+
+/// Clock class of a master-capable ordinary clock without time source
+const DEFAULT_CLOCK_CLASS: u8 = 248;
+const CLOCK_ACCURACY_UNKNOWN: u8 = 0xFE;
+const LOG_VARIANCE_UNKNOWN: u16 = 0xFFFF;
+const DEFAULT_PRIORITY2: u8 = 128;
+const TIME_SOURCE_INTERNAL_OSCILLATOR: u8 = 0xA0;
+
+/// Default priority1 when master capable: dedicated grandmasters and devices
+/// with default settings (128) win the BMCA
+const DEFAULT_MASTER_PRIORITY1: u8 = 250;
+
+/// PTP Clock state
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum PtpState {
+    /// Not started
+    Initializing,
+    /// Listening for master clock
+    Listening,
+    /// Synchronized to master
+    Slave,
+    /// Acting as master clock
+    Master,
+    /// Lost sync
+    Holdover,
+}
+
+/// PTP Clock statistics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PtpStats {
+    /// Offset from master in nanoseconds
+    pub offset_ns: i64,
+    /// Path delay in nanoseconds
+    pub path_delay_ns: i64,
+    /// Number of sync messages received
+    pub sync_count: u64,
+    /// Clock accuracy estimate
+    pub clock_accuracy_ns: u64,
+    /// Steps removed from grandmaster
+    pub steps_removed: u16,
+    /// Frequency correction against the system clock in ppb
+    pub frequency_ppb: f64,
+    /// Number of Delay_Req messages sent
+    pub delay_req_count: u64,
+    /// Grandmaster clock identity (empty without master)
+    pub grandmaster_identity: String,
+    /// Port identity of the master we follow
+    pub parent_port_identity: String,
+    /// Grandmaster priority1
+    pub grandmaster_priority1: u8,
+    /// Grandmaster priority2
+    pub grandmaster_priority2: u8,
+    /// Grandmaster clock class
+    pub clock_class: u8,
+    /// Grandmaster clock accuracy (enumeration, 0x20 = 25 ns ... 0xFE = unknown)
+    pub clock_accuracy: u8,
+    /// Timestamp source in use for event messages
+    pub timestamping: PtpTimestamping,
+    /// TAI - UTC in seconds, as announced by the grandmaster
+    pub utc_offset_s: i16,
+}
+
+/// PTP Clock for AES67 synchronization
+pub struct PtpClock {
+    /// Current state
+    state: Arc<RwLock<PtpState>>,
+    /// Running flag
+    running: Arc<AtomicBool>,
+    /// Last measured offset from master (nanoseconds)
+    offset_ns: Arc<AtomicI64>,
+    /// PTP time on top of the system clock
+    clock: Arc<RwLock<VirtualClock>>,
+    /// Network interface to use
+    interface: String,
+    /// Address of the interface PTP multicast is bound to (None = all)
+    interface_address: Option<Ipv4Addr>,
+    /// PTP domain (default 0 for AES67)
+    domain: u8,
+    /// Take the master role when no better clock is present
+    master_capable: bool,
+    /// priority1 announced as master (lower wins)
+    priority1: u8,
+    /// Requested timestamp source
+    timestamping: PtpTimestamping,
+    /// UDP port of event messages (Sync, Delay_Req)
+    event_port: u16,
+    /// UDP port of general messages (Announce, Follow_Up, Delay_Resp)
+    general_port: u16,
+    /// Statistics
+    stats: Arc<RwLock<PtpStats>>,
+}
+
+impl PtpClock {
+    /// Create a new PTP clock
+    pub fn new(interface: &str) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(PtpState::Initializing)),
+            running: Arc::new(AtomicBool::new(false)),
+            offset_ns: Arc::new(AtomicI64::new(0)),
+            // Starts on TAI from the system clock (UTC) until synchronized
+            clock: Arc::new(RwLock::new(VirtualClock::with_offset(UTC_OFFSET_S as i64 * 1_000_000_000))),
+            interface: interface.to_string(),
+            interface_address: None,
+            domain: 0, // AES67 default domain
+            master_capable: false,
+            priority1: DEFAULT_MASTER_PRIORITY1,
+            timestamping: PtpTimestamping::Auto,
+            event_port: PTP_EVENT_PORT,
+            general_port: PTP_GENERAL_PORT,
+            stats: Arc::new(RwLock::new(PtpStats { utc_offset_s: UTC_OFFSET_S, ..Default::default() })),
+        }
+    }
+
+    /// Send and receive PTP only on the interface with this address
+    pub fn set_interface_address(&mut self, address: Option<Ipv4Addr>) {
+        self.interface_address = address;
+    }
+
+    /// Set PTP domain (0-127)
+    pub fn set_domain(&mut self, domain: u8) {
+        self.domain = domain.min(127);
+    }
+
+    /// PTP domain
+    pub fn domain(&self) -> u8 {
+        self.domain
+    }
+
+    /// Allow the master role (BMCA) with the given priority1
+    pub fn set_master_capable(&mut self, enabled: bool, priority1: u8) {
+        self.master_capable = enabled;
+        self.priority1 = priority1;
+    }
+
+    /// Select the timestamp source (falls back if unavailable)
+    pub fn set_timestamping(&mut self, mode: PtpTimestamping) {
+        self.timestamping = mode;
+    }
+
+    /// Use other UDP ports than 319/320
+    ///
+    /// Only clocks on the same ports see each other. The standard ports need
+    /// root or CAP_NET_BIND_SERVICE, tests use unprivileged ones.
+    pub fn set_ports(&mut self, event_port: u16, general_port: u16) {
+        self.event_port = event_port;
+        self.general_port = general_port;
+    }
+
+    /// Get current state
+    pub fn state(&self) -> PtpState {
+        *self.state.read()
+    }
+
+    /// Get current offset from master in nanoseconds
+    pub fn offset_ns(&self) -> i64 {
+        self.offset_ns.load(Ordering::Relaxed)
+    }
+
+    /// Get statistics
+    pub fn stats(&self) -> PtpStats {
+        self.stats.read().clone()
+    }
+
+    /// Check if synchronized
+    pub fn is_synchronized(&self) -> bool {
+        matches!(self.state(), PtpState::Slave | PtpState::Master)
+    }
+
+    /// Start the PTP clock
+    pub fn start(&self) -> Result<()> {
+        if self.running.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        info!("⏱️  PTP Clock starting on interface: {}", self.interface);
+        info!("    Domain: {}", self.domain);
+        if self.master_capable {
+            info!("    Master capable (priority1 {})", self.priority1);
+        }
+
+        let port_identity = PortIdentity {
+            clock_identity: clock_identity_for(&self.interface),
+            port_number: 1,
+        };
+        info!("    Clock identity: {}", port_identity.clock_identity);
+
+        // Create event socket (default port 319) and general socket (320)
+        let event_socket = create_ptp_socket(self.event_port, PTP_PRIMARY_MULTICAST, self.interface_address)?;
+        let general_socket = create_ptp_socket(self.general_port, PTP_PRIMARY_MULTICAST, self.interface_address)?;
+
+        // Only event messages (Sync, Delay_Req) need precise timestamps
+        let timestamper = Arc::new(Timestamper::new(&event_socket, &self.interface, self.timestamping));
+        self.stats.write().timestamping = timestamper.mode();
+
+        self.running.store(true, Ordering::Relaxed);
+        *self.state.write() = PtpState::Listening;
+
+        // Receive threads timestamp immediately, the port thread runs the protocol
+        let (tx, rx) = mpsc::channel();
+        spawn_receiver("ptp-event", event_socket.try_clone()?, timestamper.clone(), tx.clone(), self.running.clone())?;
+        spawn_receiver("ptp-general", general_socket.try_clone()?, Arc::new(Timestamper::user()), tx, self.running.clone())?;
+
+        let mut port = PtpPort::new(self, port_identity, event_socket, general_socket, timestamper);
+        let running = self.running.clone();
+
+        std::thread::Builder::new()
+            .name("ptp".to_string())
+            .spawn(move || {
+                info!("📡 PTP sockets bound, listening for sync messages...");
+                while running.load(Ordering::Relaxed) {
+                    let timeout = port.next_deadline(Instant::now()).saturating_duration_since(Instant::now());
+                    match rx.recv_timeout(timeout.min(Duration::from_millis(100))) {
+                        Ok(received) => port.handle(received),
+                        Err(mpsc::RecvTimeoutError::Timeout) => {}
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    }
+                    port.poll(Instant::now());
+                }
+                info!("PTP loop stopped");
+            })?;
+
+        Ok(())
+    }
+
+    /// Stop the PTP clock
+    pub fn stop(&self) {
+        info!("⏱️  PTP Clock stopping");
+        self.running.store(false, Ordering::Relaxed);
+        *self.state.write() = PtpState::Initializing;
+    }
+
+    /// Current PTP time in nanoseconds since epoch (system clock steered by the servo)
+    pub fn now_ns(&self) -> u64 {
+        self.clock.read().time_at(get_system_time_ns()).max(0) as u64
+    }
+
+    /// Current UTC in nanoseconds since the Unix epoch (PTP time minus the
+    /// grandmaster's UTC offset), e.g. for RTCP wallclock timestamps
+    pub fn utc_ns(&self) -> u64 {
+        let offset_ns = self.stats.read().utc_offset_s as i64 * 1_000_000_000;
+        (self.now_ns() as i64 - offset_ns).max(0) as u64
+    }
+
+    /// Get media clock timestamp for RTP
+    /// Returns the current media clock in samples at `sample_rate` since epoch
+    pub fn media_timestamp(&self, sample_rate: u32) -> u32 {
+        // RTP timestamp = PTP time * sample_rate / 1_000_000_000
+        let corrected_ns = self.now_ns() as u128;
+
+        // Convert to media clock samples (wrap at 32 bits)
+        ((corrected_ns * sample_rate as u128 / 1_000_000_000) & 0xFFFFFFFF) as u32
+    }
+}
+
+/// A received message with its receive time (system clock)
+struct Received {
+    message: Message,
+    system_ns: i64,
+}
+
+/// Receive, timestamp and parse PTP messages of one socket
+fn spawn_receiver(
+    name: &str,
+    socket: UdpSocket,
+    timestamper: Arc<Timestamper>,
+    tx: mpsc::Sender<Received>,
+    running: Arc<AtomicBool>,
+) -> Result<()> {
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+
+    std::thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            let mut buf = [0u8; 1024];
+            while running.load(Ordering::Relaxed) {
+                match timestamper.recv(&socket, &mut buf) {
+                    Ok((len, system_ns)) => {
+                        if let Some(message) = Message::parse(&buf[..len]) {
+                            if tx.send(Received { message, system_ns }).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(ref e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+                    Err(e) => {
+                        warn!("PTP socket error: {}", e);
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Announcing master seen on the network
+#[derive(Debug, Clone, Copy)]
+struct ForeignMaster {
+    header: Header,
+    announce: Announce,
+    count: u32,
+    last_seen: Instant,
+}
+
+impl ForeignMaster {
+    /// Dataset comparison key (IEEE 1588 9.3.4, lower is better)
+    fn dataset_key(&self) -> (u8, u8, u8, u16, u8, ClockIdentity, u16, PortIdentity) {
+        let quality = self.announce.grandmaster_clock_quality;
+        (
+            self.announce.grandmaster_priority1,
+            quality.clock_class,
+            quality.clock_accuracy,
+            quality.offset_scaled_log_variance,
+            self.announce.grandmaster_priority2,
+            self.announce.grandmaster_identity,
+            self.announce.steps_removed,
+            self.header.source_port,
+        )
+    }
+
+    fn announce_timeout(&self) -> Duration {
+        log_interval(self.header.log_interval, 1.0) * ANNOUNCE_RECEIPT_TIMEOUT
+    }
+}
+
+/// Our clock's dataset for the best master clock algorithm
+#[derive(Debug, Clone, Copy, Default)]
+struct LocalDataset {
+    priority1: u8,
+    quality: ClockQuality,
+    priority2: u8,
+}
+
+/// Two-step Sync waiting for its Follow_Up
+#[derive(Debug, Clone, Copy)]
+struct PendingSync {
+    sequence_id: u16,
+    system_ns: i64,
+    correction_ns: i64,
+}
+
+/// Sent Delay_Req waiting for its Delay_Resp
+#[derive(Debug, Clone, Copy)]
+struct PendingDelayReq {
+    sequence_id: u16,
+    /// t3 (system clock)
+    system_ns: i64,
+    /// Sync pair before the request: t1 (master) and t2 (system clock)
+    t1: i64,
+    t2_system_ns: i64,
+}
+
+/// Ordinary clock port: slave of the best master, or master itself when
+/// no better clock is on the network
+struct PtpPort {
+    port_identity: PortIdentity,
+    domain: u8,
+    /// Our dataset for the best master clock algorithm (None = slave only)
+    local: Option<LocalDataset>,
+    event_socket: UdpSocket,
+    general_socket: UdpSocket,
+    event_port: u16,
+    general_port: u16,
+    timestamper: Arc<Timestamper>,
+    clock: Arc<RwLock<VirtualClock>>,
+    state: Arc<RwLock<PtpState>>,
+    offset_ns: Arc<AtomicI64>,
+    stats: Arc<RwLock<PtpStats>>,
+    servo: PiServo,
+    foreign_masters: HashMap<PortIdentity, ForeignMaster>,
+    parent: Option<PortIdentity>,
+    /// Acting as master
+    master: bool,
+    /// End of the initial listening period (no master role before)
+    listen_until: Instant,
+    pending_sync: Option<PendingSync>,
+    last_sync_pair: Option<(i64, i64)>,
+    last_sync: Option<Instant>,
+    pending_delay_req: Option<PendingDelayReq>,
+    delay_req_sequence: u16,
+    delay_req_interval: Duration,
+    next_delay_req: Instant,
+    delays: VecDeque<i64>,
+    path_delay_ns: i64,
+    sync_sequence: u16,
+    next_sync: Instant,
+    announce_sequence: u16,
+    next_announce: Instant,
+}
+
+impl PtpPort {
+    fn new(
+        ptp: &PtpClock,
+        port_identity: PortIdentity,
+        event_socket: UdpSocket,
+        general_socket: UdpSocket,
+        timestamper: Arc<Timestamper>,
+    ) -> Self {
+        let now = Instant::now();
+        let local = ptp.master_capable.then_some(LocalDataset {
+            priority1: ptp.priority1,
+            quality: ClockQuality {
+                clock_class: DEFAULT_CLOCK_CLASS,
+                clock_accuracy: CLOCK_ACCURACY_UNKNOWN,
+                offset_scaled_log_variance: LOG_VARIANCE_UNKNOWN,
+            },
+            priority2: DEFAULT_PRIORITY2,
+        });
+
+        Self {
+            port_identity,
+            domain: ptp.domain,
+            local,
+            event_socket,
+            general_socket,
+            event_port: ptp.event_port,
+            general_port: ptp.general_port,
+            timestamper,
+            clock: ptp.clock.clone(),
+            state: ptp.state.clone(),
+            offset_ns: ptp.offset_ns.clone(),
+            stats: ptp.stats.clone(),
+            servo: PiServo::new(0.125),
+            foreign_masters: HashMap::new(),
+            parent: None,
+            master: false,
+            listen_until: now + log_interval(LOG_ANNOUNCE_INTERVAL, 2.0) * ANNOUNCE_RECEIPT_TIMEOUT,
+            pending_sync: None,
+            last_sync_pair: None,
+            last_sync: None,
+            pending_delay_req: None,
+            delay_req_sequence: rand::random(),
+            delay_req_interval: Duration::from_secs(1),
+            next_delay_req: now,
+            delays: VecDeque::with_capacity(DELAY_FILTER_LENGTH),
+            path_delay_ns: 0,
+            sync_sequence: rand::random(),
+            next_sync: now,
+            announce_sequence: rand::random(),
+            next_announce: now,
+        }
+    }
+
+    fn handle(&mut self, received: Received) {
+        let header = *received.message.header();
+        if header.domain != self.domain || header.source_port == self.port_identity {
+            return;
+        }
+
+        match received.message {
+            Message::Announce { header, announce } => self.on_announce(header, announce),
+            Message::Sync { header, origin_timestamp } => {
+                if Some(header.source_port) != self.parent {
+                    return;
+                }
+                self.servo.set_sync_interval(log_interval(header.log_interval, 0.125).as_secs_f64());
+                if header.two_step() {
+                    self.pending_sync = Some(PendingSync {
+                        sequence_id: header.sequence_id,
+                        system_ns: received.system_ns,
+                        correction_ns: header.correction_ns(),
+                    });
+                } else {
+                    self.on_sync_pair(origin_timestamp + header.correction_ns(), received.system_ns);
+                }
+            }
+            Message::FollowUp { header, precise_origin_timestamp } => {
+                if Some(header.source_port) != self.parent {
+                    return;
+                }
+                if let Some(sync) = self.pending_sync.take() {
+                    if sync.sequence_id == header.sequence_id {
+                        let t1 = precise_origin_timestamp + sync.correction_ns + header.correction_ns();
+                        self.on_sync_pair(t1, sync.system_ns);
+                    }
+                }
+            }
+            Message::DelayResp { header, receive_timestamp, requesting_port } => {
+                if Some(header.source_port) != self.parent || requesting_port != self.port_identity {
+                    return;
+                }
+                self.on_delay_resp(header, receive_timestamp - header.correction_ns());
+            }
+            Message::DelayReq { header, .. } => {
+                if self.master {
+                    let response = self.delay_resp(header, received.system_ns);
+                    self.send(&response, self.general_port);
+                }
+            }
+        }
+    }
+
+    fn on_announce(&mut self, header: Header, announce: Announce) {
+        // Messages that went through us are not a usable master
+        if announce.grandmaster_identity == self.port_identity.clock_identity {
+            return;
+        }
+
+        let now = Instant::now();
+        let entry = self.foreign_masters.entry(header.source_port).or_insert(ForeignMaster {
+            header,
+            announce,
+            count: 0,
+            last_seen: now,
+        });
+        entry.header = header;
+        entry.announce = announce;
+        entry.count = entry.count.saturating_add(1);
+        entry.last_seen = now;
+
+        self.select_master(now);
+    }
+
+    /// Best master clock algorithm: follow the best qualified foreign master
+    /// or become master when our own clock is better
+    fn select_master(&mut self, now: Instant) {
+        let best = self.foreign_masters
+            .values()
+            .filter(|m| m.count >= FOREIGN_MASTER_THRESHOLD)
+            .min_by_key(|m| m.dataset_key())
+            .copied();
+
+        let local_is_best = match (&self.local, &best) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(_), Some(best)) => self.local_key() < best.dataset_key(),
+        };
+
+        if local_is_best {
+            if self.master || now >= self.listen_until {
+                self.become_master(now);
+            }
+            return;
+        }
+
+        let Some(best) = best else {
+            if self.parent.take().is_some() {
+                warn!("⚠️  PTP master lost");
+                self.reset_sync();
+                *self.state.write() = PtpState::Listening;
+                let mut stats = self.stats.write();
+                stats.grandmaster_identity.clear();
+                stats.parent_port_identity.clear();
+            }
+            return;
+        };
+
+        if self.master {
+            info!("📡 PTP: better master {} on the network, leaving master role", best.header.source_port);
+            self.master = false;
+            *self.state.write() = PtpState::Listening;
+        }
+
+        if self.parent != Some(best.header.source_port) {
+            info!("📡 PTP master {} (grandmaster {}, class {}, priority {}/{})",
+                best.header.source_port,
+                best.announce.grandmaster_identity,
+                best.announce.grandmaster_clock_quality.clock_class,
+                best.announce.grandmaster_priority1,
+                best.announce.grandmaster_priority2);
+            self.parent = Some(best.header.source_port);
+            self.reset_sync();
+        }
+
+        let mut stats = self.stats.write();
+        stats.grandmaster_identity = best.announce.grandmaster_identity.to_string();
+        stats.parent_port_identity = best.header.source_port.to_string();
+        stats.grandmaster_priority1 = best.announce.grandmaster_priority1;
+        stats.grandmaster_priority2 = best.announce.grandmaster_priority2;
+        stats.clock_class = best.announce.grandmaster_clock_quality.clock_class;
+        stats.clock_accuracy = best.announce.grandmaster_clock_quality.clock_accuracy;
+        stats.steps_removed = best.announce.steps_removed.saturating_add(1);
+        if best.header.flags & FLAG_UTC_OFFSET_VALID != 0 {
+            stats.utc_offset_s = best.announce.current_utc_offset;
+        }
+    }
+
+    /// Our dataset in the comparison order of `ForeignMaster::dataset_key`
+    fn local_key(&self) -> (u8, u8, u8, u16, u8, ClockIdentity, u16, PortIdentity) {
+        let local = self.local.unwrap_or_default();
+        (
+            local.priority1,
+            local.quality.clock_class,
+            local.quality.clock_accuracy,
+            local.quality.offset_scaled_log_variance,
+            local.priority2,
+            self.port_identity.clock_identity,
+            0,
+            self.port_identity,
+        )
+    }
+
+    fn become_master(&mut self, now: Instant) {
+        let Some(local) = self.local else {
+            return;
+        };
+        if self.master {
+            return;
+        }
+
+        info!("👑 PTP: no better clock found, acting as grandmaster {}", self.port_identity.clock_identity);
+        self.master = true;
+        self.parent = None;
+        self.reset_sync();
+        self.last_sync = None;
+        self.next_announce = now;
+        self.next_sync = now;
+        self.offset_ns.store(0, Ordering::Relaxed);
+        *self.state.write() = PtpState::Master;
+
+        let mut stats = self.stats.write();
+        stats.offset_ns = 0;
+        stats.path_delay_ns = 0;
+        stats.grandmaster_identity = self.port_identity.clock_identity.to_string();
+        stats.parent_port_identity = self.port_identity.to_string();
+        stats.grandmaster_priority1 = local.priority1;
+        stats.grandmaster_priority2 = local.priority2;
+        stats.clock_class = local.quality.clock_class;
+        stats.clock_accuracy = local.quality.clock_accuracy;
+        stats.steps_removed = 0;
+    }
+
+    /// Forget measurements of the previous master
+    fn reset_sync(&mut self) {
+        self.servo.reset();
+        self.pending_sync = None;
+        self.pending_delay_req = None;
+        self.last_sync_pair = None;
+        self.delays.clear();
+        self.path_delay_ns = 0;
+    }
+
+    /// Offset measurement from a Sync (t1 = master send time, t2 = our receive time)
+    fn on_sync_pair(&mut self, t1: i64, t2_system_ns: i64) {
+        let t2 = self.clock.read().time_at(t2_system_ns);
+        let offset = t2 - t1 - self.path_delay_ns;
+
+        self.last_sync_pair = Some((t1, t2_system_ns));
+        self.last_sync = Some(Instant::now());
+
+        let frequency_ppb = {
+            let mut clock = self.clock.write();
+            match self.servo.sample(offset, t2_system_ns) {
+                ServoAction::Hold => {}
+                ServoAction::Step { offset_ns, frequency_ppb } => {
+                    info!("⏱️  PTP clock stepped by {} ns", -offset_ns);
+                    clock.step(t2_system_ns, offset_ns);
+                    clock.set_frequency(t2_system_ns, frequency_ppb);
+                }
+                ServoAction::Adjust { frequency_ppb } => clock.set_frequency(t2_system_ns, frequency_ppb),
+            }
+            clock.frequency_ppb()
+        };
+
+        self.offset_ns.store(offset, Ordering::Relaxed);
+        {
+            let mut stats = self.stats.write();
+            stats.clock_accuracy_ns = (offset - stats.offset_ns).unsigned_abs();
+            stats.offset_ns = offset;
+            stats.sync_count += 1;
+            stats.frequency_ppb = frequency_ppb;
+        }
+
+        if self.servo.is_locked() && *self.state.read() != PtpState::Slave {
+            info!("✅ PTP synchronized to master (offset: {} ns, path delay: {} ns)", offset, self.path_delay_ns);
+            *self.state.write() = PtpState::Slave;
+        }
+    }
+
+    fn on_delay_resp(&mut self, header: Header, t4: i64) {
+        let Some(request) = self.pending_delay_req else {
+            return;
+        };
+        if request.sequence_id != header.sequence_id {
+            return;
+        }
+        self.pending_delay_req = None;
+
+        // Master's logMinDelayReqInterval
+        self.delay_req_interval = log_interval(header.log_interval, 1.0);
+
+        let (t2, t3) = {
+            let clock = self.clock.read();
+            (clock.time_at(request.t2_system_ns), clock.time_at(request.system_ns))
+        };
+        let delay = ((t2 - request.t1) + (t4 - t3)) / 2;
+        if !(0..1_000_000_000).contains(&delay) {
+            debug!("PTP path delay sample {} ns discarded", delay);
+            return;
+        }
+
+        if self.delays.len() == DELAY_FILTER_LENGTH {
+            self.delays.pop_front();
+        }
+        self.delays.push_back(delay);
+        self.path_delay_ns = median(&self.delays);
+        self.stats.write().path_delay_ns = self.path_delay_ns;
+    }
+
+    /// Timers: master messages, Delay_Req, announce and sync timeouts
+    fn poll(&mut self, now: Instant) {
+        // Drop masters that stopped announcing
+        let before = self.foreign_masters.len();
+        self.foreign_masters.retain(|_, m| now.duration_since(m.last_seen) < m.announce_timeout());
+        if self.foreign_masters.len() != before || (!self.master && self.parent.is_none()) {
+            self.select_master(now);
+        }
+
+        if self.master {
+            if now >= self.next_announce {
+                self.send_announce();
+                self.next_announce = now + log_interval(LOG_ANNOUNCE_INTERVAL, 2.0);
+            }
+            if now >= self.next_sync {
+                self.send_sync();
+                self.next_sync = now + log_interval(LOG_SYNC_INTERVAL, 0.125);
+            }
+            return;
+        }
+
+        if let Some(last) = self.last_sync {
+            if now.duration_since(last) > SYNC_TIMEOUT && *self.state.read() == PtpState::Slave {
+                warn!("⚠️  PTP sync lost, entering holdover");
+                *self.state.write() = PtpState::Holdover;
+            }
+        }
+
+        if self.parent.is_some() && now >= self.next_delay_req {
+            if let Some((t1, t2_system_ns)) = self.last_sync_pair {
+                self.send_delay_req(t1, t2_system_ns);
+            }
+            // Randomised around the interval so slaves do not send in lockstep
+            self.next_delay_req = now + self.delay_req_interval.mul_f64(0.5 + rand::random::<f64>());
+        }
+    }
+
+    /// Next time `poll` has something to send
+    fn next_deadline(&self, now: Instant) -> Instant {
+        if self.master {
+            self.next_sync.min(self.next_announce)
+        } else if self.parent.is_some() {
+            self.next_delay_req
+        } else {
+            now + Duration::from_millis(100)
+        }
+    }
+
+    /// Send a message, returns its transmit time (system clock)
+    fn send(&self, message: &Message, port: u16) -> Option<i64> {
+        let socket = if port == self.event_port { &self.event_socket } else { &self.general_socket };
+        let bytes = message.to_bytes();
+        let system_ns = get_system_time_ns();
+        if let Err(e) = socket.send_to(&bytes, (PTP_PRIMARY_MULTICAST, port)) {
+            warn!("PTP {:?} send error: {}", message.header().message_type, e);
+            return None;
+        }
+
+        if port == self.event_port {
+            if let Some(tx_ns) = self.timestamper.tx_timestamp(socket, &bytes) {
+                return Some(tx_ns);
+            }
+        }
+        Some(system_ns)
+    }
+
+    fn send_delay_req(&mut self, t1: i64, t2_system_ns: i64) {
+        self.delay_req_sequence = self.delay_req_sequence.wrapping_add(1);
+        let header = Header::new(MessageType::DelayReq, self.domain, self.port_identity, self.delay_req_sequence);
+
+        let message = Message::DelayReq {
+            header,
+            origin_timestamp: self.clock.read().time_at(get_system_time_ns()),
+        };
+        if let Some(system_ns) = self.send(&message, self.event_port) {
+            self.pending_delay_req = Some(PendingDelayReq {
+                sequence_id: self.delay_req_sequence,
+                system_ns,
+                t1,
+                t2_system_ns,
+            });
+            self.stats.write().delay_req_count += 1;
+        }
+    }
+
+    /// Two-step Sync followed by its Follow_Up carrying the send time
+    fn send_sync(&mut self) {
+        self.sync_sequence = self.sync_sequence.wrapping_add(1);
+        let mut header = Header::new(MessageType::Sync, self.domain, self.port_identity, self.sync_sequence);
+        header.log_interval = LOG_SYNC_INTERVAL;
+        header.set_two_step(true);
+
+        let origin_timestamp = self.clock.read().time_at(get_system_time_ns());
+        let Some(system_ns) = self.send(&Message::Sync { header, origin_timestamp }, self.event_port) else {
+            return;
+        };
+
+        let mut header = Header::new(MessageType::FollowUp, self.domain, self.port_identity, self.sync_sequence);
+        header.log_interval = LOG_SYNC_INTERVAL;
+        let precise_origin_timestamp = self.clock.read().time_at(system_ns);
+        self.send(&Message::FollowUp { header, precise_origin_timestamp }, self.general_port);
+    }
+
+    fn send_announce(&mut self) {
+        if let Some(message) = self.announce() {
+            self.send(&message, self.general_port);
+        }
+    }
+
+    /// Announce of our clock as grandmaster
+    fn announce(&mut self) -> Option<Message> {
+        let local = self.local?;
+        self.announce_sequence = self.announce_sequence.wrapping_add(1);
+        let mut header = Header::new(MessageType::Announce, self.domain, self.port_identity, self.announce_sequence);
+        header.log_interval = LOG_ANNOUNCE_INTERVAL;
+        header.flags |= FLAG_PTP_TIMESCALE | FLAG_UTC_OFFSET_VALID;
+
+        Some(Message::Announce {
+            header,
+            announce: Announce {
+                current_utc_offset: UTC_OFFSET_S,
+                grandmaster_priority1: local.priority1,
+                grandmaster_clock_quality: local.quality,
+                grandmaster_priority2: local.priority2,
+                grandmaster_identity: self.port_identity.clock_identity,
+                steps_removed: 0,
+                time_source: TIME_SOURCE_INTERNAL_OSCILLATOR,
+            },
+        })
+    }
+
+    /// Delay_Resp for a slave's Delay_Req received at `system_ns`
+    fn delay_resp(&self, request: Header, system_ns: i64) -> Message {
+        let mut header = Header::new(MessageType::DelayResp, self.domain, self.port_identity, request.sequence_id);
+        header.correction = request.correction;
+        header.log_interval = LOG_MIN_DELAY_REQ_INTERVAL;
+
+        Message::DelayResp {
+            header,
+            receive_timestamp: self.clock.read().time_at(system_ns),
+            requesting_port: request.source_port,
+        }
+    }
+}
+
+/// Message interval from its log2 value (0x7F = not given)
+fn log_interval(log: i8, default_secs: f64) -> Duration {
+    if (-7..=6).contains(&log) {
+        Duration::from_secs_f64(2f64.powi(log as i32))
+    } else {
+        Duration::from_secs_f64(default_secs)
+    }
+}
+
+fn median(values: &VecDeque<i64>) -> i64 {
+    let mut sorted: Vec<i64> = values.iter().copied().collect();
+    sorted.sort_unstable();
+    sorted.get(sorted.len() / 2).copied().unwrap_or(0)
+}
+
+/// Clock identity from the interface's MAC address (random if unavailable)
+fn clock_identity_for(interface: &str) -> ClockIdentity {
+    let mac = std::fs::read_to_string(format!("/sys/class/net/{}/address", interface))
+        .ok()
+        .and_then(|s| {
+            let bytes: Vec<u8> = s.trim().split(':').filter_map(|b| u8::from_str_radix(b, 16).ok()).collect();
+            <[u8; 6]>::try_from(bytes).ok()
+        })
+        .filter(|mac| mac.iter().any(|&b| b != 0));
+
+    match mac {
+        Some(mac) => ClockIdentity::from_mac(mac),
+        None => {
+            warn!("No MAC address for {}, using a random PTP clock identity", interface);
+            ClockIdentity(rand::random())
+        }
+    }
+}
+
+/// Create a PTP multicast socket, joined on `interface` if given
+fn create_ptp_socket(port: u16, multicast_addr: Ipv4Addr, interface: Option<Ipv4Addr>) -> Result<UdpSocket> {
+    use socket2::{Socket, Domain, Type, Protocol};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+
+    // Allow address reuse
+    socket.set_reuse_address(true)?;
+
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+
+    // Bind to any interface on the PTP port
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
+    socket.bind(&addr.into())
+        .map_err(|e| anyhow!("Cannot open PTP port {}: {}", port, e))?;
+
+    // Join multicast group (and send from the same interface)
+    super::interface::join_multicast(&socket, multicast_addr, interface)?;
+
+    // Set multicast TTL
+    socket.set_multicast_ttl_v4(64)?;
+
+    Ok(socket.into())
+}
+
+/// Get current system time in nanoseconds since epoch
+fn get_system_time_ns() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_port(master_priority1: Option<u8>) -> PtpPort {
+        let mut clock = PtpClock::new("lo");
+        if let Some(priority1) = master_priority1 {
+            clock.set_master_capable(true, priority1);
+        }
+        PtpPort::new(
+            &clock,
+            PortIdentity { clock_identity: ClockIdentity([9; 8]), port_number: 1 },
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            Arc::new(Timestamper::user()),
+        )
+    }
+
+    fn announce_from(id: u8, priority1: u8, clock_class: u8) -> (Header, Announce) {
+        let source = PortIdentity { clock_identity: ClockIdentity([id; 8]), port_number: 1 };
+        let mut header = Header::new(MessageType::Announce, 0, source, 0);
+        header.log_interval = 1;
+        let announce = Announce {
+            current_utc_offset: 37,
+            grandmaster_priority1: priority1,
+            grandmaster_clock_quality: ClockQuality {
+                clock_class,
+                clock_accuracy: 0x21,
+                offset_scaled_log_variance: 0x4E5D,
+            },
+            grandmaster_priority2: 128,
+            grandmaster_identity: ClockIdentity([id; 8]),
+            steps_removed: 0,
+            time_source: 0x20,
+        };
+        (header, announce)
+    }
+
+    #[test]
+    fn test_ptp_clock_creation() {
+        let clock = PtpClock::new("eth0");
+        assert_eq!(clock.state(), PtpState::Initializing);
+        assert_eq!(clock.offset_ns(), 0);
+        assert!(!clock.is_synchronized());
+    }
+
+    #[test]
+    fn test_master_selection() {
+        let mut port = test_port(None);
+        let (h1, a1) = announce_from(1, 128, 248);
+        let (h2, a2) = announce_from(2, 128, 6);
+
+        // One announce is not enough to qualify
+        port.on_announce(h1, a1);
+        assert_eq!(port.parent, None);
+        port.on_announce(h1, a1);
+        assert_eq!(port.parent, Some(h1.source_port));
+
+        // Better clock class wins
+        port.on_announce(h2, a2);
+        port.on_announce(h2, a2);
+        assert_eq!(port.parent, Some(h2.source_port));
+
+        let stats = port.stats.read().clone();
+        assert_eq!(stats.grandmaster_identity, "02-02-02-02-02-02-02-02");
+        assert_eq!(stats.clock_class, 6);
+        assert_eq!(stats.steps_removed, 1);
+    }
+
+    #[test]
+    fn test_delay_request_response() {
+        let mut port = test_port(None);
+        let (header, announce) = announce_from(1, 128, 6);
+        port.on_announce(header, announce);
+        port.on_announce(header, announce);
+
+        // Master is 10 s ahead, path delay 50 µs:
+        // t1 = master send, t2 = t1 - 10 s + 50 µs (our clock)
+        let t1 = 1_000_000_000_000;
+        let t2 = t1 - 10_000_000_000 + 50_000;
+        port.on_sync_pair(t1, t2);
+
+        // t3 = our send (100 µs later), t4 = master receive
+        let t3 = t2 + 100_000;
+        let t4 = t3 + 10_000_000_000 + 50_000;
+        port.pending_delay_req = Some(PendingDelayReq { sequence_id: 5, system_ns: t3, t1, t2_system_ns: t2 });
+        let mut resp = Header::new(MessageType::DelayResp, 0, header.source_port, 5);
+        resp.log_interval = -2;
+        port.on_delay_resp(resp, t4);
+
+        assert_eq!(port.path_delay_ns, 50_000);
+        assert_eq!(port.stats.read().path_delay_ns, 50_000);
+        assert_eq!(port.delay_req_interval, Duration::from_millis(250));
+
+        // Second sync steps the clock onto the master, offset excludes the path delay
+        let t1 = t1 + 125_000_000;
+        port.on_sync_pair(t1, t2 + 125_000_000);
+        let corrected = port.clock.read().time_at(t2 + 125_000_000);
+        assert_eq!(corrected, t1 + 50_000);
+        assert!(port.servo.is_locked());
+        assert_eq!(*port.state.read(), PtpState::Slave);
+    }
+
+    #[test]
+    fn test_bmca_master_role() {
+        let mut port = test_port(Some(200));
+        let now = Instant::now();
+
+        // Listening period first
+        port.select_master(now);
+        assert!(!port.master);
+
+        // No master on the network: take the role
+        port.select_master(port.listen_until);
+        assert!(port.master);
+        assert_eq!(*port.state.read(), PtpState::Master);
+        let stats = port.stats.read().clone();
+        assert_eq!(stats.grandmaster_identity, "09-09-09-09-09-09-09-09");
+        assert_eq!(stats.grandmaster_priority1, 200);
+        assert_eq!(stats.steps_removed, 0);
+
+        // A worse clock does not take over
+        let (h1, a1) = announce_from(1, 250, 248);
+        port.on_announce(h1, a1);
+        port.on_announce(h1, a1);
+        assert!(port.master);
+        assert_eq!(port.parent, None);
+
+        // A better one does
+        let (h2, a2) = announce_from(2, 128, 248);
+        port.on_announce(h2, a2);
+        port.on_announce(h2, a2);
+        assert!(!port.master);
+        assert_eq!(port.parent, Some(h2.source_port));
+        assert_eq!(port.stats.read().grandmaster_priority1, 128);
+    }
+
+    #[test]
+    fn test_slave_only_never_master() {
+        let mut port = test_port(None);
+        port.select_master(port.listen_until + Duration::from_secs(10));
+        assert!(!port.master);
+        assert!(port.announce().is_none());
+    }
+
+    #[test]
+    fn test_master_messages() {
+        let mut port = test_port(Some(DEFAULT_MASTER_PRIORITY1));
+
+        let Some(Message::Announce { header, announce }) = port.announce() else {
+            panic!("no announce");
+        };
+        assert_eq!(header.log_interval, LOG_ANNOUNCE_INTERVAL);
+        assert_ne!(header.flags & FLAG_PTP_TIMESCALE, 0);
+        assert_eq!(announce.grandmaster_identity, ClockIdentity([9; 8]));
+        assert_eq!(announce.grandmaster_priority1, DEFAULT_MASTER_PRIORITY1);
+        assert_eq!(announce.grandmaster_clock_quality.clock_class, DEFAULT_CLOCK_CLASS);
+        assert_eq!(announce.current_utc_offset, UTC_OFFSET_S);
+
+        // Delay_Resp echoes sequence, correction and requesting port
+        let slave = PortIdentity { clock_identity: ClockIdentity([3; 8]), port_number: 1 };
+        let mut request = Header::new(MessageType::DelayReq, 0, slave, 42);
+        request.correction = 7 << 16;
+        let received_at = 1_000_000_000;
+        let response = port.delay_resp(request, received_at);
+
+        let bytes = response.to_bytes();
+        let Some(Message::DelayResp { header, receive_timestamp, requesting_port }) = Message::parse(&bytes) else {
+            panic!("no delay response");
+        };
+        assert_eq!(header.sequence_id, 42);
+        assert_eq!(header.correction_ns(), 7);
+        assert_eq!(header.source_port, port.port_identity);
+        assert_eq!(requesting_port, slave);
+        assert_eq!(receive_timestamp, received_at + UTC_OFFSET_S as i64 * 1_000_000_000);
+    }
+}
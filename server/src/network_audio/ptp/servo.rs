@@ -0,0 +1,213 @@
+//! PTP clock servo
+//!
+//! The PTP time is kept as a virtual clock on top of the system clock
+//! (offset plus frequency correction), so no privileges are needed to
+//! follow the grandmaster. A PI controller in the style of linuxptp steers
+//! the frequency; the first two samples step the clock and estimate the
+//! initial frequency error.
+
+/// Largest frequency correction (ppb)
+const MAX_FREQUENCY_PPB: f64 = 500_000.0;
+
+/// Offsets beyond this step the clock instead of slewing (ns)
+const STEP_THRESHOLD_NS: i64 = 1_000_000;
+
+/// PI constants (linuxptp defaults), scaled with the sync interval
+const KP_SCALE: f64 = 0.7;
+const KP_EXPONENT: f64 = -0.3;
+const KP_NORM_MAX: f64 = 0.7;
+const KI_SCALE: f64 = 0.3;
+const KI_EXPONENT: f64 = 0.4;
+const KI_NORM_MAX: f64 = 0.3;
+
+/// PTP time derived from the system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VirtualClock {
+    /// System time of the last rebase (ns)
+    base_system: i64,
+    /// PTP time at the last rebase (ns)
+    base_ptp: i64,
+    /// Frequency correction against the system clock (ppb)
+    frequency_ppb: f64,
+}
+
+impl VirtualClock {
+    /// Clock running `offset_ns` ahead of the system clock
+    pub fn with_offset(offset_ns: i64) -> Self {
+        Self {
+            base_ptp: offset_ns,
+            ..Self::default()
+        }
+    }
+
+    /// PTP time at the given system time
+    pub fn time_at(&self, system_ns: i64) -> i64 {
+        let elapsed = system_ns - self.base_system;
+        self.base_ptp + elapsed + (elapsed as f64 * self.frequency_ppb * 1e-9) as i64
+    }
+
+    pub fn frequency_ppb(&self) -> f64 {
+        self.frequency_ppb
+    }
+
+    fn rebase(&mut self, system_ns: i64) {
+        self.base_ptp = self.time_at(system_ns);
+        self.base_system = system_ns;
+    }
+
+    /// Step by `-offset_ns` (positive offset = we are ahead of the master)
+    pub fn step(&mut self, system_ns: i64, offset_ns: i64) {
+        self.rebase(system_ns);
+        self.base_ptp -= offset_ns;
+    }
+
+    pub fn set_frequency(&mut self, system_ns: i64, frequency_ppb: f64) {
+        self.rebase(system_ns);
+        self.frequency_ppb = frequency_ppb.clamp(-MAX_FREQUENCY_PPB, MAX_FREQUENCY_PPB);
+    }
+}
+
+/// Servo output for one offset sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServoAction {
+    /// Not enough samples yet
+    Hold,
+    /// Step the clock by the offset and set the frequency
+    Step { offset_ns: i64, frequency_ppb: f64 },
+    /// Set the frequency
+    Adjust { frequency_ppb: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ServoState {
+    Unlocked,
+    /// First sample taken (offset, local time)
+    Sampled(i64, i64),
+    Locked,
+}
+
+/// PI servo
+#[derive(Debug, Clone)]
+pub struct PiServo {
+    kp: f64,
+    ki: f64,
+    /// Integral term: frequency error of our clock (ppb, positive = fast)
+    drift_ppb: f64,
+    state: ServoState,
+}
+
+impl PiServo {
+    /// Servo for the given sync interval in seconds
+    pub fn new(sync_interval: f64) -> Self {
+        let mut servo = Self {
+            kp: 0.0,
+            ki: 0.0,
+            drift_ppb: 0.0,
+            state: ServoState::Unlocked,
+        };
+        servo.set_sync_interval(sync_interval);
+        servo
+    }
+
+    /// Adapt the constants to the master's sync interval
+    pub fn set_sync_interval(&mut self, interval: f64) {
+        let interval = interval.clamp(1.0 / 128.0, 16.0);
+        self.kp = (KP_SCALE * interval.powf(KP_EXPONENT)).min(KP_NORM_MAX / interval);
+        self.ki = (KI_SCALE * interval.powf(KI_EXPONENT)).min(KI_NORM_MAX / interval);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state == ServoState::Locked
+    }
+
+    /// Start over (keeps the frequency estimate)
+    pub fn reset(&mut self) {
+        self.state = ServoState::Unlocked;
+    }
+
+    /// Feed an offset sample (our time - master time) taken at `local_ns`
+    pub fn sample(&mut self, offset_ns: i64, local_ns: i64) -> ServoAction {
+        match self.state {
+            ServoState::Unlocked => {
+                self.state = ServoState::Sampled(offset_ns, local_ns);
+                ServoAction::Hold
+            }
+            ServoState::Sampled(first_offset, first_local) => {
+                let elapsed = local_ns - first_local;
+                if elapsed <= 0 {
+                    self.state = ServoState::Sampled(offset_ns, local_ns);
+                    return ServoAction::Hold;
+                }
+                self.drift_ppb = (self.drift_ppb + (offset_ns - first_offset) as f64 * 1e9 / elapsed as f64)
+                    .clamp(-MAX_FREQUENCY_PPB, MAX_FREQUENCY_PPB);
+                self.state = ServoState::Locked;
+                ServoAction::Step {
+                    offset_ns,
+                    frequency_ppb: -self.drift_ppb,
+                }
+            }
+            ServoState::Locked => {
+                if offset_ns.abs() > STEP_THRESHOLD_NS {
+                    self.state = ServoState::Unlocked;
+                    return ServoAction::Step {
+                        offset_ns,
+                        frequency_ppb: -self.drift_ppb,
+                    };
+                }
+                let ki_term = self.ki * offset_ns as f64;
+                let ppb = (self.kp * offset_ns as f64 + self.drift_ppb + ki_term)
+                    .clamp(-MAX_FREQUENCY_PPB, MAX_FREQUENCY_PPB);
+                self.drift_ppb = (self.drift_ppb + ki_term).clamp(-MAX_FREQUENCY_PPB, MAX_FREQUENCY_PPB);
+                ServoAction::Adjust { frequency_ppb: -ppb }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock_step_and_frequency() {
+        let mut clock = VirtualClock::default();
+        assert_eq!(clock.time_at(1_000), 1_000);
+
+        // We are 500 ns ahead: step back
+        clock.step(1_000, 500);
+        assert_eq!(clock.time_at(1_000), 500);
+
+        // +100 ppm runs 100 µs per second faster
+        clock.set_frequency(1_000, 100_000.0);
+        assert_eq!(clock.time_at(1_000_001_000), 500 + 1_000_000_000 + 100_000);
+    }
+
+    #[test]
+    fn test_servo_converges() {
+        // Our system clock runs 50 ppm fast and starts 37 s behind the master
+        let mut servo = PiServo::new(0.125);
+        let mut clock = VirtualClock::default();
+        let master_offset = 37_000_000_000i64;
+        let mut last_offset = 0;
+
+        for n in 1..400i64 {
+            let system = n * 125_000_000;
+            let master = master_offset + system - system * 50 / 1_000_000;
+            let offset = clock.time_at(system) - master;
+            last_offset = offset;
+
+            match servo.sample(offset, system) {
+                ServoAction::Hold => {}
+                ServoAction::Step { offset_ns, frequency_ppb } => {
+                    clock.step(system, offset_ns);
+                    clock.set_frequency(system, frequency_ppb);
+                }
+                ServoAction::Adjust { frequency_ppb } => clock.set_frequency(system, frequency_ppb),
+            }
+        }
+
+        assert!(servo.is_locked());
+        assert!(last_offset.abs() < 1_000, "offset {} ns", last_offset);
+        assert!((clock.frequency_ppb() + 50_000.0).abs() < 100.0, "frequency {} ppb", clock.frequency_ppb());
+    }
+}
@@ -0,0 +1,325 @@
+//! Kernel and hardware timestamps for PTP event messages (SO_TIMESTAMPING)
+//!
+//! Software timestamps are taken by the kernel when the packet passes the
+//! network stack and work on any Linux box. Hardware timestamps come from the
+//! NIC's PTP hardware clock (PHC, `/dev/ptpN`) and are converted to system
+//! time by sampling PHC and system clock around each conversion. If a mode is
+//! not available, the next lower one is used.
+
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::net::UdpSocket;
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use super::super::PtpTimestamping;
+use super::get_system_time_ns;
+
+/// How long to wait for the transmit timestamp of a sent message
+const TX_TIMESTAMP_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Control message buffer (SCM_TIMESTAMPING carries three timespecs)
+const CONTROL_LEN: usize = 256;
+
+const SOFTWARE_FLAGS: libc::c_uint = libc::SOF_TIMESTAMPING_RX_SOFTWARE
+    | libc::SOF_TIMESTAMPING_TX_SOFTWARE
+    | libc::SOF_TIMESTAMPING_SOFTWARE;
+
+const HARDWARE_FLAGS: libc::c_uint = libc::SOF_TIMESTAMPING_RX_HARDWARE
+    | libc::SOF_TIMESTAMPING_TX_HARDWARE
+    | libc::SOF_TIMESTAMPING_RAW_HARDWARE;
+
+/// Timestamp source of one socket
+#[derive(Debug)]
+pub struct Timestamper {
+    mode: PtpTimestamping,
+    phc: Option<Phc>,
+}
+
+impl Timestamper {
+    /// Enable timestamping on `socket`, falling back from the requested mode
+    pub fn new(socket: &UdpSocket, interface: &str, requested: PtpTimestamping) -> Self {
+        if matches!(requested, PtpTimestamping::Auto | PtpTimestamping::Hardware) {
+            match enable_hardware(socket, interface) {
+                Ok(phc) => {
+                    info!("    Timestamping: hardware ({})", phc.path);
+                    return Self { mode: PtpTimestamping::Hardware, phc: Some(phc) };
+                }
+                Err(e) if requested == PtpTimestamping::Hardware => {
+                    warn!("Hardware timestamping on {} not available ({}), falling back", interface, e);
+                }
+                Err(_) => {}
+            }
+        }
+
+        if requested != PtpTimestamping::User {
+            match set_timestamping(socket, SOFTWARE_FLAGS) {
+                Ok(()) => {
+                    info!("    Timestamping: kernel software");
+                    return Self { mode: PtpTimestamping::Software, phc: None };
+                }
+                Err(e) => warn!("Kernel timestamping not available ({}), using user space timestamps", e),
+            }
+        }
+
+        info!("    Timestamping: user space");
+        Self::user()
+    }
+
+    /// Timestamps taken in user space after receive/before send
+    pub fn user() -> Self {
+        Self { mode: PtpTimestamping::User, phc: None }
+    }
+
+    pub fn mode(&self) -> PtpTimestamping {
+        self.mode
+    }
+
+    /// Receive a message with its receive time (system clock, ns)
+    pub fn recv(&self, socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, i64)> {
+        if self.mode == PtpTimestamping::User {
+            let (len, _) = socket.recv_from(buf)?;
+            return Ok((len, get_system_time_ns()));
+        }
+
+        let (len, timestamp) = recv_msg(socket, buf, 0)?;
+        let system_ns = timestamp
+            .and_then(|ts| self.to_system(ts))
+            .unwrap_or_else(get_system_time_ns);
+        Ok((len, system_ns))
+    }
+
+    /// Transmit time (system clock, ns) of the message `sent` on `socket`
+    ///
+    /// Returns `None` in user space mode or when no timestamp arrives in time.
+    pub fn tx_timestamp(&self, socket: &UdpSocket, sent: &[u8]) -> Option<i64> {
+        if self.mode == PtpTimestamping::User {
+            return None;
+        }
+
+        let deadline = Instant::now() + TX_TIMESTAMP_TIMEOUT;
+        let mut buf = [0u8; 512];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || !wait_error_queue(socket, remaining) {
+                return None;
+            }
+            match recv_msg(socket, &mut buf, libc::MSG_ERRQUEUE) {
+                Ok((len, Some(ts))) => {
+                    // The packet is looped back with all headers, the message is at its end
+                    if buf[..len].ends_with(sent) {
+                        return self.to_system(ts);
+                    }
+                }
+                Ok((_, None)) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => return None,
+            }
+        }
+    }
+
+    fn to_system(&self, ts: RawTimestamp) -> Option<i64> {
+        match (self.mode, ts) {
+            (PtpTimestamping::Software, RawTimestamp { software: Some(ns), .. }) => Some(ns),
+            (PtpTimestamping::Hardware, RawTimestamp { hardware: Some(ns), .. }) => {
+                self.phc.as_ref().and_then(|phc| phc.to_system(ns))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Timestamps of one SCM_TIMESTAMPING control message
+#[derive(Debug, Clone, Copy, Default)]
+struct RawTimestamp {
+    software: Option<i64>,
+    hardware: Option<i64>,
+}
+
+/// PTP hardware clock of the interface
+#[derive(Debug)]
+struct Phc {
+    path: String,
+    /// Keeps the dynamic clock id valid
+    file: File,
+}
+
+impl Phc {
+    /// PHC of the interface (`/sys/class/net/<if>/device/ptp/ptpN`)
+    fn for_interface(interface: &str) -> io::Result<Self> {
+        let dir = format!("/sys/class/net/{}/device/ptp", interface);
+        let name = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .find(|name| name.starts_with("ptp"))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no PTP hardware clock"))?;
+
+        let path = format!("/dev/{}", name);
+        let file = File::open(&path)?;
+        Ok(Self { path, file })
+    }
+
+    /// Dynamic POSIX clock id (FD_TO_CLOCKID)
+    fn clock_id(&self) -> libc::clockid_t {
+        ((!self.file.as_raw_fd()) << 3) | 3
+    }
+
+    /// Convert a PHC timestamp to system time
+    fn to_system(&self, phc_ns: i64) -> Option<i64> {
+        // System time sampled around the PHC read, the midpoint matches the PHC sample
+        let before = clock_ns(libc::CLOCK_REALTIME)?;
+        let phc_now = clock_ns(self.clock_id())?;
+        let after = clock_ns(libc::CLOCK_REALTIME)?;
+        let offset = phc_now - before - (after - before) / 2;
+        Some(phc_ns - offset)
+    }
+}
+
+fn clock_ns(clock_id: libc::clockid_t) -> Option<i64> {
+    let mut ts: libc::timespec = unsafe { mem::zeroed() };
+    let result = unsafe { libc::clock_gettime(clock_id, &mut ts) };
+    (result == 0).then(|| nanos(&ts))
+}
+
+fn set_timestamping(socket: &UdpSocket, flags: libc::c_uint) -> io::Result<()> {
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_TIMESTAMPING,
+            &flags as *const libc::c_uint as *const libc::c_void,
+            mem::size_of::<libc::c_uint>() as libc::socklen_t,
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Switch the NIC to PTP timestamping (needs CAP_NET_ADMIN) and enable it on the socket
+fn enable_hardware(socket: &UdpSocket, interface: &str) -> io::Result<Phc> {
+    let phc = Phc::for_interface(interface)?;
+    if interface.len() >= libc::IFNAMSIZ {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "interface name too long"));
+    }
+
+    let mut config = libc::hwtstamp_config {
+        flags: 0,
+        tx_type: libc::HWTSTAMP_TX_ON as libc::c_int,
+        rx_filter: libc::HWTSTAMP_FILTER_PTP_V2_L4_EVENT as libc::c_int,
+    };
+    let mut request: libc::ifreq = unsafe { mem::zeroed() };
+    for (dst, src) in request.ifr_name.iter_mut().zip(interface.bytes()) {
+        *dst = src as libc::c_char;
+    }
+    request.ifr_ifru.ifru_data = &mut config as *mut libc::hwtstamp_config as *mut libc::c_char;
+
+    let result = unsafe { libc::ioctl(socket.as_raw_fd(), libc::SIOCSHWTSTAMP, &mut request) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    set_timestamping(socket, HARDWARE_FLAGS)?;
+    Ok(phc)
+}
+
+/// Wait until the socket's error queue has an entry
+fn wait_error_queue(socket: &UdpSocket, timeout: Duration) -> bool {
+    let mut pollfd = libc::pollfd {
+        fd: socket.as_raw_fd(),
+        events: 0, // POLLERR is always reported
+        revents: 0,
+    };
+    let timeout_ms = timeout.as_millis().clamp(1, i32::MAX as u128) as libc::c_int;
+    let result = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+    result > 0 && pollfd.revents & libc::POLLERR != 0
+}
+
+/// recvmsg with SCM_TIMESTAMPING control message
+fn recv_msg(socket: &UdpSocket, buf: &mut [u8], flags: libc::c_int) -> io::Result<(usize, Option<RawTimestamp>)> {
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    // u64 backing store keeps the control buffer aligned for cmsghdr
+    let mut control = [0u64; CONTROL_LEN / 8];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = CONTROL_LEN as _;
+
+    let len = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, flags) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut timestamp = None;
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_TIMESTAMPING {
+                // struct scm_timestamping: software, (deprecated), raw hardware
+                let ts = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const [libc::timespec; 3]);
+                timestamp = Some(RawTimestamp {
+                    software: timespec_ns(&ts[0]),
+                    hardware: timespec_ns(&ts[2]),
+                });
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    Ok((len as usize, timestamp))
+}
+
+fn nanos(ts: &libc::timespec) -> i64 {
+    ts.tv_sec * 1_000_000_000 + ts.tv_nsec
+}
+
+/// Nanoseconds of a timespec, `None` if zero (not filled)
+fn timespec_ns(ts: &libc::timespec) -> Option<i64> {
+    let ns = nanos(ts);
+    (ns != 0).then_some(ns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_software_timestamps_on_loopback() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let timestamper = Timestamper::new(&receiver, "lo", PtpTimestamping::Software);
+        if timestamper.mode() != PtpTimestamping::Software {
+            // Kernel without SO_TIMESTAMPING (e.g. restricted sandbox)
+            return;
+        }
+        receiver.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let before = get_system_time_ns();
+        sender.send_to(b"ptp", receiver.local_addr().unwrap()).unwrap();
+
+        let mut buf = [0u8; 64];
+        let (len, received_at) = timestamper.recv(&receiver, &mut buf).unwrap();
+        let after = get_system_time_ns();
+        assert_eq!(&buf[..len], b"ptp");
+        assert!(received_at >= before && received_at <= after, "{} not in {}..{}", received_at, before, after);
+    }
+
+    #[test]
+    fn test_user_mode_fallback() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let timestamper = Timestamper::new(&socket, "lo", PtpTimestamping::User);
+        assert_eq!(timestamper.mode(), PtpTimestamping::User);
+
+        // Loopback has no PHC
+        let timestamper = Timestamper::new(&socket, "lo", PtpTimestamping::Hardware);
+        assert_ne!(timestamper.mode(), PtpTimestamping::Hardware);
+    }
+}
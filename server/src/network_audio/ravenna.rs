@@ -0,0 +1,493 @@
+//! RAVENNA session discovery (mDNS) and SDP exchange (RTSP)
+//!
+//! RAVENNA devices register each session as `_ravenna_session._sub._rtsp._tcp`
+//! service; its SDP is fetched with an RTSP DESCRIBE of
+//! `rtsp://<host>:<port>/by-name/<session name>`. Our own streams are
+//! published the same way, served by a minimal RTSP server that answers
+//! OPTIONS and DESCRIBE.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+use anyhow::{Result, anyhow, bail};
+use mdns_sd::{IfKind, ServiceDaemon, ServiceEvent, ServiceInfo};
+use parking_lot::RwLock;
+use tokio::sync::broadcast;
+use tracing::{info, warn, debug};
+
+use super::sap::{Aes67Stream, SapEvent};
+
+/// mDNS service type of RAVENNA sessions
+pub const RAVENNA_SESSION_SERVICE: &str = "_ravenna_session._sub._rtsp._tcp.local.";
+
+/// Service type the instance names are registered under
+const RTSP_SERVICE: &str = "_rtsp._tcp.local.";
+
+/// Timeout for RTSP connections and requests
+const RTSP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Idle connections to our RTSP server are closed after this time
+const RTSP_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Largest accepted SDP
+const MAX_SDP_SIZE: usize = 64 * 1024;
+
+/// Clients served at once by our RTSP server, more are turned away
+const MAX_RTSP_CONNECTIONS: usize = 8;
+
+/// SDP of one of our sessions by session name or id
+pub type SessionLookup = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// RAVENNA discovery and publication of sessions
+pub struct RavennaDiscovery {
+    daemon: ServiceDaemon,
+    running: Arc<AtomicBool>,
+    /// Port of our RTSP server
+    rtsp_port: u16,
+    /// mDNS host name (`<host>.local.`)
+    host_name: String,
+    /// mDNS full names of our published sessions by session id
+    published: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl RavennaDiscovery {
+    /// Start browsing for RAVENNA sessions and serving ours
+    ///
+    /// Discovered sessions are kept in `streams` by their mDNS name and
+    /// reported as `SapEvent`s. With an `interface`, mDNS and RTSP only use
+    /// the interface with that address.
+    pub fn start(
+        streams: Arc<RwLock<HashMap<String, Aes67Stream>>>,
+        events: broadcast::Sender<SapEvent>,
+        lookup: SessionLookup,
+        interface: Option<Ipv4Addr>,
+    ) -> Result<Self> {
+        let running = Arc::new(AtomicBool::new(true));
+        let bind = SocketAddr::new(IpAddr::V4(interface.unwrap_or(Ipv4Addr::UNSPECIFIED)), 0);
+        let rtsp = start_rtsp_server(bind, lookup, running.clone())?;
+
+        let daemon = ServiceDaemon::new()?;
+        if let Some(interface) = interface {
+            daemon.disable_interface(IfKind::All)?;
+            daemon.enable_interface(IpAddr::V4(interface))?;
+        }
+        let receiver = daemon.browse(RAVENNA_SESSION_SERVICE)?;
+        let published = Arc::new(RwLock::new(HashMap::new()));
+
+        // SDPs are fetched on their own thread, a slow device does not hold up the mDNS events
+        let (updates, pending) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("ravenna-describe".to_string())
+            .spawn(move || run_sessions(pending, streams, events))?;
+        let browser_running = running.clone();
+        let browser_published = published.clone();
+        std::thread::Builder::new()
+            .name("ravenna-browse".to_string())
+            .spawn(move || run_browser(receiver, browser_running, updates, browser_published))?;
+
+        let host_name = hostname::get()
+            .map(|h| h.to_string_lossy().to_string())
+            .unwrap_or_else(|_| "audiomultiverse".to_string());
+
+        info!("📢 RAVENNA discovery started (RTSP on port {})", rtsp.port());
+
+        Ok(Self {
+            daemon,
+            running,
+            rtsp_port: rtsp.port(),
+            host_name: format!("{}.local.", host_name),
+            published,
+        })
+    }
+
+    /// Publish one of our sessions via mDNS
+    pub fn publish(&self, name: &str, session_id: &str) -> Result<()> {
+        self.unpublish(session_id);
+
+        let service = ServiceInfo::new(
+            RAVENNA_SESSION_SERVICE,
+            name,
+            &self.host_name,
+            (),
+            self.rtsp_port,
+            HashMap::<String, String>::new(),
+        )?.enable_addr_auto();
+        let fullname = service.get_fullname().to_string();
+        self.daemon.register(service)?;
+        self.published.write().insert(session_id.to_string(), fullname);
+
+        info!("📢 Published RAVENNA session: {}", name);
+        Ok(())
+    }
+
+    /// Withdraw a published session
+    pub fn unpublish(&self, session_id: &str) {
+        if let Some(fullname) = self.published.write().remove(session_id) {
+            if let Err(e) = self.daemon.unregister(&fullname) {
+                warn!("Unpublishing RAVENNA session {} failed: {}", fullname, e);
+            }
+        }
+    }
+
+    /// Stop browsing and serving
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+        for (_, fullname) in self.published.write().drain() {
+            let _ = self.daemon.unregister(&fullname);
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Change of a RAVENNA session, handled in the order of the mDNS events
+enum SessionUpdate {
+    /// Resolved: fetch its SDP from the RTSP server at `address`
+    Resolved { fullname: String, address: SocketAddr },
+    Removed { fullname: String },
+}
+
+/// Handle mDNS events of RAVENNA sessions
+fn run_browser(
+    receiver: mdns_sd::Receiver<ServiceEvent>,
+    running: Arc<AtomicBool>,
+    updates: mpsc::Sender<SessionUpdate>,
+    published: Arc<RwLock<HashMap<String, String>>>,
+) {
+    while running.load(Ordering::Relaxed) {
+        let event = match receiver.recv_timeout(Duration::from_millis(500)) {
+            Ok(event) => event,
+            Err(_) if receiver.is_disconnected() => break,
+            Err(_) => continue,
+        };
+
+        let update = match event {
+            ServiceEvent::ServiceResolved(service) => {
+                let fullname = service.get_fullname().to_string();
+                // Our own sessions
+                if published.read().values().any(|name| *name == fullname) {
+                    continue;
+                }
+                let Some(address) = service.get_addresses_v4().into_iter().next().copied() else {
+                    debug!("RAVENNA: {} has no IPv4 address", fullname);
+                    continue;
+                };
+                SessionUpdate::Resolved { fullname, address: SocketAddr::new(IpAddr::V4(address), service.get_port()) }
+            }
+            ServiceEvent::ServiceRemoved(_, fullname) => SessionUpdate::Removed { fullname },
+            _ => continue,
+        };
+        if updates.send(update).is_err() {
+            break;
+        }
+    }
+
+    info!("RAVENNA browser stopped");
+}
+
+/// Fetch the SDPs of resolved sessions and report changes
+///
+/// Ends when the browser stops.
+fn run_sessions(
+    updates: mpsc::Receiver<SessionUpdate>,
+    streams: Arc<RwLock<HashMap<String, Aes67Stream>>>,
+    events: broadcast::Sender<SapEvent>,
+) {
+    for update in updates {
+        match update {
+            SessionUpdate::Resolved { fullname, address } => {
+                let name = session_name(&fullname);
+                let path = format!("/by-name/{}", percent_encode(name));
+                match describe(address, &path).and_then(|sdp| Aes67Stream::from_sdp(&sdp)) {
+                    Ok(stream) => {
+                        debug!("RAVENNA: Discovered session {} at {}", stream.name, address.ip());
+                        let previous = streams.write().insert(fullname, stream.clone());
+                        let _ = events.send(match previous {
+                            Some(_) => SapEvent::Updated(stream),
+                            None => SapEvent::Discovered(stream),
+                        });
+                    }
+                    Err(e) => warn!("RAVENNA: Session '{}' at {} not usable: {}", name, address.ip(), e),
+                }
+            }
+            SessionUpdate::Removed { fullname } => {
+                if let Some(stream) = streams.write().remove(&fullname) {
+                    debug!("RAVENNA: Session {} removed", stream.name);
+                    let _ = events.send(SapEvent::Removed { session_id: stream.session_id });
+                }
+            }
+        }
+    }
+}
+
+/// Session name from the mDNS full name (`<name>._rtsp._tcp.local.`)
+fn session_name(fullname: &str) -> &str {
+    fullname.strip_suffix(RTSP_SERVICE)
+        .and_then(|name| name.strip_suffix('.'))
+        .unwrap_or(fullname)
+}
+
+/// Fetch an SDP with RTSP DESCRIBE
+pub fn describe(address: SocketAddr, path: &str) -> Result<String> {
+    let mut stream = TcpStream::connect_timeout(&address, RTSP_TIMEOUT)?;
+    stream.set_read_timeout(Some(RTSP_TIMEOUT))?;
+    stream.set_write_timeout(Some(RTSP_TIMEOUT))?;
+
+    let url = format!("rtsp://{}{}", address, path);
+    write!(stream, "DESCRIBE {} RTSP/1.0\r\nCSeq: 1\r\nAccept: application/sdp\r\n\r\n", url)?;
+
+    let mut reader = BufReader::new(stream);
+    let response = read_message(&mut reader)?
+        .ok_or_else(|| anyhow!("{} closed the connection", address))?;
+    let status = response.start.splitn(3, ' ').collect::<Vec<_>>();
+    match status[..] {
+        ["RTSP/1.0", "200", ..] => {}
+        [_, code, reason] => bail!("RTSP DESCRIBE {} failed: {} {}", url, code, reason),
+        _ => bail!("invalid RTSP response from {}: '{}'", address, response.start),
+    }
+
+    let length: usize = response.header("Content-Length")
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| anyhow!("RTSP response from {} has no Content-Length", address))?;
+    if length > MAX_SDP_SIZE {
+        bail!("SDP from {} too large ({} bytes)", address, length);
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Start line and headers of an RTSP request or response
+#[derive(Debug)]
+struct RtspMessage {
+    start: String,
+    headers: Vec<(String, String)>,
+}
+
+impl RtspMessage {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Read start line and headers, None at end of stream
+fn read_message(reader: &mut impl BufRead) -> Result<Option<RtspMessage>> {
+    let mut start = String::new();
+    // Skip empty lines between messages
+    while start.trim().is_empty() {
+        start.clear();
+        if reader.read_line(&mut start)? == 0 {
+            return Ok(None);
+        }
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(Some(RtspMessage { start: start.trim().to_string(), headers }))
+}
+
+/// Serve our SDPs over RTSP, returns the bound address
+///
+/// Every client gets its own thread, at most `MAX_RTSP_CONNECTIONS` at once.
+fn start_rtsp_server(bind: SocketAddr, lookup: SessionLookup, running: Arc<AtomicBool>) -> Result<SocketAddr> {
+    let listener = TcpListener::bind(bind)?;
+    listener.set_nonblocking(true)?;
+    let address = listener.local_addr()?;
+    let connections = Arc::new(AtomicUsize::new(0));
+
+    std::thread::Builder::new()
+        .name("ravenna-rtsp".to_string())
+        .spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        if connections.fetch_add(1, Ordering::Relaxed) >= MAX_RTSP_CONNECTIONS {
+                            connections.fetch_sub(1, Ordering::Relaxed);
+                            debug!("RTSP connection from {} refused, {} clients connected", peer, MAX_RTSP_CONNECTIONS);
+                            continue;
+                        }
+                        let lookup = lookup.clone();
+                        let client = connections.clone();
+                        let spawned = std::thread::Builder::new()
+                            .name("ravenna-rtsp-client".to_string())
+                            .spawn(move || {
+                                if let Err(e) = serve_connection(stream, &lookup) {
+                                    debug!("RTSP connection from {} closed: {}", peer, e);
+                                }
+                                client.fetch_sub(1, Ordering::Relaxed);
+                            });
+                        if let Err(e) = spawned {
+                            warn!("RTSP connection from {} not served: {}", peer, e);
+                            connections.fetch_sub(1, Ordering::Relaxed);
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(e) => warn!("RTSP accept failed: {}", e),
+                }
+            }
+        })?;
+
+    Ok(address)
+}
+
+/// Answer requests of one RTSP client until it disconnects
+fn serve_connection(stream: TcpStream, lookup: &SessionLookup) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(RTSP_IDLE_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    while let Some(request) = read_message(&mut reader)? {
+        writer.write_all(&respond(&request, lookup))?;
+    }
+    Ok(())
+}
+
+/// Response to an RTSP request
+fn respond(request: &RtspMessage, lookup: &SessionLookup) -> Vec<u8> {
+    let cseq = request.header("CSeq").unwrap_or("0");
+    let mut parts = request.start.split_whitespace();
+    let (method, url) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+    match method {
+        "OPTIONS" => format!("RTSP/1.0 200 OK\r\nCSeq: {}\r\nPublic: OPTIONS, DESCRIBE\r\n\r\n", cseq).into_bytes(),
+        "DESCRIBE" => {
+            let sdp = session_key(url).and_then(|key| lookup(&key));
+            match sdp {
+                Some(sdp) => format!(
+                    "RTSP/1.0 200 OK\r\nCSeq: {}\r\nContent-Base: {}/\r\nContent-Type: application/sdp\r\nContent-Length: {}\r\n\r\n{}",
+                    cseq, url, sdp.len(), sdp
+                ).into_bytes(),
+                None => format!("RTSP/1.0 404 Not Found\r\nCSeq: {}\r\n\r\n", cseq).into_bytes(),
+            }
+        }
+        _ => format!("RTSP/1.0 501 Not Implemented\r\nCSeq: {}\r\n\r\n", cseq).into_bytes(),
+    }
+}
+
+/// Session name or id from `rtsp://host:port/by-name/<name>` or `/by-id/<id>`
+fn session_key(url: &str) -> Option<String> {
+    let path = match url.strip_prefix("rtsp://") {
+        Some(rest) => &rest[rest.find('/')?..],
+        None => url,
+    };
+    let key = path.strip_prefix("/by-name/").or_else(|| path.strip_prefix("/by-id/"))?;
+    Some(percent_decode(key.trim_end_matches('/')))
+}
+
+/// Percent-encode a session name for an RTSP URL
+fn percent_encode(name: &str) -> String {
+    name.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDP: &str = "v=0\r\no=- 99 1 IN IP4 127.0.0.1\r\ns=Main Out\r\nc=IN IP4 239.69.9.9/32\r\nt=0 0\r\n\
+        m=audio 5004 RTP/AVP 98\r\na=rtpmap:98 L24/48000/2\r\n";
+
+    #[test]
+    fn test_session_names() {
+        assert_eq!(session_name("Main Out._rtsp._tcp.local."), "Main Out");
+        assert_eq!(percent_encode("Main Out/1"), "Main%20Out%2F1");
+        assert_eq!(percent_decode("Main%20Out%2F1"), "Main Out/1");
+        assert_eq!(session_key("rtsp://10.0.0.1:9090/by-name/Main%20Out"), Some("Main Out".to_string()));
+        assert_eq!(session_key("/by-id/-_99"), Some("-_99".to_string()));
+        assert_eq!(session_key("rtsp://10.0.0.1/other"), None);
+    }
+
+    #[test]
+    fn test_rtsp_describe() {
+        let lookup: SessionLookup = Arc::new(|key| (key == "Main Out").then(|| SDP.to_string()));
+        let running = Arc::new(AtomicBool::new(true));
+        let address = start_rtsp_server("127.0.0.1:0".parse().unwrap(), lookup, running.clone()).unwrap();
+
+        let sdp = describe(address, "/by-name/Main%20Out").unwrap();
+        assert_eq!(sdp, SDP);
+        let stream = Aes67Stream::from_sdp(&sdp).unwrap();
+        assert_eq!(stream.name, "Main Out");
+
+        let error = describe(address, "/by-name/Unknown").unwrap_err().to_string();
+        assert!(error.contains("404"), "{}", error);
+        running.store(false, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_rtsp_connection_limit() {
+        let lookup: SessionLookup = Arc::new(|_| Some(SDP.to_string()));
+        let running = Arc::new(AtomicBool::new(true));
+        let address = start_rtsp_server("127.0.0.1:0".parse().unwrap(), lookup, running.clone()).unwrap();
+
+        // Idle clients take all connections
+        let mut idle: Vec<TcpStream> = (0..MAX_RTSP_CONNECTIONS).map(|_| TcpStream::connect(address).unwrap()).collect();
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(describe(address, "/by-name/Main%20Out").is_err());
+
+        // A slot becomes free when a client leaves
+        idle.pop();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        let mut result = describe(address, "/by-name/Main%20Out");
+        while result.is_err() && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(100));
+            result = describe(address, "/by-name/Main%20Out");
+        }
+        assert_eq!(result.unwrap(), SDP);
+        running.store(false, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_rtsp_options() {
+        let lookup: SessionLookup = Arc::new(|_| None);
+        let request = RtspMessage {
+            start: "OPTIONS rtsp://10.0.0.1/by-name/x RTSP/1.0".to_string(),
+            headers: vec![("CSeq".to_string(), "7".to_string())],
+        };
+        let response = String::from_utf8(respond(&request, &lookup)).unwrap();
+        assert!(response.starts_with("RTSP/1.0 200 OK\r\nCSeq: 7\r\n"));
+        assert!(response.contains("Public: OPTIONS, DESCRIBE"));
+
+        let request = RtspMessage { start: "SETUP rtsp://10.0.0.1/x RTSP/1.0".to_string(), headers: Vec::new() };
+        assert!(String::from_utf8(respond(&request, &lookup)).unwrap().starts_with("RTSP/1.0 501"));
+    }
+}
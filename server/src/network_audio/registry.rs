@@ -0,0 +1,282 @@
+//! Backend registry
+//!
+//! Network audio backends are created by name from registered factories,
+//! selected by `network_audio.backend` in the config. Several backends run
+//! at once: the engine mixes what all of them receive into the mixer inputs
+//! and hands the mixer outputs to each of them, the API lists every backend
+//! with its devices.
+
+use std::sync::Arc;
+use anyhow::{Result, anyhow};
+use parking_lot::RwLock;
+use serde::Serialize;
+use tracing::{info, warn};
+
+#[cfg(feature = "dante")]
+use super::backend::DanteBackend;
+use super::backend::{AudioNetworkBackend, Aes67Backend, Aes67Config, NetworkDevice};
+use super::loopback::LoopbackBackend;
+use super::{Aes67Subscriptions, Aes67TxStreams, PtpClock, SapDiscovery};
+use crate::audio::DriftMonitor;
+
+/// A running backend, shared by engine, audio callback and API
+pub type SharedBackend = Arc<RwLock<Box<dyn AudioNetworkBackend>>>;
+
+/// Creates a backend (not yet initialized)
+pub type BackendFactory = fn(&BackendContext) -> Result<Box<dyn AudioNetworkBackend>>;
+
+/// Settings the factories create their backends from
+#[derive(Clone)]
+pub struct BackendContext {
+    /// Engine sample rate
+    pub sample_rate: u32,
+    /// AES67 settings (interface, PTP, streams, link offset)
+    pub aes67: Aes67Config,
+    /// Channels of the virtual loopback
+    pub virtual_channels: usize,
+    /// Clock drift of the local device, for resampling received and sent streams
+    pub drift_monitor: Arc<DriftMonitor>,
+}
+
+/// Named backend factories
+pub struct BackendRegistry {
+    factories: Vec<(&'static str, BackendFactory)>,
+}
+
+impl BackendRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self { factories: Vec::new() }
+    }
+
+    /// Registry with the built-in backends: "aes67", "virtual" and, with
+    /// the `dante` feature, "dante"
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register("aes67", |context| {
+            let mut backend = Aes67Backend::with_config(context.aes67.clone())?;
+            backend.set_drift_monitor(context.drift_monitor.clone());
+            Ok(Box::new(backend))
+        });
+        registry.register("virtual", |context| {
+            Ok(Box::new(LoopbackBackend::new(context.virtual_channels, context.sample_rate)))
+        });
+        #[cfg(feature = "dante")]
+        registry.register("dante", |_| Ok(Box::new(DanteBackend::new())));
+        registry
+    }
+
+    /// Register a factory under a name, replacing one of the same name
+    pub fn register(&mut self, name: &'static str, factory: BackendFactory) {
+        self.factories.retain(|(existing, _)| *existing != name);
+        self.factories.push((name, factory));
+    }
+
+    /// Names of the registered backends
+    pub fn names(&self) -> Vec<&'static str> {
+        self.factories.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Is a backend of this name registered? (case-insensitive)
+    pub fn contains(&self, name: &str) -> bool {
+        self.factory(name).is_some()
+    }
+
+    fn factory(&self, name: &str) -> Option<&(&'static str, BackendFactory)> {
+        self.factories.iter().find(|(registered, _)| registered.eq_ignore_ascii_case(name))
+    }
+
+    /// Create and initialize the named backends
+    ///
+    /// An unknown name is an error. A backend that fails to start is left
+    /// out with a warning, the others run anyway; a name given twice starts
+    /// one backend.
+    pub fn start(&self, names: &[String], context: &BackendContext) -> Result<NetworkBackends> {
+        let mut selected = Vec::new();
+        for name in names {
+            let (name, factory) = self.factory(name)
+                .ok_or_else(|| anyhow!("Unknown network audio backend '{}' (available: {})", name, self.names().join(", ")))?;
+            if !selected.iter().any(|(existing, _)| existing == name) {
+                selected.push((*name, *factory));
+            }
+        }
+
+        let mut backends = NetworkBackends::default();
+        for (name, factory) in selected {
+            let started = factory(context).and_then(|mut backend| {
+                backend.init()?;
+                Ok(backend)
+            });
+            match started {
+                Ok(backend) => {
+                    info!("🌐 Network audio backend '{}' running", name);
+                    backends.add(name, backend);
+                }
+                Err(e) => warn!("Network audio backend '{}' could not be started: {}", name, e),
+            }
+        }
+        Ok(backends)
+    }
+}
+
+impl Default for BackendRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+/// The running backends with their registry names
+#[derive(Clone, Default)]
+pub struct NetworkBackends {
+    backends: Vec<(&'static str, SharedBackend)>,
+}
+
+impl NetworkBackends {
+    pub fn is_empty(&self) -> bool {
+        self.backends.is_empty()
+    }
+
+    /// Add a backend that is already initialized
+    pub fn add(&mut self, name: &'static str, backend: Box<dyn AudioNetworkBackend>) {
+        self.backends.push((name, Arc::new(RwLock::new(backend))));
+    }
+
+    /// Registry names of the running backends
+    pub fn names(&self) -> Vec<&'static str> {
+        self.backends.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Backend by registry name (case-insensitive), with its registry name
+    pub fn get(&self, name: &str) -> Option<(&'static str, SharedBackend)> {
+        self.backends.iter()
+            .find(|(registered, _)| registered.eq_ignore_ascii_case(name))
+            .map(|(registered, backend)| (*registered, backend.clone()))
+    }
+
+    /// All running backends
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &SharedBackend)> {
+        self.backends.iter().map(|(name, backend)| (*name, backend))
+    }
+
+    /// Run `f` on the running backend of type `T`, e.g. the AES67 backend
+    /// for its subscriptions
+    ///
+    /// Only the matching backend is write-locked, the others stay readable
+    /// for the audio callback.
+    pub fn with<T: AudioNetworkBackend, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let (_, backend) = self.backends.iter()
+            .find(|(_, backend)| backend.read().as_any().is::<T>())?;
+        let mut backend = backend.write();
+        backend.as_any_mut().downcast_mut::<T>().map(f)
+    }
+
+    /// SAP discovery of the AES67 backend
+    pub fn sap_discovery(&self) -> Option<Arc<SapDiscovery>> {
+        self.with(|b: &mut Aes67Backend| b.sap_discovery())
+    }
+
+    /// PTP clock of the AES67 backend
+    pub fn ptp_clock(&self) -> Option<Arc<PtpClock>> {
+        self.with(|b: &mut Aes67Backend| b.ptp_clock())
+    }
+
+    /// Received AES67 streams
+    pub fn aes67_subscriptions(&self) -> Option<Arc<Aes67Subscriptions>> {
+        self.with(|b: &mut Aes67Backend| b.subscriptions())
+    }
+
+    /// Sent AES67 streams
+    pub fn aes67_tx_streams(&self) -> Option<Arc<Aes67TxStreams>> {
+        self.with(|b: &mut Aes67Backend| b.tx_streams())
+    }
+
+    /// Every backend with its state and devices
+    pub fn infos(&self) -> Vec<BackendInfo> {
+        self.iter().map(|(name, backend)| BackendInfo::of(name, backend.read().as_ref())).collect()
+    }
+}
+
+/// A running backend for API and clients
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendInfo {
+    /// Registry name ("aes67", "virtual", ...)
+    pub id: String,
+    /// Display name
+    pub name: String,
+    pub connected: bool,
+    /// Latency in samples at the engine rate
+    pub latency: usize,
+    pub devices: Vec<NetworkDevice>,
+    /// Device discovery failed
+    pub error: Option<String>,
+}
+
+impl BackendInfo {
+    pub fn of(id: &str, backend: &dyn AudioNetworkBackend) -> Self {
+        let (devices, error) = match backend.discover() {
+            Ok(devices) => (devices, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        Self {
+            id: id.to_string(),
+            name: backend.name().to_string(),
+            connected: backend.is_connected(),
+            latency: backend.latency(),
+            devices,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::loopback::{LoopbackBackend, LOOPBACK_DEVICE_ID};
+
+    fn context() -> BackendContext {
+        BackendContext {
+            sample_rate: 48000,
+            aes67: Aes67Config::default(),
+            virtual_channels: 4,
+            drift_monitor: Arc::new(DriftMonitor::new()),
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_registry_selection() {
+        let registry = BackendRegistry::with_defaults();
+        assert!(registry.contains("aes67") && registry.contains("Virtual"));
+        assert!(!registry.contains("ravenna"));
+
+        let error = registry.start(&names(&["virtual", "ravenna"]), &context()).err().unwrap().to_string();
+        assert!(error.contains("'ravenna'") && error.contains("aes67, virtual"), "{}", error);
+
+        let backends = registry.start(&names(&["virtual", "VIRTUAL"]), &context()).unwrap();
+        assert_eq!(backends.names(), vec!["virtual"]);
+        assert!(backends.get("aes67").is_none());
+        assert_eq!(backends.with(|b: &mut LoopbackBackend| b.name()), Some("Virtual"));
+    }
+
+    #[test]
+    fn test_several_backends() {
+        // A failing backend is left out, the others run
+        let mut registry = BackendRegistry::with_defaults();
+        registry.register("broken", |_| Err(anyhow!("no hardware")));
+        registry.register("second", |_| Ok(Box::new(LoopbackBackend::new(2, 48000))));
+        let backends = registry.start(&names(&["virtual", "broken", "second"]), &context()).unwrap();
+        assert_eq!(backends.names(), vec!["virtual", "second"]);
+
+        let device = backends.infos()[0].devices[0].clone();
+        assert_eq!(device.id, LOOPBACK_DEVICE_ID);
+        backends.get("virtual").unwrap().1.write().connect(&device).unwrap();
+
+        let infos = backends.infos();
+        assert_eq!(infos.len(), 2);
+        assert_eq!((infos[0].id.as_str(), infos[0].connected, infos[0].devices[0].channels), ("virtual", true, 4));
+        assert_eq!((infos[1].id.as_str(), infos[1].connected, infos[1].devices[0].channels), ("second", false, 2));
+    }
+}
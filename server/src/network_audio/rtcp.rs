@@ -0,0 +1,858 @@
+//! RTCP (RFC 3550) sender and receiver reports for AES67 streams
+//!
+//! Every stream has an RTCP session on its RTP port + 1. For sent streams we
+//! send sender reports (SR) and collect the receiver reports (RR) of remote
+//! devices, which give their loss, jitter and the round-trip time. For
+//! received streams we track loss and interarrival jitter per RFC 3550
+//! appendix A and report them to the sender in RRs. Compound packets carry
+//! an SDES CNAME; a BYE is sent when the session ends.
+//!
+//! RTCP runs on the primary network only, also for ST 2022-7 streams.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::{Result, anyhow, bail};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use super::rtp::RtpSender;
+
+/// Packet types
+const PT_SR: u8 = 200;
+const PT_RR: u8 = 201;
+const PT_SDES: u8 = 202;
+const PT_BYE: u8 = 203;
+
+/// SDES item type of the canonical name
+const SDES_CNAME: u8 = 1;
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch (1970)
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Report interval (RFC 3550 minimum), randomised by ±50 %
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Remote receivers without a report for this long are dropped
+const RECEIVER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Reception report block (one per reported source)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportBlock {
+    /// Source the block is about
+    pub ssrc: u32,
+    /// Fraction lost since the previous report (fixed point, /256)
+    pub fraction_lost: u8,
+    /// Packets lost in total (24-bit signed)
+    pub cumulative_lost: i32,
+    /// Extended highest sequence number received
+    pub highest_sequence: u32,
+    /// Interarrival jitter in RTP timestamp units
+    pub jitter: u32,
+    /// Middle 32 bits of the NTP timestamp of the last SR (0 = none)
+    pub last_sr: u32,
+    /// Delay since the last SR in 1/65536 s
+    pub delay_since_last_sr: u32,
+}
+
+/// RTCP packet of a compound packet
+#[derive(Debug, Clone, PartialEq)]
+pub enum RtcpPacket {
+    SenderReport {
+        ssrc: u32,
+        /// NTP timestamp (32.32 fixed point seconds since 1900)
+        ntp_timestamp: u64,
+        rtp_timestamp: u32,
+        packet_count: u32,
+        octet_count: u32,
+        reports: Vec<ReportBlock>,
+    },
+    ReceiverReport {
+        ssrc: u32,
+        reports: Vec<ReportBlock>,
+    },
+    /// CNAME per source (other SDES items are skipped)
+    SourceDescription(Vec<(u32, String)>),
+    Bye(Vec<u32>),
+    /// Packet types we do not handle (APP, XR, ...)
+    Other(u8),
+}
+
+impl ReportBlock {
+    fn parse(data: &[u8]) -> Self {
+        let word = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        let lost = word(4);
+        Self {
+            ssrc: word(0),
+            fraction_lost: (lost >> 24) as u8,
+            // Sign-extend the 24-bit count
+            cumulative_lost: ((lost << 8) as i32) >> 8,
+            highest_sequence: word(8),
+            jitter: word(12),
+            last_sr: word(16),
+            delay_since_last_sr: word(20),
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.ssrc.to_be_bytes());
+        let lost = self.cumulative_lost.clamp(-0x80_0000, 0x7F_FFFF) as u32 & 0xFF_FFFF;
+        out.extend_from_slice(&(((self.fraction_lost as u32) << 24) | lost).to_be_bytes());
+        out.extend_from_slice(&self.highest_sequence.to_be_bytes());
+        out.extend_from_slice(&self.jitter.to_be_bytes());
+        out.extend_from_slice(&self.last_sr.to_be_bytes());
+        out.extend_from_slice(&self.delay_since_last_sr.to_be_bytes());
+    }
+}
+
+impl RtcpPacket {
+    /// Parse a compound packet
+    pub fn parse_compound(mut data: &[u8]) -> Result<Vec<Self>> {
+        let mut packets = Vec::new();
+        while !data.is_empty() {
+            if data.len() < 4 {
+                bail!("truncated RTCP header");
+            }
+            if data[0] >> 6 != 2 {
+                bail!("unsupported RTCP version {}", data[0] >> 6);
+            }
+            let count = (data[0] & 0x1F) as usize;
+            let packet_type = data[1];
+            let length = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+            if length > data.len() {
+                bail!("RTCP packet of {} bytes exceeds the datagram", length);
+            }
+            let mut body = &data[4..length];
+            if data[0] & 0x20 != 0 {
+                // Padding: the last octet is the padding length
+                let padding = *body.last().unwrap_or(&0) as usize;
+                body = body.get(..body.len().saturating_sub(padding)).unwrap_or_default();
+            }
+            packets.push(Self::parse(packet_type, count, body)?);
+            data = &data[length..];
+        }
+        Ok(packets)
+    }
+
+    fn parse(packet_type: u8, count: usize, body: &[u8]) -> Result<Self> {
+        let word = |i: usize| -> Result<u32> {
+            body.get(i..i + 4)
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or_else(|| anyhow!("truncated RTCP packet type {}", packet_type))
+        };
+        let blocks = |offset: usize| -> Result<Vec<ReportBlock>> {
+            let end = offset + count * 24;
+            let data = body.get(offset..end).ok_or_else(|| anyhow!("truncated report blocks"))?;
+            Ok(data.chunks_exact(24).map(ReportBlock::parse).collect())
+        };
+
+        Ok(match packet_type {
+            PT_SR => RtcpPacket::SenderReport {
+                ssrc: word(0)?,
+                ntp_timestamp: ((word(4)? as u64) << 32) | word(8)? as u64,
+                rtp_timestamp: word(12)?,
+                packet_count: word(16)?,
+                octet_count: word(20)?,
+                reports: blocks(24)?,
+            },
+            PT_RR => RtcpPacket::ReceiverReport {
+                ssrc: word(0)?,
+                reports: blocks(4)?,
+            },
+            PT_SDES => {
+                let mut names = Vec::new();
+                let mut offset = 0;
+                for _ in 0..count {
+                    let ssrc = word(offset)?;
+                    offset += 4;
+                    // Items up to the null item, then padding to the next word
+                    while let Some(&item) = body.get(offset) {
+                        if item == 0 {
+                            break;
+                        }
+                        let length = *body.get(offset + 1).ok_or_else(|| anyhow!("truncated SDES item"))? as usize;
+                        let text = body.get(offset + 2..offset + 2 + length).ok_or_else(|| anyhow!("truncated SDES item"))?;
+                        if item == SDES_CNAME {
+                            names.push((ssrc, String::from_utf8_lossy(text).into_owned()));
+                        }
+                        offset += 2 + length;
+                    }
+                    offset = (offset + 4) & !3;
+                }
+                RtcpPacket::SourceDescription(names)
+            }
+            PT_BYE => RtcpPacket::Bye((0..count).map(|i| word(i * 4)).collect::<Result<_>>()?),
+            other => RtcpPacket::Other(other),
+        })
+    }
+
+    /// Append the packet in wire format
+    pub fn write(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        let (count, packet_type) = match self {
+            RtcpPacket::SenderReport { reports, .. } => (reports.len(), PT_SR),
+            RtcpPacket::ReceiverReport { reports, .. } => (reports.len(), PT_RR),
+            RtcpPacket::SourceDescription(names) => (names.len(), PT_SDES),
+            RtcpPacket::Bye(sources) => (sources.len(), PT_BYE),
+            RtcpPacket::Other(_) => return,
+        };
+        out.extend_from_slice(&[0x80 | count.min(31) as u8, packet_type, 0, 0]);
+
+        match self {
+            RtcpPacket::SenderReport { ssrc, ntp_timestamp, rtp_timestamp, packet_count, octet_count, reports } => {
+                out.extend_from_slice(&ssrc.to_be_bytes());
+                out.extend_from_slice(&ntp_timestamp.to_be_bytes());
+                out.extend_from_slice(&rtp_timestamp.to_be_bytes());
+                out.extend_from_slice(&packet_count.to_be_bytes());
+                out.extend_from_slice(&octet_count.to_be_bytes());
+                reports.iter().take(31).for_each(|block| block.write(out));
+            }
+            RtcpPacket::ReceiverReport { ssrc, reports } => {
+                out.extend_from_slice(&ssrc.to_be_bytes());
+                reports.iter().take(31).for_each(|block| block.write(out));
+            }
+            RtcpPacket::SourceDescription(names) => {
+                for (ssrc, name) in names.iter().take(31) {
+                    let name = &name.as_bytes()[..name.len().min(255)];
+                    out.extend_from_slice(&ssrc.to_be_bytes());
+                    out.extend_from_slice(&[SDES_CNAME, name.len() as u8]);
+                    out.extend_from_slice(name);
+                    // Null item, padded to the next word
+                    out.push(0);
+                    while !(out.len() - start).is_multiple_of(4) {
+                        out.push(0);
+                    }
+                }
+            }
+            RtcpPacket::Bye(sources) => {
+                sources.iter().take(31).for_each(|ssrc| out.extend_from_slice(&ssrc.to_be_bytes()));
+            }
+            RtcpPacket::Other(_) => {}
+        }
+
+        let words = ((out.len() - start) / 4 - 1) as u16;
+        out[start + 2..start + 4].copy_from_slice(&words.to_be_bytes());
+    }
+
+    /// Compound packet of several RTCP packets
+    pub fn compound(packets: &[RtcpPacket]) -> Vec<u8> {
+        let mut out = Vec::new();
+        packets.iter().for_each(|packet| packet.write(&mut out));
+        out
+    }
+}
+
+/// NTP timestamp (32.32 fixed point, 1900 epoch) of a Unix/PTP time in ns
+pub fn ntp_timestamp(unix_ns: u64) -> u64 {
+    let seconds = unix_ns / 1_000_000_000 + NTP_UNIX_OFFSET;
+    let fraction = ((unix_ns % 1_000_000_000) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
+
+/// Middle 32 bits of an NTP timestamp (LSR/DLSR units of 1/65536 s)
+fn ntp_middle(ntp: u64) -> u32 {
+    (ntp >> 16) as u32
+}
+
+/// Reception of a stream, RFC 3550 appendix A.1, A.3 and A.8
+pub struct ReceptionStats {
+    sample_rate: u32,
+    /// Sender SSRC and address of the RTP packets
+    source: Option<(u32, IpAddr)>,
+    base_sequence: u32,
+    max_sequence: u16,
+    cycles: u32,
+    received: u64,
+    expected_prior: u64,
+    received_prior: u64,
+    /// Relative transit time of the previous packet (RTP units)
+    transit: Option<i32>,
+    /// Interarrival jitter (RTP units)
+    jitter: f64,
+    /// Middle NTP bits of the last SR and its arrival
+    last_sr: Option<(u32, Instant)>,
+    epoch: Instant,
+}
+
+impl ReceptionStats {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            source: None,
+            base_sequence: 0,
+            max_sequence: 0,
+            cycles: 0,
+            received: 0,
+            expected_prior: 0,
+            received_prior: 0,
+            transit: None,
+            jitter: 0.0,
+            last_sr: None,
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Count a received RTP packet (duplicates must not be counted)
+    pub fn record(&mut self, ssrc: u32, sequence: u16, timestamp: u32, from: IpAddr, arrival: Instant) {
+        if self.source.map(|(s, _)| s) != Some(ssrc) {
+            // New sender: start over
+            *self = Self::new(self.sample_rate);
+            self.source = Some((ssrc, from));
+            self.base_sequence = sequence as u32;
+            self.max_sequence = sequence;
+        } else {
+            let delta = sequence.wrapping_sub(self.max_sequence) as i16;
+            if delta > 0 {
+                if sequence < self.max_sequence {
+                    self.cycles += 1 << 16;
+                }
+                self.max_sequence = sequence;
+            }
+        }
+        self.received += 1;
+
+        // Interarrival jitter in RTP timestamp units
+        let arrival = (arrival.duration_since(self.epoch).as_nanos() * self.sample_rate as u128 / 1_000_000_000) as u32;
+        let transit = arrival.wrapping_sub(timestamp) as i32;
+        if let Some(previous) = self.transit {
+            let d = transit.wrapping_sub(previous).unsigned_abs() as f64;
+            self.jitter += (d - self.jitter) / 16.0;
+        }
+        self.transit = Some(transit);
+    }
+
+    /// Remember a sender report of the stream's sender
+    pub fn sender_report(&mut self, ssrc: u32, ntp_timestamp: u64, arrival: Instant) {
+        if self.source.is_none_or(|(s, _)| s == ssrc) {
+            self.last_sr = Some((ntp_middle(ntp_timestamp), arrival));
+        }
+    }
+
+    /// Sender address of the RTP packets
+    pub fn source_address(&self) -> Option<IpAddr> {
+        self.source.map(|(_, address)| address)
+    }
+
+    fn extended_max(&self) -> u32 {
+        self.cycles.wrapping_add(self.max_sequence as u32)
+    }
+
+    fn expected(&self) -> u64 {
+        (self.extended_max().wrapping_sub(self.base_sequence) as u64) + 1
+    }
+
+    /// Report block for an RR, starts a new interval for the fraction lost
+    pub fn report_block(&mut self, now: Instant) -> Option<ReportBlock> {
+        let (ssrc, _) = self.source?;
+        let expected = self.expected();
+        let expected_interval = expected - self.expected_prior;
+        let received_interval = self.received - self.received_prior;
+        self.expected_prior = expected;
+        self.received_prior = self.received;
+        let lost_interval = expected_interval as i64 - received_interval as i64;
+        let fraction_lost = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / expected_interval as i64).min(255) as u8
+        };
+
+        let (last_sr, delay_since_last_sr) = match self.last_sr {
+            Some((middle, arrival)) => (middle, (now.duration_since(arrival).as_secs_f64() * 65536.0) as u32),
+            None => (0, 0),
+        };
+        Some(ReportBlock {
+            ssrc,
+            fraction_lost,
+            cumulative_lost: self.cumulative_lost().clamp(-0x80_0000, 0x7F_FFFF) as i32,
+            highest_sequence: self.extended_max(),
+            jitter: self.jitter as u32,
+            last_sr,
+            delay_since_last_sr,
+        })
+    }
+
+    fn cumulative_lost(&self) -> i64 {
+        self.expected() as i64 - self.received as i64
+    }
+
+    /// Current reception for the API (does not start a new interval)
+    fn summary(&self) -> Option<ReceptionReport> {
+        let (ssrc, address) = self.source?;
+        let expected = self.expected() - self.expected_prior;
+        let lost = expected as i64 - (self.received - self.received_prior) as i64;
+        Some(ReceptionReport {
+            ssrc,
+            cname: None,
+            address: Some(SocketAddr::new(address, 0)),
+            fraction_lost: if expected == 0 { 0.0 } else { lost.max(0) as f64 / expected as f64 },
+            cumulative_lost: self.cumulative_lost(),
+            jitter_ms: self.jitter * 1000.0 / self.sample_rate as f64,
+            round_trip_ms: None,
+        })
+    }
+}
+
+/// Reception quality of a stream
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceptionReport {
+    /// Sent streams: SSRC of the remote receiver; received streams: SSRC of the sender
+    pub ssrc: u32,
+    /// Canonical name from SDES
+    pub cname: Option<String>,
+    /// Address of the remote device
+    pub address: Option<SocketAddr>,
+    /// Fraction of packets lost in the last report interval (0.0-1.0)
+    pub fraction_lost: f64,
+    /// Packets lost in total
+    pub cumulative_lost: i64,
+    /// Interarrival jitter in ms
+    pub jitter_ms: f64,
+    /// Round-trip time from LSR/DLSR (sent streams only)
+    pub round_trip_ms: Option<f64>,
+}
+
+/// RTCP state of a stream for API/status
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RtcpStats {
+    pub sender_reports: u64,
+    pub receiver_reports: u64,
+    /// Sent streams: one report per remote receiver; received streams: our
+    /// reception of the sender
+    pub reports: Vec<ReceptionReport>,
+}
+
+/// Local end of the session
+enum Role {
+    /// We send the stream
+    Sender(Arc<RtpSender>),
+    /// We receive the stream with our own SSRC
+    Receiver { ssrc: u32, reception: Arc<Mutex<ReceptionStats>> },
+}
+
+impl Role {
+    fn ssrc(&self) -> u32 {
+        match self {
+            Role::Sender(sender) => sender.ssrc(),
+            Role::Receiver { ssrc, .. } => *ssrc,
+        }
+    }
+}
+
+/// A remote receiver of our stream
+struct RemoteReceiver {
+    report: ReceptionReport,
+    last_report: Instant,
+}
+
+/// Counters and remote receivers of a session
+#[derive(Default)]
+struct SessionState {
+    sender_reports: u64,
+    receiver_reports: u64,
+    receivers: HashMap<u32, RemoteReceiver>,
+    names: HashMap<u32, String>,
+}
+
+/// RTCP session of one stream (RTP port + 1)
+pub struct RtcpSession {
+    role: Arc<Role>,
+    state: Arc<Mutex<SessionState>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RtcpSession {
+    /// Session of a sent stream: SRs to `destination` (RTP address, port + 1 is used)
+    pub fn for_sender(sender: Arc<RtpSender>, destination: SocketAddrV4, interface: Option<Ipv4Addr>, cname: String) -> Result<Self> {
+        Self::start(Role::Sender(sender), destination, interface, cname)
+    }
+
+    /// Session of a received stream: RRs about the stream at `address` (RTP address)
+    pub fn for_receiver(
+        reception: Arc<Mutex<ReceptionStats>>,
+        address: SocketAddrV4,
+        interface: Option<Ipv4Addr>,
+        cname: String,
+    ) -> Result<Self> {
+        Self::start(Role::Receiver { ssrc: rand::random(), reception }, address, interface, cname)
+    }
+
+    fn start(role: Role, address: SocketAddrV4, interface: Option<Ipv4Addr>, cname: String) -> Result<Self> {
+        let port = address.port().checked_add(1).ok_or_else(|| anyhow!("No RTCP port above {}", address.port()))?;
+        let socket = rtcp_socket(*address.ip(), port, interface)?;
+        let role = Arc::new(role);
+        let state = Arc::new(Mutex::new(SessionState::default()));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread = {
+            let role = role.clone();
+            let state = state.clone();
+            let running = running.clone();
+            let destination = SocketAddrV4::new(*address.ip(), port);
+            std::thread::Builder::new()
+                .name("aes67-rtcp".to_string())
+                .spawn(move || run_session(socket, &role, &state, &running, destination, &cname))?
+        };
+        debug!("RTCP session on {}:{}", address.ip(), port);
+
+        Ok(Self { role, state, running, thread: Some(thread) })
+    }
+
+    /// Received reports and the reception quality of the stream
+    pub fn stats(&self) -> RtcpStats {
+        let state = self.state.lock();
+        let reports = match self.role.as_ref() {
+            Role::Sender(_) => {
+                let mut reports: Vec<ReceptionReport> = state.receivers.values().map(|r| ReceptionReport {
+                    cname: state.names.get(&r.report.ssrc).cloned(),
+                    ..r.report.clone()
+                }).collect();
+                reports.sort_by_key(|r| r.ssrc);
+                reports
+            }
+            Role::Receiver { reception, .. } => reception.lock().summary()
+                .map(|report| ReceptionReport { cname: state.names.get(&report.ssrc).cloned(), ..report })
+                .into_iter()
+                .collect(),
+        };
+        RtcpStats {
+            sender_reports: state.sender_reports,
+            receiver_reports: state.receiver_reports,
+            reports,
+        }
+    }
+
+    /// Send BYE and stop the session
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for RtcpSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Socket on `address`:`port`, joined if `address` is a multicast group
+fn rtcp_socket(address: Ipv4Addr, port: u16, interface: Option<Ipv4Addr>) -> Result<UdpSocket> {
+    use socket2::{Socket, Domain, Type, Protocol};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    let multicast = address.is_multicast();
+    #[cfg(unix)]
+    if multicast {
+        socket.set_reuse_port(true)?;
+    }
+
+    #[cfg(unix)]
+    let bind_ip = if multicast { address } else { interface.unwrap_or(Ipv4Addr::UNSPECIFIED) };
+    #[cfg(not(unix))]
+    let bind_ip = if multicast { Ipv4Addr::UNSPECIFIED } else { interface.unwrap_or(Ipv4Addr::UNSPECIFIED) };
+    socket.bind(&SocketAddr::new(IpAddr::V4(bind_ip), port).into())
+        .map_err(|e| anyhow!("Cannot open RTCP port {}:{}: {}", bind_ip, port, e))?;
+
+    if multicast {
+        super::interface::join_multicast(&socket, address, interface)?;
+        socket.set_multicast_ttl_v4(64)?;
+    }
+
+    let socket: UdpSocket = socket.into();
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+    Ok(socket)
+}
+
+/// Time of the next report (interval randomised by ±50 %)
+fn next_report(now: Instant, interval: Duration) -> Instant {
+    now + interval.mul_f64(0.5 + rand::random::<f64>())
+}
+
+/// Current wallclock (UTC) in ns for the NTP timestamps (PTP if available)
+fn now_ns(role: &Role) -> u64 {
+    match role {
+        Role::Sender(sender) => sender.utc_ns(),
+        Role::Receiver { .. } => SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64,
+    }
+}
+
+fn run_session(
+    socket: UdpSocket,
+    role: &Role,
+    state: &Mutex<SessionState>,
+    running: &AtomicBool,
+    group: SocketAddrV4,
+    cname: &str,
+) {
+    let our_ssrc = role.ssrc();
+    let mut buf = [0u8; 1500];
+    // First report after half an interval (RFC 3550 6.2)
+    let mut next = next_report(Instant::now(), REPORT_INTERVAL / 2);
+
+    while running.load(Ordering::Relaxed) {
+        match socket.recv_from(&mut buf) {
+            Ok((len, from)) => match RtcpPacket::parse_compound(&buf[..len]) {
+                Ok(packets) => handle_packets(role, state, packets, from, now_ns(role), Instant::now()),
+                Err(e) => debug!("Invalid RTCP packet from {}: {}", from, e),
+            },
+            Err(ref e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(e) => warn!("RTCP receive error: {}", e),
+        }
+
+        let now = Instant::now();
+        state.lock().receivers.retain(|_, r| now.duration_since(r.last_report) < RECEIVER_TIMEOUT);
+        if now < next {
+            continue;
+        }
+        next = next_report(now, REPORT_INTERVAL);
+
+        let (report, destination) = build_report(role, now_ns(role), now, group);
+        let Some(destination) = destination else {
+            continue;
+        };
+        let sdes = RtcpPacket::SourceDescription(vec![(our_ssrc, cname.to_string())]);
+        if let Err(e) = socket.send_to(&RtcpPacket::compound(&[report, sdes]), destination) {
+            debug!("RTCP send to {} failed: {}", destination, e);
+        }
+    }
+
+    // Leaving the session
+    if let (_, Some(destination)) = build_report(role, now_ns(role), Instant::now(), group) {
+        let bye = RtcpPacket::compound(&[
+            RtcpPacket::ReceiverReport { ssrc: our_ssrc, reports: Vec::new() },
+            RtcpPacket::Bye(vec![our_ssrc]),
+        ]);
+        let _ = socket.send_to(&bye, destination);
+    }
+    info!("RTCP session {} stopped", group);
+}
+
+/// Our SR or RR and where to send it (None = sender of a unicast stream unknown yet)
+fn build_report(role: &Role, now_ns: u64, now: Instant, group: SocketAddrV4) -> (RtcpPacket, Option<SocketAddr>) {
+    let multicast_or_unicast = SocketAddr::V4(group);
+    match role {
+        Role::Sender(sender) => {
+            let info = sender.sender_info();
+            (RtcpPacket::SenderReport {
+                ssrc: info.ssrc,
+                ntp_timestamp: ntp_timestamp(now_ns),
+                rtp_timestamp: info.rtp_timestamp,
+                packet_count: info.packets,
+                octet_count: info.octets,
+                reports: Vec::new(),
+            }, Some(multicast_or_unicast))
+        }
+        Role::Receiver { ssrc, reception } => {
+            let mut reception = reception.lock();
+            let report = RtcpPacket::ReceiverReport {
+                ssrc: *ssrc,
+                reports: reception.report_block(now).into_iter().collect(),
+            };
+            // Multicast: to the group; unicast: back to the sender
+            let destination = if group.ip().is_multicast() {
+                Some(multicast_or_unicast)
+            } else {
+                reception.source_address().map(|address| SocketAddr::new(address, group.port()))
+            };
+            (report, destination)
+        }
+    }
+}
+
+/// Process received RTCP packets
+fn handle_packets(role: &Role, state: &Mutex<SessionState>, packets: Vec<RtcpPacket>, from: SocketAddr, now_ns: u64, now: Instant) {
+    let our_ssrc = role.ssrc();
+    let mut state = state.lock();
+    for packet in packets {
+        match packet {
+            RtcpPacket::SenderReport { ssrc, ntp_timestamp, reports, .. } => {
+                if ssrc == our_ssrc {
+                    continue;
+                }
+                state.sender_reports += 1;
+                if let Role::Receiver { reception, .. } = role {
+                    reception.lock().sender_report(ssrc, ntp_timestamp, now);
+                }
+                receiver_reports(role, &mut state, ssrc, &reports, from, now_ns, now);
+            }
+            RtcpPacket::ReceiverReport { ssrc, reports } => {
+                if ssrc == our_ssrc {
+                    continue;
+                }
+                state.receiver_reports += 1;
+                receiver_reports(role, &mut state, ssrc, &reports, from, now_ns, now);
+            }
+            RtcpPacket::SourceDescription(names) => {
+                for (ssrc, name) in names {
+                    state.names.insert(ssrc, name);
+                }
+            }
+            RtcpPacket::Bye(sources) => {
+                for ssrc in sources {
+                    state.receivers.remove(&ssrc);
+                    state.names.remove(&ssrc);
+                }
+            }
+            RtcpPacket::Other(_) => {}
+        }
+    }
+}
+
+/// Report blocks about our stream from remote receiver `reporter`
+fn receiver_reports(
+    role: &Role,
+    state: &mut SessionState,
+    reporter: u32,
+    blocks: &[ReportBlock],
+    from: SocketAddr,
+    now_ns: u64,
+    now: Instant,
+) {
+    let Role::Sender(sender) = role else {
+        return;
+    };
+    let our_ssrc = sender.ssrc();
+    let sample_rate = sender.format().sample_rate.max(1) as f64;
+    for block in blocks.iter().filter(|block| block.ssrc == our_ssrc) {
+        // RTT = arrival - LSR - DLSR (RFC 3550 6.4.1)
+        let round_trip_ms = (block.last_sr != 0).then(|| {
+            let rtt = ntp_middle(ntp_timestamp(now_ns))
+                .wrapping_sub(block.last_sr)
+                .wrapping_sub(block.delay_since_last_sr);
+            rtt as f64 * 1000.0 / 65536.0
+        });
+        state.receivers.insert(reporter, RemoteReceiver {
+            report: ReceptionReport {
+                ssrc: reporter,
+                cname: None,
+                address: Some(from),
+                fraction_lost: block.fraction_lost as f64 / 256.0,
+                cumulative_lost: block.cumulative_lost as i64,
+                jitter_ms: block.jitter as f64 * 1000.0 / sample_rate,
+                round_trip_ms,
+            },
+            last_report: now,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network_audio::rtp::Aes67Format;
+
+    #[test]
+    fn test_compound_roundtrip() {
+        let block = ReportBlock {
+            ssrc: 0x1234_5678,
+            fraction_lost: 64,
+            cumulative_lost: -3,
+            highest_sequence: 0x0001_0010,
+            jitter: 12,
+            last_sr: 0xABCD_0000,
+            delay_since_last_sr: 32768,
+        };
+        let packets = vec![
+            RtcpPacket::SenderReport {
+                ssrc: 1,
+                ntp_timestamp: ntp_timestamp(1_700_000_000_500_000_000),
+                rtp_timestamp: 48_000,
+                packet_count: 1000,
+                octet_count: 288_000,
+                reports: vec![block],
+            },
+            RtcpPacket::SourceDescription(vec![(1, "audiomultiverse@10.0.0.5".to_string())]),
+            RtcpPacket::Bye(vec![1]),
+        ];
+        let data = RtcpPacket::compound(&packets);
+        assert_eq!(data.len() % 4, 0);
+        assert_eq!(RtcpPacket::parse_compound(&data).unwrap(), packets);
+
+        // Half a second is half of the 32-bit fraction
+        assert_eq!(ntp_timestamp(1_500_000_000) & 0xFFFF_FFFF, 0x8000_0000);
+        assert_eq!(ntp_timestamp(0) >> 32, NTP_UNIX_OFFSET);
+
+        assert!(RtcpPacket::parse_compound(&data[..data.len() - 4]).is_err());
+        assert!(RtcpPacket::parse_compound(&[0x40, 200, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_reception_stats() {
+        let mut stats = ReceptionStats::new(48000);
+        let source: IpAddr = "192.168.1.10".parse().unwrap();
+        let start = Instant::now();
+
+        // Packets 65534..=65545 over the wrap, 65536 and 2 missing, steady arrival
+        for (i, sequence) in (65534u32..=65545).map(|s| s as u16).enumerate() {
+            if sequence == 0 || sequence == 2 {
+                continue;
+            }
+            let arrival = start + Duration::from_millis(i as u64);
+            stats.record(7, sequence, i as u32 * 48, source, arrival);
+        }
+        stats.sender_report(7, 0x0000_1234_5678_0000, start);
+
+        let block = stats.report_block(start + Duration::from_millis(500)).unwrap();
+        assert_eq!(block.ssrc, 7);
+        assert_eq!(block.cumulative_lost, 2);
+        assert_eq!(block.highest_sequence, 65536 + 9);
+        assert_eq!(block.fraction_lost, (2 * 256 / 12) as u8);
+        assert!(block.jitter <= 1);
+        assert_eq!(block.last_sr, 0x1234_5678);
+        assert_eq!(block.delay_since_last_sr, 32768);
+
+        // Next interval without losses
+        stats.record(7, 10, 12 * 48, source, start + Duration::from_millis(12));
+        assert_eq!(stats.report_block(start).unwrap().fraction_lost, 0);
+        assert_eq!(stats.source_address(), Some(source));
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let format = Aes67Format::new(48000, 2);
+        let port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let sender = Arc::new(RtpSender::with_options(Ipv4Addr::LOCALHOST, port, format, 64, 0, Some(Ipv4Addr::LOCALHOST)).unwrap());
+        let sender_ssrc = sender.ssrc();
+        let sender_role = Role::Sender(sender);
+        let reception = Arc::new(Mutex::new(ReceptionStats::new(48000)));
+        let receiver_role = Role::Receiver { ssrc: 0x5EC0_0001, reception: reception.clone() };
+        let group = SocketAddrV4::new(Ipv4Addr::new(239, 69, 1, 1), port + 1);
+        let sender_address: SocketAddr = "192.168.1.10:5005".parse().unwrap();
+        let receiver_address: SocketAddr = "192.168.1.20:5005".parse().unwrap();
+        let start = Instant::now();
+        reception.lock().record(sender_ssrc, 1, 0, sender_address.ip(), start);
+
+        // The SR carries UTC, not PTP time
+        let sr_ns = now_ns(&sender_role);
+        let (report, _) = build_report(&sender_role, sr_ns, start, group);
+        let RtcpPacket::SenderReport { ntp_timestamp: sr_ntp, .. } = report else {
+            panic!("sender must send an SR");
+        };
+        let utc_s = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + NTP_UNIX_OFFSET;
+        assert!(((sr_ntp >> 32) as i64 - utc_s as i64).abs() <= 1);
+
+        // Receiver gets the SR and answers 200 ms later
+        let packets = RtcpPacket::parse_compound(&RtcpPacket::compound(&[report])).unwrap();
+        let receiver_state = Mutex::new(SessionState::default());
+        handle_packets(&receiver_role, &receiver_state, packets, sender_address, now_ns(&receiver_role), start);
+        assert_eq!(receiver_state.lock().sender_reports, 1);
+        let (report, _) = build_report(&receiver_role, now_ns(&receiver_role), start + Duration::from_millis(200), group);
+
+        // RR arrives at the sender 250 ms after its SR: 50 ms round trip
+        let packets = RtcpPacket::parse_compound(&RtcpPacket::compound(&[report])).unwrap();
+        let sender_state = Mutex::new(SessionState::default());
+        handle_packets(&sender_role, &sender_state, packets, receiver_address, sr_ns + 250_000_000, start);
+        let state = sender_state.lock();
+        let remote = &state.receivers[&0x5EC0_0001].report;
+        assert_eq!(remote.address, Some(receiver_address));
+        let round_trip_ms = remote.round_trip_ms.unwrap();
+        assert!((round_trip_ms - 50.0).abs() < 0.1, "round trip {} ms", round_trip_ms);
+    }
+}
@@ -1,24 +1,40 @@
 //! RTP (Real-time Transport Protocol) for AES67 Audio Streaming
 //!
-//! Implements RTP packet encoding/decoding for L24 (24-bit linear PCM) audio.
+//! Implements RTP packet encoding/decoding for AES67 audio.
 //! AES67 uses RTP with specific parameters:
 //! - Payload type: 97 (dynamic)
-//! - Clock rate: 48000 Hz
-//! - Sample format: L24 (24-bit linear, big-endian)
+//! - Clock rate: 48000 Hz (44100 and 96000 Hz are supported as well)
+//! - Sample format: L24 (24-bit linear, big-endian), L16 or AM824 (AES3
+//!   subframes, SMPTE ST 2110-31)
 //! - Channels: 1-8 typically
-//! - Packet time: 1ms (48 samples at 48kHz)
+//! - Packet time: 1ms (48 samples at 48kHz), 125 µs to 4 ms
+//!
+//! Streams go to multicast groups or unicast addresses; multicast streams
+//! with a known sender are joined source-specific (SSM).
+//!
+//! Received packets go into a timestamp-indexed playout buffer that plays
+//! out at the link offset behind PTP time and conceals lost packets.
+//! With SMPTE ST 2022-7 every packet is sent on two networks; the receiver
+//! merges both copies in the playout buffer, so a failing path is inaudible.
+//! Streams whose sample rate differs from the engine rate are converted on
+//! the read side with the asynchronous resampler.
 
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use anyhow::{Result, anyhow};
 use bytes::{BufMut, BytesMut};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock, RwLockWriteGuard};
+use serde::{Deserialize, Serialize};
 use tracing::{info, warn, error, debug, trace};
 
 // Use PtpClock from parent module (either real or stub depending on platform)
 use super::PtpClock;
+use super::health::StreamActivity;
+use super::latency::{LatencyMonitor, LatencyStats};
+use super::rtcp::ReceptionStats;
+use crate::audio::{DriftController, DriftMonitor, Resampler};
 
 /// RTP Header (12 bytes minimum)
 /// 
@@ -49,7 +65,7 @@ pub struct RtpHeader {
     pub payload_type: u8,
     /// Sequence number
     pub sequence: u16,
-    /// Timestamp (in samples at 48kHz)
+    /// Timestamp (in samples at the stream's media clock rate)
     pub timestamp: u32,
     /// Synchronization source ID
     pub ssrc: u32,
@@ -119,6 +135,67 @@ impl RtpHeader {
             ssrc: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
         })
     }
+
+    /// Payload of the packet `data` with this header
+    ///
+    /// Skips the CSRC list and the header extension and strips the padding,
+    /// None if they do not fit into the packet.
+    pub fn payload<'a>(&self, data: &'a [u8]) -> Option<&'a [u8]> {
+        let mut start = 12 + 4 * self.csrc_count as usize;
+        if self.extension {
+            let words = data.get(start + 2..start + 4)?;
+            start += 4 + 4 * u16::from_be_bytes([words[0], words[1]]) as usize;
+        }
+        let mut end = data.len();
+        if self.padding {
+            // The last octet counts the padding, itself included
+            let padding = *data.last()? as usize;
+            if padding == 0 {
+                return None;
+            }
+            end = end.checked_sub(padding)?;
+        }
+        data.get(start..end)
+    }
+}
+
+/// Sample encoding of an RTP stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RtpEncoding {
+    /// 16-bit linear PCM
+    L16,
+    /// 24-bit linear PCM
+    #[default]
+    L24,
+    /// AES3 subframes as 32-bit AM824 words (IEC 61883-6)
+    Am824,
+}
+
+impl RtpEncoding {
+    /// Encoding name as used in `a=rtpmap`
+    pub fn name(self) -> &'static str {
+        match self {
+            RtpEncoding::L16 => "L16",
+            RtpEncoding::L24 => "L24",
+            RtpEncoding::Am824 => "AM824",
+        }
+    }
+
+    /// Encoding of an `a=rtpmap` name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        [RtpEncoding::L16, RtpEncoding::L24, RtpEncoding::Am824].into_iter()
+            .find(|encoding| encoding.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Bytes per sample on the wire
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            RtpEncoding::L16 => 2,
+            RtpEncoding::L24 => 3,
+            RtpEncoding::Am824 => 4,
+        }
+    }
 }
 
 /// AES67 Audio format
@@ -128,8 +205,8 @@ pub struct Aes67Format {
     pub sample_rate: u32,
     /// Number of channels (1-8)
     pub channels: u8,
-    /// Bits per sample (24 for L24)
-    pub bits_per_sample: u8,
+    /// Sample encoding (L24 by default)
+    pub encoding: RtpEncoding,
     /// Samples per packet (48 for 1ms at 48kHz)
     pub samples_per_packet: u16,
 }
@@ -139,29 +216,79 @@ impl Default for Aes67Format {
         Self {
             sample_rate: 48000,
             channels: 2,
-            bits_per_sample: 24,
+            encoding: RtpEncoding::L24,
             samples_per_packet: 48, // 1ms at 48kHz
         }
     }
 }
 
 impl Aes67Format {
+    /// Create an L24 format with 1ms packet time at the given sample rate
+    pub fn new(sample_rate: u32, channels: u8) -> Self {
+        Self::with_ptime(sample_rate, channels, RtpEncoding::L24, 1000)
+    }
+
+    /// Create a format with the given encoding and packet time
+    pub fn with_ptime(sample_rate: u32, channels: u8, encoding: RtpEncoding, ptime_us: u32) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            encoding,
+            // Rounded, so 333 µs at 48 kHz gives the usual 16 samples
+            samples_per_packet: ((sample_rate as u64 * ptime_us as u64 + 500_000) / 1_000_000).max(1) as u16,
+        }
+    }
+
+    /// Bytes per sample (L16 = 2, L24 = 3, AM824 = 4)
+    pub fn bytes_per_sample(&self) -> usize {
+        self.encoding.bytes_per_sample()
+    }
+
     /// Bytes per packet for audio payload
     pub fn bytes_per_packet(&self) -> usize {
-        self.samples_per_packet as usize * self.channels as usize * 3 // 24-bit = 3 bytes
+        self.samples_per_packet as usize * self.channels as usize * self.bytes_per_sample()
     }
 }
 
-/// RTP Stream for sending audio
-pub struct RtpSender {
-    /// Socket for sending
+/// One network path of a sent stream
+struct SendPath {
     socket: UdpSocket,
-    /// Destination address
     destination: SocketAddr,
+    errors: AtomicU64,
+}
+
+/// Send errors of one network path
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SendPathStats {
+    pub destination: SocketAddr,
+    pub errors: u64,
+}
+
+/// Sender state for RTCP sender reports
+#[derive(Debug, Clone, Copy)]
+pub struct SenderInfo {
+    pub ssrc: u32,
+    /// RTP timestamp corresponding to the current clock time
+    pub rtp_timestamp: u32,
+    /// Packets and payload octets sent (wrap at 32 bits)
+    pub packets: u32,
+    pub octets: u32,
+}
+
+/// RTP Stream for sending audio
+pub struct RtpSender {
+    /// Primary path, then the ST 2022-7 secondary path (if any)
+    paths: Vec<SendPath>,
+    /// IP time to live and DSCP for all paths
+    ttl: u32,
+    dscp: u8,
     /// SSRC (randomly generated)
     ssrc: u32,
     /// Sequence number
     sequence: AtomicU32,
+    /// Packets and payload octets sent (RTCP sender info)
+    packets: AtomicU32,
+    octets: AtomicU32,
     /// Audio format
     format: Aes67Format,
     /// PTP clock for timestamps
@@ -175,21 +302,39 @@ pub struct RtpSender {
 impl RtpSender {
     /// Create a new RTP sender
     pub fn new(multicast_addr: Ipv4Addr, port: u16, format: Aes67Format) -> Result<Self> {
-        let socket = UdpSocket::bind("0.0.0.0:0")?;
-        
-        // Set multicast TTL
-        socket.set_multicast_ttl_v4(64)?;
-        
-        let destination = SocketAddr::new(IpAddr::V4(multicast_addr), port);
+        Self::with_options(multicast_addr, port, format, 64, 0, None)
+    }
+
+    /// Create an RTP sender to a multicast or unicast destination
+    ///
+    /// `ttl` applies to multicast and unicast, `dscp` is written to the IP
+    /// header's DS field (AES67 recommends EF = 46 for media). Packets leave
+    /// via `interface` if given.
+    pub fn with_options(
+        destination: Ipv4Addr,
+        port: u16,
+        format: Aes67Format,
+        ttl: u32,
+        dscp: u8,
+        interface: Option<Ipv4Addr>,
+    ) -> Result<Self> {
+        let socket = send_socket(destination, interface, ttl, dscp)?;
         
         // Generate random SSRC
         let ssrc = rand::random();
         
         Ok(Self {
-            socket,
-            destination,
+            paths: vec![SendPath {
+                socket,
+                destination: SocketAddr::new(IpAddr::V4(destination), port),
+                errors: AtomicU64::new(0),
+            }],
+            ttl,
+            dscp,
             ssrc,
             sequence: AtomicU32::new(rand::random::<u16>() as u32),
+            packets: AtomicU32::new(0),
+            octets: AtomicU32::new(0),
             format,
             ptp_clock: None,
             running: Arc::new(AtomicBool::new(false)),
@@ -202,14 +347,80 @@ impl RtpSender {
         self.ptp_clock = Some(clock);
     }
 
+    /// Also send every packet to `destination` via `interface` (ST 2022-7)
+    ///
+    /// Both copies are identical (sequence, timestamp, SSRC), so receivers
+    /// can merge them packet by packet.
+    pub fn add_secondary(&mut self, destination: Ipv4Addr, interface: Ipv4Addr) -> Result<()> {
+        let port = self.paths[0].destination.port();
+        self.paths.truncate(1);
+        self.paths.push(SendPath {
+            socket: send_socket(destination, Some(interface), self.ttl, self.dscp)?,
+            destination: SocketAddr::new(IpAddr::V4(destination), port),
+            errors: AtomicU64::new(0),
+        });
+        Ok(())
+    }
+
+    /// Send errors per path (primary first)
+    pub fn path_stats(&self) -> Vec<SendPathStats> {
+        self.paths.iter().map(|path| SendPathStats {
+            destination: path.destination,
+            errors: path.errors.load(Ordering::Relaxed),
+        }).collect()
+    }
+
     /// Get SSRC
     pub fn ssrc(&self) -> u32 {
         self.ssrc
     }
 
+    /// Audio format of the sent stream
+    pub fn format(&self) -> Aes67Format {
+        self.format
+    }
+
+    /// Current time in ns on the stream's clock (PTP, or system time)
+    pub fn clock_ns(&self) -> u64 {
+        self.ptp_clock
+            .as_ref()
+            .map(|c| c.now_ns())
+            .unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos() as u64
+            })
+    }
+
+    /// Current UTC in ns for RTCP wallclock timestamps (PTP minus its UTC
+    /// offset, or system time)
+    pub fn utc_ns(&self) -> u64 {
+        self.ptp_clock
+            .as_ref()
+            .map(|c| c.utc_ns())
+            .unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos() as u64
+            })
+    }
+
+    /// Counters and current RTP timestamp for an RTCP sender report
+    pub fn sender_info(&self) -> SenderInfo {
+        let now = self.clock_ns() as u128;
+        SenderInfo {
+            ssrc: self.ssrc,
+            rtp_timestamp: ((now * self.format.sample_rate as u128 / 1_000_000_000) & 0xFFFF_FFFF) as u32,
+            packets: self.packets.load(Ordering::Relaxed),
+            octets: self.octets.load(Ordering::Relaxed),
+        }
+    }
+
     /// Send audio samples
     /// 
-    /// Expects interleaved f32 samples, will convert to L24
+    /// Expects interleaved f32 samples, will convert to the stream encoding
     pub fn send(&self, samples: &[f32]) -> Result<()> {
         let samples_per_channel = samples.len() / self.format.channels as usize;
         let packets_needed = (samples_per_channel + self.format.samples_per_packet as usize - 1) 
@@ -236,16 +447,15 @@ impl RtpSender {
     /// Send a single RTP packet
     fn send_packet(&self, samples: &[f32]) -> Result<()> {
         // Get timestamp from PTP clock or system time
-        let timestamp = self.ptp_clock
-            .as_ref()
-            .map(|c| c.media_timestamp())
-            .unwrap_or_else(|| {
-                (std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_nanos() as u64 * 48000 / 1_000_000_000) as u32
-            });
-        
+        let timestamp = self.sender_info().rtp_timestamp;
+        self.send_packet_at(samples, timestamp)
+    }
+
+    /// Send a single RTP packet with the given media clock timestamp
+    ///
+    /// Used by the paced transmitter, which derives the timestamp from the
+    /// packet's slot on the PTP media clock.
+    pub fn send_packet_at(&self, samples: &[f32], timestamp: u32) -> Result<()> {
         // Get next sequence number
         let sequence = self.sequence.fetch_add(1, Ordering::Relaxed) as u16;
         
@@ -255,22 +465,34 @@ impl RtpSender {
         header.timestamp = timestamp;
         
         // Build packet
-        let mut packet = BytesMut::with_capacity(12 + samples.len() * 3);
+        let mut packet = BytesMut::with_capacity(12 + samples.len() * self.format.bytes_per_sample());
         packet.extend_from_slice(&header.to_bytes());
         
-        // Convert f32 samples to L24 (24-bit big-endian)
-        for sample in samples {
-            // Clamp and scale to 24-bit range
-            let clamped = sample.clamp(-1.0, 1.0);
-            let scaled = (clamped * 8388607.0) as i32; // 2^23 - 1
-            
-            // Write as 24-bit big-endian
-            let bytes = scaled.to_be_bytes();
-            packet.put_slice(&bytes[1..4]); // Skip the MSB, write 3 bytes
+        match self.format.encoding {
+            RtpEncoding::L16 => encode_f32_to_l16(samples, &mut packet),
+            RtpEncoding::L24 => encode_f32_to_l24(samples, &mut packet),
+            RtpEncoding::Am824 => {
+                encode_f32_to_am824(samples, self.format.channels.max(1) as usize, timestamp, &mut packet)
+            }
         }
         
-        // Send packet
-        self.socket.send_to(&packet, self.destination)?;
+        // Send packet on every path, it is lost only if all of them fail
+        let mut result = Ok(());
+        let mut sent = false;
+        for path in &self.paths {
+            match path.socket.send_to(&packet, path.destination) {
+                Ok(_) => sent = true,
+                Err(e) => {
+                    path.errors.fetch_add(1, Ordering::Relaxed);
+                    result = Err(anyhow!("{}: {}", path.destination, e));
+                }
+            }
+        }
+        if !sent {
+            return result;
+        }
+        self.packets.fetch_add(1, Ordering::Relaxed);
+        self.octets.fetch_add((packet.len() - 12) as u32, Ordering::Relaxed);
         
         trace!("Sent RTP packet: seq={}, ts={}, samples={}", 
             sequence, timestamp, samples.len());
@@ -279,59 +501,311 @@ impl RtpSender {
     }
 }
 
+/// UDP socket sending to `destination`, via `interface` if given
+fn send_socket(destination: Ipv4Addr, interface: Option<Ipv4Addr>, ttl: u32, dscp: u8) -> Result<UdpSocket> {
+    use socket2::{Socket, Domain, Type, Protocol};
+    
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    let bind_ip = interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+    socket.bind(&SocketAddr::new(IpAddr::V4(bind_ip), 0).into())?;
+    
+    if destination.is_multicast() {
+        socket.set_multicast_ttl_v4(ttl)?;
+        if let Some(interface) = interface {
+            socket.set_multicast_if_v4(&interface)?;
+        }
+    } else {
+        socket.set_ttl(ttl)?;
+    }
+    
+    if dscp > 0 {
+        if let Err(e) = socket.set_tos((dscp as u32) << 2) {
+            warn!("Could not set DSCP {}: {}", dscp, e);
+        }
+    }
+    
+    Ok(socket.into())
+}
+
+/// A receive path counts as active while its last packet is at most this old
+const PATH_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Reception statistics of one network path
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PathStats {
+    /// Multicast group (or unicast address) and port
+    pub group: SocketAddrV4,
+    /// Packets received on this path
+    pub received: u64,
+    /// Packets missing from this path's sequence
+    pub lost: u64,
+    /// Packets this path delivered first (the other copy came later or never)
+    pub used: u64,
+    /// Packets arrived within the last 200 ms
+    pub active: bool,
+}
+
+/// Per-path sequence tracking of a received stream
+struct PathMonitor {
+    group: SocketAddrV4,
+    last_sequence: Option<u16>,
+    last_packet: Option<Instant>,
+    received: u64,
+    lost: u64,
+    used: u64,
+}
+
+impl PathMonitor {
+    fn new(group: SocketAddrV4) -> Self {
+        Self {
+            group,
+            last_sequence: None,
+            last_packet: None,
+            received: 0,
+            lost: 0,
+            used: 0,
+        }
+    }
+
+    /// Count a packet; `used` = it was not already received on another path
+    fn record(&mut self, sequence: u16, used: bool, now: Instant) {
+        self.received += 1;
+        if used {
+            self.used += 1;
+        }
+        match self.last_sequence {
+            Some(last) => {
+                let delta = sequence.wrapping_sub(last) as i16;
+                if delta > 0 {
+                    self.lost += (delta - 1) as u64;
+                    self.last_sequence = Some(sequence);
+                } else {
+                    // Counted as lost when the gap showed up
+                    self.lost = self.lost.saturating_sub(1);
+                }
+            }
+            None => self.last_sequence = Some(sequence),
+        }
+        self.last_packet = Some(now);
+    }
+
+    fn stats(&self, now: Instant) -> PathStats {
+        PathStats {
+            group: self.group,
+            received: self.received,
+            lost: self.lost,
+            used: self.used,
+            active: self.last_packet.is_some_and(|t| now.duration_since(t) < PATH_TIMEOUT),
+        }
+    }
+}
+
+/// One network path of a received stream
+struct ReceivePath {
+    socket: UdpSocket,
+    /// Only packets from this sender are accepted (None = any)
+    source: Option<Ipv4Addr>,
+    monitor: Arc<Mutex<PathMonitor>>,
+}
+
 /// RTP Stream for receiving audio
 pub struct RtpReceiver {
-    /// Socket for receiving
-    socket: UdpSocket,
+    /// Primary path, then the ST 2022-7 secondary path (if any)
+    paths: Vec<ReceivePath>,
     /// Audio format
     format: Aes67Format,
     /// Running flag
     running: Arc<AtomicBool>,
-    /// Jitter buffer (in samples)
+    /// Timestamp-indexed playout buffer
     jitter_buffer: Arc<RwLock<JitterBuffer>>,
-    /// Last received sequence
-    last_sequence: Arc<AtomicU32>,
+    /// PTP clock for playout at the link offset (None = free-running)
+    ptp_clock: Arc<RwLock<Option<Arc<PtpClock>>>>,
     /// Expected SSRC (None = accept any)
     expected_ssrc: Option<u32>,
+    /// Payload type from the stream's SDP (None = accept any)
+    expected_payload_type: Option<u8>,
+    /// Sample rate converter (if stream rate differs from engine rate
+    /// or drift compensation is active)
+    resampler: Mutex<Option<Resampler>>,
+    /// Resampler input taken from the playout buffer (sized for the whole
+    /// playout buffer, never grown in the audio callback)
+    resampler_input: Mutex<Vec<f32>>,
+    /// Frames of the stream's rate concealed because a lock was busy,
+    /// skipped on the next read to stay on the timeline
+    missed_frames: AtomicU32,
+    /// Engine sample rate
+    output_rate: AtomicU32,
+    /// Drift compensation (shared monitor + buffer fill controller)
+    drift: Mutex<Option<(Arc<DriftMonitor>, DriftController)>>,
+    /// RTP timestamp of the sender at media clock zero
+    media_clock_offset: Arc<AtomicU32>,
+    /// Loss and interarrival jitter for RTCP receiver reports
+    reception: Arc<Mutex<ReceptionStats>>,
+    /// Packet arrival for health monitoring
+    activity: Arc<Mutex<StreamActivity>>,
+    /// Transit and playout latency against PTP time
+    latency: Arc<Mutex<LatencyMonitor>>,
 }
 
 impl RtpReceiver {
     /// Create a new RTP receiver
     pub fn new(multicast_addr: Ipv4Addr, port: u16, format: Aes67Format) -> Result<Self> {
-        use socket2::{Socket, Domain, Type, Protocol};
-        
-        // Create socket with reuse
-        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
-        socket.set_reuse_address(true)?;
-        
-        #[cfg(unix)]
-        socket.set_reuse_port(true)?;
-        
-        // Bind to the port
-        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
-        socket.bind(&addr.into())?;
-        
-        // Join multicast group
-        socket.join_multicast_v4(&multicast_addr, &Ipv4Addr::UNSPECIFIED)?;
-        
-        let socket: UdpSocket = socket.into();
-        socket.set_read_timeout(Some(Duration::from_millis(10)))?;
-        
+        Self::with_options(multicast_addr, port, format, None, None)
+    }
+
+    /// Create an RTP receiver for a multicast group or a unicast stream
+    ///
+    /// `address` is the stream's destination: a multicast group, or for
+    /// unicast streams our own address. With a `source`, multicast groups
+    /// are joined source-specific and packets from other senders are
+    /// dropped. `interface` selects the network (None = all interfaces).
+    pub fn with_options(
+        address: Ipv4Addr,
+        port: u16,
+        format: Aes67Format,
+        source: Option<Ipv4Addr>,
+        interface: Option<Ipv4Addr>,
+    ) -> Result<Self> {
+        let jitter_buffer = JitterBuffer::new(
+            format.channels as usize,
+            format.samples_per_packet as usize,
+            format.samples_per_packet as usize * DEFAULT_LINK_OFFSET_PACKETS,
+        );
+        let resampler_input = vec![0.0; jitter_buffer.samples.len()];
+
         Ok(Self {
-            socket,
+            paths: vec![ReceivePath::open(address, port, source, interface)?],
             format,
             running: Arc::new(AtomicBool::new(false)),
-            jitter_buffer: Arc::new(RwLock::new(JitterBuffer::new(format.samples_per_packet as usize * 4))),
-            last_sequence: Arc::new(AtomicU32::new(0)),
+            jitter_buffer: Arc::new(RwLock::new(jitter_buffer)),
+            ptp_clock: Arc::new(RwLock::new(None)),
             expected_ssrc: None,
+            expected_payload_type: None,
+            resampler: Mutex::new(None),
+            resampler_input: Mutex::new(resampler_input),
+            missed_frames: AtomicU32::new(0),
+            output_rate: AtomicU32::new(format.sample_rate),
+            drift: Mutex::new(None),
+            media_clock_offset: Arc::new(AtomicU32::new(0)),
+            reception: Arc::new(Mutex::new(ReceptionStats::new(format.sample_rate))),
+            activity: Arc::new(Mutex::new(StreamActivity::new(Instant::now()))),
+            latency: Arc::new(Mutex::new(LatencyMonitor::new(format.sample_rate, Instant::now()))),
         })
     }
 
+    /// Set the engine sample rate
+    ///
+    /// If it differs from the stream's sample rate, samples are converted with
+    /// the asynchronous resampler when reading.
+    pub fn set_output_rate(&self, output_rate: u32) {
+        self.output_rate.store(output_rate, Ordering::Relaxed);
+        if output_rate != self.format.sample_rate {
+            info!("RTP stream SRC: {} Hz -> {} Hz", self.format.sample_rate, output_rate);
+        }
+        self.update_resampler();
+    }
+
+    /// Enable adaptive resampling against the local device clock
+    ///
+    /// The resampling ratio follows the measured drift and keeps playout
+    /// at the link offset.
+    pub fn set_drift_monitor(&self, monitor: Arc<DriftMonitor>) {
+        let target = self.link_offset();
+        *self.drift.lock() = Some((monitor, DriftController::new(target)));
+        self.update_resampler();
+    }
+
+    /// Play out relative to PTP time (AES67 link offset)
+    ///
+    /// Without a clock, or if the sender's timestamps are not on our PTP
+    /// time, playout follows the newest packet instead.
+    pub fn set_ptp_clock(&self, clock: Arc<PtpClock>) {
+        *self.ptp_clock.write() = Some(clock);
+    }
+
+    /// Media clock offset of the sender (`a=mediaclk:direct=` in its SDP)
+    pub fn set_media_clock_offset(&self, offset: u32) {
+        self.media_clock_offset.store(offset, Ordering::Relaxed);
+    }
+
+    /// Set the link offset in frames of the stream's sample rate
+    pub fn set_link_offset(&self, frames: usize) {
+        let frames = frames.max(self.format.samples_per_packet as usize);
+        let samples = {
+            let mut jitter_buffer = self.jitter_buffer.write();
+            jitter_buffer.set_link_offset(frames);
+            jitter_buffer.samples.len()
+        };
+        self.resampler_input.lock().resize(samples, 0.0);
+        if let Some((_, controller)) = self.drift.lock().as_mut() {
+            *controller = DriftController::new(frames);
+        }
+    }
+
+    /// Link offset in frames
+    pub fn link_offset(&self) -> usize {
+        self.jitter_buffer.read().link_offset
+    }
+
+    /// Playout statistics (loss, late, reordered, concealed frames, ...)
+    pub fn jitter_stats(&self) -> JitterStats {
+        self.jitter_buffer.read().stats()
+    }
+
+    /// Also receive the stream's ST 2022-7 copy on `group` via `interface`
+    ///
+    /// Packets of both paths are merged by timestamp in the playout buffer,
+    /// whichever copy arrives first is played. Call before `start`.
+    pub fn add_secondary(&mut self, group: Ipv4Addr, port: u16, interface: Ipv4Addr) -> Result<()> {
+        self.paths.truncate(1);
+        self.paths.push(ReceivePath::open(group, port, None, Some(interface))?);
+        Ok(())
+    }
+
+    /// Reception statistics per path (primary first)
+    pub fn path_stats(&self) -> Vec<PathStats> {
+        let now = Instant::now();
+        self.paths.iter().map(|path| path.monitor.lock().stats(now)).collect()
+    }
+
+    /// Reception statistics of the stream (merged paths) for RTCP
+    pub fn reception(&self) -> Arc<Mutex<ReceptionStats>> {
+        self.reception.clone()
+    }
+
+    /// Packet arrival of the stream (merged paths) for health monitoring
+    pub fn activity(&self) -> Arc<Mutex<StreamActivity>> {
+        self.activity.clone()
+    }
+
+    /// Transit and end-to-end latency against PTP time
+    pub fn latency_stats(&self) -> LatencyStats {
+        self.latency.lock().stats(self.link_offset())
+    }
+
+    fn update_resampler(&self) {
+        let output_rate = self.output_rate.load(Ordering::Relaxed);
+        let needed = output_rate != self.format.sample_rate || self.drift.lock().is_some();
+        *self.resampler.lock() = needed.then(|| {
+            Resampler::new(self.format.channels as usize, self.format.sample_rate, output_rate)
+        });
+    }
+
+    /// Audio format of the received stream
+    pub fn format(&self) -> Aes67Format {
+        self.format
+    }
+
     /// Set expected SSRC (filter packets)
     pub fn set_expected_ssrc(&mut self, ssrc: u32) {
         self.expected_ssrc = Some(ssrc);
     }
 
+    /// Set the payload type of the stream's SDP, packets with others are dropped
+    pub fn set_expected_payload_type(&mut self, payload_type: u8) {
+        self.expected_payload_type = Some(payload_type);
+    }
+
     /// Start receiving (spawns background thread)
     pub fn start(&self) -> Result<()> {
         if self.running.load(Ordering::Relaxed) {
@@ -340,22 +814,45 @@ impl RtpReceiver {
         
         self.running.store(true, Ordering::Relaxed);
         
-        let socket = self.socket.try_clone()?;
+        for path in &self.paths {
+            if let Err(e) = self.spawn_path(path) {
+                self.stop();
+                return Err(e);
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// Receive thread of one path
+    fn spawn_path(&self, path: &ReceivePath) -> Result<()> {
+        let socket = path.socket.try_clone()?;
+        let source = path.source;
+        let monitor = path.monitor.clone();
+        let reception = self.reception.clone();
+        let activity = self.activity.clone();
+        let latency = self.latency.clone();
+        let ptp_clock = self.ptp_clock.clone();
+        let media_clock_offset = self.media_clock_offset.clone();
         let running = self.running.clone();
         let jitter_buffer = self.jitter_buffer.clone();
         let expected_ssrc = self.expected_ssrc;
-        let last_sequence = self.last_sequence.clone();
-        let channels = self.format.channels;
+        let expected_payload_type = self.expected_payload_type;
+        let encoding = self.format.encoding;
+        let sample_rate = self.format.sample_rate;
         
         std::thread::spawn(move || {
             let mut buf = [0u8; 2048];
             
             while running.load(Ordering::Relaxed) {
                 match socket.recv_from(&mut buf) {
-                    Ok((len, _src)) => {
+                    Ok((len, src)) => {
                         if len < 12 {
                             continue;
                         }
+                        if source.is_some_and(|source| src.ip() != IpAddr::V4(source)) {
+                            continue;
+                        }
                         
                         if let Some(header) = RtpHeader::from_bytes(&buf[..len]) {
                             // Check SSRC if filtering
@@ -365,22 +862,32 @@ impl RtpReceiver {
                                 }
                             }
                             
-                            // Check sequence for packet loss
-                            let last_seq = last_sequence.swap(header.sequence as u32, Ordering::Relaxed) as u16;
-                            let expected_seq = last_seq.wrapping_add(1);
-                            if header.sequence != expected_seq && last_seq != 0 {
-                                let lost = header.sequence.wrapping_sub(expected_seq);
-                                if lost > 0 && lost < 100 {
-                                    warn!("Packet loss detected: {} packets", lost);
-                                }
+                            // Another payload on the same port is not this stream's audio
+                            if expected_payload_type.is_some_and(|pt| header.payload_type != pt) {
+                                continue;
                             }
                             
-                            // Decode L24 payload to f32
-                            let payload = &buf[12..len];
-                            let samples = decode_l24_to_f32(payload, channels);
+                            // Decode the payload to f32
+                            let Some(payload) = header.payload(&buf[..len]) else {
+                                continue;
+                            };
+                            let samples = decode_samples(payload, encoding);
                             
-                            // Add to jitter buffer
-                            jitter_buffer.write().push_samples(&samples);
+                            // Store at its timestamp (reordering, loss and late detection),
+                            // the copy from the other path is dropped as duplicate
+                            let used = jitter_buffer.write().push(header.sequence, header.timestamp, &samples);
+                            let now = Instant::now();
+                            monitor.lock().record(header.sequence, used, now);
+                            if used {
+                                reception.lock().record(header.ssrc, header.sequence, header.timestamp, src.ip(), now);
+                                activity.lock().record(header.ssrc, now);
+                                // Media time at arrival minus the packet's timestamp
+                                if let Some(clock) = ptp_clock.read().as_ref() {
+                                    let arrival = clock.media_timestamp(sample_rate)
+                                        .wrapping_add(media_clock_offset.load(Ordering::Relaxed));
+                                    latency.lock().record_transit(arrival.wrapping_sub(header.timestamp) as i32, now);
+                                }
+                            }
                         }
                     }
                     Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -392,7 +899,7 @@ impl RtpReceiver {
                 }
             }
             
-            info!("RTP receiver stopped");
+            debug!("RTP receive path {} stopped", monitor.lock().group);
         });
         
         Ok(())
@@ -403,100 +910,605 @@ impl RtpReceiver {
         self.running.store(false, Ordering::Relaxed);
     }
 
-    /// Read samples from jitter buffer
+    /// Read samples from the playout buffer (converted to the engine rate if needed)
+    ///
+    /// Runs in the audio callback and never waits for a lock: if the receive
+    /// thread or a setter holds one, the block is concealed with silence and
+    /// playout skips the missed time on the next read.
     pub fn read(&self, buffer: &mut [f32]) -> usize {
-        self.jitter_buffer.write().pop_samples(buffer)
+        // Media time that should be played now, before the link offset
+        let Some(now) = self.ptp_clock.try_read().map(|clock| clock.as_ref().map(|clock| {
+            clock.media_timestamp(self.format.sample_rate)
+                .wrapping_add(self.media_clock_offset.load(Ordering::Relaxed))
+        })) else {
+            return self.conceal(buffer);
+        };
+
+        let Some(mut resampler) = self.resampler.try_lock() else {
+            return self.conceal(buffer);
+        };
+        let Some(resampler) = resampler.as_mut() else {
+            let Some(mut jitter_buffer) = try_write_briefly(&self.jitter_buffer) else {
+                return self.conceal(buffer);
+            };
+            jitter_buffer.skip(self.missed_frames.swap(0, Ordering::Relaxed) as usize);
+            let ideal = now.map(|now| now.wrapping_sub(jitter_buffer.link_offset as u32));
+            let delay = jitter_buffer.sync(ideal, 0);
+            let aligned = jitter_buffer.aligned;
+            let read = jitter_buffer.pop(buffer);
+            drop(jitter_buffer);
+            if let Some(mut latency) = self.latency.try_lock() {
+                latency.record_playout(aligned.then_some(delay));
+            }
+            return read;
+        };
+
+        let channels = self.format.channels.max(1) as usize;
+        let frames = buffer.len() / channels;
+        let lookahead = resampler.buffered_frames().max(0.0) as usize;
+        let Some(mut input) = self.resampler_input.try_lock() else {
+            return self.conceal(buffer);
+        };
+        let Some(mut jitter_buffer) = try_write_briefly(&self.jitter_buffer) else {
+            return self.conceal(buffer);
+        };
+        jitter_buffer.skip(self.missed_frames.swap(0, Ordering::Relaxed) as usize);
+        let ideal = now.map(|now| now.wrapping_sub(jitter_buffer.link_offset as u32));
+        let delay = jitter_buffer.sync(ideal, lookahead);
+        let aligned = jitter_buffer.aligned;
+
+        // Drift compensation: keep the playout delay at the link offset
+        // (on contention the previous ratio stays)
+        if let Some(mut drift) = self.drift.try_lock() {
+            if let Some((monitor, controller)) = drift.as_mut() {
+                let correction = controller.update(monitor.drift_ppm(), delay);
+                resampler.set_ratio_adjust_ppm(correction);
+                monitor.report_buffer(delay, controller.target_fill(), correction);
+            }
+        }
+
+        let needed = resampler.input_frames_needed(frames).min(input.len() / channels);
+        if needed > 0 {
+            let read = jitter_buffer.pop(&mut input[..needed * channels]);
+            resampler.push(&input[..read - read % channels]);
+        }
+        drop(jitter_buffer);
+        if let Some(mut latency) = self.latency.try_lock() {
+            latency.record_playout(aligned.then_some(delay));
+        }
+
+        let produced = resampler.pull(buffer);
+        buffer[produced * channels..].fill(0.0);
+        produced * channels
+    }
+
+    /// Silence for a block that could not be read, remembered as missed time
+    fn conceal(&self, buffer: &mut [f32]) -> usize {
+        buffer.fill(0.0);
+        self.skip(buffer.len() / self.format.channels.max(1) as usize);
+        0
+    }
+
+    /// Frames of the engine rate that were not read; playout skips them on
+    /// the next read to stay on time
+    pub fn skip(&self, frames: usize) {
+        let output_rate = self.output_rate.load(Ordering::Relaxed).max(1) as u64;
+        let frames = frames as u64 * self.format.sample_rate as u64 / output_rate;
+        self.missed_frames.fetch_add(frames as u32, Ordering::Relaxed);
     }
 
     /// Check if receiving data
     pub fn is_receiving(&self) -> bool {
-        self.jitter_buffer.read().available() > 0
+        self.jitter_buffer.read().fill() > 0
     }
 }
 
-/// Decode L24 (24-bit big-endian) audio to f32
-fn decode_l24_to_f32(data: &[u8], channels: u8) -> Vec<f32> {
-    let sample_count = data.len() / 3;
-    let mut samples = Vec::with_capacity(sample_count);
-    
-    for i in 0..sample_count {
-        let offset = i * 3;
-        if offset + 3 > data.len() {
-            break;
+impl ReceivePath {
+    /// Socket receiving `address`:`port` (multicast group or unicast)
+    ///
+    /// Multicast groups are joined via `interface` if given, source-specific
+    /// if `source` is known.
+    fn open(address: Ipv4Addr, port: u16, source: Option<Ipv4Addr>, interface: Option<Ipv4Addr>) -> Result<Self> {
+        use socket2::{Socket, Domain, Type, Protocol};
+        
+        let multicast = address.is_multicast();
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        
+        // Unicast sockets sharing a port would get the packets load-balanced
+        #[cfg(unix)]
+        if multicast {
+            socket.set_reuse_port(true)?;
         }
         
-        // Read 24-bit big-endian, sign-extend to 32-bit
-        let raw = ((data[offset] as i32) << 16)
-            | ((data[offset + 1] as i32) << 8)
-            | (data[offset + 2] as i32);
+        // Bind to the group address so that several streams can share the
+        // same port without receiving each other's packets (Unix only).
+        // Unicast streams arrive on our address of the interface.
+        #[cfg(unix)]
+        let bind_ip = if multicast { address } else { interface.unwrap_or(Ipv4Addr::UNSPECIFIED) };
+        #[cfg(not(unix))]
+        let bind_ip = if multicast { Ipv4Addr::UNSPECIFIED } else { interface.unwrap_or(Ipv4Addr::UNSPECIFIED) };
+        let addr = SocketAddr::new(IpAddr::V4(bind_ip), port);
+        socket.bind(&addr.into())
+            .map_err(|e| anyhow!("Cannot receive on {}:{}: {}", bind_ip, port, e))?;
         
-        // Sign extend from 24-bit
-        let signed = if raw & 0x800000 != 0 {
-            raw | !0xFFFFFF // Sign extend negative
-        } else {
-            raw
-        };
+        if multicast {
+            if interface.is_some() {
+                super::interface::only_joined_groups(&socket)?;
+            }
+            let interface = interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+            match source {
+                Some(source) => {
+                    if let Err(e) = socket.join_ssm_v4(&source, &address, &interface) {
+                        // Packets of other senders are still dropped on receive
+                        warn!("Source-specific join of {} from {} failed ({}), joining the whole group", address, source, e);
+                        socket.join_multicast_v4(&address, &interface)?;
+                    }
+                }
+                None => socket.join_multicast_v4(&address, &interface)?,
+            }
+        }
+        
+        let socket: UdpSocket = socket.into();
+        socket.set_read_timeout(Some(Duration::from_millis(10)))?;
+        
+        Ok(Self {
+            socket,
+            source,
+            monitor: Arc::new(Mutex::new(PathMonitor::new(SocketAddrV4::new(address, port)))),
+        })
+    }
+}
+
+/// Encode f32 samples as L24 (24-bit big-endian)
+fn encode_f32_to_l24(samples: &[f32], packet: &mut BytesMut) {
+    for sample in samples {
+        // Clamp and scale to 24-bit range
+        let clamped = sample.clamp(-1.0, 1.0);
+        let scaled = (clamped * 8388607.0) as i32; // 2^23 - 1
         
-        // Convert to f32 (-1.0 to 1.0)
-        let sample = signed as f32 / 8388607.0;
-        samples.push(sample);
+        // Write as 24-bit big-endian
+        let bytes = scaled.to_be_bytes();
+        packet.put_slice(&bytes[1..4]); // Skip the MSB, write 3 bytes
     }
-    
-    samples
 }
 
-/// Simple jitter buffer for smoothing out network jitter
+/// Encode f32 samples as L16 (16-bit big-endian)
+fn encode_f32_to_l16(samples: &[f32], packet: &mut BytesMut) {
+    for sample in samples {
+        let scaled = (sample.clamp(-1.0, 1.0) * 32767.0) as i16;
+        packet.put_i16(scaled);
+    }
+}
+
+/// Frames per AES3 channel status block
+const AES3_BLOCK_FRAMES: u32 = 192;
+
+/// AES3 channel status sent with AM824: professional use, linear PCM, all
+/// other fields not indicated
+const AM824_CHANNEL_STATUS: [u8; 24] = aes3_channel_status();
+
+/// Channel status block with its CRCC in byte 23
+/// (x^8 + x^4 + x^3 + x^2 + 1, preset to ones, LSB first)
+const fn aes3_channel_status() -> [u8; 24] {
+    let mut block = [0u8; 24];
+    block[0] = 0x01;
+    let mut crc = 0xFFu8;
+    let mut i = 0;
+    while i < 23 {
+        crc ^= block[i];
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xB8 } else { crc >> 1 };
+            bit += 1;
+        }
+        i += 1;
+    }
+    block[23] = crc;
+    block
+}
+
+/// Encode f32 samples as AM824 words (IEC 60958 conformant, 24-bit audio)
+///
+/// Channel pairs form AES3 frames. `frame` is the media clock timestamp of
+/// the first frame, it places the block start and channel status bits.
+fn encode_f32_to_am824(samples: &[f32], channels: usize, frame: u32, packet: &mut BytesMut) {
+    for (i, sample) in samples.iter().enumerate() {
+        let frame = frame.wrapping_add((i / channels) as u32) % AES3_BLOCK_FRAMES;
+        let audio = ((sample.clamp(-1.0, 1.0) * 8388607.0) as i32 as u32) & 0xFF_FFFF;
+        let status = (AM824_CHANNEL_STATUS[frame as usize / 8] >> (frame % 8)) as u32 & 1;
+        // Preamble code: B at block start, M for the first and W for the second subframe
+        let preamble = match (i % channels % 2, frame) {
+            (1, _) => 0b11,
+            (_, 0) => 0b00,
+            _ => 0b10,
+        };
+        // Even parity over audio, V, U and C
+        let parity = (audio.count_ones() + status) & 1;
+        let label = (preamble << 4) | (parity << 3) | (status << 2);
+        packet.put_u32((label << 24) | audio);
+    }
+}
+
+/// Decode a payload to f32 samples
+pub fn decode_samples(data: &[u8], encoding: RtpEncoding) -> Vec<f32> {
+    match encoding {
+        RtpEncoding::L16 => decode_l16_to_f32(data),
+        RtpEncoding::L24 => decode_l24_to_f32(data),
+        // The label byte carries AES3 metadata, the audio is the lower 24 bits
+        RtpEncoding::Am824 => data.chunks_exact(4).map(|word| l24_to_f32(&word[1..])).collect(),
+    }
+}
+
+/// Decode L16 (16-bit big-endian) audio to f32
+fn decode_l16_to_f32(data: &[u8]) -> Vec<f32> {
+    data.chunks_exact(2)
+        .map(|bytes| i16::from_be_bytes([bytes[0], bytes[1]]) as f32 / 32767.0)
+        .collect()
+}
+
+/// Decode L24 (24-bit big-endian) audio to f32
+fn decode_l24_to_f32(data: &[u8]) -> Vec<f32> {
+    data.chunks_exact(3).map(l24_to_f32).collect()
+}
+
+/// One 24-bit big-endian sample as f32 (-1.0 to 1.0)
+fn l24_to_f32(bytes: &[u8]) -> f32 {
+    // Sign-extend by placing the sample in the upper 24 bits
+    let signed = i32::from_be_bytes([bytes[0], bytes[1], bytes[2], 0]) >> 8;
+    signed as f32 / 8388607.0
+}
+
+/// Default link offset in packets (playout delay behind the sender)
+const DEFAULT_LINK_OFFSET_PACKETS: usize = 3;
+
+/// Concealment fades the repeated packet out over this many packets
+const CONCEALMENT_FADE_PACKETS: usize = 2;
+
+/// Sequence numbers remembered for duplicate detection (power of two)
+const SEEN_SEQUENCES: usize = 1024;
+
+/// Playout statistics of a received stream
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct JitterStats {
+    /// Packets received
+    pub received: u64,
+    /// Packets missing from the sequence
+    pub lost: u64,
+    /// Packets that arrived after their playout time
+    pub late: u64,
+    /// Packets that arrived out of order but in time
+    pub reordered: u64,
+    /// Packets received twice (includes the copies of the ST 2022-7 secondary path)
+    pub duplicates: u64,
+    /// Frames filled by loss concealment
+    pub concealed_frames: u64,
+    /// Playout position realigned (timestamp jump, drift beyond the link offset)
+    pub resyncs: u64,
+    /// Frames buffered ahead of the playout position
+    pub fill: u32,
+    /// Link offset in frames
+    pub link_offset: u32,
+    /// Playout follows PTP time (false = free-running on the stream's timestamps)
+    pub ptp_aligned: bool,
+}
+
+/// Attempts of the audio callback to take the playout buffer, which the
+/// receive threads hold only while storing a packet
+const PLAYOUT_LOCK_ATTEMPTS: usize = 64;
+
+/// Attempts spent spinning before yielding to a preempted lock holder
+const PLAYOUT_LOCK_SPINS: usize = 16;
+
+/// Write lock for the audio callback: retries briefly, never parks
+fn try_write_briefly<T>(lock: &RwLock<T>) -> Option<RwLockWriteGuard<'_, T>> {
+    for attempt in 0..PLAYOUT_LOCK_ATTEMPTS {
+        if let Some(guard) = lock.try_write() {
+            return Some(guard);
+        }
+        if attempt < PLAYOUT_LOCK_SPINS {
+            std::hint::spin_loop();
+        } else {
+            std::thread::yield_now();
+        }
+    }
+    None
+}
+
+/// Timestamp-indexed playout buffer
+///
+/// Frames are stored at their RTP timestamp, so packets are reordered and
+/// gaps are detected on the way out. Each slot remembers the timestamp it
+/// holds; stale or missing frames are concealed by repeating the last
+/// packet with a fade-out. Playout runs `link_offset` frames behind the PTP
+/// media clock (AES67 link offset), or behind the newest packet if the
+/// sender's timestamps are not on our clock.
 struct JitterBuffer {
-    buffer: Vec<f32>,
-    read_pos: usize,
-    write_pos: usize,
-    capacity: usize,
+    channels: usize,
+    frames_per_packet: usize,
+    link_offset: usize,
+    /// Interleaved frames, ring indexed by timestamp (power of two frames)
+    samples: Vec<f32>,
+    /// Timestamp held by each slot (None = empty)
+    slots: Vec<Option<u32>>,
+    /// Next timestamp to play (None = playout not started)
+    read_ts: Option<u32>,
+    /// First timestamp received since the last reset
+    oldest_ts: Option<u32>,
+    /// End of the newest data received (exclusive)
+    newest_ts: Option<u32>,
+    last_sequence: Option<u16>,
+    /// Recently received sequence numbers, indexed by their low bits
+    seen: Vec<Option<u16>>,
+    /// Last packet played, repeated for concealment
+    history: Vec<f32>,
+    history_pos: usize,
+    /// Consecutive concealed frames
+    concealed_run: usize,
+    aligned: bool,
+    stats: JitterStats,
 }
 
 impl JitterBuffer {
-    fn new(capacity: usize) -> Self {
+    fn new(channels: usize, frames_per_packet: usize, link_offset: usize) -> Self {
+        let channels = channels.max(1);
+        let frames_per_packet = frames_per_packet.max(1);
+        let capacity = Self::capacity_for(frames_per_packet, link_offset);
+
         Self {
-            buffer: vec![0.0; capacity],
-            read_pos: 0,
-            write_pos: 0,
-            capacity,
+            channels,
+            frames_per_packet,
+            link_offset,
+            samples: vec![0.0; capacity * channels],
+            slots: vec![None; capacity],
+            read_ts: None,
+            oldest_ts: None,
+            newest_ts: None,
+            last_sequence: None,
+            seen: vec![None; SEEN_SEQUENCES],
+            history: vec![0.0; frames_per_packet * channels],
+            history_pos: 0,
+            concealed_run: 0,
+            aligned: false,
+            stats: JitterStats::default(),
         }
     }
 
-    fn push_samples(&mut self, samples: &[f32]) {
-        for &sample in samples {
-            self.buffer[self.write_pos] = sample;
-            self.write_pos = (self.write_pos + 1) % self.capacity;
-            
-            // Prevent overflow (overwrite oldest)
-            if self.write_pos == self.read_pos {
-                self.read_pos = (self.read_pos + 1) % self.capacity;
+    /// Ring size in frames: room for the link offset plus late and early packets
+    fn capacity_for(frames_per_packet: usize, link_offset: usize) -> usize {
+        (link_offset * 4).max(frames_per_packet * 16).next_power_of_two()
+    }
+
+    /// Capacity in frames
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn slot(&self, timestamp: u32) -> usize {
+        timestamp as usize & (self.capacity() - 1)
+    }
+
+    fn set_link_offset(&mut self, link_offset: usize) {
+        let capacity = Self::capacity_for(self.frames_per_packet, link_offset);
+        if capacity != self.capacity() {
+            self.samples = vec![0.0; capacity * self.channels];
+            self.slots = vec![None; capacity];
+        }
+        self.link_offset = link_offset;
+        self.reset();
+    }
+
+    /// Drop all buffered audio and restart playout
+    fn reset(&mut self) {
+        self.slots.iter_mut().for_each(|s| *s = None);
+        self.read_ts = None;
+        self.oldest_ts = None;
+        self.newest_ts = None;
+        self.last_sequence = None;
+        self.seen.iter_mut().for_each(|s| *s = None);
+        self.aligned = false;
+    }
+
+    /// Store a packet (interleaved samples) at its timestamp
+    ///
+    /// Returns false if the packet was received before (duplicate or the
+    /// copy from the other ST 2022-7 path) or carries no audio.
+    fn push(&mut self, sequence: u16, timestamp: u32, samples: &[f32]) -> bool {
+        let frames = samples.len() / self.channels;
+        if frames == 0 {
+            return false;
+        }
+        let seen = sequence as usize & (SEEN_SEQUENCES - 1);
+        if self.seen[seen] == Some(sequence) {
+            self.stats.duplicates += 1;
+            return false;
+        }
+        self.stats.received += 1;
+
+        // Sender restarted or jumped: start over
+        if let Some(newest) = self.newest_ts {
+            if (timestamp.wrapping_sub(newest) as i32).unsigned_abs() as usize > self.capacity() {
+                self.reset();
+                self.stats.resyncs += 1;
+            }
+        }
+
+        self.seen[sequence as usize & (SEEN_SEQUENCES - 1)] = Some(sequence);
+
+        let mut out_of_order = false;
+        match self.last_sequence {
+            Some(last) => {
+                let delta = sequence.wrapping_sub(last) as i16;
+                if delta > 0 {
+                    self.stats.lost += (delta - 1) as u64;
+                    self.last_sequence = Some(sequence);
+                } else {
+                    out_of_order = true;
+                }
             }
+            None => self.last_sequence = Some(sequence),
         }
+
+        // Frames before the playout position are too late
+        let mut first = 0;
+        if let Some(read) = self.read_ts {
+            let behind = read.wrapping_sub(timestamp) as i32;
+            if behind > 0 {
+                first = (behind as usize).min(frames);
+            }
+        }
+        let late = first > 0;
+
+        if out_of_order {
+            // It was counted as lost when the gap showed up
+            self.stats.lost = self.stats.lost.saturating_sub(1);
+            if !late {
+                self.stats.reordered += 1;
+            }
+        }
+        if late {
+            self.stats.late += 1;
+            if first == frames {
+                return true;
+            }
+        }
+
+        for frame in first..frames {
+            let ts = timestamp.wrapping_add(frame as u32);
+            let slot = self.slot(ts);
+            let src = &samples[frame * self.channels..(frame + 1) * self.channels];
+            self.samples[slot * self.channels..(slot + 1) * self.channels].copy_from_slice(src);
+            self.slots[slot] = Some(ts);
+        }
+
+        let end = timestamp.wrapping_add(frames as u32);
+        self.oldest_ts.get_or_insert(timestamp);
+        match self.newest_ts {
+            Some(newest) if (end.wrapping_sub(newest) as i32) <= 0 => {}
+            _ => self.newest_ts = Some(end),
+        }
+
+        // Playout fell too far behind to hold the data: jump to the link offset
+        if let Some(read) = self.read_ts {
+            if end.wrapping_sub(read) as i32 as i64 > self.capacity() as i64 {
+                self.read_ts = Some(end.wrapping_sub(self.link_offset as u32));
+                self.stats.resyncs += 1;
+            }
+        }
+        true
     }
 
-    fn pop_samples(&mut self, buffer: &mut [f32]) -> usize {
-        let available = self.available();
-        let to_read = std::cmp::min(buffer.len(), available);
-        
-        for sample in buffer.iter_mut().take(to_read) {
-            *sample = self.buffer[self.read_pos];
-            self.read_pos = (self.read_pos + 1) % self.capacity;
+    /// Frames buffered ahead of the playout position
+    fn fill(&self) -> usize {
+        let (Some(newest), Some(start)) = (self.newest_ts, self.read_ts.or(self.oldest_ts)) else {
+            return 0;
+        };
+        (newest.wrapping_sub(start) as i32).max(0) as usize
+    }
+
+    /// Start or realign playout before reading
+    ///
+    /// `ideal` is the timestamp that should be played now (PTP media time
+    /// minus the link offset, None without PTP). `lookahead` are frames
+    /// already taken from the buffer but not yet played (resampler). Returns
+    /// the playout delay in frames, which the drift controller keeps at the
+    /// link offset.
+    fn sync(&mut self, ideal: Option<u32>, lookahead: usize) -> usize {
+        let Some(newest) = self.newest_ts else {
+            return 0;
+        };
+
+        // Timestamps far from our media clock: sender is not on our PTP time
+        let ideal = ideal.filter(|&t| (newest.wrapping_sub(t) as i32).unsigned_abs() as usize <= self.capacity());
+        self.aligned = ideal.is_some();
+
+        match (self.read_ts, ideal) {
+            (None, Some(ideal)) => self.read_ts = Some(ideal),
+            (None, None) => {
+                if self.fill() >= self.link_offset {
+                    self.read_ts = Some(newest.wrapping_sub(self.link_offset as u32));
+                }
+            }
+            (Some(read), Some(ideal)) => {
+                let error = read.wrapping_sub(ideal) as i32 as i64 - lookahead as i64;
+                if error.unsigned_abs() as usize > self.link_offset.max(self.frames_per_packet * 2) {
+                    self.read_ts = Some(ideal);
+                    self.stats.resyncs += 1;
+                }
+            }
+            (Some(_), None) => {}
         }
-        
-        // Fill rest with silence if not enough data
-        for sample in buffer.iter_mut().skip(to_read) {
-            *sample = 0.0;
+
+        match (self.read_ts, ideal) {
+            (Some(read), Some(ideal)) => {
+                let error = read.wrapping_sub(ideal) as i32 as i64 - lookahead as i64;
+                (self.link_offset as i64 - error).max(0) as usize
+            }
+            _ => self.fill() + lookahead,
         }
-        
-        to_read
     }
 
-    fn available(&self) -> usize {
-        if self.write_pos >= self.read_pos {
-            self.write_pos - self.read_pos
-        } else {
-            self.capacity - self.read_pos + self.write_pos
+    /// Advance playout by frames that were not read (busy lock)
+    fn skip(&mut self, frames: usize) {
+        if frames == 0 {
+            return;
+        }
+        if let Some(read) = self.read_ts {
+            self.read_ts = Some(read.wrapping_add(frames as u32));
+            self.stats.concealed_frames += frames as u64;
+        }
+    }
+
+    /// Play out frames (interleaved), missing frames are concealed
+    ///
+    /// Returns the number of samples written, 0 before playout has started.
+    fn pop(&mut self, out: &mut [f32]) -> usize {
+        let Some(mut read) = self.read_ts else {
+            out.fill(0.0);
+            return 0;
+        };
+        let channels = self.channels;
+        let frames = out.len() / channels;
+        let fade_frames = self.frames_per_packet * CONCEALMENT_FADE_PACKETS;
+
+        for frame in out.chunks_exact_mut(channels).take(frames) {
+            let slot = self.slot(read);
+            let history = self.history_pos * channels;
+
+            if self.slots[slot] == Some(read) {
+                frame.copy_from_slice(&self.samples[slot * channels..(slot + 1) * channels]);
+                self.slots[slot] = None;
+                self.history[history..history + channels].copy_from_slice(frame);
+                self.history_pos = (self.history_pos + 1) % self.frames_per_packet;
+                self.concealed_run = 0;
+            } else {
+                // Repeat the last packet, fading out
+                let gain = 1.0 - self.concealed_run as f32 / fade_frames as f32;
+                if gain > 0.0 {
+                    let repeat = (self.history_pos + self.concealed_run) % self.frames_per_packet * channels;
+                    for (sample, &last) in frame.iter_mut().zip(&self.history[repeat..repeat + channels]) {
+                        *sample = last * gain;
+                    }
+                    self.stats.concealed_frames += 1;
+                } else {
+                    frame.fill(0.0);
+                }
+                self.concealed_run += 1;
+            }
+            read = read.wrapping_add(1);
+        }
+        out[frames * channels..].fill(0.0);
+
+        self.read_ts = Some(read);
+        frames * channels
+    }
+
+    fn stats(&self) -> JitterStats {
+        JitterStats {
+            fill: self.fill() as u32,
+            link_offset: self.link_offset as u32,
+            ptp_aligned: self.aligned,
+            ..self.stats
         }
     }
 }
@@ -530,38 +1542,354 @@ mod tests {
         assert_eq!(parsed.ssrc, 0xCAFEBABE);
     }
     
+    #[test]
+    fn test_rtp_payload_offset() {
+        let mut header = RtpHeader::new(97, 1);
+        let payload = [1, 2, 3, 4, 5, 6];
+        let packet = |header: &RtpHeader, extra: &[u8], padding: &[u8]| {
+            [&header.to_bytes()[..], extra, &payload, padding].concat()
+        };
+        assert_eq!(header.payload(&packet(&header, &[], &[])), Some(&payload[..]));
+
+        // Two CSRCs
+        header.csrc_count = 2;
+        let csrcs = [0xAA; 8];
+        assert_eq!(header.payload(&packet(&header, &csrcs, &[])), Some(&payload[..]));
+
+        // Header extension with one 32-bit word after the CSRCs
+        header.extension = true;
+        let extension = [&csrcs[..], &[0xBE, 0xDE, 0, 1, 9, 9, 9, 9]].concat();
+        assert_eq!(header.payload(&packet(&header, &extension, &[])), Some(&payload[..]));
+
+        // Padding, its last octet counts itself
+        header.padding = true;
+        assert_eq!(header.payload(&packet(&header, &extension, &[0, 0, 3])), Some(&payload[..]));
+    }
+
+    #[test]
+    fn test_rtp_payload_malformed() {
+        let mut header = RtpHeader::new(97, 1);
+        header.csrc_count = 4;
+        assert_eq!(header.payload(&[&header.to_bytes()[..], &[0; 8]].concat()), None);
+
+        // Extension longer than the packet
+        let mut header = RtpHeader::new(97, 1);
+        header.extension = true;
+        assert_eq!(header.payload(&[&header.to_bytes()[..], &[0, 0, 0, 8, 1, 2]].concat()), None);
+
+        // Padding longer than the packet, or zero
+        let mut header = RtpHeader::new(97, 1);
+        header.padding = true;
+        assert_eq!(header.payload(&[&header.to_bytes()[..], &[1, 2, 40]].concat()), None);
+        assert_eq!(header.payload(&[&header.to_bytes()[..], &[1, 2, 0]].concat()), None);
+    }
+
     #[test]
     fn test_l24_decode() {
         // Test: 0x7FFFFF = max positive = ~1.0
         let data = [0x7F, 0xFF, 0xFF];
-        let samples = decode_l24_to_f32(&data, 1);
+        let samples = decode_l24_to_f32(&data);
         assert!((samples[0] - 1.0).abs() < 0.001);
         
         // Test: 0x800000 = max negative = ~-1.0
         let data = [0x80, 0x00, 0x00];
-        let samples = decode_l24_to_f32(&data, 1);
+        let samples = decode_l24_to_f32(&data);
         assert!((samples[0] + 1.0).abs() < 0.001);
         
         // Test: 0x000000 = zero
         let data = [0x00, 0x00, 0x00];
-        let samples = decode_l24_to_f32(&data, 1);
+        let samples = decode_l24_to_f32(&data);
         assert_eq!(samples[0], 0.0);
     }
     
     #[test]
-    fn test_jitter_buffer() {
-        let mut jb = JitterBuffer::new(100);
-        
-        // Push samples
-        jb.push_samples(&[0.1, 0.2, 0.3, 0.4]);
-        assert_eq!(jb.available(), 4);
-        
-        // Pop samples
-        let mut out = [0.0; 2];
-        let read = jb.pop_samples(&mut out);
-        assert_eq!(read, 2);
-        assert_eq!(out[0], 0.1);
-        assert_eq!(out[1], 0.2);
-        assert_eq!(jb.available(), 2);
+    fn test_l16_l24_encode() {
+        let mut packet = BytesMut::new();
+        encode_f32_to_l16(&[1.0, -1.0, 0.5], &mut packet);
+        assert_eq!(&packet[..], &[0x7F, 0xFF, 0x80, 0x01, 0x3F, 0xFF]);
+        
+        let mut packet = BytesMut::new();
+        encode_f32_to_l24(&[1.0, -0.5], &mut packet);
+        let decoded = decode_l24_to_f32(&packet);
+        assert!((decoded[0] - 1.0).abs() < 1e-6);
+        assert!((decoded[1] + 0.5).abs() < 1e-6);
+        
+        let decoded = decode_samples(&[0x7F, 0xFF, 0xC0, 0x00], RtpEncoding::L16);
+        assert!((decoded[0] - 1.0).abs() < 1e-6);
+        assert!((decoded[1] + 0.5).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn test_am824_encode() {
+        // Stereo, first frame at the block start
+        let mut packet = BytesMut::new();
+        encode_f32_to_am824(&[0.5, -0.5, 0.0, 0.0], 2, 0, &mut packet);
+        let words: Vec<u32> = packet.chunks_exact(4).map(|w| u32::from_be_bytes(w.try_into().unwrap())).collect();
+        
+        // B preamble, channel status bit 0 set (professional), audio 0x3FFFFF
+        assert_eq!(words[0] >> 24, 0b0000_0100 | (((0x3FFFFF_u32.count_ones() + 1) & 1) << 3));
+        assert_eq!(words[0] & 0xFF_FFFF, 0x3F_FFFF);
+        // W preamble for the second subframe, M once the block has started
+        assert_eq!(words[1] >> 28, 0b0011);
+        assert_eq!(words[2] >> 28, 0b0010);
+        
+        // Every word has even parity over bits 0-27
+        assert!(words.iter().all(|w| (w & 0x0FFF_FFFF).count_ones() % 2 == 0));
+        
+        let decoded = decode_samples(&packet, RtpEncoding::Am824);
+        assert!((decoded[0] - 0.5).abs() < 1e-6 && (decoded[1] + 0.5).abs() < 1e-6);
+        assert_eq!(RtpEncoding::from_name("am824"), Some(RtpEncoding::Am824));
+    }
+    
+    #[test]
+    fn test_format_ptime() {
+        let format = Aes67Format::with_ptime(48000, 8, RtpEncoding::L16, 250);
+        assert_eq!(format.samples_per_packet, 12);
+        assert_eq!(format.bytes_per_packet(), 12 * 8 * 2);
+        
+        let format = Aes67Format::with_ptime(96000, 2, RtpEncoding::L24, 125);
+        assert_eq!(format.samples_per_packet, 12);
+        assert_eq!(Aes67Format::with_ptime(48000, 2, RtpEncoding::L24, 333).samples_per_packet, 16);
+        let format = Aes67Format::with_ptime(48000, 2, RtpEncoding::Am824, 4000);
+        assert_eq!(format.samples_per_packet, 192);
+        assert_eq!(format.bytes_per_packet(), 192 * 2 * 4);
+        assert_eq!(Aes67Format::new(44100, 2).samples_per_packet, 44);
+    }
+    
+    /// Packet of `frames` frames (2 channels) with sample value = timestamp
+    fn packet(timestamp: u32, frames: usize) -> Vec<f32> {
+        (0..frames).flat_map(|i| {
+            let v = (timestamp as usize + i) as f32;
+            [v, -v]
+        }).collect()
+    }
+    
+    #[test]
+    fn test_jitter_buffer_reorders() {
+        let mut jb = JitterBuffer::new(2, 4, 8);
+        
+        // Packets 0, 2, 1: gap is detected and filled by the late arrival
+        jb.push(0, 1000, &packet(1000, 4));
+        jb.push(2, 1008, &packet(1008, 4));
+        assert_eq!(jb.stats.lost, 1);
+        jb.push(1, 1004, &packet(1004, 4));
+        assert_eq!(jb.stats.lost, 0);
+        assert_eq!(jb.stats.reordered, 1);
+        
+        // Duplicate is ignored
+        jb.push(1, 1004, &packet(1004, 4));
+        assert_eq!(jb.stats.duplicates, 1);
+        
+        // Free-running: starts link offset behind the newest packet
+        assert_eq!(jb.sync(None, 0), 8);
+        let mut out = [0.0; 8];
+        assert_eq!(jb.pop(&mut out), 8);
+        assert_eq!(out, [1004.0, -1004.0, 1005.0, -1005.0, 1006.0, -1006.0, 1007.0, -1007.0]);
+    }
+    
+    #[test]
+    fn test_jitter_buffer_conceals_loss() {
+        let mut jb = JitterBuffer::new(1, 4, 4);
+        jb.push(0, 0, &[0.1, 0.2, 0.3, 0.4]);
+        jb.push(2, 8, &[0.9; 4]);
+        jb.sync(Some(0), 0);
+        
+        // Lost packet 1 is replaced by the previous packet, fading out
+        let mut out = [0.0; 12];
+        jb.pop(&mut out);
+        assert_eq!(&out[..4], &[0.1, 0.2, 0.3, 0.4]);
+        assert!((out[4] - 0.1).abs() < 1e-6);
+        assert!((out[5] - 0.2 * 7.0 / 8.0).abs() < 1e-6);
+        assert_eq!(&out[8..], &[0.9; 4]);
+        assert_eq!(jb.stats.concealed_frames, 4);
+        assert_eq!(jb.stats.lost, 1);
+        
+        // Packet 1 arriving now is late
+        jb.push(1, 4, &[0.5; 4]);
+        assert_eq!(jb.stats.late, 1);
+        assert_eq!(jb.stats.lost, 0);
+    }
+    
+    #[test]
+    fn test_jitter_buffer_link_offset() {
+        let mut jb = JitterBuffer::new(1, 48, 144);
+        jb.push(0, 10_000, &[0.5; 48]);
+        
+        // PTP-aligned playout: starts exactly at the ideal timestamp
+        let delay = jb.sync(Some(10_000 - 96), 0);
+        assert_eq!(delay, 144);
+        assert!(jb.stats().ptp_aligned);
+        
+        let mut out = [0.0; 96];
+        jb.pop(&mut out);
+        assert_eq!(jb.read_ts, Some(10_000));
+        
+        // Playout drifted beyond the link offset: realigned
+        jb.sync(Some(10_400), 0);
+        assert_eq!(jb.read_ts, Some(10_400));
+        assert_eq!(jb.stats.resyncs, 1);
+        
+        // Timestamps far away from our clock: free-running
+        jb.sync(Some(3_000_000_000), 0);
+        assert!(!jb.stats().ptp_aligned);
+    }    
+    #[test]
+    fn test_redundant_paths_merge() {
+        // ST 2022-7: path A loses packets 3 and 7, path B loses packet 5
+        let group = SocketAddrV4::new(Ipv4Addr::new(239, 69, 1, 1), 5004);
+        let mut jb = JitterBuffer::new(1, 4, 8);
+        let mut a = PathMonitor::new(group);
+        let mut b = PathMonitor::new(group);
+        let now = Instant::now();
+        
+        for sequence in 0..10u16 {
+            let timestamp = sequence as u32 * 4;
+            let samples: Vec<f32> = (timestamp..timestamp + 4).map(|t| t as f32).collect();
+            if sequence != 3 && sequence != 7 {
+                let used = jb.push(sequence, timestamp, &samples);
+                a.record(sequence, used, now);
+            }
+            if sequence != 5 {
+                let used = jb.push(sequence, timestamp, &samples);
+                b.record(sequence, used, now);
+            }
+        }
+        
+        let stats = jb.stats();
+        assert_eq!(stats.received, 10);
+        assert_eq!(stats.lost, 0);
+        assert_eq!(stats.duplicates, 7);
+        
+        let (a, b) = (a.stats(now), b.stats(now));
+        assert_eq!((a.received, a.lost, a.used), (8, 2, 8));
+        assert_eq!((b.received, b.lost, b.used), (9, 1, 2));
+        assert!(a.active && b.active);
+        
+        // Every frame is played, nothing is concealed
+        jb.sync(Some(0), 0);
+        let mut out = [0.0; 40];
+        jb.pop(&mut out);
+        assert_eq!(out[12], 12.0);
+        assert_eq!(out[20], 20.0);
+        assert_eq!(out[28], 28.0);
+        assert_eq!(jb.stats.concealed_frames, 0);
+    }
+    
+    #[test]
+    fn test_unicast_receive_source_filter() {
+        let format = Aes67Format::new(48000, 2);
+        let port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let sender = RtpSender::with_options(Ipv4Addr::LOCALHOST, port, format, 64, 0, Some(Ipv4Addr::LOCALHOST)).unwrap();
+        
+        // Packets from another sender are dropped
+        let other = Some(Ipv4Addr::new(192, 0, 2, 1));
+        let filtered = RtpReceiver::with_options(Ipv4Addr::LOCALHOST, port, format, other, Some(Ipv4Addr::LOCALHOST)).unwrap();
+        filtered.start().unwrap();
+        for packet in 0..5 {
+            sender.send_packet_at(&[0.25; 96], packet * 48).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(50));
+        filtered.stop();
+        assert_eq!(filtered.jitter_stats().received, 0);
+        std::thread::sleep(Duration::from_millis(20));
+        drop(filtered);
+        
+        let receiver = RtpReceiver::with_options(Ipv4Addr::LOCALHOST, port, format, Some(Ipv4Addr::LOCALHOST), None).unwrap();
+        receiver.start().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while receiver.jitter_stats().received == 0 && Instant::now() < deadline {
+            sender.send_packet_at(&[0.25; 96], 0).unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        receiver.stop();
+        assert!(receiver.jitter_stats().received >= 1);
+        assert_eq!(receiver.path_stats()[0].group, SocketAddrV4::new(Ipv4Addr::LOCALHOST, port));
+    }
+    
+    #[test]
+    fn test_receiver_drops_other_payload_types() {
+        let format = Aes67Format::new(48000, 1);
+        let port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut receiver = RtpReceiver::with_options(Ipv4Addr::LOCALHOST, port, format, None, None).unwrap();
+        receiver.set_expected_payload_type(96);
+        receiver.start().unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        // L24 samples behind a CSRC and a header extension, padded
+        let packet = |payload_type: u8, sequence: u16| {
+            let mut header = RtpHeader::new(payload_type, 7);
+            header.sequence = sequence;
+            header.timestamp = sequence as u32 * 48;
+            header.csrc_count = 1;
+            header.extension = true;
+            header.padding = true;
+            let samples: Vec<u8> = (0..48).flat_map(|_| [0x40, 0, 0]).collect();
+            [&header.to_bytes()[..], &[0; 4], &[0x10, 0, 0, 1, 0, 0, 0, 0], &samples, &[0, 2]].concat()
+        };
+
+        for sequence in 0..5 {
+            socket.send_to(&packet(97, sequence), ("127.0.0.1", port)).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(receiver.jitter_stats().received, 0);
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut sequence = 0;
+        while receiver.jitter_stats().received == 0 && Instant::now() < deadline {
+            socket.send_to(&packet(96, sequence), ("127.0.0.1", port)).unwrap();
+            sequence += 1;
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        receiver.stop();
+        assert!(receiver.jitter_stats().received >= 1);
+
+        let mut jb = receiver.jitter_buffer.write();
+        jb.sync(None, 0);
+        jb.read_ts = Some(0);
+        let mut out = [0.0; 48];
+        jb.pop(&mut out);
+        assert!(out.iter().all(|&s| (s - 0.5).abs() < 1e-6), "{:?}", &out[..4]);
+    }
+
+    #[test]
+    fn test_read_conceals_while_buffer_locked() {
+        let format = Aes67Format::new(48000, 2);
+        let port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let receiver = RtpReceiver::with_options(Ipv4Addr::LOCALHOST, port, format, None, None).unwrap();
+        for packet in 0..4u16 {
+            let timestamp = packet as u32 * 48;
+            let samples: Vec<f32> = (timestamp..timestamp + 48).flat_map(|t| [t as f32; 2]).collect();
+            receiver.jitter_buffer.write().push(packet, timestamp, &samples);
+        }
+        
+        let mut block = [0.0; 96];
+        assert_eq!(receiver.read(&mut block), 96);
+        assert_eq!(block[0], 48.0);
+        
+        // Receive thread holds the buffer: silence instead of waiting
+        let guard = receiver.jitter_buffer.write();
+        block.fill(1.0);
+        assert_eq!(receiver.read(&mut block), 0);
+        assert!(block.iter().all(|&s| s == 0.0));
+        drop(guard);
+        
+        // The missed block is skipped, playout stays on time
+        assert_eq!(receiver.read(&mut block), 96);
+        assert_eq!(block[0], 144.0);
+        assert_eq!(receiver.jitter_stats().concealed_frames, 48);
+    }
+    
+    #[test]
+    fn test_path_monitor_timeout() {
+        let mut path = PathMonitor::new(SocketAddrV4::new(Ipv4Addr::new(239, 69, 2, 1), 5004));
+        let start = Instant::now();
+        assert!(!path.stats(start).active);
+        
+        path.record(10, true, start);
+        path.record(12, false, start);
+        path.record(11, true, start);
+        assert_eq!(path.stats(start).lost, 0);
+        assert!(path.stats(start + Duration::from_millis(100)).active);
+        assert!(!path.stats(start + PATH_TIMEOUT).active);
     }
 }
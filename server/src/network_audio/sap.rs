@@ -4,26 +4,52 @@
 //! Session Description Protocol (SDP) describes the stream parameters.
 //!
 //! SAP Multicast Address: 224.2.127.254:9875
-//! AES67 also uses mDNS for discovery (Ravenna compatible)
+//!
+//! Our streams are re-announced periodically (RFC 2974) and withdrawn with a
+//! deletion packet. Discovered streams expire when their announcements stop;
+//! changes are published as `SapEvent`s.
+//! Sessions of RAVENNA devices (mDNS/RTSP, see `ravenna`) are merged into the
+//! discovered streams, and our streams are published there as well.
 
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::collections::HashMap;
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use parking_lot::RwLock;
+use tokio::sync::broadcast;
 use tracing::{info, warn, error, debug};
 
 use super::backend::{NetworkDevice, NetworkDeviceType};
+use super::ravenna::{RavennaDiscovery, SessionLookup};
+use super::sdp::{Connection, Direction, MediaClock, MediaDescription, Origin, RefClock, RtpMap,
+    SessionDescription, SourceFilter, PTP_VERSION_2008};
+use super::PtpClock;
+use super::rtp::RtpEncoding;
 
 /// SAP Multicast address and port
 pub const SAP_MULTICAST_ADDR: Ipv4Addr = Ipv4Addr::new(224, 2, 127, 254);
 pub const SAP_PORT: u16 = 9875;
 
-/// AES67 mDNS service type
-pub const AES67_MDNS_SERVICE: &str = "_aes67._sub._ravenna._udp.local.";
-pub const RAVENNA_MDNS_SERVICE: &str = "_ravenna._udp.local.";
+/// TTL of our announcements
+const SAP_TTL: u32 = 64;
+
+/// Bandwidth for all our announcements in bit/s (RFC 2974 default)
+const SAP_BANDWIDTH_LIMIT: f64 = 4000.0;
+
+/// Shortest re-announcement interval
+const MIN_ANNOUNCE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Bounds for the expiry of discovered streams
+const MIN_STREAM_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_STREAM_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Seconds between the NTP (1900) and Unix (1970) epochs
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Dynamic payload type of our streams
+pub const DEFAULT_PAYLOAD_TYPE: u8 = 97;
 
 /// Discovered AES67 stream
 #[derive(Debug, Clone)]
@@ -42,12 +68,22 @@ pub struct Aes67Stream {
     pub channels: u8,
     /// Sample rate
     pub sample_rate: u32,
-    /// Bits per sample (usually 24)
-    pub bits_per_sample: u8,
+    /// Sample encoding (usually L24)
+    pub encoding: RtpEncoding,
     /// Packet time in microseconds (usually 1000 = 1ms)
     pub ptime_us: u32,
     /// Is this a sender or receiver
     pub direction: StreamDirection,
+    /// RTP payload type
+    pub payload_type: u8,
+    /// RTP timestamp at media clock zero (`a=mediaclk:direct=`)
+    pub media_clock_offset: u32,
+    /// Reference clock of the timestamps (`a=ts-refclk`)
+    pub ref_clock: Option<RefClock>,
+    /// Sender address from `a=source-filter` (source-specific multicast)
+    pub source_address: Option<Ipv4Addr>,
+    /// Group and port of the ST 2022-7 copy on the secondary network
+    pub secondary: Option<SocketAddrV4>,
     /// Raw SDP content
     pub sdp: String,
 }
@@ -60,14 +96,87 @@ pub enum StreamDirection {
     SendReceive,
 }
 
+/// Change of the discovered streams
+#[derive(Debug, Clone)]
+pub enum SapEvent {
+    /// New stream announced
+    Discovered(Aes67Stream),
+    /// Announced with a new session version
+    Updated(Aes67Stream),
+    /// Deleted by its sender or timed out
+    Removed { session_id: String },
+}
+
+/// A stream discovered via SAP
+#[derive(Debug, Clone)]
+struct DiscoveredStream {
+    stream: Aes67Stream,
+    /// Session version from the SDP origin line
+    version: u64,
+    /// Message identifier hash and originating source of the announcement
+    hash: u16,
+    source: IpAddr,
+    last_seen: Instant,
+    /// Observed re-announcement interval
+    interval: Option<Duration>,
+}
+
+impl DiscoveredStream {
+    /// RFC 2974: deleted after ten announcement intervals (or one hour)
+    fn timeout(&self) -> Duration {
+        self.interval
+            .map(|i| (i * 10).clamp(MIN_STREAM_TIMEOUT, MAX_STREAM_TIMEOUT))
+            .unwrap_or(MAX_STREAM_TIMEOUT)
+    }
+}
+
+/// One of our announcements
+#[derive(Debug, Clone)]
+struct Announcement {
+    stream: Aes67Stream,
+    version: u64,
+    sdp: String,
+    packet: SapPacket,
+}
+
+impl Announcement {
+    /// Announcement of `stream` with the given reference clock
+    ///
+    /// The session version only changes with the description.
+    fn new(previous: Option<&Announcement>, mut stream: Aes67Stream, ref_clock: Option<RefClock>) -> Self {
+        if ref_clock.is_some() {
+            stream.ref_clock = ref_clock;
+        }
+        let version = match previous {
+            Some(previous) if stream.session_description(previous.version).to_string() == previous.sdp => previous.version,
+            Some(previous) => previous.version + 1,
+            None => session_version_now(),
+        };
+        let sdp = stream.session_description(version).to_string();
+        let packet = SapPacket::announcement(origin_address(&stream.origin), &sdp);
+        stream.sdp = sdp.clone();
+        Self { stream, version, sdp, packet }
+    }
+}
+
 /// SAP/SDP Discovery service
 pub struct SapDiscovery {
     /// Discovered streams
-    streams: Arc<RwLock<HashMap<String, Aes67Stream>>>,
+    streams: Arc<RwLock<HashMap<String, DiscoveredStream>>>,
     /// Running flag
     running: Arc<AtomicBool>,
-    /// Our announced streams
-    announced: Arc<RwLock<Vec<Aes67Stream>>>,
+    /// Our announced streams by session id
+    announced: Arc<RwLock<HashMap<String, Announcement>>>,
+    /// Change events for clients
+    events: broadcast::Sender<SapEvent>,
+    /// PTP clock whose grandmaster our announcements advertise
+    ptp_clock: Option<Arc<PtpClock>>,
+    /// Sessions discovered via RAVENNA (mDNS/RTSP) by mDNS name
+    ravenna_streams: Arc<RwLock<HashMap<String, Aes67Stream>>>,
+    /// RAVENNA browser and publisher (None until started or if mDNS is unavailable)
+    ravenna: RwLock<Option<RavennaDiscovery>>,
+    /// Address of the interface SAP and mDNS are bound to (None = all)
+    interface: Option<Ipv4Addr>,
 }
 
 impl SapDiscovery {
@@ -76,18 +185,49 @@ impl SapDiscovery {
         Self {
             streams: Arc::new(RwLock::new(HashMap::new())),
             running: Arc::new(AtomicBool::new(false)),
-            announced: Arc::new(RwLock::new(Vec::new())),
+            announced: Arc::new(RwLock::new(HashMap::new())),
+            events: broadcast::channel(64).0,
+            ptp_clock: None,
+            ravenna_streams: Arc::new(RwLock::new(HashMap::new())),
+            ravenna: RwLock::new(None),
+            interface: None,
+        }
+    }
+
+    /// Create a discovery service advertising the grandmaster of `clock`
+    ///
+    /// Our streams are re-announced when the grandmaster changes.
+    pub fn with_ptp_clock(clock: Arc<PtpClock>) -> Self {
+        Self {
+            ptp_clock: Some(clock),
+            ..Self::new()
         }
     }
 
-    /// Get discovered streams
+    /// Announce and discover only on the interface with this address
+    pub fn set_interface(&mut self, address: Option<Ipv4Addr>) {
+        self.interface = address;
+    }
+
+    /// Get discovered streams (SAP and RAVENNA, announced by both once)
     pub fn streams(&self) -> Vec<Aes67Stream> {
-        self.streams.read().values().cloned().collect()
+        let mut streams: Vec<Aes67Stream> = self.streams.read().values().map(|s| s.stream.clone()).collect();
+        for stream in self.ravenna_streams.read().values() {
+            if !streams.iter().any(|s| s.session_id == stream.session_id) {
+                streams.push(stream.clone());
+            }
+        }
+        streams
+    }
+
+    /// Receive stream changes (discovered, updated, removed)
+    pub fn subscribe(&self) -> broadcast::Receiver<SapEvent> {
+        self.events.subscribe()
     }
 
     /// Convert streams to NetworkDevice format
     pub fn as_devices(&self) -> Vec<NetworkDevice> {
-        self.streams.read().values().map(|s| {
+        self.streams().iter().map(|s| {
             NetworkDevice {
                 id: s.session_id.clone(),
                 name: s.name.clone(),
@@ -100,28 +240,53 @@ impl SapDiscovery {
                 sample_rate: s.sample_rate,
                 ip_address: Some(s.origin.clone()),
                 multicast_group: Some(s.multicast_addr.to_string()),
+                port: Some(s.port),
             }
         }).collect()
     }
 
-    /// Start listening for SAP announcements
+    /// Start listening for SAP announcements and re-announcing our streams
     pub fn start(&self) -> Result<()> {
         if self.running.load(Ordering::Relaxed) {
             return Ok(());
         }
 
         info!("📢 Starting SAP discovery on {}:{}", SAP_MULTICAST_ADDR, SAP_PORT);
+        let socket = sap_socket(self.interface)?;
         
         self.running.store(true, Ordering::Relaxed);
         
         let running = self.running.clone();
         let streams = self.streams.clone();
+        let announced = self.announced.clone();
+        let events = self.events.clone();
+        let ptp_clock = self.ptp_clock.clone();
+        let interface = self.interface;
         
         std::thread::spawn(move || {
-            if let Err(e) = run_sap_listener(running, streams) {
+            if let Err(e) = run_sap_listener(socket, interface, running, streams, announced, events, ptp_clock) {
                 error!("SAP listener error: {}", e);
             }
         });
+
+        // RAVENNA serves the current SDP of our sessions by name or id
+        let announced = self.announced.clone();
+        let lookup: SessionLookup = Arc::new(move |key| {
+            announced.read().values()
+                .find(|a| a.stream.name == key || a.stream.session_id == key)
+                .map(|a| a.sdp.clone())
+        });
+        match RavennaDiscovery::start(self.ravenna_streams.clone(), self.events.clone(), lookup, self.interface) {
+            Ok(ravenna) => {
+                for announcement in self.announced.read().values() {
+                    if let Err(e) = ravenna.publish(&announcement.stream.name, &announcement.stream.session_id) {
+                        warn!("Publishing RAVENNA session {} failed: {}", announcement.stream.name, e);
+                    }
+                }
+                *self.ravenna.write() = Some(ravenna);
+            }
+            Err(e) => warn!("RAVENNA discovery unavailable, using SAP only: {}", e),
+        }
         
         Ok(())
     }
@@ -130,37 +295,61 @@ impl SapDiscovery {
     pub fn stop(&self) {
         info!("📢 Stopping SAP discovery");
         self.running.store(false, Ordering::Relaxed);
+        if let Some(ravenna) = self.ravenna.write().take() {
+            ravenna.stop();
+        }
     }
 
     /// Announce our own stream via SAP
+    ///
+    /// Announcing a session again with a changed description increments its
+    /// session version. The announcement is repeated until it is removed.
     pub fn announce(&self, stream: Aes67Stream) -> Result<()> {
-        let sdp = generate_sdp(&stream)?;
-        let sap_packet = build_sap_packet(&stream.session_id, &sdp, false)?;
-        
-        // Send announcement
-        let socket = UdpSocket::bind("0.0.0.0:0")?;
-        socket.set_multicast_ttl_v4(64)?;
-        socket.send_to(&sap_packet, (SAP_MULTICAST_ADDR, SAP_PORT))?;
-        
-        info!("📢 Announced stream: {}", stream.name);
-        
-        // Store for periodic re-announcement
-        self.announced.write().push(stream);
+        let ref_clock = self.ptp_clock.as_deref().map(ptp_ref_clock);
+        let announcement = {
+            let mut announced = self.announced.write();
+            let announcement = Announcement::new(announced.get(&stream.session_id), stream, ref_clock);
+            announced.insert(announcement.stream.session_id.clone(), announcement.clone());
+            announcement
+        };
+
+        match send_sap(&announcement.packet.to_bytes(), self.interface) {
+            Ok(()) => info!("📢 Announced stream: {} (version {})", announcement.stream.name, announcement.version),
+            Err(e) => warn!("📢 Announcing stream {} failed, retrying periodically: {}", announcement.stream.name, e),
+        }
+
+        if let Some(ravenna) = self.ravenna.read().as_ref() {
+            if let Err(e) = ravenna.publish(&announcement.stream.name, &announcement.stream.session_id) {
+                warn!("Publishing RAVENNA session {} failed: {}", announcement.stream.name, e);
+            }
+        }
         
         Ok(())
     }
 
+    /// Current SDP of one of our announced streams
+    pub fn announced_sdp(&self, session_id: &str) -> Option<String> {
+        self.announced.read().get(session_id).map(|a| a.sdp.clone())
+    }
+
     /// Remove stream announcement
     pub fn remove_announcement(&self, session_id: &str) -> Result<()> {
-        // Send deletion announcement
-        let sap_packet = build_sap_packet(session_id, "", true)?;
-        
-        let socket = UdpSocket::bind("0.0.0.0:0")?;
-        socket.set_multicast_ttl_v4(64)?;
-        socket.send_to(&sap_packet, (SAP_MULTICAST_ADDR, SAP_PORT))?;
-        
-        // Remove from announced list
-        self.announced.write().retain(|s| s.session_id != session_id);
+        let announcement = self.announced.write().remove(session_id)
+            .ok_or_else(|| anyhow!("Stream '{}' is not announced", session_id))?;
+        if let Some(ravenna) = self.ravenna.read().as_ref() {
+            ravenna.unpublish(session_id);
+        }
+
+        // The deletion carries the hash and origin of the announcement and its o= line
+        let origin_line = announcement.sdp.lines()
+            .find(|line| line.starts_with("o="))
+            .unwrap_or_default();
+        let packet = SapPacket {
+            deletion: true,
+            payload: format!("{}\r\n", origin_line),
+            ..announcement.packet
+        };
+        send_sap(&packet.to_bytes(), self.interface)?;
         
         info!("📢 Removed stream announcement: {}", session_id);
         
@@ -168,11 +357,49 @@ impl SapDiscovery {
     }
 }
 
-/// Run the SAP listener thread
-fn run_sap_listener(
-    running: Arc<AtomicBool>,
-    streams: Arc<RwLock<HashMap<String, Aes67Stream>>>,
-) -> Result<()> {
+impl Default for SapDiscovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Session version for new announcements (NTP seconds, RFC 4566)
+fn session_version_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() + NTP_UNIX_OFFSET)
+        .unwrap_or(NTP_UNIX_OFFSET)
+}
+
+/// Originating source of our announcements
+fn origin_address(origin: &str) -> Ipv4Addr {
+    origin.parse().unwrap_or(Ipv4Addr::UNSPECIFIED)
+}
+
+/// Send one SAP packet to the announcement group (via `interface` if given)
+fn send_sap(packet: &[u8], interface: Option<Ipv4Addr>) -> Result<()> {
+    let socket = UdpSocket::bind((interface.unwrap_or(Ipv4Addr::UNSPECIFIED), 0))?;
+    if let Some(interface) = interface {
+        socket2::SockRef::from(&socket).set_multicast_if_v4(&interface)?;
+    }
+    socket.set_multicast_ttl_v4(SAP_TTL)?;
+    socket.send_to(packet, (SAP_MULTICAST_ADDR, SAP_PORT))?;
+    Ok(())
+}
+
+/// RFC 2974 announcement interval for `total_bytes` of announcements
+///
+/// The bandwidth term shares 4000 bit/s between all announcements; the
+/// floor follows common AES67 practice instead of the RFC's 300 s so that
+/// receivers find new streams quickly. Randomised by ±1/3.
+fn announce_interval(total_bytes: usize) -> Duration {
+    let base = (8.0 * total_bytes as f64 / SAP_BANDWIDTH_LIMIT).max(MIN_ANNOUNCE_INTERVAL.as_secs_f64());
+    let offset = rand::random::<f64>() * 2.0 / 3.0 - 1.0 / 3.0;
+    Duration::from_secs_f64(base * (1.0 + offset))
+}
+
+/// Socket receiving SAP announcements (on `interface` if given)
+fn sap_socket(interface: Option<Ipv4Addr>) -> Result<UdpSocket> {
     use socket2::{Socket, Domain, Type, Protocol};
     
     // Create multicast socket
@@ -183,32 +410,36 @@ fn run_sap_listener(
     socket.set_reuse_port(true)?;
     
     let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), SAP_PORT);
-    socket.bind(&addr.into())?;
-    socket.join_multicast_v4(&SAP_MULTICAST_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+    socket.bind(&addr.into())
+        .map_err(|e| anyhow!("Cannot open SAP port {}: {}", SAP_PORT, e))?;
+    super::interface::join_multicast(&socket, SAP_MULTICAST_ADDR, interface)?;
     
     let socket: UdpSocket = socket.into();
     socket.set_read_timeout(Some(Duration::from_millis(500)))?;
-    
+    Ok(socket)
+}
+
+/// Run the SAP listener thread
+fn run_sap_listener(
+    socket: UdpSocket,
+    interface: Option<Ipv4Addr>,
+    running: Arc<AtomicBool>,
+    streams: Arc<RwLock<HashMap<String, DiscoveredStream>>>,
+    announced: Arc<RwLock<HashMap<String, Announcement>>>,
+    events: broadcast::Sender<SapEvent>,
+    ptp_clock: Option<Arc<PtpClock>>,
+) -> Result<()> {
     let mut buf = [0u8; 4096];
+    let mut next_announce = Instant::now() + announce_interval(0);
+    let mut ref_clock = None;
     
     while running.load(Ordering::Relaxed) {
         match socket.recv_from(&mut buf) {
             Ok((len, src)) => {
-                if len < 4 {
-                    continue;
-                }
-                
-                if let Some(stream) = parse_sap_packet(&buf[..len], src.ip()) {
-                    let session_id = stream.session_id.clone();
-                    
-                    // Check for deletion
-                    if buf[0] & 0x04 != 0 {
-                        // Deletion bit set
-                        streams.write().remove(&session_id);
-                        debug!("SAP: Removed stream {}", session_id);
-                    } else {
-                        debug!("SAP: Discovered stream {} from {}", stream.name, src);
-                        streams.write().insert(session_id, stream);
+                if let Some(packet) = SapPacket::parse(&buf[..len]) {
+                    let event = process_packet(&mut streams.write(), &packet, src.ip(), Instant::now());
+                    if let Some(event) = event {
+                        let _ = events.send(event);
                     }
                 }
             }
@@ -227,205 +458,475 @@ fn run_sap_listener(
                 }
             }
         }
+
+        let now = Instant::now();
+        for event in expire_streams(&mut streams.write(), now) {
+            let _ = events.send(event);
+        }
+
+        // Announce right away when the grandmaster changes
+        let current = ptp_clock.as_deref().map(ptp_ref_clock);
+        if current != ref_clock {
+            for packet in refresh_announcements(&mut announced.write(), current.clone()) {
+                if let Err(e) = send_sap(&packet, interface) {
+                    warn!("SAP announcement failed: {}", e);
+                }
+            }
+            ref_clock = current;
+        }
+
+        // Periodic re-announcement of our streams
+        if now >= next_announce {
+            let packets: Vec<Vec<u8>> = announced.read().values().map(|a| a.packet.to_bytes()).collect();
+            for packet in &packets {
+                if let Err(e) = send_sap(packet, interface) {
+                    warn!("SAP re-announcement failed: {}", e);
+                }
+            }
+            next_announce = now + announce_interval(packets.iter().map(Vec::len).sum());
+        }
     }
     
     info!("SAP listener stopped");
     Ok(())
 }
 
-/// Parse a SAP packet and extract stream info
-fn parse_sap_packet(data: &[u8], source: IpAddr) -> Option<Aes67Stream> {
-    if data.len() < 8 {
-        return None;
+/// Update our announcements to a new reference clock
+///
+/// Returns the packets of the announcements that changed.
+fn refresh_announcements(announced: &mut HashMap<String, Announcement>, ref_clock: Option<RefClock>) -> Vec<Vec<u8>> {
+    announced.values_mut().filter_map(|announcement| {
+        let updated = Announcement::new(Some(announcement), announcement.stream.clone(), ref_clock.clone());
+        if updated.version == announcement.version {
+            return None;
+        }
+        info!("📢 Stream {} now announced with {}", updated.stream.name,
+            updated.stream.ref_clock.as_ref().map(|c| c.to_string()).unwrap_or_default());
+        *announcement = updated;
+        Some(announcement.packet.to_bytes())
+    }).collect()
+}
+
+/// Apply a received SAP packet to the discovered streams
+fn process_packet(
+    streams: &mut HashMap<String, DiscoveredStream>,
+    packet: &SapPacket,
+    source: IpAddr,
+    now: Instant,
+) -> Option<SapEvent> {
+    let origin = match packet.origin {
+        IpAddr::V4(addr) if addr.is_unspecified() => source,
+        origin => origin,
+    };
+
+    if packet.deletion {
+        // Identified by the o= line, else by hash and originating source
+        let key = parse_origin(&packet.payload).map(|(id, _)| id).or_else(|| {
+            streams.iter()
+                .find(|(_, s)| s.hash == packet.hash && s.source == origin)
+                .map(|(id, _)| id.clone())
+        })?;
+        streams.remove(&key)?;
+        debug!("SAP: Removed stream {}", key);
+        return Some(SapEvent::Removed { session_id: key });
     }
-    
-    // SAP Header:
-    // Byte 0: V=1, A, R, T, E, C, Message Type (Announce=0, Delete=1)
-    // Byte 1: Auth length (usually 0)
-    // Bytes 2-3: Message ID Hash
-    // Bytes 4-7 (or more): Originating source
-    // Then optional auth data
-    // Then MIME type (usually "application/sdp\0")
-    // Then SDP payload
-    
-    let version = (data[0] >> 5) & 0x07;
-    if version != 1 {
-        return None;
+
+    let stream = match Aes67Stream::from_sdp(&packet.payload) {
+        Ok(stream) => stream,
+        Err(e) => {
+            debug!("SAP: Ignoring announcement from {}: {}", origin, e);
+            return None;
+        }
+    };
+    let (_, version) = parse_origin(&packet.payload)?;
+
+    match streams.get_mut(&stream.session_id) {
+        Some(existing) if version < existing.version => None,
+        Some(existing) if version == existing.version => {
+            existing.interval = Some(now.duration_since(existing.last_seen));
+            existing.last_seen = now;
+            None
+        }
+        Some(existing) => {
+            debug!("SAP: Stream {} changed (version {})", stream.name, version);
+            existing.interval = Some(now.duration_since(existing.last_seen));
+            existing.last_seen = now;
+            existing.stream = stream.clone();
+            existing.version = version;
+            existing.hash = packet.hash;
+            Some(SapEvent::Updated(stream))
+        }
+        None => {
+            debug!("SAP: Discovered stream {} from {}", stream.name, origin);
+            streams.insert(stream.session_id.clone(), DiscoveredStream {
+                stream: stream.clone(),
+                version,
+                hash: packet.hash,
+                source: origin,
+                last_seen: now,
+                interval: None,
+            });
+            Some(SapEvent::Discovered(stream))
+        }
     }
-    
-    let auth_len = data[1] as usize * 4;
-    let addr_type = if data[0] & 0x10 != 0 { 6 } else { 4 }; // IPv6 or IPv4
-    
-    let header_len = 4 + addr_type + auth_len;
-    if data.len() < header_len {
-        return None;
+}
+
+/// Remove streams whose announcements stopped
+fn expire_streams(streams: &mut HashMap<String, DiscoveredStream>, now: Instant) -> Vec<SapEvent> {
+    let expired: Vec<String> = streams.iter()
+        .filter(|(_, s)| now.duration_since(s.last_seen) > s.timeout())
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    expired.into_iter().filter_map(|id| {
+        let removed = streams.remove(&id)?;
+        info!("📢 SAP: Stream '{}' timed out", removed.stream.name);
+        Some(SapEvent::Removed { session_id: id })
+    }).collect()
+}
+
+/// SAP packet (RFC 2974)
+#[derive(Debug, Clone, PartialEq)]
+struct SapPacket {
+    deletion: bool,
+    /// Message identifier hash (identifies the announcement together with the origin)
+    hash: u16,
+    /// Originating source
+    origin: IpAddr,
+    /// SDP payload
+    payload: String,
+}
+
+impl SapPacket {
+    /// Announcement of `sdp`, the hash identifies this payload version
+    fn announcement(origin: Ipv4Addr, sdp: &str) -> Self {
+        Self {
+            deletion: false,
+            hash: message_id_hash(sdp),
+            origin: IpAddr::V4(origin),
+            payload: sdp.to_string(),
+        }
     }
-    
-    // Find MIME type and SDP
-    let payload = &data[header_len..];
-    
-    // Look for "application/sdp\0" or just parse as SDP
-    let sdp_start = if let Some(pos) = payload.iter().position(|&b| b == 0) {
-        pos + 1
-    } else {
-        0
-    };
-    
-    if sdp_start >= payload.len() {
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        // SAP Header:
+        // Byte 0: V=1, A (IPv6), R, T (deletion), E (encrypted), C (compressed)
+        // Byte 1: Auth length in 32-bit words
+        // Bytes 2-3: Message ID Hash
+        // Bytes 4-7 (or 4-19 for IPv6): Originating source
+        // Then optional auth data
+        // Then optional MIME type (usually "application/sdp\0")
+        // Then SDP payload
+        if data.len() < 8 {
+            return None;
+        }
+
+        let version = (data[0] >> 5) & 0x07;
+        // Encrypted or compressed payloads cannot be read
+        if version != 1 || data[0] & 0x03 != 0 {
+            return None;
+        }
+
+        let ipv6 = data[0] & 0x10 != 0;
+        let addr_len = if ipv6 { 16 } else { 4 };
+        let header_len = 4 + addr_len + data[1] as usize * 4;
+        if data.len() < header_len {
+            return None;
+        }
+
+        let origin = if ipv6 {
+            let bytes: [u8; 16] = data[4..20].try_into().ok()?;
+            IpAddr::from(bytes)
+        } else {
+            IpAddr::from([data[4], data[5], data[6], data[7]])
+        };
+
+        // The MIME type is optional, an SDP payload starts with "v=0"
+        let payload = &data[header_len..];
+        let payload = if payload.starts_with(b"v=0") || payload.starts_with(b"o=") {
+            payload
+        } else {
+            let end = payload.iter().position(|&b| b == 0)?;
+            if &payload[..end] != b"application/sdp" {
+                return None;
+            }
+            &payload[end + 1..]
+        };
+
+        Some(Self {
+            deletion: data[0] & 0x04 != 0,
+            hash: u16::from_be_bytes([data[2], data[3]]),
+            origin,
+            payload: String::from_utf8_lossy(payload).into_owned(),
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(40 + self.payload.len());
+
+        // V=1, A=IPv6, T=deletion
+        let mut flags = 0x20;
+        if self.origin.is_ipv6() {
+            flags |= 0x10;
+        }
+        if self.deletion {
+            flags |= 0x04;
+        }
+        packet.push(flags);
+        packet.push(0); // no authentication
+        packet.extend_from_slice(&self.hash.to_be_bytes());
+        match self.origin {
+            IpAddr::V4(addr) => packet.extend_from_slice(&addr.octets()),
+            IpAddr::V6(addr) => packet.extend_from_slice(&addr.octets()),
+        }
+        packet.extend_from_slice(b"application/sdp\0");
+        packet.extend_from_slice(self.payload.as_bytes());
+        packet
+    }
+}
+
+/// Message identifier hash of a payload (FNV-1a folded to 16 bits, never 0)
+fn message_id_hash(payload: &str) -> u16 {
+    let hash = payload.bytes().fold(0x811C9DC5u32, |h, b| (h ^ b as u32).wrapping_mul(0x01000193));
+    match ((hash >> 16) ^ (hash & 0xFFFF)) as u16 {
+        0 => 1,
+        folded => folded,
+    }
+}
+
+/// Session key and version from the SDP origin line
+fn parse_origin(sdp: &str) -> Option<(String, u64)> {
+    // o=<username> <sess-id> <sess-version> <nettype> <addrtype> <unicast-address>
+    let line = sdp.lines().map(str::trim).find(|l| l.starts_with("o="))?;
+    let parts: Vec<&str> = line[2..].split_whitespace().collect();
+    if parts.len() < 6 {
         return None;
     }
-    
-    let sdp_str = String::from_utf8_lossy(&payload[sdp_start..]);
-    
-    parse_sdp(&sdp_str, source.to_string())
+    Some((format!("{}_{}", parts[0], parts[1]), parts[2].parse().unwrap_or(0)))
 }
 
-/// Parse SDP content
-fn parse_sdp(sdp: &str, default_origin: String) -> Option<Aes67Stream> {
-    let mut name = String::new();
-    let mut session_id = String::new();
-    let mut origin = default_origin;
-    let mut multicast_addr = Ipv4Addr::new(0, 0, 0, 0);
-    let mut port: u16 = 5004;
-    let mut channels: u8 = 2;
-    let mut sample_rate: u32 = 48000;
-    let mut bits_per_sample: u8 = 24;
-    let mut ptime_us: u32 = 1000;
-    
-    for line in sdp.lines() {
-        let line = line.trim();
-        
-        if line.starts_with("s=") {
-            name = line[2..].to_string();
-        } else if line.starts_with("o=") {
-            // o=<username> <sess-id> <sess-version> <nettype> <addrtype> <unicast-address>
-            let parts: Vec<&str> = line[2..].split_whitespace().collect();
-            if parts.len() >= 6 {
-                session_id = format!("{}_{}", parts[0], parts[1]);
-                origin = parts[5].to_string();
-            }
-        } else if line.starts_with("c=") {
-            // c=IN IP4 <multicast-address>/<ttl>
-            let parts: Vec<&str> = line[2..].split_whitespace().collect();
-            if parts.len() >= 3 {
-                let addr_part = parts[2].split('/').next().unwrap_or("");
-                if let Ok(addr) = addr_part.parse() {
-                    multicast_addr = addr;
-                }
-            }
-        } else if line.starts_with("m=audio ") {
-            // m=audio <port> RTP/AVP <payload-type>
-            let parts: Vec<&str> = line[8..].split_whitespace().collect();
-            if let Some(p) = parts.first() {
-                port = p.parse().unwrap_or(5004);
-            }
-        } else if line.starts_with("a=rtpmap:") {
-            // a=rtpmap:<payload> L24/<sample-rate>/<channels>
-            if line.contains("L24/") || line.contains("L16/") {
-                let parts: Vec<&str> = line.split('/').collect();
-                if parts.len() >= 2 {
-                    sample_rate = parts[1].parse().unwrap_or(48000);
-                }
-                if parts.len() >= 3 {
-                    channels = parts[2].parse().unwrap_or(2);
-                }
-                bits_per_sample = if line.contains("L24") { 24 } else { 16 };
+impl Aes67Stream {
+    /// Stream from a session description
+    ///
+    /// Uses the first audio media section (the primary one of an ST 2022-7
+    /// duplication group); media-level attributes override session-level
+    /// ones. Encodings other than L16/L24/AM824, transports other than
+    /// RTP/AVP and IPv6 destinations are rejected.
+    pub fn from_sdp(text: &str) -> Result<Self> {
+        let sdp = SessionDescription::parse(text)?;
+        // ST 2022-7: the first media section of the group is the primary path
+        let (media, secondary_media) = match sdp.duplication_group() {
+            Some(mids) => (
+                sdp.media_by_id(mids[0]).ok_or_else(|| anyhow!("no media section with mid '{}'", mids[0]))?,
+                sdp.media_by_id(mids[1]),
+            ),
+            None => (
+                sdp.media.iter().find(|m| m.media == "audio")
+                    .ok_or_else(|| anyhow!("SDP '{}' has no audio media section", sdp.name))?,
+                None,
+            ),
+        };
+        if media.media != "audio" {
+            bail!("unsupported media '{}'", media.media);
+        }
+        if media.protocol != "RTP/AVP" {
+            bail!("unsupported transport '{}' (only RTP/AVP)", media.protocol);
+        }
+
+        let payload_type = media.formats.first().and_then(|f| f.parse::<u8>().ok())
+            .ok_or_else(|| anyhow!("invalid payload type '{}'", media.formats.join(" ")))?;
+        let map = media.rtpmap_for(payload_type)
+            .ok_or_else(|| anyhow!("no rtpmap for payload type {}", payload_type))?;
+        let encoding = RtpEncoding::from_name(&map.encoding)
+            .ok_or_else(|| anyhow!("unsupported encoding '{}' (only L16, L24 and AM824)", map.encoding))?;
+        let channels = map.channels.unwrap_or(1);
+        let channels = u8::try_from(channels).ok().filter(|c| *c > 0)
+            .ok_or_else(|| anyhow!("unsupported channel count {}", channels))?;
+        if map.clock_rate == 0 {
+            bail!("invalid sample rate 0");
+        }
+
+        let connection = media.connection.or(sdp.connection)
+            .ok_or_else(|| anyhow!("SDP has no connection (c=) line"))?;
+        let IpAddr::V4(multicast_addr) = connection.address else {
+            bail!("IPv6 streams are not supported ({})", connection.address);
+        };
+        let secondary = match secondary_media {
+            Some(secondary) => match secondary.connection.or(sdp.connection).map(|c| c.address) {
+                Some(IpAddr::V4(address)) => Some(SocketAddrV4::new(address, secondary.port)),
+                _ => bail!("secondary media section has no IPv4 connection"),
+            },
+            None => None,
+        };
+
+        let direction = media.attributes.direction.or(sdp.attributes.direction);
+        let media_clock_offset = match media.attributes.mediaclk.as_ref().or(sdp.attributes.mediaclk.as_ref()) {
+            Some(MediaClock::Direct { offset, .. }) => *offset,
+            Some(other) => bail!("unsupported media clock '{}'", other),
+            None => 0,
+        };
+        let source_address = media.attributes.source_filters.iter()
+            .chain(&sdp.attributes.source_filters)
+            .filter(|filter| filter.include)
+            .flat_map(|filter| &filter.sources)
+            .find_map(|source| match source {
+                IpAddr::V4(source) => Some(*source),
+                IpAddr::V6(_) => None,
+            });
+
+        let session_id = format!("{}_{}", sdp.origin.username, sdp.origin.session_id);
+        let name = if sdp.name.trim().is_empty() {
+            format!("AES67 Stream {}", session_id)
+        } else {
+            sdp.name.clone()
+        };
+
+        Ok(Self {
+            name,
+            session_id,
+            origin: sdp.origin.address.clone(),
+            multicast_addr,
+            port: media.port,
+            channels,
+            sample_rate: map.clock_rate,
+            encoding,
+            ptime_us: (media.ptime.unwrap_or(1.0) * 1000.0).round() as u32,
+            direction: stream_direction(direction, multicast_addr.is_multicast())?,
+            payload_type,
+            media_clock_offset,
+            ref_clock: media.attributes.ts_refclk.clone().or_else(|| sdp.attributes.ts_refclk.clone()),
+            source_address,
+            secondary,
+            sdp: text.to_string(),
+        })
+    }
+
+    /// Username and numeric session id for the `o=` line (RFC 4566)
+    ///
+    /// Stream ids are `<username>_<session id>` as built from received
+    /// descriptions; ours are `audiomultiverse_<SSRC>`, so they parse back
+    /// to the same id. Other ids get a number derived from the id.
+    fn origin_ids(&self) -> (String, String) {
+        match self.session_id.rsplit_once('_') {
+            Some((username, id)) if !username.is_empty() && !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) => {
+                (username.to_string(), id.to_string())
             }
-        } else if line.starts_with("a=ptime:") {
-            // a=ptime:<ms>
-            if let Ok(ptime_ms) = line[8..].parse::<f32>() {
-                ptime_us = (ptime_ms * 1000.0) as u32;
+            _ => {
+                // FNV-1a, stable across restarts
+                let hash = self.session_id.bytes()
+                    .fold(0xcbf2_9ce4_8422_2325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+                ("-".to_string(), (hash >> 1).to_string())
             }
         }
     }
-    
-    if name.is_empty() {
-        name = format!("AES67 Stream {}", &session_id);
-    }
-    
-    if session_id.is_empty() {
-        return None;
+
+    /// Session description announcing this stream
+    ///
+    /// Without a reference clock the timestamps are advertised as traceable
+    /// PTP time. Streams with a secondary path get one media section per
+    /// network in a duplication group (ST 2022-7).
+    pub fn session_description(&self, session_version: u64) -> SessionDescription {
+        let multicast = self.multicast_addr.is_multicast();
+        let ref_clock = self.ref_clock.clone().unwrap_or(RefClock::Ptp {
+            version: PTP_VERSION_2008.to_string(),
+            grandmaster: None,
+            domain: None,
+        });
+
+        let mut media = MediaDescription::new("audio", self.port, "RTP/AVP", vec![self.payload_type.to_string()]);
+        media.rtpmap.push(RtpMap {
+            payload_type: self.payload_type,
+            encoding: self.encoding.name().to_string(),
+            clock_rate: self.sample_rate,
+            channels: Some(self.channels as u16),
+        });
+        media.ptime = Some(self.ptime_us as f64 / 1000.0);
+        // Multicast: every participant only receives; unicast: we only send
+        media.attributes.direction = Some(if multicast { Direction::RecvOnly } else { Direction::SendOnly });
+        media.attributes.mediaclk = Some(MediaClock::Direct { offset: self.media_clock_offset, rate: None });
+        if let (true, Ok(source)) = (multicast, self.origin.parse::<Ipv4Addr>()) {
+            media.attributes.source_filters.push(SourceFilter {
+                include: true,
+                address_type: "IP4".to_string(),
+                destination: self.multicast_addr.to_string(),
+                sources: vec![IpAddr::V4(source)],
+            });
+        }
+
+        // Multicast connections carry the TTL, unicast ones must not
+        let connection = |address: Ipv4Addr| Connection {
+            address: IpAddr::V4(address),
+            ttl: address.is_multicast().then_some(SAP_TTL as u8),
+            count: None,
+        };
+
+        let (username, session_id) = self.origin_ids();
+        let mut description = SessionDescription {
+            origin: Origin {
+                username,
+                session_id,
+                session_version,
+                address_type: "IP4".to_string(),
+                address: self.origin.clone(),
+            },
+            name: self.name.clone(),
+            info: None,
+            connection: Some(connection(self.multicast_addr)),
+            timing: (0, 0),
+            attributes: Default::default(),
+            media: vec![media],
+        };
+        // RAVENNA: PTP domain of the media clock
+        if let RefClock::Ptp { domain: Some(domain), .. } = &ref_clock {
+            description.attributes.other.push(("clock-domain".to_string(), Some(format!("PTPv2 {}", domain))));
+        }
+        if let Some(secondary) = self.secondary {
+            let mut primary = description.media.remove(0);
+            primary.connection = description.connection.take();
+            let mut copy = primary.clone();
+            primary.attributes.other.push(("mid".to_string(), Some("primary".to_string())));
+            // Sent from the secondary interface, whose address is not the origin
+            copy.attributes.source_filters.clear();
+            copy.attributes.other.push(("mid".to_string(), Some("secondary".to_string())));
+            copy.port = secondary.port();
+            copy.connection = Some(connection(*secondary.ip()));
+            description.media = vec![primary, copy];
+            description.attributes.other.push(("group".to_string(), Some("DUP primary secondary".to_string())));
+        }
+        for media in &mut description.media {
+            media.attributes.ts_refclk = Some(ref_clock.clone());
+        }
+        description
     }
-    
-    Some(Aes67Stream {
-        name,
-        session_id,
-        origin,
-        multicast_addr,
-        port,
-        channels,
-        sample_rate,
-        bits_per_sample,
-        ptime_us,
-        direction: StreamDirection::Send,
-        sdp: sdp.to_string(),
-    })
 }
 
-/// Generate SDP for our stream
-fn generate_sdp(stream: &Aes67Stream) -> Result<String> {
-    let session_version = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)?
-        .as_secs();
-    
-    let ptime_ms = stream.ptime_us as f32 / 1000.0;
-    
-    // Payload type 97 is commonly used for L24
-    let payload_type = 97;
-    
-    let sdp = format!(
-        "v=0\r\n\
-        o=- {sess_id} {version} IN IP4 {origin}\r\n\
-        s={name}\r\n\
-        c=IN IP4 {mcast}/64\r\n\
-        t=0 0\r\n\
-        m=audio {port} RTP/AVP {pt}\r\n\
-        a=rtpmap:{pt} L{bits}/{rate}/{ch}\r\n\
-        a=ptime:{ptime:.3}\r\n\
-        a=ts-refclk:ptp=IEEE1588-2008:00-00-00-00-00-00-00-00:0\r\n\
-        a=mediaclk:direct=0\r\n",
-        sess_id = stream.session_id,
-        version = session_version,
-        origin = stream.origin,
-        name = stream.name,
-        mcast = stream.multicast_addr,
-        port = stream.port,
-        pt = payload_type,
-        bits = stream.bits_per_sample,
-        rate = stream.sample_rate,
-        ch = stream.channels,
-        ptime = ptime_ms,
-    );
-    
-    Ok(sdp)
+/// Direction of the announcing device
+///
+/// For multicast sessions the attribute applies to all participants
+/// (RFC 3264): `recvonly` is a stream transmitted by the announcer, as used
+/// by RAVENNA and most AES67 devices. Unicast descriptions are from the
+/// announcer's point of view. Without an attribute the announcer sends.
+fn stream_direction(direction: Option<Direction>, multicast: bool) -> Result<StreamDirection> {
+    Ok(match (direction, multicast) {
+        (None, _) | (Some(Direction::RecvOnly), true) | (Some(Direction::SendOnly), false) => StreamDirection::Send,
+        (Some(Direction::SendOnly), true) | (Some(Direction::RecvOnly), false) => StreamDirection::Receive,
+        (Some(Direction::SendRecv), _) => StreamDirection::SendReceive,
+        (Some(Direction::Inactive), _) => bail!("stream is inactive"),
+    })
 }
 
-/// Build a SAP packet
-fn build_sap_packet(session_id: &str, sdp: &str, deletion: bool) -> Result<Vec<u8>> {
-    let mut packet = Vec::with_capacity(8 + 16 + sdp.len());
-    
-    // SAP Header
-    // Byte 0: V=1 (001), A=0, R=0, T=deletion, E=0, C=0
-    let flags = if deletion { 0x24 } else { 0x20 }; // V=1, T=deletion
-    packet.push(flags);
-    
-    // Byte 1: Auth length = 0
-    packet.push(0);
-    
-    // Bytes 2-3: Message ID Hash (simple hash of session_id)
-    let hash: u16 = session_id.bytes().fold(0u16, |acc, b| acc.wrapping_add(b as u16));
-    packet.extend_from_slice(&hash.to_be_bytes());
-    
-    // Bytes 4-7: Originating source (0.0.0.0 for now)
-    packet.extend_from_slice(&[0, 0, 0, 0]);
-    
-    // MIME type
-    packet.extend_from_slice(b"application/sdp\0");
-    
-    // SDP payload
-    packet.extend_from_slice(sdp.as_bytes());
-    
-    Ok(packet)
+/// Reference clock of our streams: the current PTP grandmaster
+fn ptp_ref_clock(clock: &PtpClock) -> RefClock {
+    let grandmaster = clock.stats().grandmaster_identity;
+    if grandmaster.is_empty() {
+        // No grandmaster known yet
+        return RefClock::Ptp { version: PTP_VERSION_2008.to_string(), grandmaster: None, domain: None };
+    }
+    RefClock::Ptp {
+        version: PTP_VERSION_2008.to_string(),
+        grandmaster: Some(grandmaster),
+        domain: Some(clock.domain()),
+    }
 }
 
 #[cfg(test)]
@@ -444,36 +945,265 @@ a=rtpmap:97 L24/48000/2
 a=ptime:1.000
 "#;
         
-        let stream = parse_sdp(sdp, "192.168.1.100".to_string()).unwrap();
+        let stream = Aes67Stream::from_sdp(sdp).unwrap();
         
         assert_eq!(stream.name, "Test AES67 Stream");
         assert_eq!(stream.channels, 2);
         assert_eq!(stream.sample_rate, 48000);
-        assert_eq!(stream.bits_per_sample, 24);
+        assert_eq!(stream.encoding, RtpEncoding::L24);
         assert_eq!(stream.port, 5004);
         assert_eq!(stream.multicast_addr, Ipv4Addr::new(239, 69, 1, 1));
+        assert_eq!(stream.payload_type, 97);
+        assert_eq!(stream.direction, StreamDirection::Send);
     }
-    
+
     #[test]
-    fn test_generate_sdp() {
-        let stream = Aes67Stream {
+    fn test_parse_sdp_attributes() {
+        let sdp = "v=0\r\no=- 77 3 IN IP4 192.168.1.20\r\ns=Desk\r\nt=0 0\r\n\
+            a=ts-refclk:ptp=IEEE1588-2008:00-1D-C1-FF-FE-00-00-01:0\r\n\
+            m=audio 5006 RTP/AVP 96\r\nc=IN IP4 239.69.3.3/32\r\n\
+            a=rtpmap:96 L16/96000/4\r\na=ptime:0.25\r\na=recvonly\r\n\
+            a=mediaclk:direct=1234567\r\na=source-filter: incl IN IP4 239.69.3.3 192.168.1.21\r\n\
+            m=audio 5008 RTP/AVP 96\r\nc=IN IP4 239.69.3.4/32\r\na=rtpmap:96 L16/96000/4\r\n";
+        let stream = Aes67Stream::from_sdp(sdp).unwrap();
+        assert_eq!(stream.multicast_addr, Ipv4Addr::new(239, 69, 3, 3));
+        assert_eq!((stream.sample_rate, stream.channels, stream.encoding), (96000, 4, RtpEncoding::L16));
+        assert_eq!(stream.ptime_us, 250);
+        assert_eq!(stream.media_clock_offset, 1234567);
+        assert_eq!(stream.source_address, Some(Ipv4Addr::new(192, 168, 1, 21)));
+        assert!(matches!(stream.ref_clock, Some(RefClock::Ptp { grandmaster: Some(ref gm), domain: Some(0), .. })
+            if gm == "00-1D-C1-FF-FE-00-00-01"));
+
+        // Multicast sendonly: participants send, the announcer receives
+        let receiver = Aes67Stream::from_sdp(&sdp.replace("recvonly", "sendonly")).unwrap();
+        assert_eq!(receiver.direction, StreamDirection::Receive);
+        assert!(Aes67Stream::from_sdp(&sdp.replace("recvonly", "inactive")).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_formats() {
+        let sdp = |media: &str| format!("v=0\r\no=- 1 1 IN IP4 10.0.0.1\r\ns=X\r\nc=IN IP4 239.1.1.1/32\r\nt=0 0\r\n{}", media);
+        let error = |media: &str| Aes67Stream::from_sdp(&sdp(media)).unwrap_err().to_string();
+
+        assert!(error("m=audio 5004 RTP/AVP 96\r\na=rtpmap:96 opus/48000/2\r\n").contains("unsupported encoding 'opus'"));
+        assert!(error("m=audio 5004 RTP/SAVP 96\r\na=rtpmap:96 L24/48000/2\r\n").contains("RTP/SAVP"));
+        assert!(error("m=audio 5004 RTP/AVP 96\r\n").contains("no rtpmap"));
+        assert!(error("m=video 5004 RTP/AVP 96\r\n").contains("no audio"));
+        assert!(error("m=audio 5004 RTP/AVP 96\r\na=rtpmap:96 L24/48000/2\r\na=mediaclk:sender\r\n").contains("media clock"));
+
+        // Static payload type 11 is L16/44100 mono
+        let stream = Aes67Stream::from_sdp(&sdp("m=audio 5004 RTP/AVP 11\r\n")).unwrap();
+        assert_eq!((stream.sample_rate, stream.channels, stream.encoding), (44100, 1, RtpEncoding::L16));
+
+        // AES3 transparent with 125 µs packets
+        let stream = Aes67Stream::from_sdp(&sdp("m=audio 5004 RTP/AVP 98\r\na=rtpmap:98 AM824/48000/2\r\na=ptime:0.125\r\n")).unwrap();
+        assert_eq!((stream.encoding, stream.ptime_us), (RtpEncoding::Am824, 125));
+        assert!(stream.session_description(1).to_string().contains("a=rtpmap:98 AM824/48000/2\r\n"));
+    }
+
+    fn test_stream() -> Aes67Stream {
+        Aes67Stream {
             name: "Test Stream".to_string(),
-            session_id: "test123".to_string(),
+            session_id: "audiomultiverse_3735928559".to_string(),
             origin: "192.168.1.1".to_string(),
             multicast_addr: Ipv4Addr::new(239, 69, 1, 1),
             port: 5004,
             channels: 8,
             sample_rate: 48000,
-            bits_per_sample: 24,
+            encoding: RtpEncoding::L24,
             ptime_us: 1000,
             direction: StreamDirection::Send,
+            payload_type: DEFAULT_PAYLOAD_TYPE,
+            media_clock_offset: 0,
+            ref_clock: None,
+            source_address: None,
+            secondary: None,
             sdp: String::new(),
-        };
+        }
+    }
+    
+    #[test]
+    fn test_generate_sdp() {
+        let mut stream = test_stream();
+        stream.ref_clock = Some(RefClock::Ptp {
+            version: PTP_VERSION_2008.to_string(),
+            grandmaster: Some("00-1D-C1-FF-FE-AB-CD-EF".to_string()),
+            domain: Some(3),
+        });
+        stream.media_clock_offset = 4711;
         
-        let sdp = generate_sdp(&stream).unwrap();
+        let sdp = stream.session_description(42).to_string();
         
         assert!(sdp.contains("s=Test Stream"));
         assert!(sdp.contains("L24/48000/8"));
-        assert!(sdp.contains("239.69.1.1"));
+        assert!(sdp.contains("c=IN IP4 239.69.1.1/64\r\n"));
+        assert!(sdp.contains("o=audiomultiverse 3735928559 42 IN IP4 192.168.1.1"));
+        assert!(sdp.contains("a=ts-refclk:ptp=IEEE1588-2008:00-1D-C1-FF-FE-AB-CD-EF:3\r\n"));
+        assert!(sdp.contains("a=mediaclk:direct=4711\r\n"));
+        assert!(sdp.contains("a=clock-domain:PTPv2 3\r\n"));
+        assert!(sdp.contains("a=source-filter: incl IN IP4 239.69.1.1 192.168.1.1\r\n"));
+
+        // Our description parses back to the same stream
+        let parsed = Aes67Stream::from_sdp(&sdp).unwrap();
+        assert_eq!(parsed.session_id, stream.session_id);
+        // Other ids also get a numeric session id, the same in every announcement
+        let other = Aes67Stream { session_id: "test123".to_string(), ..test_stream() };
+        let origin = other.session_description(1).origin;
+        assert!(origin.session_id.bytes().all(|b| b.is_ascii_digit()));
+        assert_eq!(origin.session_id, other.session_description(2).origin.session_id);
+        assert_eq!(parsed.ref_clock, stream.ref_clock);
+        assert_eq!(parsed.media_clock_offset, 4711);
+        assert_eq!(parsed.direction, StreamDirection::Send);
+        assert_eq!((parsed.channels, parsed.ptime_us, parsed.payload_type), (8, 1000, DEFAULT_PAYLOAD_TYPE));
+
+        // Unicast: no TTL and no source filter
+        stream.multicast_addr = Ipv4Addr::new(192, 168, 1, 2);
+        let sdp = stream.session_description(42).to_string();
+        assert!(sdp.contains("c=IN IP4 192.168.1.2\r\n") && sdp.contains("a=sendonly"));
+        assert!(!sdp.contains("source-filter"));
+        assert_eq!(Aes67Stream::from_sdp(&sdp).unwrap().direction, StreamDirection::Send);
+    }
+
+    #[test]
+    fn test_redundant_stream_sdp() {
+        let stream = Aes67Stream {
+            secondary: Some(SocketAddrV4::new(Ipv4Addr::new(239, 69, 2, 1), 5006)),
+            ..test_stream()
+        };
+        let sdp = stream.session_description(1).to_string();
+        assert!(sdp.contains("a=group:DUP primary secondary\r\n"));
+        assert!(sdp.contains("c=IN IP4 239.69.2.1/64\r\n") && sdp.contains("a=mid:secondary\r\n"));
+        assert_eq!(sdp.matches("a=ts-refclk").count(), 2);
+        assert_eq!(sdp.matches("source-filter").count(), 1);
+
+        let parsed = Aes67Stream::from_sdp(&sdp).unwrap();
+        assert_eq!(parsed.multicast_addr, stream.multicast_addr);
+        assert_eq!(parsed.secondary, stream.secondary);
+
+        // Without the group the second media section is ignored
+        let single = sdp.replace("a=group:DUP primary secondary\r\n", "");
+        assert_eq!(Aes67Stream::from_sdp(&single).unwrap().secondary, None);
+    }
+
+    #[test]
+    fn test_grandmaster_change_bumps_version() {
+        let mut announced = HashMap::new();
+        let announcement = Announcement::new(None, test_stream(), None);
+        assert!(announcement.sdp.contains("ptp=IEEE1588-2008:traceable"));
+        let version = announcement.version;
+        announced.insert("test123".to_string(), announcement);
+
+        let clock = RefClock::Ptp {
+            version: PTP_VERSION_2008.to_string(),
+            grandmaster: Some("00-00-00-FF-FE-00-00-07".to_string()),
+            domain: Some(0),
+        };
+        assert_eq!(refresh_announcements(&mut announced, Some(clock.clone())).len(), 1);
+        assert_eq!(announced["test123"].version, version + 1);
+        assert!(announced["test123"].sdp.contains("00-00-00-FF-FE-00-00-07:0"));
+        assert!(refresh_announcements(&mut announced, Some(clock)).is_empty());
+    }
+
+    fn sdp_with_version(version: u64) -> String {
+        format!(
+            "v=0\r\no=- 4711 {} IN IP4 192.168.1.50\r\ns=Stage Box\r\nc=IN IP4 239.69.2.1/32\r\nt=0 0\r\n\
+            m=audio 5004 RTP/AVP 98\r\na=rtpmap:98 L24/48000/8\r\n",
+            version
+        )
+    }
+
+    #[test]
+    fn test_sap_packet_roundtrip() {
+        let sdp = sdp_with_version(1);
+        let packet = SapPacket::announcement(Ipv4Addr::new(192, 168, 1, 50), &sdp);
+        let parsed = SapPacket::parse(&packet.to_bytes()).unwrap();
+        assert_eq!(parsed, packet);
+        assert_eq!(parsed.origin, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 50)));
+
+        // Hash follows the payload, not the session
+        assert_ne!(packet.hash, 0);
+        assert_eq!(packet.hash, message_id_hash(&sdp));
+        assert_ne!(packet.hash, message_id_hash(&sdp_with_version(2)));
+
+        // Payload without MIME type
+        let mut raw = packet.to_bytes()[..8].to_vec();
+        raw.extend_from_slice(sdp.as_bytes());
+        assert_eq!(SapPacket::parse(&raw).unwrap().payload, sdp);
+
+        // Encrypted payloads are ignored
+        raw[0] |= 0x02;
+        assert!(SapPacket::parse(&raw).is_none());
+    }
+
+    #[test]
+    fn test_discovery_versions_and_deletion() {
+        let mut streams = HashMap::new();
+        let source: IpAddr = "192.168.1.50".parse().unwrap();
+        let origin = Ipv4Addr::new(192, 168, 1, 50);
+        let now = Instant::now();
+
+        let announce = SapPacket::announcement(origin, &sdp_with_version(5));
+        assert!(matches!(process_packet(&mut streams, &announce, source, now), Some(SapEvent::Discovered(_))));
+
+        // Re-announcement only refreshes, an older version is ignored
+        let later = now + Duration::from_secs(30);
+        assert!(process_packet(&mut streams, &announce, source, later).is_none());
+        assert_eq!(streams["-_4711"].interval, Some(Duration::from_secs(30)));
+        let old = SapPacket::announcement(origin, &sdp_with_version(4));
+        assert!(process_packet(&mut streams, &old, source, later).is_none());
+
+        // New version
+        let changed = SapPacket::announcement(origin, &sdp_with_version(6));
+        assert!(matches!(process_packet(&mut streams, &changed, source, later), Some(SapEvent::Updated(_))));
+        assert_eq!(streams["-_4711"].version, 6);
+
+        // Deletion by hash and origin (no o= line)
+        let deletion = SapPacket { deletion: true, payload: String::new(), ..changed };
+        let event = process_packet(&mut streams, &deletion, source, later);
+        assert!(matches!(event, Some(SapEvent::Removed { ref session_id, .. }) if session_id == "-_4711"));
+        assert!(streams.is_empty());
+    }
+
+    #[test]
+    fn test_stream_expiry() {
+        let mut streams = HashMap::new();
+        let source: IpAddr = "192.168.1.50".parse().unwrap();
+        let announce = SapPacket::announcement(Ipv4Addr::new(192, 168, 1, 50), &sdp_with_version(1));
+        let now = Instant::now();
+        process_packet(&mut streams, &announce, source, now);
+        process_packet(&mut streams, &announce, source, now + Duration::from_secs(30));
+
+        // Ten intervals of 30 s
+        assert!(expire_streams(&mut streams, now + Duration::from_secs(300)).is_empty());
+        let events = expire_streams(&mut streams, now + Duration::from_secs(331));
+        assert_eq!(events.len(), 1);
+        assert!(streams.is_empty());
+    }
+
+    #[test]
+    fn test_announce_version_tracking() {
+        let sap = SapDiscovery::new();
+        let mut stream = Aes67Stream {
+            name: "Out".to_string(),
+            session_id: "1234".to_string(),
+            origin: "127.0.0.1".to_string(),
+            channels: 2,
+            ..test_stream()
+        };
+        sap.announce(stream.clone()).unwrap();
+        let first = sap.announced.read()["1234"].version;
+        assert_eq!(sap.announced.read()["1234"].packet.origin, IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        sap.announce(stream.clone()).unwrap();
+        assert_eq!(sap.announced.read()["1234"].version, first);
+
+        stream.channels = 4;
+        sap.announce(stream).unwrap();
+        assert_eq!(sap.announced.read()["1234"].version, first + 1);
+
+        // Sending the deletion may fail without a multicast route
+        let _ = sap.remove_announcement("1234");
+        assert!(sap.announced.read().is_empty());
+        assert!(sap.remove_announcement("1234").is_err());
     }
 }
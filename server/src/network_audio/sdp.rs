@@ -0,0 +1,695 @@
+//! SDP session descriptions (RFC 4566) for AES67/RAVENNA streams
+//!
+//! Typed model of the lines and attributes AES67 relies on: `a=rtpmap`,
+//! `a=ptime`, the reference clock `a=ts-refclk` (RFC 7273), the media clock
+//! `a=mediaclk`, `a=source-filter` (RFC 4570) and the stream direction.
+//! SMPTE ST 2022-7 streams are described as two media sections tied
+//! together by `a=group:DUP` (RFC 7104) and their `a=mid` identifiers.
+//! Attributes without a typed field (e.g. RAVENNA's `a=clock-domain`) are
+//! kept in order, so a parsed description serialises to an equivalent one.
+
+use std::fmt;
+use std::net::IpAddr;
+use anyhow::{Result, anyhow, bail};
+
+/// PTP profile used in `a=ts-refclk` (AES67 requires IEEE 1588-2008)
+pub const PTP_VERSION_2008: &str = "IEEE1588-2008";
+
+/// Session description
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionDescription {
+    pub origin: Origin,
+    /// Session name (`s=`)
+    pub name: String,
+    /// Session information (`i=`)
+    pub info: Option<String>,
+    /// Session-level connection, media sections may override it
+    pub connection: Option<Connection>,
+    /// Start and stop time (`t=`, 0 0 = unbounded)
+    pub timing: (u64, u64),
+    /// Session-level attributes (defaults for all media sections)
+    pub attributes: Attributes,
+    pub media: Vec<MediaDescription>,
+}
+
+/// Origin line (`o=`), identifies the session and its version
+#[derive(Debug, Clone, PartialEq)]
+pub struct Origin {
+    pub username: String,
+    pub session_id: String,
+    pub session_version: u64,
+    /// Address type (`IP4` or `IP6`)
+    pub address_type: String,
+    pub address: String,
+}
+
+/// Connection data (`c=`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Connection {
+    pub address: IpAddr,
+    /// Multicast TTL (IPv4 multicast only)
+    pub ttl: Option<u8>,
+    /// Number of consecutive multicast addresses
+    pub count: Option<u32>,
+}
+
+/// Media section (`m=` and the lines up to the next one)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaDescription {
+    /// Media type (`audio`, `video`, ...)
+    pub media: String,
+    pub port: u16,
+    /// Transport protocol (`RTP/AVP` for AES67)
+    pub protocol: String,
+    /// Payload types
+    pub formats: Vec<String>,
+    /// Media title (`i=`)
+    pub info: Option<String>,
+    pub connection: Option<Connection>,
+    pub rtpmap: Vec<RtpMap>,
+    /// Packet time in milliseconds
+    pub ptime: Option<f64>,
+    pub attributes: Attributes,
+}
+
+/// Payload type mapping (`a=rtpmap:<pt> <encoding>/<rate>[/<channels>]`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtpMap {
+    pub payload_type: u8,
+    pub encoding: String,
+    pub clock_rate: u32,
+    pub channels: Option<u16>,
+}
+
+/// Attributes allowed on session and media level
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attributes {
+    pub direction: Option<Direction>,
+    pub ts_refclk: Option<RefClock>,
+    pub mediaclk: Option<MediaClock>,
+    pub source_filters: Vec<SourceFilter>,
+    /// Other attributes as name and value, in order of appearance
+    pub other: Vec<(String, Option<String>)>,
+}
+
+/// Stream direction attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    SendRecv,
+    SendOnly,
+    RecvOnly,
+    Inactive,
+}
+
+/// Timestamp reference clock (`a=ts-refclk`, RFC 7273)
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefClock {
+    /// PTP clock, `grandmaster` is None for `traceable` (any PTP clock
+    /// traceable to TAI)
+    Ptp {
+        version: String,
+        grandmaster: Option<String>,
+        domain: Option<u8>,
+    },
+    /// Sender's free-running clock (`localmac=<mac>`)
+    LocalMac(String),
+    /// Other reference clocks (NTP, GPS, ...) as written
+    Other(String),
+}
+
+/// Media clock (`a=mediaclk`, RFC 7273)
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaClock {
+    /// RTP timestamps are the media clock plus `offset` (AES67)
+    Direct {
+        offset: u32,
+        /// Optional `rate=<num>/<den>` parameter
+        rate: Option<String>,
+    },
+    Other(String),
+}
+
+/// Source filter (`a=source-filter`, RFC 4570)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFilter {
+    /// `incl` (true) or `excl`
+    pub include: bool,
+    pub address_type: String,
+    /// Destination address (`*` for all)
+    pub destination: String,
+    pub sources: Vec<IpAddr>,
+}
+
+impl SessionDescription {
+    /// Parse a session description
+    ///
+    /// Accepts LF and CRLF line endings. Missing mandatory lines (`o=`, `s=`)
+    /// and malformed values are reported with their line.
+    pub fn parse(sdp: &str) -> Result<Self> {
+        let mut origin = None;
+        let mut name = None;
+        let mut info = None;
+        let mut connection = None;
+        let mut timing = (0, 0);
+        let mut attributes = Attributes::default();
+        let mut media: Vec<MediaDescription> = Vec::new();
+
+        for (index, line) in sdp.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (kind, value) = line.split_at_checked(2)
+                .filter(|(kind, _)| kind.ends_with('='))
+                .ok_or_else(|| anyhow!("SDP line {}: '{}' is not of the form <type>=<value>", index + 1, line))?;
+            let error = |message: String| anyhow!("SDP line {} ({}): {}", index + 1, line, message);
+
+            if kind == "m=" {
+                media.push(MediaDescription::parse(value).map_err(|e| error(e.to_string()))?);
+                continue;
+            }
+            if let Some(current) = media.last_mut() {
+                match kind {
+                    "i=" => current.info = Some(value.to_string()),
+                    "c=" => current.connection = Some(Connection::parse(value).map_err(|e| error(e.to_string()))?),
+                    "a=" => current.parse_attribute(value).map_err(|e| error(e.to_string()))?,
+                    _ => {}
+                }
+                continue;
+            }
+            match kind {
+                "v=" if value != "0" => return Err(error(format!("unsupported SDP version {}", value))),
+                "o=" => origin = Some(Origin::parse(value).map_err(|e| error(e.to_string()))?),
+                "s=" => name = Some(value.to_string()),
+                "i=" => info = Some(value.to_string()),
+                "c=" => connection = Some(Connection::parse(value).map_err(|e| error(e.to_string()))?),
+                "t=" => {
+                    let mut parts = value.split_whitespace().map(str::parse::<u64>);
+                    match (parts.next(), parts.next()) {
+                        (Some(Ok(start)), Some(Ok(stop))) => timing = (start, stop),
+                        _ => return Err(error("invalid timing".to_string())),
+                    }
+                }
+                "a=" => attributes.parse(value).map_err(|e| error(e.to_string()))?,
+                // v=0, bandwidth, repeat times, ... are not needed
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            origin: origin.ok_or_else(|| anyhow!("SDP has no origin (o=) line"))?,
+            name: name.ok_or_else(|| anyhow!("SDP has no session name (s=) line"))?,
+            info,
+            connection,
+            timing,
+            attributes,
+            media,
+        })
+    }
+}
+
+impl SessionDescription {
+    /// Media identifiers of a duplication group (`a=group:DUP <mid> <mid>`)
+    pub fn duplication_group(&self) -> Option<Vec<&str>> {
+        self.attributes.other.iter()
+            .filter(|(name, _)| name == "group")
+            .filter_map(|(_, value)| value.as_deref()?.strip_prefix("DUP "))
+            .map(|mids| mids.split_whitespace().collect::<Vec<_>>())
+            .find(|mids| mids.len() >= 2)
+    }
+
+    /// Media section with the given `a=mid`
+    pub fn media_by_id(&self, mid: &str) -> Option<&MediaDescription> {
+        self.media.iter().find(|media| media.mid() == Some(mid))
+    }
+}
+
+impl fmt::Display for SessionDescription {
+    /// Serialise with CRLF line endings
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v=0\r\n")?;
+        write!(f, "o={}\r\n", self.origin)?;
+        write!(f, "s={}\r\n", self.name)?;
+        if let Some(info) = &self.info {
+            write!(f, "i={}\r\n", info)?;
+        }
+        if let Some(connection) = &self.connection {
+            write!(f, "c={}\r\n", connection)?;
+        }
+        write!(f, "t={} {}\r\n", self.timing.0, self.timing.1)?;
+        write!(f, "{}", self.attributes)?;
+        for media in &self.media {
+            write!(f, "{}", media)?;
+        }
+        Ok(())
+    }
+}
+
+impl Origin {
+    fn parse(value: &str) -> Result<Self> {
+        // <username> <sess-id> <sess-version> <nettype> <addrtype> <unicast-address>
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        let [username, session_id, session_version, net_type, address_type, address] = parts[..] else {
+            bail!("origin needs 6 fields, found {}", parts.len());
+        };
+        if net_type != "IN" {
+            bail!("unsupported network type '{}'", net_type);
+        }
+        Ok(Self {
+            username: username.to_string(),
+            session_id: session_id.to_string(),
+            session_version: session_version.parse()
+                .map_err(|_| anyhow!("invalid session version '{}'", session_version))?,
+            address_type: address_type.to_string(),
+            address: address.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} IN {} {}",
+            self.username, self.session_id, self.session_version, self.address_type, self.address)
+    }
+}
+
+impl Connection {
+    fn parse(value: &str) -> Result<Self> {
+        // IN IP4 <address>[/<ttl>[/<count>]] or IN IP6 <address>[/<count>]
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        let [net_type, address_type, address] = parts[..] else {
+            bail!("connection needs 3 fields, found {}", parts.len());
+        };
+        if net_type != "IN" || !matches!(address_type, "IP4" | "IP6") {
+            bail!("unsupported connection type '{} {}'", net_type, address_type);
+        }
+
+        let mut fields = address.split('/');
+        let address: IpAddr = fields.next().unwrap_or_default().parse()
+            .map_err(|_| anyhow!("invalid connection address '{}'", address))?;
+        if address.is_ipv4() != (address_type == "IP4") {
+            bail!("address {} does not match address type {}", address, address_type);
+        }
+        let mut number = |what: &str| -> Result<Option<u32>> {
+            fields.next()
+                .map(|n| n.parse().map_err(|_| anyhow!("invalid {} '{}'", what, n)))
+                .transpose()
+        };
+        let (ttl, count) = if address.is_ipv4() && address.is_multicast() {
+            let ttl = number("TTL")?.map(|ttl| u8::try_from(ttl).map_err(|_| anyhow!("TTL {} out of range", ttl))).transpose()?;
+            (ttl, number("address count")?)
+        } else {
+            (None, number("address count")?)
+        };
+        Ok(Self { address, ttl, count })
+    }
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let address_type = if self.address.is_ipv4() { "IP4" } else { "IP6" };
+        write!(f, "IN {} {}", address_type, self.address)?;
+        if let Some(ttl) = self.ttl {
+            write!(f, "/{}", ttl)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, "/{}", count)?;
+        }
+        Ok(())
+    }
+}
+
+impl MediaDescription {
+    /// New media section without attributes
+    pub fn new(media: &str, port: u16, protocol: &str, formats: Vec<String>) -> Self {
+        Self {
+            media: media.to_string(),
+            port,
+            protocol: protocol.to_string(),
+            formats,
+            info: None,
+            connection: None,
+            rtpmap: Vec::new(),
+            ptime: None,
+            attributes: Attributes::default(),
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        // <media> <port>[/<count>] <proto> <fmt> ...
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        if parts.len() < 4 {
+            bail!("media line needs at least 4 fields, found {}", parts.len());
+        }
+        let port = parts[1].split('/').next().unwrap_or_default();
+        let port = port.parse().map_err(|_| anyhow!("invalid port '{}'", parts[1]))?;
+        Ok(Self::new(parts[0], port, parts[2], parts[3..].iter().map(|s| s.to_string()).collect()))
+    }
+
+    fn parse_attribute(&mut self, value: &str) -> Result<()> {
+        let (name, argument) = split_attribute(value);
+        match (name, argument) {
+            ("rtpmap", Some(argument)) => self.rtpmap.push(RtpMap::parse(argument)?),
+            ("ptime", Some(argument)) => {
+                let ptime: f64 = argument.trim().parse().map_err(|_| anyhow!("invalid ptime '{}'", argument))?;
+                if ptime.is_nan() || ptime <= 0.0 {
+                    bail!("ptime must be positive");
+                }
+                self.ptime = Some(ptime);
+            }
+            _ => self.attributes.parse(value)?,
+        }
+        Ok(())
+    }
+
+    /// Media identifier (`a=mid`, RFC 5888)
+    pub fn mid(&self) -> Option<&str> {
+        self.attributes.other.iter()
+            .find(|(name, _)| name == "mid")
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Mapping of a payload type, static RFC 3551 payload types included
+    pub fn rtpmap_for(&self, payload_type: u8) -> Option<RtpMap> {
+        self.rtpmap.iter().find(|map| map.payload_type == payload_type).cloned().or_else(|| {
+            let (encoding, clock_rate, channels) = match payload_type {
+                10 => ("L16", 44100, 2),
+                11 => ("L16", 44100, 1),
+                _ => return None,
+            };
+            Some(RtpMap { payload_type, encoding: encoding.to_string(), clock_rate, channels: Some(channels) })
+        })
+    }
+}
+
+impl fmt::Display for MediaDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m={} {} {} {}\r\n", self.media, self.port, self.protocol, self.formats.join(" "))?;
+        if let Some(info) = &self.info {
+            write!(f, "i={}\r\n", info)?;
+        }
+        if let Some(connection) = &self.connection {
+            write!(f, "c={}\r\n", connection)?;
+        }
+        for map in &self.rtpmap {
+            write!(f, "a=rtpmap:{}\r\n", map)?;
+        }
+        if let Some(ptime) = self.ptime {
+            write!(f, "a=ptime:{}\r\n", ptime)?;
+        }
+        write!(f, "{}", self.attributes)
+    }
+}
+
+impl RtpMap {
+    fn parse(value: &str) -> Result<Self> {
+        // <payload type> <encoding name>/<clock rate>[/<encoding parameters>]
+        let (payload_type, encoding) = value.split_once(' ')
+            .ok_or_else(|| anyhow!("rtpmap needs a payload type and an encoding"))?;
+        let payload_type = payload_type.parse::<u8>().ok().filter(|pt| *pt < 128)
+            .ok_or_else(|| anyhow!("invalid payload type '{}'", payload_type))?;
+        let mut fields = encoding.trim().split('/');
+        let encoding = fields.next().unwrap_or_default().to_string();
+        let clock_rate = fields.next().and_then(|r| r.parse().ok())
+            .ok_or_else(|| anyhow!("rtpmap for {} has no valid clock rate", encoding))?;
+        let channels = fields.next()
+            .map(|c| c.parse().map_err(|_| anyhow!("invalid channel count '{}'", c)))
+            .transpose()?;
+        Ok(Self { payload_type, encoding, clock_rate, channels })
+    }
+}
+
+impl fmt::Display for RtpMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}/{}", self.payload_type, self.encoding, self.clock_rate)?;
+        if let Some(channels) = self.channels {
+            write!(f, "/{}", channels)?;
+        }
+        Ok(())
+    }
+}
+
+impl Attributes {
+    fn parse(&mut self, value: &str) -> Result<()> {
+        let (name, argument) = split_attribute(value);
+        match (name, argument) {
+            ("sendrecv", None) => self.direction = Some(Direction::SendRecv),
+            ("sendonly", None) => self.direction = Some(Direction::SendOnly),
+            ("recvonly", None) => self.direction = Some(Direction::RecvOnly),
+            ("inactive", None) => self.direction = Some(Direction::Inactive),
+            ("ts-refclk", Some(argument)) => self.ts_refclk = Some(RefClock::parse(argument)?),
+            ("mediaclk", Some(argument)) => self.mediaclk = Some(MediaClock::parse(argument)?),
+            ("source-filter", Some(argument)) => self.source_filters.push(SourceFilter::parse(argument)?),
+            (name, argument) => self.other.push((name.to_string(), argument.map(str::to_string))),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(direction) = self.direction {
+            write!(f, "a={}\r\n", direction)?;
+        }
+        if let Some(ts_refclk) = &self.ts_refclk {
+            write!(f, "a=ts-refclk:{}\r\n", ts_refclk)?;
+        }
+        if let Some(mediaclk) = &self.mediaclk {
+            write!(f, "a=mediaclk:{}\r\n", mediaclk)?;
+        }
+        for filter in &self.source_filters {
+            write!(f, "a=source-filter: {}\r\n", filter)?;
+        }
+        for (name, value) in &self.other {
+            match value {
+                Some(value) => write!(f, "a={}:{}\r\n", name, value)?,
+                None => write!(f, "a={}\r\n", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::SendRecv => "sendrecv",
+            Direction::SendOnly => "sendonly",
+            Direction::RecvOnly => "recvonly",
+            Direction::Inactive => "inactive",
+        })
+    }
+}
+
+impl RefClock {
+    fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if let Some(ptp) = value.strip_prefix("ptp=") {
+            // ptp=<version>:<gmid>[:<domain>] or ptp=<version>:traceable
+            let mut fields = ptp.split(':');
+            let version = fields.next().unwrap_or_default().to_string();
+            let grandmaster = match fields.next() {
+                Some("traceable") | None => None,
+                Some(gmid) if is_clock_identity(gmid) => Some(gmid.to_uppercase()),
+                Some(gmid) => bail!("invalid PTP grandmaster identity '{}'", gmid),
+            };
+            let domain = fields.next()
+                .map(|d| d.parse::<u8>().ok().filter(|d| *d < 128).ok_or_else(|| anyhow!("invalid PTP domain '{}'", d)))
+                .transpose()?;
+            return Ok(RefClock::Ptp { version, grandmaster, domain });
+        }
+        if let Some(mac) = value.strip_prefix("localmac=") {
+            return Ok(RefClock::LocalMac(mac.to_string()));
+        }
+        Ok(RefClock::Other(value.to_string()))
+    }
+}
+
+impl fmt::Display for RefClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefClock::Ptp { version, grandmaster, domain } => {
+                write!(f, "ptp={}:{}", version, grandmaster.as_deref().unwrap_or("traceable"))?;
+                if let Some(domain) = domain {
+                    write!(f, ":{}", domain)?;
+                }
+                Ok(())
+            }
+            RefClock::LocalMac(mac) => write!(f, "localmac={}", mac),
+            RefClock::Other(value) => f.write_str(value),
+        }
+    }
+}
+
+impl MediaClock {
+    fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let Some(direct) = value.strip_prefix("direct=") else {
+            return Ok(MediaClock::Other(value.to_string()));
+        };
+        let (offset, rate) = match direct.split_once(' ') {
+            Some((offset, parameters)) => (offset, parameters.trim().strip_prefix("rate=").map(str::to_string)),
+            None => (direct, None),
+        };
+        // The offset is taken modulo 2^32 like the RTP timestamps
+        let offset = offset.parse::<u64>().map_err(|_| anyhow!("invalid media clock offset '{}'", offset))?;
+        Ok(MediaClock::Direct { offset: offset as u32, rate })
+    }
+}
+
+impl fmt::Display for MediaClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaClock::Direct { offset, rate: Some(rate) } => write!(f, "direct={} rate={}", offset, rate),
+            MediaClock::Direct { offset, rate: None } => write!(f, "direct={}", offset),
+            MediaClock::Other(value) => f.write_str(value),
+        }
+    }
+}
+
+impl SourceFilter {
+    fn parse(value: &str) -> Result<Self> {
+        // <incl|excl> IN <addrtype> <dest-address> <src-list>
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        if parts.len() < 5 {
+            bail!("source filter needs at least 5 fields, found {}", parts.len());
+        }
+        let include = match parts[0] {
+            "incl" => true,
+            "excl" => false,
+            mode => bail!("invalid source filter mode '{}'", mode),
+        };
+        let sources = parts[4..].iter()
+            .map(|s| s.parse().map_err(|_| anyhow!("invalid source address '{}'", s)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            include,
+            address_type: parts[2].to_string(),
+            destination: parts[3].to_string(),
+            sources,
+        })
+    }
+}
+
+impl fmt::Display for SourceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sources: Vec<String> = self.sources.iter().map(|s| s.to_string()).collect();
+        write!(f, "{} IN {} {} {}",
+            if self.include { "incl" } else { "excl" }, self.address_type, self.destination, sources.join(" "))
+    }
+}
+
+/// Split `name[:value]`
+fn split_attribute(value: &str) -> (&str, Option<&str>) {
+    match value.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (value, None),
+    }
+}
+
+/// EUI-64 clock identity as written in SDP (`XX-XX-XX-XX-XX-XX-XX-XX`)
+fn is_clock_identity(value: &str) -> bool {
+    let octets: Vec<&str> = value.split('-').collect();
+    octets.len() == 8 && octets.iter().all(|o| o.len() == 2 && o.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Typical RAVENNA/AES67 sender description
+    const RAVENNA_SDP: &str = "v=0\r\n\
+        o=- 1311738121 1311738121 IN IP4 192.168.1.10\r\n\
+        s=Stage Box 1-8\r\n\
+        c=IN IP4 239.69.11.44/32\r\n\
+        t=0 0\r\n\
+        a=keywds:Dante\r\n\
+        a=clock-domain:PTPv2 0\r\n\
+        m=audio 5004 RTP/AVP 98\r\n\
+        i=Channels 1-8\r\n\
+        a=rtpmap:98 L24/48000/8\r\n\
+        a=ptime:0.125\r\n\
+        a=recvonly\r\n\
+        a=ts-refclk:ptp=IEEE1588-2008:00-1D-C1-FF-FE-12-34-56:0\r\n\
+        a=mediaclk:direct=2216659908\r\n\
+        a=source-filter: incl IN IP4 239.69.11.44 192.168.1.10\r\n\
+        a=framecount:6\r\n";
+
+    #[test]
+    fn test_parse_aes67_attributes() {
+        let sdp = SessionDescription::parse(RAVENNA_SDP).unwrap();
+        assert_eq!(sdp.origin.session_id, "1311738121");
+        assert_eq!(sdp.origin.address, "192.168.1.10");
+        assert_eq!(sdp.name, "Stage Box 1-8");
+        assert_eq!(sdp.connection.unwrap().ttl, Some(32));
+        assert!(sdp.attributes.other.contains(&("clock-domain".to_string(), Some("PTPv2 0".to_string()))));
+
+        let media = &sdp.media[0];
+        assert_eq!(media.formats, vec!["98"]);
+        assert_eq!(media.info.as_deref(), Some("Channels 1-8"));
+        assert_eq!(media.rtpmap_for(98).unwrap(), RtpMap {
+            payload_type: 98, encoding: "L24".to_string(), clock_rate: 48000, channels: Some(8),
+        });
+        assert_eq!(media.ptime, Some(0.125));
+        assert_eq!(media.attributes.direction, Some(Direction::RecvOnly));
+        assert_eq!(media.attributes.ts_refclk, Some(RefClock::Ptp {
+            version: PTP_VERSION_2008.to_string(),
+            grandmaster: Some("00-1D-C1-FF-FE-12-34-56".to_string()),
+            domain: Some(0),
+        }));
+        assert_eq!(media.attributes.mediaclk, Some(MediaClock::Direct { offset: 2216659908, rate: None }));
+        assert_eq!(media.attributes.source_filters[0].sources, vec!["192.168.1.10".parse::<IpAddr>().unwrap()]);
+        assert_eq!(media.attributes.other, vec![("framecount".to_string(), Some("6".to_string()))]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let sdp = SessionDescription::parse(RAVENNA_SDP).unwrap();
+        assert_eq!(sdp.to_string(), RAVENNA_SDP);
+        assert_eq!(SessionDescription::parse(&sdp.to_string()).unwrap(), sdp);
+
+        // LF line endings, traceable clock, several media sections
+        let text = "v=0\no=user 1 2 IN IP4 10.0.0.1\ns=Two\nt=0 0\n\
+            a=ts-refclk:ptp=IEEE1588-2008:traceable\n\
+            m=audio 5004 RTP/AVP 96\nc=IN IP4 239.1.1.1/16\na=rtpmap:96 L16/44100/2\n\
+            m=audio 5006 RTP/AVP 10\nc=IN IP4 239.1.1.2/16\na=sendonly\n";
+        let sdp = SessionDescription::parse(text).unwrap();
+        assert_eq!(sdp.media.len(), 2);
+        assert_eq!(sdp.attributes.ts_refclk, Some(RefClock::Ptp {
+            version: PTP_VERSION_2008.to_string(), grandmaster: None, domain: None,
+        }));
+        assert_eq!(sdp.media[1].rtpmap_for(10).unwrap().channels, Some(2));
+        assert_eq!(SessionDescription::parse(&sdp.to_string()).unwrap(), sdp);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let missing_origin = "v=0\r\ns=No origin\r\nt=0 0\r\n";
+        assert!(SessionDescription::parse(missing_origin).unwrap_err().to_string().contains("o="));
+
+        let bad_rtpmap = RAVENNA_SDP.replace("L24/48000/8", "L24/fast/8");
+        let error = SessionDescription::parse(&bad_rtpmap).unwrap_err().to_string();
+        assert!(error.contains("line 10") && error.contains("clock rate"), "{}", error);
+
+        let bad_clock = RAVENNA_SDP.replace("00-1D-C1-FF-FE-12-34-56", "gm");
+        assert!(SessionDescription::parse(&bad_clock).unwrap_err().to_string().contains("grandmaster"));
+
+        assert!(SessionDescription::parse("v=1\r\n").is_err());
+        assert!(SessionDescription::parse("garbage").is_err());
+    }
+
+    #[test]
+    fn test_duplication_group() {
+        // ST 2022-7 sender with one media section per network
+        let text = "v=0\r\no=- 7 1 IN IP4 10.0.0.1\r\ns=Redundant\r\nt=0 0\r\n\
+            a=group:DUP primary secondary\r\n\
+            m=audio 5004 RTP/AVP 97\r\nc=IN IP4 239.69.1.1/32\r\na=mid:primary\r\n\
+            m=audio 5004 RTP/AVP 97\r\nc=IN IP4 239.69.2.1/32\r\na=mid:secondary\r\n";
+        let sdp = SessionDescription::parse(text).unwrap();
+        assert_eq!(sdp.duplication_group(), Some(vec!["primary", "secondary"]));
+        let secondary = sdp.media_by_id("secondary").unwrap();
+        assert_eq!(secondary.connection.unwrap().address, "239.69.2.1".parse::<IpAddr>().unwrap());
+        assert!(sdp.media_by_id("tertiary").is_none());
+        assert_eq!(SessionDescription::parse(RAVENNA_SDP).unwrap().duplication_group(), None);
+    }
+}
@@ -0,0 +1,404 @@
+//! Paced RTP transmitter
+//!
+//! The audio engine pushes the samples of our output stream into a lock-free
+//! ring. A dedicated real-time thread takes exactly one packet (1 ms) per
+//! media clock period and sends it with the timestamp of its slot on the PTP
+//! media clock, so packets leave at a steady cadence independent of the
+//! audio callback's block size. The ring is filled at the rate of the local
+//! device clock, so the engine resamples each stream adaptively to keep its
+//! fill level steady (see `Aes67TxStreams`).
+//!
+//! Without a local audio device, a `MediaTicker` on the same clock drives the
+//! mixer so every stream keeps receiving samples.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use anyhow::Result;
+use tracing::{info, warn};
+
+use super::PtpClock;
+use super::rtp::{RtpSender, SendPathStats};
+
+/// Remaining time before a deadline that is spent spinning instead of sleeping
+const SPIN_THRESHOLD_NS: u64 = 300_000;
+
+/// Packets the sender may fall behind before it resynchronises to the clock
+const MAX_BEHIND_PACKETS: u64 = 20;
+
+/// Real-time priority of the sender thread (SCHED_FIFO)
+#[cfg(target_os = "linux")]
+const SENDER_RT_PRIORITY: i32 = 70;
+
+/// Callback run once per tick with the number of frames to produce
+pub type TickHook = Box<dyn FnMut(usize) + Send>;
+
+/// Single-producer/single-consumer sample ring (lock-free)
+pub struct TxRing {
+    buffer: Vec<AtomicU32>,
+    /// Total samples written (producer)
+    write_pos: AtomicUsize,
+    /// Total samples read (consumer)
+    read_pos: AtomicUsize,
+}
+
+impl TxRing {
+    /// Create a ring holding `capacity` samples
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: (0..capacity.max(1)).map(|_| AtomicU32::new(0)).collect(),
+            write_pos: AtomicUsize::new(0),
+            read_pos: AtomicUsize::new(0),
+        }
+    }
+
+    /// Capacity in samples
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Samples ready to be read
+    pub fn available(&self) -> usize {
+        self.write_pos.load(Ordering::Acquire) - self.read_pos.load(Ordering::Acquire)
+    }
+
+    /// Push samples (producer side), returns the number written
+    ///
+    /// Samples that do not fit are dropped.
+    pub fn push(&self, samples: &[f32]) -> usize {
+        let write = self.write_pos.load(Ordering::Relaxed);
+        let read = self.read_pos.load(Ordering::Acquire);
+        let count = samples.len().min(self.capacity() - (write - read));
+
+        for (i, sample) in samples[..count].iter().enumerate() {
+            self.buffer[(write + i) % self.capacity()].store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.write_pos.store(write + count, Ordering::Release);
+        count
+    }
+
+    /// Pop samples (consumer side), returns the number read
+    pub fn pop(&self, out: &mut [f32]) -> usize {
+        let read = self.read_pos.load(Ordering::Relaxed);
+        let write = self.write_pos.load(Ordering::Acquire);
+        let count = out.len().min(write - read);
+
+        for (i, sample) in out[..count].iter_mut().enumerate() {
+            *sample = f32::from_bits(self.buffer[(read + i) % self.capacity()].load(Ordering::Relaxed));
+        }
+        self.read_pos.store(read + count, Ordering::Release);
+        count
+    }
+}
+
+/// Packet slots on the media clock
+///
+/// Slot `n` starts at media time `n * samples_per_packet`; its RTP timestamp
+/// is that media time modulo 2^32.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketClock {
+    sample_rate: u64,
+    samples_per_packet: u64,
+}
+
+impl PacketClock {
+    pub fn new(sample_rate: u32, samples_per_packet: u16) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1) as u64,
+            samples_per_packet: samples_per_packet.max(1) as u64,
+        }
+    }
+
+    /// First slot starting after `now_ns`
+    pub fn next_slot(&self, now_ns: u64) -> u64 {
+        let media = now_ns as u128 * self.sample_rate as u128 / 1_000_000_000;
+        (media / self.samples_per_packet as u128) as u64 + 1
+    }
+
+    /// PTP time at which a slot starts (rounded up to whole nanoseconds)
+    pub fn slot_start_ns(&self, slot: u64) -> u64 {
+        let media_ns = slot as u128 * self.samples_per_packet as u128 * 1_000_000_000;
+        media_ns.div_ceil(self.sample_rate as u128) as u64
+    }
+
+    /// RTP timestamp of a slot
+    pub fn timestamp(&self, slot: u64) -> u32 {
+        (slot.wrapping_mul(self.samples_per_packet) & 0xFFFF_FFFF) as u32
+    }
+}
+
+/// Transmitter statistics
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransmitterStats {
+    pub packets_sent: u64,
+    pub underruns: u64,
+}
+
+/// Real-time RTP transmitter thread
+pub struct RtpTransmitter {
+    sender: Arc<RtpSender>,
+    ring: Arc<TxRing>,
+    running: Arc<AtomicBool>,
+    packets_sent: Arc<AtomicU64>,
+    underruns: Arc<AtomicU64>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RtpTransmitter {
+    /// Start sending `sender`'s stream paced by the PTP media clock
+    ///
+    /// `prefill_frames` is the amount of audio collected before sending
+    /// starts (and again after an underrun), it absorbs the block size of the
+    /// audio callback.
+    pub fn start(sender: RtpSender, ptp_clock: Arc<PtpClock>, prefill_frames: usize) -> Result<Self> {
+        let sender = Arc::new(sender);
+        let format = sender.format();
+        let channels = format.channels.max(1) as usize;
+        let frames_per_packet = format.samples_per_packet.max(1) as usize;
+
+        let ring = Arc::new(TxRing::new((prefill_frames + frames_per_packet) * channels * 4));
+        let running = Arc::new(AtomicBool::new(true));
+        let packets_sent = Arc::new(AtomicU64::new(0));
+        let underruns = Arc::new(AtomicU64::new(0));
+
+        let thread = {
+            let sender = sender.clone();
+            let ring = ring.clone();
+            let running = running.clone();
+            let packets_sent = packets_sent.clone();
+            let underruns = underruns.clone();
+            let clock = PacketClock::new(format.sample_rate, format.samples_per_packet);
+            let prefill = prefill_frames * channels;
+
+            std::thread::Builder::new()
+                .name("aes67-tx".to_string())
+                .spawn(move || {
+                    set_realtime_priority();
+
+                    let mut packet = vec![0.0f32; frames_per_packet * channels];
+                    let mut primed = false;
+                    let mut slot = clock.next_slot(ptp_clock.now_ns());
+
+                    while running.load(Ordering::Relaxed) {
+                        let deadline = clock.slot_start_ns(slot);
+                        wait_until(&ptp_clock, deadline);
+
+                        // Resynchronise after a stall instead of bursting old packets
+                        let now_slot = clock.next_slot(ptp_clock.now_ns());
+                        if now_slot > slot + MAX_BEHIND_PACKETS {
+                            slot = now_slot;
+                            continue;
+                        }
+
+                        if !primed && ring.available() >= prefill.max(packet.len()) {
+                            primed = true;
+                        }
+                        let read = if primed { ring.pop(&mut packet) } else { 0 };
+                        if read < packet.len() {
+                            packet[read..].fill(0.0);
+                            if primed {
+                                underruns.fetch_add(1, Ordering::Relaxed);
+                                primed = false;
+                            }
+                        }
+
+                        if let Err(e) = sender.send_packet_at(&packet, clock.timestamp(slot)) {
+                            warn!("RTP send error: {}", e);
+                        } else {
+                            packets_sent.fetch_add(1, Ordering::Relaxed);
+                        }
+                        slot += 1;
+                    }
+
+                    info!("RTP transmitter stopped");
+                })?
+        };
+
+        Ok(Self {
+            sender,
+            ring,
+            running,
+            packets_sent,
+            underruns,
+            thread: Some(thread),
+        })
+    }
+
+    /// Ring the engine writes our stream's samples into
+    pub fn ring(&self) -> Arc<TxRing> {
+        self.ring.clone()
+    }
+
+    /// Transmitter statistics
+    pub fn stats(&self) -> TransmitterStats {
+        TransmitterStats {
+            packets_sent: self.packets_sent.load(Ordering::Relaxed),
+            underruns: self.underruns.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Send errors per network path (primary first)
+    pub fn path_stats(&self) -> Vec<SendPathStats> {
+        self.sender.path_stats()
+    }
+
+    /// The RTP sender (for its RTCP session)
+    pub fn sender(&self) -> Arc<RtpSender> {
+        self.sender.clone()
+    }
+
+    /// Stop the thread and wait for it to finish
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for RtpTransmitter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Thread running a callback once per period on the PTP media clock
+///
+/// Used to drive the mixer when there is no local audio device.
+pub struct MediaTicker {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MediaTicker {
+    /// Call `hook` every `frames_per_tick` frames of the media clock
+    pub fn start(ptp_clock: Arc<PtpClock>, sample_rate: u32, frames_per_tick: u16, mut hook: TickHook) -> Result<Self> {
+        let running = Arc::new(AtomicBool::new(true));
+        let clock = PacketClock::new(sample_rate, frames_per_tick);
+        let frames = frames_per_tick.max(1) as usize;
+
+        let thread = {
+            let running = running.clone();
+            std::thread::Builder::new()
+                .name("aes67-clock".to_string())
+                .spawn(move || {
+                    set_realtime_priority();
+
+                    let mut slot = clock.next_slot(ptp_clock.now_ns());
+                    while running.load(Ordering::Relaxed) {
+                        wait_until(&ptp_clock, clock.slot_start_ns(slot));
+
+                        let now_slot = clock.next_slot(ptp_clock.now_ns());
+                        if now_slot > slot + MAX_BEHIND_PACKETS {
+                            slot = now_slot;
+                            continue;
+                        }
+
+                        hook(frames);
+                        slot += 1;
+                    }
+                })?
+        };
+
+        Ok(Self {
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Stop the thread and wait for it to finish
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MediaTicker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Sleep until shortly before `deadline_ns` (PTP time), then spin
+fn wait_until(clock: &PtpClock, deadline_ns: u64) {
+    loop {
+        let now = clock.now_ns();
+        if now >= deadline_ns {
+            return;
+        }
+        let remaining = deadline_ns - now;
+        if remaining > SPIN_THRESHOLD_NS {
+            std::thread::sleep(Duration::from_nanos(remaining - SPIN_THRESHOLD_NS));
+        } else {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Raise the current thread to SCHED_FIFO (needs CAP_SYS_NICE or rtprio limit)
+#[cfg(target_os = "linux")]
+fn set_realtime_priority() {
+    let param = libc::sched_param { sched_priority: SENDER_RT_PRIORITY };
+    // SAFETY: pthread_self() is always valid for the calling thread and
+    // `param` lives for the duration of the call.
+    let result = unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+    if result != 0 {
+        warn!("AES67 sender: real-time priority not available (error {}), using normal priority", result);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_realtime_priority() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_ring_wraps() {
+        let ring = TxRing::new(8);
+
+        assert_eq!(ring.push(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), 6);
+        let mut out = [0.0; 4];
+        assert_eq!(ring.pop(&mut out), 4);
+        assert_eq!(out, [1.0, 2.0, 3.0, 4.0]);
+
+        // Wraps around, overflow is dropped
+        assert_eq!(ring.push(&[7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0]), 6);
+        assert_eq!(ring.available(), 8);
+
+        let mut out = [0.0; 10];
+        assert_eq!(ring.pop(&mut out), 8);
+        assert_eq!(&out[..8], &[5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0]);
+    }
+
+    #[test]
+    fn test_packet_clock_slots() {
+        let clock = PacketClock::new(48000, 48);
+
+        // 1 ms slots
+        assert_eq!(clock.slot_start_ns(1), 1_000_000);
+        assert_eq!(clock.next_slot(0), 1);
+        assert_eq!(clock.next_slot(999_999), 1);
+        assert_eq!(clock.next_slot(1_000_000), 2);
+
+        // Timestamps advance by one packet and wrap at 32 bits
+        assert_eq!(clock.timestamp(10).wrapping_sub(clock.timestamp(9)), 48);
+        let wrap_slot = (1u64 << 32) / 48 + 1;
+        assert_eq!(clock.timestamp(wrap_slot).wrapping_sub(clock.timestamp(wrap_slot - 1)), 48);
+    }
+
+    #[test]
+    fn test_packet_clock_44k1() {
+        // 44.1 kHz with 44 samples per packet: slot starts are rounded up,
+        // so the media clock has reached the slot at its start time
+        let clock = PacketClock::new(44100, 44);
+        let start = clock.slot_start_ns(100);
+        assert_eq!(start, (100 * 44 * 1_000_000_000u64).div_ceil(44100));
+        assert_eq!(clock.next_slot(start), 101);
+        assert_eq!(clock.next_slot(start - 1), 100);
+    }
+}
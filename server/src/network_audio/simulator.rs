@@ -0,0 +1,339 @@
+//! Simulated AES67 device for tests
+//!
+//! Runs in-process what a Dante or RAVENNA device does on the network: a PTP
+//! grandmaster, SAP announcements and a paced RTP stream of a known test
+//! signal. It also discovers our announcements and captures the RTP streams
+//! we send. On loopback (127.0.0.1) this exercises the real PTP, SAP and RTP
+//! code of both ends without any hardware.
+//!
+//! The test signal is a ramp over the media clock: every sample tells the
+//! RTP timestamp it was sent at, so a receiver can check that playout is
+//! gapless and at the right PTP time.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use parking_lot::Mutex;
+use tracing::info;
+
+use super::PtpClock;
+use super::interface;
+use super::rtp::{decode_samples, Aes67Format, RtpEncoding, RtpHeader, RtpSender};
+use super::sap::{Aes67Stream, SapDiscovery, StreamDirection, DEFAULT_PAYLOAD_TYPE};
+use super::sender::PacketClock;
+
+/// Period of the test signal ramp in frames
+pub const RAMP_PERIOD: u32 = 32768;
+
+/// Ramp offset between neighbouring channels in frames
+const CHANNEL_OFFSET: u32 = 4096;
+
+/// Samples may differ this much after encoding (L16 quantisation)
+pub const SIGNAL_TOLERANCE: f32 = 1.0 / 65536.0;
+
+/// The simulator re-announces its stream this often
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Unprivileged PTP ports (event, general) of the simulator, so the tests run
+/// without root; backends under test need the same `Aes67Config::ptp_ports`
+pub const TEST_PTP_PORTS: (u16, u16) = (10319, 10320);
+
+/// Test signal of `channel` at media clock `timestamp`
+///
+/// A ramp from -0.5 to 0.5 with a step of 1/32768, exactly representable
+/// in L16, L24 and AM824.
+pub fn test_signal(channel: usize, timestamp: u32) -> f32 {
+    let phase = timestamp.wrapping_add(channel as u32 * CHANNEL_OFFSET) % RAMP_PERIOD;
+    (phase as f32 - (RAMP_PERIOD / 2) as f32) / RAMP_PERIOD as f32
+}
+
+/// Ramp phase of a test signal sample of `channel` (timestamp modulo the period)
+pub fn signal_phase(channel: usize, sample: f32) -> u32 {
+    let phase = (sample * RAMP_PERIOD as f32).round() as i64 + (RAMP_PERIOD / 2) as i64;
+    (phase - (channel as u32 * CHANNEL_OFFSET) as i64).rem_euclid(RAMP_PERIOD as i64) as u32
+}
+
+/// Distance of two ramp phases in frames (shortest way around)
+pub fn phase_distance(a: u32, b: u32) -> u32 {
+    let d = a.abs_diff(b) % RAMP_PERIOD;
+    d.min(RAMP_PERIOD - d)
+}
+
+/// Simulated device
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    pub name: String,
+    /// Interface address everything is bound to
+    pub interface: Ipv4Addr,
+    /// Destination of the device's stream
+    pub group: Ipv4Addr,
+    pub port: u16,
+    pub channels: u8,
+    pub sample_rate: u32,
+    pub encoding: RtpEncoding,
+    pub ptime_us: u32,
+    pub ptp_domain: u8,
+    /// priority1 of the grandmaster (lower than ours so that it wins the BMCA)
+    pub ptp_priority1: u8,
+    /// UDP ports of PTP event and general messages
+    pub ptp_ports: (u16, u16),
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            name: "Simulated AES67 device".to_string(),
+            interface: Ipv4Addr::LOCALHOST,
+            group: Ipv4Addr::new(239, 69, 250, 1),
+            port: 15004,
+            channels: 2,
+            sample_rate: 48000,
+            encoding: RtpEncoding::L24,
+            ptime_us: 1000,
+            ptp_domain: 0,
+            ptp_priority1: 100,
+            ptp_ports: TEST_PTP_PORTS,
+        }
+    }
+}
+
+/// An AES67 device running in-process
+pub struct SimulatedDevice {
+    ptp_clock: Arc<PtpClock>,
+    sap: Arc<SapDiscovery>,
+    stream: Aes67Stream,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SimulatedDevice {
+    /// Start the grandmaster, SAP and the test signal stream
+    pub fn start(config: SimulatorConfig) -> Result<Self> {
+        // Not the interface name: two clocks on one interface need different identities
+        let mut ptp_clock = PtpClock::new("simulator");
+        ptp_clock.set_interface_address(Some(config.interface));
+        ptp_clock.set_domain(config.ptp_domain);
+        ptp_clock.set_master_capable(true, config.ptp_priority1);
+        ptp_clock.set_ports(config.ptp_ports.0, config.ptp_ports.1);
+        let ptp_clock = Arc::new(ptp_clock);
+        ptp_clock.start()?;
+
+        let mut sap = SapDiscovery::with_ptp_clock(ptp_clock.clone());
+        sap.set_interface(Some(config.interface));
+        let sap = Arc::new(sap);
+        sap.start()?;
+
+        let format = Aes67Format::with_ptime(config.sample_rate, config.channels, config.encoding, config.ptime_us);
+        let mut sender = RtpSender::with_options(config.group, config.port, format, 1, 0, Some(config.interface))?;
+        sender.set_ptp_clock(ptp_clock.clone());
+
+        let stream = Aes67Stream {
+            name: config.name.clone(),
+            session_id: sender.ssrc().to_string(),
+            origin: config.interface.to_string(),
+            multicast_addr: config.group,
+            port: config.port,
+            channels: config.channels,
+            sample_rate: config.sample_rate,
+            encoding: config.encoding,
+            ptime_us: config.ptime_us,
+            direction: StreamDirection::Send,
+            payload_type: DEFAULT_PAYLOAD_TYPE,
+            media_clock_offset: 0,
+            ref_clock: None,
+            source_address: Some(config.interface),
+            secondary: None,
+            sdp: String::new(),
+        };
+        sap.announce(stream.clone())?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let running = running.clone();
+            let clock = ptp_clock.clone();
+            let sap = sap.clone();
+            let stream = stream.clone();
+            std::thread::Builder::new()
+                .name("aes67-simulator".to_string())
+                .spawn(move || run_sender(sender, &clock, &sap, stream, &running))?
+        };
+        info!("🧪 Simulated AES67 device '{}' on {}:{}", config.name, config.group, config.port);
+
+        Ok(Self { ptp_clock, sap, stream, running, thread: Some(thread) })
+    }
+
+    /// The stream the device sends, as announced
+    pub fn stream(&self) -> &Aes67Stream {
+        &self.stream
+    }
+
+    /// SDP of the device's stream, as announced
+    pub fn sdp(&self) -> Option<String> {
+        self.sap.announced_sdp(&self.stream.session_id)
+    }
+
+    /// The device's PTP clock (grandmaster once the BMCA has settled)
+    pub fn ptp_clock(&self) -> Arc<PtpClock> {
+        self.ptp_clock.clone()
+    }
+
+    /// Streams the device discovered via SAP
+    pub fn discovered(&self) -> Vec<Aes67Stream> {
+        self.sap.streams()
+    }
+
+    /// Stop sending, withdraw the announcement and stop PTP
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+            let _ = self.sap.remove_announcement(&self.stream.session_id);
+            self.sap.stop();
+            self.ptp_clock.stop();
+        }
+    }
+}
+
+impl Drop for SimulatedDevice {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Send the test signal paced by the device's PTP media clock
+fn run_sender(sender: RtpSender, clock: &PtpClock, sap: &SapDiscovery, stream: Aes67Stream, running: &AtomicBool) {
+    let format = sender.format();
+    let channels = format.channels.max(1) as usize;
+    let packet_clock = PacketClock::new(format.sample_rate, format.samples_per_packet);
+    let mut packet = vec![0.0f32; format.samples_per_packet as usize * channels];
+    let mut slot = packet_clock.next_slot(clock.now_ns());
+    let mut next_announce = Instant::now() + ANNOUNCE_INTERVAL;
+
+    while running.load(Ordering::Relaxed) {
+        let deadline = packet_clock.slot_start_ns(slot);
+        let now = clock.now_ns();
+        if deadline > now {
+            std::thread::sleep(Duration::from_nanos(deadline - now));
+        }
+
+        let timestamp = packet_clock.timestamp(slot);
+        for (i, sample) in packet.iter_mut().enumerate() {
+            *sample = test_signal(i % channels, timestamp.wrapping_add((i / channels) as u32));
+        }
+        let _ = sender.send_packet_at(&packet, timestamp);
+        slot += 1;
+
+        // Receivers that start later find the stream quickly
+        if Instant::now() >= next_announce {
+            let _ = sap.announce(stream.clone());
+            next_announce += ANNOUNCE_INTERVAL;
+        }
+    }
+}
+
+/// An RTP packet received by a capture
+#[derive(Debug, Clone)]
+pub struct CapturedPacket {
+    pub header: RtpHeader,
+    /// Decoded samples (interleaved)
+    pub samples: Vec<f32>,
+}
+
+/// Records every packet of an RTP stream
+pub struct StreamCapture {
+    packets: Arc<Mutex<Vec<CapturedPacket>>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StreamCapture {
+    /// Capture `group`:`port` on `interface`
+    pub fn start(group: Ipv4Addr, port: u16, encoding: RtpEncoding, interface: Ipv4Addr) -> Result<Self> {
+        use socket2::{Socket, Domain, Type, Protocol};
+
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        socket.bind(&SocketAddr::new(IpAddr::V4(group), port).into())?;
+        interface::join_multicast(&socket, group, Some(interface))?;
+        let socket: UdpSocket = socket.into();
+        socket.set_read_timeout(Some(Duration::from_millis(10)))?;
+
+        let packets = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let packets = packets.clone();
+            let running = running.clone();
+            std::thread::spawn(move || {
+                let mut buf = [0u8; 2048];
+                while running.load(Ordering::Relaxed) {
+                    let Ok(len) = socket.recv(&mut buf) else {
+                        continue;
+                    };
+                    let packet = &buf[..len];
+                    if let Some(header) = RtpHeader::from_bytes(packet) {
+                        let Some(payload) = header.payload(packet) else {
+                            continue;
+                        };
+                        let samples = decode_samples(payload, encoding);
+                        packets.lock().push(CapturedPacket { header, samples });
+                    }
+                }
+            })
+        };
+
+        Ok(Self { packets, running, thread: Some(thread) })
+    }
+
+    /// Packets received so far
+    pub fn packets(&self) -> Vec<CapturedPacket> {
+        self.packets.lock().clone()
+    }
+
+    /// Stop capturing
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for StreamCapture {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Poll `condition` until it holds or `timeout` passes
+pub fn wait_for(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    condition()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_phase() {
+        for channel in 0..4 {
+            for timestamp in [0u32, 1, 16383, 16384, 32767, 32768, u32::MAX] {
+                let sample = test_signal(channel, timestamp);
+                assert!((-0.5..0.5).contains(&sample));
+                assert_eq!(signal_phase(channel, sample), timestamp % RAMP_PERIOD);
+            }
+        }
+        assert_eq!(phase_distance(32760, 5), 13);
+    }
+}
@@ -0,0 +1,578 @@
+//! Outgoing AES67 streams
+//!
+//! Any number of streams can be sent at once. Each stream takes a selection
+//! of mixer outputs, runs its own paced transmitter, is announced via SAP for
+//! as long as it exists and has an RTCP session that collects the receiver
+//! reports of remote devices. Streams come from `config.toml` and can be
+//! added or removed at runtime; the table is shared between the audio engine,
+//! which feeds the transmitters, and the API.
+//!
+//! The engine produces samples at the rate of the local device clock while the
+//! transmitters send at the PTP media clock. With a drift monitor, every stream
+//! is resampled adaptively before it enters the transmitter's ring, holding the
+//! ring at its prefill level instead of overflowing or running dry.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Arc;
+use anyhow::{Result, anyhow};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::audio::{DriftController, DriftMonitor, Resampler};
+
+use super::PtpClock;
+use super::interface;
+use super::rtcp::{RtcpSession, RtcpStats};
+use super::rtp::{Aes67Format, RtpEncoding, RtpSender, SendPathStats};
+use super::sap::{Aes67Stream, SapDiscovery, StreamDirection, DEFAULT_PAYLOAD_TYPE};
+use super::sender::{RtpTransmitter, TxRing};
+
+/// Packet times allowed by AES67 (µs)
+pub const SUPPORTED_PTIMES_US: [u32; 5] = [125, 250, 333, 1000, 4000];
+
+/// Largest RTP payload that fits a standard Ethernet MTU
+const MAX_PAYLOAD_BYTES: usize = 1440;
+
+/// Definition of an outgoing stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxStreamConfig {
+    /// Stream name (SDP session name)
+    pub name: String,
+    /// Multicast group or unicast receiver address
+    pub destination: Ipv4Addr,
+    /// Destination on the secondary network (SMPTE ST 2022-7), used when a
+    /// secondary interface is configured (None = same as `destination`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_destination: Option<Ipv4Addr>,
+    /// RTP port
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Number of channels
+    #[serde(default = "default_channels")]
+    pub channels: u8,
+    /// Mixer output per channel (empty = outputs 0..channels)
+    #[serde(default)]
+    pub outputs: Vec<usize>,
+    /// Packet time in microseconds
+    #[serde(default = "default_ptime")]
+    pub ptime_us: u32,
+    /// Sample encoding
+    #[serde(default)]
+    pub encoding: RtpEncoding,
+    /// IP time to live
+    #[serde(default = "default_ttl")]
+    pub ttl: u32,
+    /// DSCP for the IP header (46 = EF)
+    #[serde(default = "default_dscp")]
+    pub dscp: u8,
+}
+
+fn default_port() -> u16 { 5004 }
+fn default_channels() -> u8 { 8 }
+fn default_ptime() -> u32 { 1000 }
+fn default_ttl() -> u32 { 64 }
+fn default_dscp() -> u8 { 46 }
+
+impl TxStreamConfig {
+    /// Stream with default settings
+    pub fn new(name: &str, destination: Ipv4Addr, channels: u8) -> Self {
+        Self {
+            name: name.to_string(),
+            destination,
+            secondary_destination: None,
+            port: default_port(),
+            channels,
+            outputs: Vec::new(),
+            ptime_us: default_ptime(),
+            encoding: RtpEncoding::default(),
+            ttl: default_ttl(),
+            dscp: default_dscp(),
+        }
+    }
+
+    /// Mixer output for every channel
+    pub fn output_map(&self) -> Vec<usize> {
+        if self.outputs.is_empty() {
+            (0..self.channels as usize).collect()
+        } else {
+            self.outputs.clone()
+        }
+    }
+
+    /// RTP format of this stream at the engine sample rate
+    pub fn format(&self, sample_rate: u32) -> Aes67Format {
+        Aes67Format::with_ptime(sample_rate, self.channels, self.encoding, self.ptime_us)
+    }
+
+    /// Check the stream against the engine sample rate and mixer output count
+    pub fn validate(&self, sample_rate: u32, output_count: usize) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow!("Stream name must not be empty"));
+        }
+        if self.channels == 0 || self.channels > 64 {
+            return Err(anyhow!("Stream '{}': invalid channel count {} (1-64)", self.name, self.channels));
+        }
+        if self.destination.is_unspecified() || self.destination.is_broadcast() {
+            return Err(anyhow!("Stream '{}': invalid destination {}", self.name, self.destination));
+        }
+        if let Some(secondary) = self.secondary_destination {
+            if secondary.is_unspecified() || secondary.is_broadcast() {
+                return Err(anyhow!("Stream '{}': invalid secondary destination {}", self.name, secondary));
+            }
+        }
+        if self.port == 0 {
+            return Err(anyhow!("Stream '{}': invalid port 0", self.name));
+        }
+        if !SUPPORTED_PTIMES_US.contains(&self.ptime_us) {
+            return Err(anyhow!(
+                "Stream '{}': unsupported ptime {} µs (allowed: {:?})",
+                self.name, self.ptime_us, SUPPORTED_PTIMES_US
+            ));
+        }
+        if self.dscp > 63 {
+            return Err(anyhow!("Stream '{}': invalid DSCP {} (0-63)", self.name, self.dscp));
+        }
+        if !self.outputs.is_empty() && self.outputs.len() != self.channels as usize {
+            return Err(anyhow!(
+                "Stream '{}': {} outputs given for {} channels",
+                self.name, self.outputs.len(), self.channels
+            ));
+        }
+        if let Some(output) = self.output_map().into_iter().find(|&o| o >= output_count) {
+            return Err(anyhow!(
+                "Stream '{}': output {} out of range ({} outputs)",
+                self.name, output + 1, output_count
+            ));
+        }
+        let payload = self.format(sample_rate).bytes_per_packet();
+        if payload > MAX_PAYLOAD_BYTES {
+            return Err(anyhow!(
+                "Stream '{}': {} byte packets exceed the MTU, use fewer channels or a shorter ptime",
+                self.name, payload
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An outgoing stream
+struct TxStream {
+    id: String,
+    config: TxStreamConfig,
+    output_map: Vec<usize>,
+    transmitter: RtpTransmitter,
+    /// Frames buffered before sending, the fill level drift compensation holds
+    prefill: usize,
+    /// Drift compensation (None = samples go straight into the ring)
+    drift: Mutex<Option<TxDrift>>,
+    /// Sender reports, receiver reports of remote devices (None = RTCP port unavailable)
+    rtcp: Option<RtcpSession>,
+}
+
+/// Outgoing stream state for API/status
+#[derive(Debug, Clone, Serialize)]
+pub struct TxStreamInfo {
+    pub id: String,
+    #[serde(flatten)]
+    pub config: TxStreamConfig,
+    pub sample_rate: u32,
+    pub packets_sent: u64,
+    pub underruns: u64,
+    /// Send errors per network path (two paths with ST 2022-7)
+    pub paths: Vec<SendPathStats>,
+    /// Loss, jitter and round-trip reported by remote receivers
+    pub rtcp: Option<RtcpStats>,
+}
+
+/// All outgoing streams
+pub struct Aes67TxStreams {
+    /// Active streams in creation order
+    streams: RwLock<Vec<TxStream>>,
+    ptp_clock: Arc<PtpClock>,
+    sap_discovery: Arc<SapDiscovery>,
+    /// Engine sample rate
+    sample_rate: u32,
+    /// Number of mixer outputs available for streams
+    output_count: usize,
+    /// Frames buffered before a transmitter starts sending
+    prefill_frames: usize,
+    /// Address of the network interface streams are sent from (None = routing table)
+    interface: Option<Ipv4Addr>,
+    /// Interface of the secondary network (ST 2022-7, None = single path)
+    secondary_interface: Option<String>,
+    /// Clock drift of the local device (None = no drift compensation)
+    drift_monitor: Mutex<Option<Arc<DriftMonitor>>>,
+}
+
+impl Aes67TxStreams {
+    pub fn new(
+        ptp_clock: Arc<PtpClock>,
+        sap_discovery: Arc<SapDiscovery>,
+        sample_rate: u32,
+        output_count: usize,
+        prefill_frames: usize,
+        interface: Option<Ipv4Addr>,
+        secondary_interface: Option<String>,
+    ) -> Self {
+        Self {
+            streams: RwLock::new(Vec::new()),
+            ptp_clock,
+            sap_discovery,
+            sample_rate,
+            output_count,
+            prefill_frames,
+            interface,
+            secondary_interface,
+            drift_monitor: Mutex::new(None),
+        }
+    }
+
+    /// Resample all streams against the local device clock
+    pub fn set_drift_monitor(&self, monitor: Arc<DriftMonitor>) {
+        for stream in self.streams.read().iter() {
+            *stream.drift.lock() = Some(TxDrift::new(stream.output_map.len(), monitor.clone(), stream.prefill));
+        }
+        *self.drift_monitor.lock() = Some(monitor);
+    }
+
+    /// Number of active streams
+    pub fn count(&self) -> usize {
+        self.streams.read().len()
+    }
+
+    /// State of all streams
+    pub fn infos(&self) -> Vec<TxStreamInfo> {
+        self.streams.read().iter().map(|s| {
+            let stats = s.transmitter.stats();
+            TxStreamInfo {
+                id: s.id.clone(),
+                config: TxStreamConfig {
+                    outputs: s.output_map.clone(),
+                    ..s.config.clone()
+                },
+                sample_rate: self.sample_rate,
+                packets_sent: stats.packets_sent,
+                underruns: stats.underruns,
+                paths: s.transmitter.path_stats(),
+                rtcp: s.rtcp.as_ref().map(|rtcp| rtcp.stats()),
+            }
+        }).collect()
+    }
+
+    /// State of one stream
+    pub fn info(&self, id: &str) -> Option<TxStreamInfo> {
+        self.infos().into_iter().find(|i| i.id == id)
+    }
+
+    /// Start sending a stream and announce it
+    pub fn add(&self, config: TxStreamConfig) -> Result<TxStreamInfo> {
+        config.validate(self.sample_rate, self.output_count)?;
+        {
+            let streams = self.streams.read();
+            if let Some(existing) = streams.iter().find(|s| s.config.destination == config.destination && s.config.port == config.port) {
+                return Err(anyhow!(
+                    "{}:{} is already used by stream '{}'",
+                    config.destination, config.port, existing.config.name
+                ));
+            }
+        }
+
+        let format = config.format(self.sample_rate);
+        let mut sender = RtpSender::with_options(
+            config.destination, config.port, format, config.ttl, config.dscp, self.interface,
+        )?;
+        sender.set_ptp_clock(self.ptp_clock.clone());
+        let secondary = match &self.secondary_interface {
+            Some(name) => {
+                let destination = config.secondary_destination.unwrap_or(config.destination);
+                sender.add_secondary(destination, interface::ipv4_address(name)?)?;
+                Some(SocketAddrV4::new(destination, config.port))
+            }
+            None => None,
+        };
+        let id = format!("audiomultiverse_{}", sender.ssrc());
+
+        // Collect at least two packets so long packet times do not underrun
+        let prefill = self.prefill_frames.max(format.samples_per_packet as usize * 2);
+        let transmitter = RtpTransmitter::start(sender, self.ptp_clock.clone(), prefill)?;
+
+        // Also the source of SSM source filters, so the interface's address
+        let origin = self.interface.map(|a| a.to_string())
+            .or_else(super::backend::get_local_ip)
+            .unwrap_or_else(|| "0.0.0.0".to_string());
+        let rtcp = RtcpSession::for_sender(
+            transmitter.sender(),
+            SocketAddrV4::new(config.destination, config.port),
+            self.interface,
+            format!("audiomultiverse@{}", origin),
+        ).map_err(|e| warn!("No RTCP for stream '{}': {}", config.name, e)).ok();
+
+        self.sap_discovery.announce(Aes67Stream {
+            name: config.name.clone(),
+            session_id: id.clone(),
+            origin,
+            multicast_addr: config.destination,
+            port: config.port,
+            channels: config.channels,
+            sample_rate: self.sample_rate,
+            encoding: config.encoding,
+            ptime_us: config.ptime_us,
+            direction: StreamDirection::Send,
+            payload_type: DEFAULT_PAYLOAD_TYPE,
+            media_clock_offset: 0,
+            ref_clock: None,
+            source_address: None,
+            secondary,
+            sdp: String::new(),
+        })?;
+
+        info!("📤 Sending AES67 stream '{}' to {}:{} ({} channels, {}, {} µs)",
+            config.name, config.destination, config.port, config.channels,
+            config.encoding.name(), config.ptime_us);
+        if let Some(secondary) = secondary {
+            info!("   ST 2022-7 secondary path: {}", secondary);
+        }
+
+        let drift = self.drift_monitor.lock().clone()
+            .map(|monitor| TxDrift::new(config.channels as usize, monitor, prefill));
+        self.streams.write().push(TxStream {
+            id: id.clone(),
+            output_map: config.output_map(),
+            config,
+            transmitter,
+            prefill,
+            drift: Mutex::new(drift),
+            rtcp,
+        });
+
+        self.info(&id).ok_or_else(|| anyhow!("Stream '{}' was removed", id))
+    }
+
+    /// Stop sending a stream and withdraw its announcement
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let stream = {
+            let mut streams = self.streams.write();
+            let index = streams.iter().position(|s| s.id == id)
+                .ok_or_else(|| anyhow!("Stream '{}' does not exist", id))?;
+            streams.remove(index)
+        };
+        self.shutdown(stream);
+        Ok(())
+    }
+
+    /// Remove all streams
+    pub fn clear(&self) {
+        let streams: Vec<TxStream> = self.streams.write().drain(..).collect();
+        for stream in streams {
+            self.shutdown(stream);
+        }
+    }
+
+    fn shutdown(&self, mut stream: TxStream) {
+        if let Some(rtcp) = stream.rtcp.as_mut() {
+            rtcp.stop();
+        }
+        stream.transmitter.stop();
+        if let Err(e) = self.sap_discovery.remove_announcement(&stream.id) {
+            warn!("Could not withdraw announcement of '{}': {}", stream.config.name, e);
+        }
+        info!("📤 Stopped AES67 stream '{}'", stream.config.name);
+    }
+
+    /// Hand one block of mixer outputs to all streams
+    ///
+    /// `outputs` is interleaved with `output_count` channels per frame.
+    /// Streams are written through `buffer` in pieces of its size, it is
+    /// never grown. Runs in the audio callback: while the table is being
+    /// changed the block is dropped, the transmitters conceal it.
+    pub fn write_outputs(&self, outputs: &[f32], output_count: usize, frames: usize, buffer: &mut [f32]) {
+        let Some(streams) = self.streams.try_read() else {
+            return;
+        };
+        for stream in streams.iter() {
+            let chunk = buffer.len() / stream.output_map.len().max(1);
+            if chunk == 0 {
+                continue;
+            }
+            let ring = stream.transmitter.ring();
+            // Only locked while the monitor is set, write uncorrected meanwhile
+            let mut drift = stream.drift.try_lock();
+            for start in (0..frames).step_by(chunk) {
+                let len = chunk.min(frames - start);
+                let samples = &mut buffer[..len * stream.output_map.len()];
+                map_outputs(&outputs[start * output_count..], output_count, len, &stream.output_map, samples);
+                match drift.as_mut().and_then(|drift| drift.as_mut()) {
+                    Some(drift) => drift.write(samples, &ring),
+                    None => {
+                        ring.push(samples);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Adaptive resampling of one stream from the device clock to the media clock
+struct TxDrift {
+    channels: usize,
+    monitor: Arc<DriftMonitor>,
+    /// Keeps the ring at the prefill level
+    controller: DriftController,
+    resampler: Resampler,
+    /// Resampled frames, sized once and pulled in pieces
+    output: Vec<f32>,
+}
+
+impl TxDrift {
+    fn new(channels: usize, monitor: Arc<DriftMonitor>, target_fill: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            monitor,
+            controller: DriftController::new(target_fill),
+            resampler: Resampler::new(channels, 1, 1),
+            output: vec![0.0; target_fill.max(1) * channels],
+        }
+    }
+
+    /// Resample one block of stream samples into the ring
+    fn write(&mut self, samples: &[f32], ring: &TxRing) {
+        self.resampler.push(samples);
+
+        // Here the device clock is the source and the media clock the sink:
+        // a faster device has to be read faster, so the measured drift enters
+        // the controller with the opposite sign of a receiver's
+        let fill = ring.available() / self.channels;
+        let correction = self.controller.update(-self.monitor.drift_ppm(), fill);
+        self.resampler.set_ratio_adjust_ppm(correction);
+
+        loop {
+            let produced = self.resampler.pull(&mut self.output);
+            if produced == 0 {
+                break;
+            }
+            ring.push(&self.output[..produced * self.channels]);
+        }
+    }
+}
+
+impl Drop for Aes67TxStreams {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Pick the mapped mixer outputs into an interleaved stream buffer
+/// (`buffer` holds `frames` frames of the stream)
+fn map_outputs(outputs: &[f32], output_count: usize, frames: usize, output_map: &[usize], buffer: &mut [f32]) {
+    let channels = output_map.len();
+
+    for frame in 0..frames {
+        for (ch, &output) in output_map.iter().enumerate() {
+            buffer[frame * channels + ch] = if output < output_count {
+                outputs[frame * output_count + output]
+            } else {
+                0.0
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_map() {
+        let ring = TxRing::new(64);
+        let mut buffer = [0.0; 6];
+
+        // 2 frames with 4 mixer outputs
+        let outputs = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+        map_outputs(&outputs, 4, 2, &[2, 0, 7], &mut buffer);
+        ring.push(&buffer);
+
+        let mut sent = [0.0; 6];
+        assert_eq!(ring.pop(&mut sent), 6);
+        assert_eq!(sent, [0.3, 0.1, 0.0, 0.7, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_drift_keeps_ring_fill_stable() {
+        // Device runs 100 ppm faster than the media clock, the transmitter
+        // drains one 48 frame packet per millisecond
+        let monitor = Arc::new(DriftMonitor::new());
+        monitor.set_drift_ppm(100.0);
+        let target = 256;
+        let ring = TxRing::new(target * 8);
+        let mut drift = TxDrift::new(1, monitor, target);
+        let block = vec![0.0; 64];
+        let mut packet = [0.0; 48];
+        ring.push(&vec![0.0; target]);
+
+        let device_rate = 48000.0 * 1.0001;
+        let mut sent = 0.0;
+        let mut underruns = 0;
+        // 20 seconds, uncorrected the ring would grow by 96 frames
+        let blocks = (20.0 * device_rate / block.len() as f64) as usize;
+        for i in 0..blocks {
+            drift.write(&block, &ring);
+
+            let t = ((i + 1) * block.len()) as f64 / device_rate;
+            while sent + 48.0 <= t * 48000.0 {
+                if ring.pop(&mut packet) < packet.len() {
+                    underruns += 1;
+                }
+                sent += 48.0;
+            }
+        }
+
+        assert_eq!(underruns, 0);
+        let fill = ring.available() as i64;
+        assert!((fill - target as i64).abs() < 48, "fill = {}", fill);
+    }
+
+    #[test]
+    fn test_stream_config_validation() {
+        let mut config = TxStreamConfig::new("Main", Ipv4Addr::new(239, 69, 1, 10), 8);
+        assert!(config.validate(48000, 32).is_ok());
+        assert_eq!(config.output_map(), (0..8).collect::<Vec<_>>());
+
+        // Unicast destination is fine
+        config.destination = Ipv4Addr::new(192, 168, 1, 20);
+        assert!(config.validate(48000, 32).is_ok());
+
+        config.outputs = vec![0, 1];
+        assert!(config.validate(48000, 32).is_err());
+        config.outputs = vec![24, 25, 26, 27, 28, 29, 30, 32];
+        assert!(config.validate(48000, 32).is_err());
+        config.outputs.clear();
+
+        config.ptime_us = 500;
+        assert!(config.validate(48000, 32).is_err());
+
+        // 8 channels L24 at 4 ms do not fit a packet, 2 channels L16 do
+        config.ptime_us = 4000;
+        assert!(config.validate(48000, 32).is_err());
+        config.channels = 2;
+        config.encoding = RtpEncoding::L16;
+        assert!(config.validate(48000, 32).is_ok());
+    }
+
+    #[test]
+    fn test_stream_config_toml_defaults() {
+        let config: TxStreamConfig = toml::from_str(r#"
+            name = "Monitor"
+            destination = "239.69.2.1"
+            encoding = "L16"
+        "#).unwrap();
+
+        assert_eq!(config.port, 5004);
+        assert_eq!(config.channels, 8);
+        assert_eq!(config.ptime_us, 1000);
+        assert_eq!(config.encoding, RtpEncoding::L16);
+        assert_eq!(config.ttl, 64);
+        assert_eq!(config.dscp, 46);
+    }
+}
@@ -0,0 +1,805 @@
+//! AES67 Stream Subscriptions
+//!
+//! Keeps track of all received streams. Each subscription owns an
+//! `RtpReceiver`, a patch table that maps every stream channel onto a mixer
+//! input (or leaves it unpatched) and an RTCP session that reports loss and
+//! jitter to the sender. The table is shared between the audio engine, which
+//! reads the samples, and the API, which edits patches.
+//!
+//! The health of every stream is watched (see `health`), stalls and
+//! recoveries are sent as events. Streams can be delayed on top of their link
+//! offset so that streams from different devices play in phase.
+//! Subscriptions can be saved to a file; saved ones stay pending after a
+//! restart until their stream is announced. Subscriptions of NMOS receivers
+//! are not saved.
+
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::Instant;
+use anyhow::{Result, anyhow};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use super::PtpClock;
+use super::health::{HealthEvent, HealthState, StreamHealth};
+use super::latency::{align_delays, AlignInput, LatencyStats};
+use super::rtcp::{RtcpSession, RtcpStats};
+use super::rtp::{JitterStats, PathStats, RtpEncoding, RtpReceiver};
+use super::sap::Aes67Stream;
+
+/// Channel patch: stream channel -> mixer input (None = not patched)
+pub type ChannelPatch = Vec<Option<usize>>;
+
+/// Longest alignment delay of a stream (µs)
+pub const MAX_DELAY_US: u32 = 100_000;
+
+/// Subscription as saved to disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSubscription {
+    /// Session id when it was subscribed
+    pub stream_id: String,
+    /// Stream name, also matches when the sender comes back with a new session id
+    pub stream_name: String,
+    pub patch: ChannelPatch,
+    /// Alignment delay in µs
+    #[serde(default)]
+    pub delay_us: u32,
+}
+
+impl SavedSubscription {
+    fn matches(&self, stream: &Aes67Stream) -> bool {
+        self.stream_id == stream.session_id || self.stream_name == stream.name
+    }
+}
+
+/// A received AES67 stream
+pub struct Aes67Subscription {
+    /// Stream description (from SAP/SDP)
+    pub stream: Aes67Stream,
+    /// Patch table (one entry per stream channel)
+    pub patch: ChannelPatch,
+    /// RTP receiver for this stream
+    receiver: RtpReceiver,
+    /// Receiver reports to the sender (None = no RTCP)
+    rtcp: Option<RtcpSession>,
+    /// Health at the last check, for stall/recovery events
+    state: Mutex<HealthState>,
+    /// Automatic resubscriptions so far
+    reconnects: u64,
+    /// Link offset of the receiver without alignment delay (frames)
+    link_offset: usize,
+    /// Alignment delay on top of the link offset (µs)
+    delay_us: AtomicU32,
+    /// Written to the store (false for subscriptions of NMOS receivers)
+    persistent: bool,
+}
+
+impl Aes67Subscription {
+    /// Create a subscription (the receiver should already be started)
+    pub fn new(stream: Aes67Stream, patch: ChannelPatch, receiver: RtpReceiver) -> Self {
+        let link_offset = receiver.link_offset();
+        Self {
+            stream,
+            patch,
+            receiver,
+            rtcp: None,
+            state: Mutex::new(HealthState::Waiting),
+            reconnects: 0,
+            link_offset,
+            delay_us: AtomicU32::new(0),
+            persistent: true,
+        }
+    }
+
+    /// Keep the subscription out of the store, it is not restored after a restart
+    pub fn set_persistent(&mut self, persistent: bool) {
+        self.persistent = persistent;
+    }
+
+    /// Report reception to the sender via RTCP
+    pub fn set_rtcp(&mut self, rtcp: RtcpSession) {
+        self.rtcp = Some(rtcp);
+    }
+
+    /// Stop receiving (RTCP sends BYE)
+    fn stop(mut self) {
+        if let Some(rtcp) = self.rtcp.as_mut() {
+            rtcp.stop();
+        }
+        self.receiver.stop();
+    }
+
+    fn health(&self, grandmaster: Option<&str>, now: Instant) -> StreamHealth {
+        StreamHealth::evaluate(
+            &mut self.receiver.activity().lock(),
+            &self.receiver.jitter_stats(),
+            self.stream.ref_clock.as_ref(),
+            grandmaster,
+            self.reconnects,
+            now,
+        )
+    }
+
+    fn saved(&self) -> SavedSubscription {
+        SavedSubscription {
+            stream_id: self.stream.session_id.clone(),
+            stream_name: self.stream.name.clone(),
+            patch: self.patch.clone(),
+            delay_us: self.delay_us(),
+        }
+    }
+
+    /// Play `delay_us` later than the link offset (playout restarts)
+    ///
+    /// Needs no write access to the table, the audio engine keeps reading.
+    fn set_delay(&self, delay_us: u32) {
+        let frames = self.stream.sample_rate as u64 * delay_us as u64 / 1_000_000;
+        self.receiver.set_link_offset(self.link_offset + frames as usize);
+        self.delay_us.store(delay_us, Ordering::Relaxed);
+    }
+
+    fn delay_us(&self) -> u32 {
+        self.delay_us.load(Ordering::Relaxed)
+    }
+
+    fn align_input(&self) -> AlignInput {
+        let rate = self.stream.sample_rate.max(1) as u64;
+        AlignInput {
+            link_offset_us: (self.link_offset as u64 * 1_000_000 / rate) as u32,
+            transit_max_us: self.receiver.latency_stats().transit_max_us,
+            ptime_us: self.stream.ptime_us,
+        }
+    }
+}
+
+/// Subscription state for API/status
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionInfo {
+    pub stream_id: String,
+    pub stream_name: String,
+    pub channels: u8,
+    pub sample_rate: u32,
+    pub encoding: RtpEncoding,
+    pub ptime_us: u32,
+    pub multicast_addr: Ipv4Addr,
+    pub port: u16,
+    pub patch: ChannelPatch,
+    pub receiving: bool,
+    /// Playout buffer statistics
+    pub jitter: JitterStats,
+    /// Reception per network path (two paths with ST 2022-7)
+    pub paths: Vec<PathStats>,
+    /// Loss and jitter of the stream, as reported to the sender
+    pub rtcp: Option<RtcpStats>,
+    /// Packet rate, stalls, SSRC changes, PTP lock
+    pub health: StreamHealth,
+    /// Transit and end-to-end latency
+    pub latency: LatencyStats,
+    /// Alignment delay on top of the link offset (µs)
+    pub delay_us: u32,
+}
+
+/// All active subscriptions
+pub struct Aes67Subscriptions {
+    /// Active subscriptions in subscription order
+    subscriptions: RwLock<Vec<Aes67Subscription>>,
+    /// Number of mixer inputs available for patching
+    input_count: usize,
+    /// Our PTP clock, its grandmaster should be the senders' reference clock
+    ptp_clock: Option<Arc<PtpClock>>,
+    /// Saved subscriptions waiting for their stream to be announced
+    pending: RwLock<Vec<SavedSubscription>>,
+    /// File the subscriptions are saved to (None = not saved)
+    store: Option<PathBuf>,
+    /// Stalls, recoveries and automatic resubscriptions
+    events: broadcast::Sender<HealthEvent>,
+    /// Frames not read because the table was locked
+    missed_frames: AtomicUsize,
+}
+
+impl Aes67Subscriptions {
+    /// Create an empty table for the given number of mixer inputs
+    pub fn new(input_count: usize) -> Self {
+        let (events, _) = broadcast::channel(64);
+        Self {
+            subscriptions: RwLock::new(Vec::new()),
+            input_count,
+            ptp_clock: None,
+            pending: RwLock::new(Vec::new()),
+            store: None,
+            events,
+            missed_frames: AtomicUsize::new(0),
+        }
+    }
+
+    /// Create a table that checks the senders against our PTP grandmaster
+    pub fn with_ptp_clock(input_count: usize, clock: Arc<PtpClock>) -> Self {
+        Self {
+            ptp_clock: Some(clock),
+            ..Self::new(input_count)
+        }
+    }
+
+    /// Save subscriptions to `path` and restore the ones saved there
+    ///
+    /// Restored subscriptions are pending until their stream is announced.
+    pub fn set_store(&mut self, path: PathBuf) {
+        match load_saved(&path) {
+            Ok(saved) => {
+                if !saved.is_empty() {
+                    info!("📂 {} saved AES67 subscription(s) waiting for their streams", saved.len());
+                }
+                *self.pending.write() = saved;
+            }
+            Err(e) => warn!("Could not read saved AES67 subscriptions from {}: {}", path.display(), e),
+        }
+        self.store = Some(path);
+    }
+
+    /// Receive stalls, recoveries and automatic resubscriptions
+    pub fn subscribe_events(&self) -> broadcast::Receiver<HealthEvent> {
+        self.events.subscribe()
+    }
+
+    /// Check if a stream is subscribed
+    pub fn contains(&self, stream_id: &str) -> bool {
+        self.subscriptions.read().iter().any(|s| s.stream.session_id == stream_id)
+    }
+
+    /// Number of active subscriptions
+    pub fn len(&self) -> usize {
+        self.subscriptions.read().len()
+    }
+
+    /// No active subscriptions
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.read().is_empty()
+    }
+
+    /// IDs of all subscribed streams
+    pub fn stream_ids(&self) -> Vec<String> {
+        self.subscriptions.read().iter().map(|s| s.stream.session_id.clone()).collect()
+    }
+
+    /// State of all subscriptions
+    pub fn infos(&self) -> Vec<SubscriptionInfo> {
+        let grandmaster = self.grandmaster();
+        let now = Instant::now();
+        self.subscriptions.read().iter().map(|s| SubscriptionInfo {
+            stream_id: s.stream.session_id.clone(),
+            stream_name: s.stream.name.clone(),
+            channels: s.stream.channels,
+            sample_rate: s.stream.sample_rate,
+            encoding: s.stream.encoding,
+            ptime_us: s.stream.ptime_us,
+            multicast_addr: s.stream.multicast_addr,
+            port: s.stream.port,
+            patch: s.patch.clone(),
+            receiving: s.receiver.is_receiving(),
+            jitter: s.receiver.jitter_stats(),
+            paths: s.receiver.path_stats(),
+            rtcp: s.rtcp.as_ref().map(|rtcp| rtcp.stats()),
+            health: s.health(grandmaster.as_deref(), now),
+            latency: s.receiver.latency_stats(),
+            delay_us: s.delay_us(),
+        }).collect()
+    }
+
+    /// Add a subscription (its receiver is stopped if it is rejected)
+    pub fn add(&self, subscription: Aes67Subscription) -> Result<()> {
+        let stream = subscription.stream.clone();
+        self.insert(subscription)?;
+        // Subscribed by hand before its saved subscription was restored
+        self.pending.write().retain(|p| !p.matches(&stream));
+        self.save();
+        Ok(())
+    }
+
+    fn insert(&self, subscription: Aes67Subscription) -> Result<()> {
+        let mut subscriptions = self.subscriptions.write();
+        let result = if subscriptions.iter().any(|s| s.stream.session_id == subscription.stream.session_id) {
+            Err(anyhow!("Stream '{}' is already subscribed", subscription.stream.session_id))
+        } else {
+            self.validate_patch(&subscription.patch, subscription.stream.channels)
+        };
+
+        match result {
+            Ok(()) => {
+                subscriptions.push(subscription);
+                Ok(())
+            }
+            Err(e) => {
+                drop(subscriptions);
+                subscription.stop();
+                Err(e)
+            }
+        }
+    }
+
+    /// Start a saved subscription whose stream was announced as `subscription`
+    pub fn restore(&self, saved: &SavedSubscription, subscription: Aes67Subscription) -> Result<()> {
+        let stream_id = subscription.stream.session_id.clone();
+        let name = subscription.stream.name.clone();
+        if saved.delay_us > 0 {
+            subscription.set_delay(saved.delay_us.min(MAX_DELAY_US));
+        }
+        self.insert(subscription)?;
+        self.pending.write().retain(|p| p != saved);
+        self.save();
+        let _ = self.events.send(HealthEvent::Resubscribed { previous_id: saved.stream_id.clone(), stream_id, name });
+        Ok(())
+    }
+
+    /// Swap the subscription of `previous_id` for a new one of the same stream
+    ///
+    /// Used when the stream reappears or its description changed; the new
+    /// subscription takes the old one's place. Its receiver is stopped if
+    /// it is rejected.
+    pub fn replace(&self, previous_id: &str, mut subscription: Aes67Subscription) -> Result<()> {
+        let event = HealthEvent::Resubscribed {
+            previous_id: previous_id.to_string(),
+            stream_id: subscription.stream.session_id.clone(),
+            name: subscription.stream.name.clone(),
+        };
+        // Checked under the read lock, the new receiver is set up outside
+        // of it and only the swap takes the write lock
+        let previous = {
+            let subscriptions = self.subscriptions.read();
+            let index = subscriptions.iter().position(|s| s.stream.session_id == previous_id);
+            let duplicate = subscription.stream.session_id != previous_id
+                && subscriptions.iter().any(|s| s.stream.session_id == subscription.stream.session_id);
+            match index {
+                None => Err(anyhow!("Stream '{}' is not subscribed", previous_id)),
+                Some(_) if duplicate => Err(anyhow!("Stream '{}' is already subscribed", subscription.stream.session_id)),
+                Some(index) => self.validate_patch(&subscription.patch, subscription.stream.channels).map(|()| {
+                    let previous = &subscriptions[index];
+                    (previous.reconnects, previous.persistent, previous.delay_us())
+                }),
+            }
+        };
+        let (reconnects, persistent, delay_us) = match previous {
+            Ok(previous) => previous,
+            Err(e) => {
+                subscription.stop();
+                return Err(e);
+            }
+        };
+        subscription.reconnects = reconnects + 1;
+        subscription.persistent = persistent;
+        if delay_us > 0 {
+            subscription.set_delay(delay_us);
+        }
+
+        let mut subscriptions = self.subscriptions.write();
+        let Some(index) = subscriptions.iter().position(|s| s.stream.session_id == previous_id) else {
+            // Removed in the meantime
+            drop(subscriptions);
+            subscription.stop();
+            return Err(anyhow!("Stream '{}' is not subscribed", previous_id));
+        };
+        let previous = std::mem::replace(&mut subscriptions[index], subscription);
+        // Stopped outside the lock, the audio engine reads the table
+        drop(subscriptions);
+        previous.stop();
+        self.save();
+        let _ = self.events.send(event);
+        Ok(())
+    }
+
+    /// Remove a subscription and stop its receiver
+    pub fn remove(&self, stream_id: &str) -> Option<Aes67Stream> {
+        // Stopped outside the lock, the audio engine reads the table
+        let subscription = {
+            let mut subscriptions = self.subscriptions.write();
+            let index = subscriptions.iter().position(|s| s.stream.session_id == stream_id)?;
+            subscriptions.remove(index)
+        };
+        let stream = subscription.stream.clone();
+        subscription.stop();
+        self.save();
+        Some(stream)
+    }
+
+    /// Saved subscriptions whose stream has not been announced yet
+    pub fn pending(&self) -> Vec<SavedSubscription> {
+        self.pending.read().clone()
+    }
+
+    /// Saved subscription waiting for `stream`
+    pub fn pending_for(&self, stream: &Aes67Stream) -> Option<SavedSubscription> {
+        self.pending.read().iter().find(|p| p.matches(stream)).cloned()
+    }
+
+    /// Forget a pending subscription
+    pub fn remove_pending(&self, stream_id: &str) -> bool {
+        let removed = {
+            let mut pending = self.pending.write();
+            let before = pending.len();
+            pending.retain(|p| p.stream_id != stream_id);
+            pending.len() != before
+        };
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    /// Subscribed stream matching an announcement: its description, patch
+    /// and health
+    ///
+    /// Matches by session id, or by name if the subscription is stalled or
+    /// its session is no longer announced (the sender restarted with a new
+    /// session id).
+    pub fn subscribed_stream(
+        &self,
+        stream: &Aes67Stream,
+        is_announced: impl Fn(&str) -> bool,
+    ) -> Option<(Aes67Stream, ChannelPatch, HealthState)> {
+        let subscriptions = self.subscriptions.read();
+        let state = |s: &Aes67Subscription| *s.state.lock();
+        let replaced = |s: &&Aes67Subscription| {
+            s.stream.name == stream.name
+                && (state(s) == HealthState::Stalled || !is_announced(&s.stream.session_id))
+        };
+        subscriptions.iter()
+            .find(|s| s.stream.session_id == stream.session_id)
+            .or_else(|| subscriptions.iter().find(replaced))
+            .map(|s| (s.stream.clone(), s.patch.clone(), state(s)))
+    }
+
+    /// Check the health of all streams and send events for stalls and recoveries
+    pub fn update_health(&self) {
+        let grandmaster = self.grandmaster();
+        let now = Instant::now();
+        let mut events = Vec::new();
+
+        for subscription in self.subscriptions.read().iter() {
+            let health = subscription.health(grandmaster.as_deref(), now);
+            let mut state = subscription.state.lock();
+            let stream_id = subscription.stream.session_id.clone();
+            let name = subscription.stream.name.clone();
+            match (*state, health.state) {
+                (HealthState::Waiting | HealthState::Ok, HealthState::Stalled) => {
+                    warn!("⚠️ AES67 stream '{}' stalled (no packets)", name);
+                    events.push(HealthEvent::Stalled { stream_id, name });
+                }
+                (HealthState::Stalled, HealthState::Ok) => {
+                    info!("✅ AES67 stream '{}' recovered", name);
+                    events.push(HealthEvent::Recovered { stream_id, name });
+                }
+                _ => {}
+            }
+            *state = health.state;
+        }
+
+        for event in events {
+            // Nobody listening without API clients
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// Current PTP grandmaster (None = unknown)
+    fn grandmaster(&self) -> Option<String> {
+        self.ptp_clock.as_ref()
+            .map(|clock| clock.stats().grandmaster_identity)
+            .filter(|gm| !gm.is_empty())
+    }
+
+    /// Write persistent active and pending subscriptions to the store
+    fn save(&self) {
+        let Some(path) = &self.store else {
+            return;
+        };
+        let saved: Vec<SavedSubscription> = self.subscriptions.read().iter()
+            .filter(|s| s.persistent)
+            .map(|s| s.saved())
+            .chain(self.pending.read().iter().cloned())
+            .collect();
+        if let Err(e) = write_saved(path, &saved) {
+            warn!("Could not save AES67 subscriptions to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Remove all subscriptions (they stay saved)
+    pub fn clear(&self) {
+        let subscriptions: Vec<Aes67Subscription> = self.subscriptions.write().drain(..).collect();
+        for subscription in subscriptions {
+            subscription.stop();
+        }
+    }
+
+    /// Replace the patch table of a subscription
+    pub fn set_patch(&self, stream_id: &str, mut patch: ChannelPatch) -> Result<SubscriptionInfo> {
+        {
+            let channels = self.subscriptions.read()
+                .iter()
+                .find(|s| s.stream.session_id == stream_id)
+                .map(|s| s.stream.channels)
+                .ok_or_else(|| anyhow!("Stream '{}' is not subscribed", stream_id))?;
+            self.validate_patch(&patch, channels)?;
+            // Only the swap takes the write lock, the old table is dropped after it
+            if let Some(subscription) = self.subscriptions.write().iter_mut().find(|s| s.stream.session_id == stream_id) {
+                std::mem::swap(&mut subscription.patch, &mut patch);
+            }
+        }
+        self.save();
+        self.infos()
+            .into_iter()
+            .find(|i| i.stream_id == stream_id)
+            .ok_or_else(|| anyhow!("Stream '{}' is not subscribed", stream_id))
+    }
+
+    /// Delay a stream on top of its link offset, e.g. to align it with
+    /// streams of other devices (playout restarts)
+    pub fn set_delay(&self, stream_id: &str, delay_us: u32) -> Result<SubscriptionInfo> {
+        if delay_us > MAX_DELAY_US {
+            return Err(anyhow!("Delay {} µs is longer than {} µs", delay_us, MAX_DELAY_US));
+        }
+        {
+            let subscriptions = self.subscriptions.read();
+            let subscription = subscriptions
+                .iter()
+                .find(|s| s.stream.session_id == stream_id)
+                .ok_or_else(|| anyhow!("Stream '{}' is not subscribed", stream_id))?;
+            if subscription.delay_us() != delay_us {
+                subscription.set_delay(delay_us);
+            }
+        }
+        self.save();
+        self.infos()
+            .into_iter()
+            .find(|i| i.stream_id == stream_id)
+            .ok_or_else(|| anyhow!("Stream '{}' is not subscribed", stream_id))
+    }
+
+    /// Delay all streams so that they play at the same latency
+    ///
+    /// Uses the transit measured over the last second; streams that are not
+    /// on our PTP time keep their delay.
+    pub fn align(&self) -> Vec<SubscriptionInfo> {
+        {
+            let subscriptions = self.subscriptions.read();
+            let inputs: Vec<AlignInput> = subscriptions.iter().map(|s| s.align_input()).collect();
+            for (subscription, delay_us) in subscriptions.iter().zip(align_delays(&inputs)) {
+                match delay_us.map(|d| d.min(MAX_DELAY_US)) {
+                    Some(delay_us) if delay_us != subscription.delay_us() => {
+                        info!("⏱️ AES67 stream '{}' delayed by {} µs for alignment", subscription.stream.name, delay_us);
+                        subscription.set_delay(delay_us);
+                    }
+                    Some(_) => {}
+                    None => warn!("AES67 stream '{}' is not on our PTP time, not aligned", subscription.stream.name),
+                }
+            }
+        }
+        self.save();
+        self.infos()
+    }
+
+    /// Largest end-to-end latency of the received streams (µs, None = none measured)
+    pub fn max_latency_us(&self) -> Option<i64> {
+        self.subscriptions.read().iter()
+            .filter_map(|s| s.receiver.latency_stats().playout_us)
+            .max()
+    }
+
+    /// Default patch: consecutive inputs starting at `start_channel`
+    pub fn consecutive_patch(&self, start_channel: usize, channels: u8) -> Result<ChannelPatch> {
+        let end = start_channel + channels as usize;
+        if end > self.input_count {
+            return Err(anyhow!(
+                "Stream with {} channels does not fit at input {} ({} inputs)",
+                channels, start_channel + 1, self.input_count
+            ));
+        }
+        Ok((start_channel..end).map(Some).collect())
+    }
+
+    /// First input after all inputs patched so far
+    pub fn next_free_input(&self) -> usize {
+        self.subscriptions
+            .read()
+            .iter()
+            .flat_map(|s| s.patch.iter().flatten())
+            .map(|&input| input + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn validate_patch(&self, patch: &ChannelPatch, channels: u8) -> Result<()> {
+        if patch.len() > channels as usize {
+            return Err(anyhow!("Patch has {} entries, stream has {} channels", patch.len(), channels));
+        }
+        if let Some(input) = patch.iter().flatten().find(|&&input| input >= self.input_count) {
+            return Err(anyhow!("Input {} out of range ({} inputs)", input + 1, self.input_count));
+        }
+        Ok(())
+    }
+
+    /// Read one block from all streams into the mixer inputs
+    ///
+    /// `inputs` is interleaved with `input_count` channels per frame. Samples
+    /// are added, so streams patched onto the same input are summed.
+    /// Streams are read through `scratch` in pieces of its size, it is never
+    /// grown. Runs in the audio callback: if the table is being changed the
+    /// block is skipped, and the streams skip it too on the next read.
+    pub fn read_into(&self, inputs: &mut [f32], input_count: usize, scratch: &mut [f32]) {
+        if input_count == 0 {
+            return;
+        }
+        let frames = inputs.len() / input_count;
+        let Some(subscriptions) = self.subscriptions.try_read() else {
+            self.missed_frames.fetch_add(frames, Ordering::Relaxed);
+            return;
+        };
+        let missed = self.missed_frames.swap(0, Ordering::Relaxed);
+
+        for subscription in subscriptions.iter() {
+            if missed > 0 {
+                subscription.receiver.skip(missed);
+            }
+            let channels = subscription.stream.channels.max(1) as usize;
+            let chunk = scratch.len() / channels;
+            if chunk == 0 {
+                continue;
+            }
+            for start in (0..frames).step_by(chunk) {
+                let len = chunk.min(frames - start);
+                let stream = &mut scratch[..len * channels];
+                subscription.receiver.read(stream);
+                let inputs = &mut inputs[start * input_count..(start + len) * input_count];
+                mix_patched(stream, channels, &subscription.patch, inputs, input_count);
+            }
+        }
+    }
+}
+
+fn load_saved(path: &Path) -> Result<Vec<SavedSubscription>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn write_saved(path: &Path, saved: &[SavedSubscription]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(saved)?)?;
+    Ok(())
+}
+
+/// Add stream samples onto the patched mixer inputs
+fn mix_patched(stream: &[f32], channels: usize, patch: &ChannelPatch, inputs: &mut [f32], input_count: usize) {
+    let frames = (stream.len() / channels).min(inputs.len() / input_count);
+
+    for (channel, target) in patch.iter().enumerate().take(channels) {
+        let Some(input) = *target else {
+            continue;
+        };
+        if input >= input_count {
+            continue;
+        }
+        for frame in 0..frames {
+            inputs[frame * input_count + input] += stream[frame * channels + channel];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network_audio::rtp::Aes67Format;
+    use crate::network_audio::sap::StreamDirection;
+
+    #[test]
+    fn test_mix_patched() {
+        // 2 frames, 3 stream channels -> 4 inputs
+        let stream = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        let patch = vec![Some(3), None, Some(0)];
+        let mut inputs = vec![0.0; 8];
+
+        mix_patched(&stream, 3, &patch, &mut inputs, 4);
+
+        assert_eq!(inputs, vec![0.3, 0.0, 0.0, 0.1, 0.6, 0.0, 0.0, 0.4]);
+
+        // Second stream on the same input is summed
+        mix_patched(&stream, 3, &vec![Some(0)], &mut inputs, 4);
+        assert!((inputs[0] - 0.4).abs() < 1e-6);
+        assert!((inputs[4] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_consecutive_patch() {
+        let table = Aes67Subscriptions::new(32);
+
+        assert_eq!(table.consecutive_patch(8, 4).unwrap(), vec![Some(8), Some(9), Some(10), Some(11)]);
+        assert!(table.consecutive_patch(28, 8).is_err());
+        assert_eq!(table.next_free_input(), 0);
+    }
+
+    fn subscription(session_id: &str, persistent: bool) -> Aes67Subscription {
+        let port = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let format = Aes67Format::new(48000, 2);
+        let receiver = RtpReceiver::with_options(Ipv4Addr::LOCALHOST, port, format, None, None).unwrap();
+        let stream = Aes67Stream {
+            name: format!("Stream {}", session_id),
+            session_id: session_id.to_string(),
+            origin: "127.0.0.1".to_string(),
+            multicast_addr: Ipv4Addr::LOCALHOST,
+            port,
+            channels: 2,
+            sample_rate: 48000,
+            encoding: RtpEncoding::L24,
+            ptime_us: 1000,
+            direction: StreamDirection::Send,
+            payload_type: 97,
+            media_clock_offset: 0,
+            ref_clock: None,
+            source_address: None,
+            secondary: None,
+            sdp: String::new(),
+        };
+        let mut subscription = Aes67Subscription::new(stream, vec![Some(0), Some(1)], receiver);
+        subscription.set_persistent(persistent);
+        subscription
+    }
+
+    #[test]
+    fn test_store_skips_unsaved() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("subscriptions.json");
+        let mut table = Aes67Subscriptions::new(16);
+        table.set_store(path.clone());
+
+        table.add(subscription("manual", true)).unwrap();
+        table.add(subscription("nmos", false)).unwrap();
+
+        let saved = load_saved(&path).unwrap();
+        assert_eq!(saved.iter().map(|s| s.stream_id.as_str()).collect::<Vec<_>>(), vec!["manual"]);
+        assert_eq!(table.len(), 2);
+
+        // A reappearing stream keeps its subscription out of the store
+        table.replace("nmos", subscription("nmos-2", false)).unwrap();
+        assert_eq!(load_saved(&path).unwrap().len(), 1);
+        table.clear();
+    }
+
+    #[test]
+    fn test_read_into_skips_locked_table() {
+        let table = Aes67Subscriptions::new(4);
+        table.add(subscription("a", true)).unwrap();
+        let mut inputs = [0.5; 8];
+        let mut scratch = [0.0; 4];
+
+        // The API changes the table: the block is skipped, not waited for
+        {
+            let _changing = table.subscriptions.write();
+            table.read_into(&mut inputs, 4, &mut scratch);
+        }
+        assert_eq!(inputs, [0.5; 8]);
+        assert_eq!(table.missed_frames.load(Ordering::Relaxed), 2);
+
+        // A delay change needs no write lock while the engine reads
+        {
+            let _reading = table.subscriptions.read();
+            assert_eq!(table.set_delay("a", 1000).unwrap().delay_us, 1000);
+        }
+        table.read_into(&mut inputs, 4, &mut scratch);
+        assert_eq!(table.missed_frames.load(Ordering::Relaxed), 0);
+        table.clear();
+    }
+
+    #[test]
+    fn test_validate_patch() {
+        let table = Aes67Subscriptions::new(16);
+
+        assert!(table.validate_patch(&vec![Some(0), None, Some(15)], 8).is_ok());
+        assert!(table.validate_patch(&vec![Some(16)], 8).is_err());
+        assert!(table.validate_patch(&vec![None; 9], 8).is_err());
+    }
+}
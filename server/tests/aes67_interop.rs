@@ -0,0 +1,240 @@
+//! AES67 interoperability with a simulated device
+//!
+//! Our backend against `network_audio::simulator` on loopback: PTP, SAP and
+//! RTP of both ends, no hardware needed. PTP runs on unprivileged ports, so
+//! the tests need no root and fail instead of being skipped.
+
+#![cfg(target_os = "linux")]
+
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+
+use audiomultiverse_server::network_audio::{AudioNetworkBackend, Aes67Backend, Aes67Config, HealthEvent, TxStreamConfig};
+use audiomultiverse_server::network_audio::health::HealthState;
+use audiomultiverse_server::network_audio::rtp::RtpEncoding;
+use audiomultiverse_server::network_audio::simulator::{
+    signal_phase, test_signal, wait_for, SimulatedDevice, SimulatorConfig, StreamCapture,
+    RAMP_PERIOD, SIGNAL_TOLERANCE, TEST_PTP_PORTS,
+};
+
+/// One simulated device at a time, the timing checks need the CPU
+static DEVICE: Mutex<()> = Mutex::new(());
+
+/// Our backend against a simulated device on loopback: PTP, discovery
+/// both ways, sample-accurate reception and our outgoing stream
+#[test]
+fn test_loopback_interoperability() {
+    let _device = DEVICE.lock();
+
+    let mut device = SimulatedDevice::start(SimulatorConfig {
+        ptp_domain: 71,
+        ..Default::default()
+    }).unwrap();
+
+    // Our stream: mixer outputs 3 and 6 as a 2-channel stream
+    let mut tx = TxStreamConfig::new("Loopback test", Ipv4Addr::new(239, 69, 250, 2), 2);
+    tx.port = 15006;
+    tx.outputs = vec![2, 5];
+    let mut backend = Aes67Backend::with_config(Aes67Config {
+        interface: Some("127.0.0.1".to_string()),
+        ptp_domain: 71,
+        ptp_ports: TEST_PTP_PORTS,
+        ptp_master: false,
+        input_channels: 8,
+        mixer_outputs: 8,
+        streams: vec![tx],
+        buffer_size: 48,
+        // Generous, CI machines do not schedule every thread each millisecond
+        link_offset_us: 20_000,
+        ..Default::default()
+    }).unwrap();
+    backend.init().unwrap();
+
+    // PTP: we follow the simulated grandmaster
+    let ptp = backend.ptp_clock();
+    let grandmaster = device.ptp_clock();
+    assert!(
+        wait_for(Duration::from_secs(20), || {
+            let stats = ptp.stats();
+            ptp.is_synchronized() && stats.grandmaster_priority1 == 100
+                && stats.grandmaster_identity == grandmaster.stats().grandmaster_identity
+        }),
+        "not synchronized to the simulated grandmaster: {:?}", ptp.stats()
+    );
+
+    // Discovery both ways
+    let name = device.stream().name.clone();
+    let sap = backend.sap_discovery();
+    assert!(wait_for(Duration::from_secs(5), || sap.streams().iter().any(|s| s.name == name)));
+    let stream = sap.streams().into_iter().find(|s| s.name == name).unwrap();
+    assert_eq!((stream.channels, stream.encoding, stream.source_address), (2, RtpEncoding::L24, Some(Ipv4Addr::LOCALHOST)));
+    assert!(stream.ref_clock.is_some());
+    assert!(wait_for(Duration::from_secs(5), || device.discovered().iter().any(|s| s.name == "Loopback test")));
+
+    // Our stream: a ramp from the mixer outputs arrives sample-accurately
+    let ours = device.discovered().into_iter().find(|s| s.name == "Loopback test").unwrap();
+    let mut capture = StreamCapture::start(ours.multicast_addr, ours.port, ours.encoding, Ipv4Addr::LOCALHOST).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    let mut outputs = vec![0.0f32; 480 * 8];
+    for (i, frame) in outputs.chunks_mut(8).enumerate() {
+        frame[2] = test_signal(0, 1000 + i as u32);
+        frame[5] = test_signal(1, 1000 + i as u32);
+    }
+    backend.tx_streams().write_outputs(&outputs, 8, 480, &mut [0.0; 4096]);
+    std::thread::sleep(Duration::from_millis(100));
+    capture.stop();
+
+    let packets = capture.packets();
+    for pair in packets.windows(2) {
+        assert_eq!(pair[1].header.sequence, pair[0].header.sequence.wrapping_add(1));
+        assert_eq!(pair[1].header.timestamp, pair[0].header.timestamp.wrapping_add(48));
+        assert_eq!(pair[1].header.ssrc, pair[0].header.ssrc);
+    }
+    let sent: Vec<f32> = packets.iter().flat_map(|p| p.samples.iter().copied()).collect();
+    let offset = sent.chunks(2).position(|frame| frame[0] != 0.0).expect("ramp not sent");
+    let ramp = &sent[offset * 2..];
+    assert!(ramp.len() >= 480 * 2);
+    for (i, frame) in ramp.chunks(2).take(480).enumerate() {
+        assert!((frame[0] - test_signal(0, 1000 + i as u32)).abs() <= SIGNAL_TOLERANCE, "frame {}: {:?}", i, frame);
+        assert!((frame[1] - test_signal(1, 1000 + i as u32)).abs() <= SIGNAL_TOLERANCE, "frame {}: {:?}", i, frame);
+    }
+
+    // The real-time transmitter would compete with the receive check on single-core CI machines
+    backend.tx_streams().clear();
+
+    // Reception: channels 1-2 of the device on mixer inputs 5 and 2,
+    // read exactly as much as the media clock advanced
+    backend.subscribe(&stream, vec![Some(4), Some(1)]).unwrap();
+    let subscriptions = backend.subscriptions();
+    let link_offset = 20_000 * 48 / 1000;
+    let mut inputs = Vec::new();
+    let mut scratch = vec![0.0; 4096];
+    let mut played: Vec<(u32, Vec<f32>)> = Vec::new();
+    let mut last = ptp.media_timestamp(48000);
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(400) {
+        std::thread::sleep(Duration::from_millis(1));
+        let now = ptp.media_timestamp(48000);
+        inputs.clear();
+        inputs.resize(now.wrapping_sub(last) as usize * 8, 0.0);
+        last = now;
+        subscriptions.read_into(&mut inputs, 8, &mut scratch);
+        played.push((now, inputs.clone()));
+    }
+
+    // Skip reads before playout started, then every frame must follow
+    // the previous one and play at the link offset behind PTP time
+    let first = played.iter().position(|(_, block)| block.iter().any(|&s| s != 0.0)).expect("nothing played");
+    // The block was read between the previous read and `now`, its first
+    // frame plays at that time minus the link offset (within two packets)
+    let (started, _) = &played[first];
+    let (now, block) = &played[first + 1];
+    let earliest = started.wrapping_sub(link_offset) % RAMP_PERIOD;
+    let phase = signal_phase(0, block[4]);
+    let span = now.wrapping_sub(*started);
+    let late = (phase + RAMP_PERIOD - earliest) % RAMP_PERIOD;
+    assert!(
+        late <= span + 96 || RAMP_PERIOD - late <= 96,
+        "played {} instead of {}-{}", phase, earliest, (earliest + span) % RAMP_PERIOD
+    );
+
+    let frames: Vec<&[f32]> = played[first + 1..].iter().flat_map(|(_, block)| block.chunks(8)).collect();
+    assert!(frames.len() > 48 * 300);
+    for (i, frame) in frames.iter().enumerate() {
+        let phase = (signal_phase(0, frames[0][4]) + i as u32) % RAMP_PERIOD;
+        assert!(
+            (frame[4] - test_signal(0, phase)).abs() <= SIGNAL_TOLERANCE,
+            "frame {}: phase {} instead of {} ({:?})", i, signal_phase(0, frame[4]), phase, subscriptions.infos()[0].jitter
+        );
+        assert!((frame[1] - test_signal(1, phase)).abs() <= SIGNAL_TOLERANCE, "frame {}: {:?}", i, frame);
+        assert_eq!(frame[0], 0.0);
+    }
+    let info = &subscriptions.infos()[0];
+    assert_eq!((info.jitter.lost, info.jitter.resyncs), (0, 0), "{:?}", info.jitter);
+
+    // Latency: packets arrive within the link offset, playout at the link offset
+    let latency = info.latency;
+    assert!(latency.transit_min_us.is_some_and(|us| us >= 0), "{:?}", latency);
+    assert!(latency.transit_max_us.is_some_and(|us| us < 20_000), "{:?}", latency);
+    assert!(latency.playout_us.is_some_and(|us| (us - 20_000).abs() <= 2_000), "{:?}", latency);
+    assert!(backend.latency().abs_diff(link_offset as usize) <= 96, "{} frames", backend.latency());
+
+    backend.unsubscribe(&stream.session_id).unwrap();
+    device.stop();
+    drop(backend);
+}
+
+/// A device that restarts is resubscribed, and the subscription is
+/// restored from its file by the next backend
+#[test]
+fn test_stream_reconnect() {
+    let _device = DEVICE.lock();
+
+    let file = std::env::temp_dir().join(format!("audiomultiverse-subscriptions-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&file);
+    let device_config = SimulatorConfig {
+        name: "Restarting device".to_string(),
+        group: Ipv4Addr::new(239, 69, 250, 3),
+        port: 15008,
+        ptp_domain: 72,
+        ..Default::default()
+    };
+    let backend_config = Aes67Config {
+        interface: Some("127.0.0.1".to_string()),
+        ptp_domain: 72,
+        input_channels: 8,
+        subscriptions_file: Some(file.clone()),
+        ..Default::default()
+    };
+    let healthy = |backend: &mut Aes67Backend| {
+        backend.supervise();
+        backend.subscriptions().infos().first().is_some_and(|info| info.health.state == HealthState::Ok)
+    };
+
+    // Only discovery and reception, no PTP or outgoing streams of our own
+    let mut device = SimulatedDevice::start(device_config.clone()).unwrap();
+    let mut backend = Aes67Backend::with_config(backend_config.clone()).unwrap();
+    backend.sap_discovery().start().unwrap();
+    let mut events = backend.subscriptions().subscribe_events();
+
+    let sap = backend.sap_discovery();
+    assert!(wait_for(Duration::from_secs(5), || sap.streams().iter().any(|s| s.name == device_config.name)));
+    let stream = sap.streams().into_iter().find(|s| s.name == device_config.name).unwrap();
+    backend.subscribe(&stream, vec![Some(6), Some(7)]).unwrap();
+    assert!(wait_for(Duration::from_secs(5), || healthy(&mut backend)));
+
+    // The device goes away: alarm
+    device.stop();
+    assert!(wait_for(Duration::from_secs(5), || {
+        backend.supervise();
+        matches!(events.try_recv(), Ok(HealthEvent::Stalled { ref stream_id, .. }) if *stream_id == stream.session_id)
+    }));
+
+    // It comes back with a new session id and is subscribed again on the same inputs
+    let device = SimulatedDevice::start(device_config.clone()).unwrap();
+    assert!(wait_for(Duration::from_secs(5), || {
+        backend.supervise();
+        matches!(events.try_recv(), Ok(HealthEvent::Resubscribed { ref previous_id, .. }) if *previous_id == stream.session_id)
+    }));
+    assert!(wait_for(Duration::from_secs(5), || healthy(&mut backend)));
+    let info = &backend.subscriptions().infos()[0];
+    assert_ne!(info.stream_id, stream.session_id);
+    assert_eq!((&info.patch, info.health.reconnects), (&vec![Some(6), Some(7)], 1));
+    let current = info.stream_id.clone();
+    drop(backend);
+
+    // After a restart the saved subscription waits for its stream
+    let mut backend = Aes67Backend::with_config(backend_config).unwrap();
+    let subscriptions = backend.subscriptions();
+    assert!(subscriptions.is_empty());
+    assert_eq!(subscriptions.pending()[0].stream_id, current);
+    backend.sap_discovery().start().unwrap();
+    assert!(wait_for(Duration::from_secs(5), || healthy(&mut backend)));
+    assert_eq!(subscriptions.stream_ids(), vec![current]);
+    assert!(subscriptions.pending().is_empty());
+
+    drop(backend);
+    drop(device);
+    let _ = std::fs::remove_file(&file);
+}
@@ -0,0 +1,84 @@
+//! NMOS against a simulated device
+//!
+//! An IS-05 controller connects our receivers to the stream of
+//! `network_audio::simulator` on loopback, by the stream's SDP.
+
+#![cfg(target_os = "linux")]
+
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+use serde_json::json;
+
+use audiomultiverse_server::network_audio::{Aes67Backend, Aes67Config, NetworkBackends};
+use audiomultiverse_server::network_audio::health::HealthState;
+use audiomultiverse_server::network_audio::nmos::{NmosNode, NodeConfig};
+use audiomultiverse_server::network_audio::simulator::{wait_for, SimulatedDevice, SimulatorConfig, TEST_PTP_PORTS};
+
+/// An NMOS controller connects a receiver to the device's stream by its
+/// SDP and disconnects it again
+#[test]
+fn test_nmos_connection() {
+    let device = SimulatedDevice::start(SimulatorConfig {
+        name: "NMOS source".to_string(),
+        group: Ipv4Addr::new(239, 69, 250, 4),
+        port: 15010,
+        ptp_domain: 73,
+        ..Default::default()
+    }).unwrap();
+    let backend = Aes67Backend::with_config(Aes67Config {
+        interface: Some("127.0.0.1".to_string()),
+        ptp_domain: 73,
+        ptp_ports: TEST_PTP_PORTS,
+        input_channels: 16,
+        ..Default::default()
+    }).unwrap();
+    let subscriptions = backend.subscriptions();
+    let mut backends = NetworkBackends::default();
+    backends.add("aes67", Box::new(backend));
+
+    // Two receivers of 4 channels from input 9
+    let node = Arc::new(NmosNode::new(NodeConfig {
+        label: "Simulator test".to_string(),
+        address: Ipv4Addr::LOCALHOST,
+        port: std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port(),
+        interfaces: vec![("lo".to_string(), Ipv4Addr::LOCALHOST)],
+        legs: 1,
+        receivers: 2,
+        receiver_channels: 4,
+        first_input: 8,
+        registry: None,
+        heartbeat_interval: Duration::from_secs(5),
+    }, backends.clone()));
+    let receiver = node.receiver_ids()[1].clone();
+
+    let connect = json!({
+        "sender_id": null,
+        "master_enable": true,
+        "transport_file": { "data": device.sdp().unwrap(), "type": "application/sdp" },
+        "activation": { "mode": "activate_immediate" },
+    });
+    let (code, staged) = node.patch_receiver(&receiver, &connect).unwrap();
+    assert_eq!(code, 200);
+    assert!(staged["activation"]["activation_time"].is_string());
+    let active = node.receiver_active(&receiver).unwrap();
+    assert_eq!(active["master_enable"], json!(true));
+    assert_eq!(active["transport_params"][0]["multicast_ip"], json!("239.69.250.4"));
+    assert_eq!(active["transport_params"][0]["destination_port"], json!(15010));
+    assert_eq!(active["transport_params"][0]["interface_ip"], json!("127.0.0.1"));
+
+    // The stream arrives on the receiver's inputs
+    assert!(wait_for(Duration::from_secs(5), || {
+        backends.with(|b: &mut Aes67Backend| b.supervise());
+        subscriptions.infos().first().is_some_and(|info| info.health.state == HealthState::Ok)
+    }));
+    assert_eq!(subscriptions.infos()[0].patch, vec![Some(12), Some(13)]);
+    let resource = node.resources().into_iter().find(|r| r.id == receiver).unwrap();
+    assert_eq!(resource.data["subscription"]["active"], json!(true));
+
+    let disconnect = json!({ "master_enable": false, "activation": { "mode": "activate_immediate" } });
+    node.patch_receiver(&receiver, &disconnect).unwrap();
+    assert!(subscriptions.is_empty());
+    assert_eq!(node.receiver_active(&receiver).unwrap()["master_enable"], json!(false));
+    drop(device);
+}
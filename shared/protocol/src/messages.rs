@@ -103,6 +103,9 @@ pub enum ClientMessage {
         stream_id: String,
         /// Erster lokaler Kanal für Mapping
         start_channel: Option<u32>,
+        /// Mixer-Eingang je Stream-Kanal (None = nicht verbunden), überschreibt start_channel
+        #[serde(default)]
+        patch: Option<Vec<Option<u32>>>,
     },
     
     /// AES67 Stream Subscription beenden
@@ -193,6 +196,14 @@ pub enum ServerMessage {
     #[serde(rename = "aes67_streams")]
     Aes67Streams(Vec<Aes67StreamInfo>),
     
+    /// AES67 Stream per SAP entdeckt oder geändert (neue Session-Version)
+    #[serde(rename = "aes67_stream_announced")]
+    Aes67StreamAnnounced(Aes67StreamInfo),
+    
+    /// AES67 Stream abgemeldet oder Announcement abgelaufen
+    #[serde(rename = "aes67_stream_removed")]
+    Aes67StreamRemoved { stream_id: String },
+    
     /// Stream Subscription erfolgreich
     #[serde(rename = "aes67_subscribed")]
     Aes67Subscribed { 
@@ -206,6 +217,23 @@ pub enum ServerMessage {
     #[serde(rename = "aes67_unsubscribed")]
     Aes67Unsubscribed { stream_id: String },
     
+    /// Abonnierter Stream liefert keine Pakete mehr (Alarm)
+    #[serde(rename = "aes67_stream_stalled")]
+    Aes67StreamStalled { stream_id: String, stream_name: String },
+    
+    /// Abonnierter Stream liefert wieder Pakete
+    #[serde(rename = "aes67_stream_recovered")]
+    Aes67StreamRecovered { stream_id: String, stream_name: String },
+    
+    /// Stream automatisch neu abonniert (wieder aufgetaucht, neue SDP oder
+    /// gespeicherte Subscription wiederhergestellt)
+    #[serde(rename = "aes67_stream_resubscribed")]
+    Aes67StreamResubscribed {
+        previous_id: String,
+        stream_id: String,
+        stream_name: String,
+    },
+    
     // === Multi-Client Sync ===
     
     /// Client-Anzahl hat sich geändert (Broadcast an alle Clients)
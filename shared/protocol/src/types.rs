@@ -167,6 +167,14 @@ pub struct Aes67Status {
     pub our_stream: Option<Aes67StreamInfo>,
     /// Aktuell empfangene Streams
     pub subscribed_streams: Vec<String>,
+    /// Gemessene Clock-Drift Soundkarte gegenüber PTP in ppm
+    pub clock_drift_ppm: f64,
+    /// Aktuelle Korrektur des Resamplers in ppm
+    pub src_correction_ppm: f64,
+    /// Füllstand des Empfangspuffers in Frames
+    pub buffer_fill: u32,
+    /// Ziel-Füllstand des Empfangspuffers in Frames
+    pub buffer_target: u32,
 }
 
 /// AES67 Stream Information